@@ -0,0 +1,159 @@
+use image::codecs::gif::GifDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, ImageDecoder};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+// Formats that may hold more than one frame
+pub fn is_animatable_extension(ext: &str) -> bool {
+    matches!(ext, "gif" | "webp")
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, String> {
+    File::open(crate::paths::extended(path))
+        .map(BufReader::new)
+        .map_err(|e| format!("Failed to open image: {}", e))
+}
+
+// The first frame, composited onto the full canvas. Decoding a GIF as a
+// still image gives whichever frame the decoder settles on; this is always
+// the one a player shows first.
+pub fn decode_first_frame(path: &Path, ext: &str) -> Result<DynamicImage, String> {
+    let reader = open(path)?;
+    let mut frames = match ext {
+        "gif" => {
+            let mut decoder = GifDecoder::new(reader).map_err(crate::decode_error)?;
+            decoder
+                .set_limits(crate::decode_limits())
+                .map_err(crate::decode_error)?;
+            decoder.into_frames()
+        }
+        _ => {
+            let mut decoder = WebPDecoder::new(reader).map_err(crate::decode_error)?;
+            decoder
+                .set_limits(crate::decode_limits())
+                .map_err(crate::decode_error)?;
+            if !decoder.has_animation() {
+                return DynamicImage::from_decoder(decoder).map_err(crate::decode_error);
+            }
+            decoder.into_frames()
+        }
+    };
+
+    let frame = frames
+        .next()
+        .ok_or_else(|| "Animation has no frames".to_string())?
+        .map_err(crate::decode_error)?;
+    Ok(DynamicImage::ImageRgba8(frame.into_buffer()))
+}
+
+// Frame count read from the container structure without decoding pixels.
+// `None` for formats that can't animate or files that can't be parsed.
+pub fn frame_count(path: &Path) -> Option<u32> {
+    // Extensionless files go by content
+    let ext = match crate::extension_lower(path) {
+        Some(ext) if crate::is_supported_extension(&ext) => ext,
+        _ => crate::sniff::image_type(path)?.extension().to_string(),
+    };
+    let mut reader = open(path).ok()?;
+    match ext.as_str() {
+        "gif" => gif_frames(&mut reader),
+        "webp" => webp_frames(&mut reader),
+        _ => None,
+    }
+}
+
+pub fn is_animated(frame_count: Option<u32>) -> bool {
+    frame_count.is_some_and(|n| n > 1)
+}
+
+fn read_u8(reader: &mut impl Read) -> Option<u8> {
+    let mut byte = [0; 1];
+    reader.read_exact(&mut byte).ok()?;
+    Some(byte[0])
+}
+
+fn skip(reader: &mut BufReader<File>, len: u64) -> Option<()> {
+    reader.seek_relative(len as i64).ok()
+}
+
+// Color table size from a GIF packed field, 0 when the table is absent
+fn gif_color_table_len(packed: u8) -> u64 {
+    if packed & 0x80 == 0 {
+        0
+    } else {
+        3 << ((packed & 0x07) + 1)
+    }
+}
+
+// Skip a chain of GIF data sub-blocks, ended by a zero-length block
+fn gif_skip_sub_blocks(reader: &mut BufReader<File>) -> Option<()> {
+    loop {
+        match read_u8(reader)? {
+            0 => return Some(()),
+            len => skip(reader, len as u64)?,
+        }
+    }
+}
+
+fn gif_frames(reader: &mut BufReader<File>) -> Option<u32> {
+    let mut header = [0; 13];
+    reader.read_exact(&mut header).ok()?;
+    if &header[..3] != b"GIF" {
+        return None;
+    }
+    skip(reader, gif_color_table_len(header[10]))?;
+
+    let mut frames = 0;
+    loop {
+        match read_u8(reader) {
+            // Image descriptor: position, size, packed field, then an
+            // optional local color table and the LZW data
+            Some(0x2C) => {
+                let mut descriptor = [0; 9];
+                reader.read_exact(&mut descriptor).ok()?;
+                skip(reader, gif_color_table_len(descriptor[8]) + 1)?;
+                gif_skip_sub_blocks(reader)?;
+                frames += 1;
+            }
+            // Extension: label, then sub-blocks
+            Some(0x21) => {
+                read_u8(reader)?;
+                gif_skip_sub_blocks(reader)?;
+            }
+            // Trailer, or junk after the last frame of a damaged file
+            _ => break,
+        }
+    }
+    (frames > 0).then_some(frames)
+}
+
+fn webp_frames(reader: &mut BufReader<File>) -> Option<u32> {
+    let mut header = [0; 12];
+    reader.read_exact(&mut header).ok()?;
+    if &header[..4] != b"RIFF" || &header[8..] != b"WEBP" {
+        return None;
+    }
+
+    let mut animated = false;
+    let mut frames = 0;
+    let mut chunk = [0; 8];
+    while reader.read_exact(&mut chunk).is_ok() {
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+        match &chunk[..4] {
+            b"VP8X" => {
+                let flags = read_u8(reader)?;
+                animated = flags & 0x02 != 0;
+                skip(reader, (size + (size & 1)).saturating_sub(1))?;
+                continue;
+            }
+            b"ANMF" => frames += 1,
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        skip(reader, size + (size & 1))?;
+    }
+
+    Some(if animated { frames.max(1) } else { 1 })
+}
@@ -0,0 +1,215 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DrawStackError;
+use crate::{config, imports, scan};
+
+const PROGRESS_EVERY: usize = 25;
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct ExtractProgress {
+    pack_id: String,
+    extracted: usize,
+}
+
+// Archive entry names are untrusted; keep only plain path components so an
+// entry like `../../evil.exe` can't land outside the extraction dir.
+pub(crate) fn safe_relative_path(name: &str) -> Option<PathBuf> {
+    let path: PathBuf = Path::new(&name.replace('\\', "/"))
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part),
+            _ => None,
+        })
+        .collect();
+
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+fn extraction_dir(app: &AppHandle, pack_id: &str) -> Result<PathBuf, String> {
+    let app_data = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+
+    let dir_name: String = pack_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    Ok(app_data.join("archives").join(dir_name))
+}
+
+struct Extractor<'a> {
+    app: &'a AppHandle,
+    pack_id: &'a str,
+    dest: &'a Path,
+    extracted: usize,
+}
+
+impl Extractor<'_> {
+    // Writes `reader` to `dest/name` if the entry is a supported image.
+    // Returns false for entries that were skipped.
+    fn extract(&mut self, name: &str, reader: &mut dyn io::Read) -> Result<bool, String> {
+        let Some(relative) = safe_relative_path(name) else {
+            return Ok(false);
+        };
+        if !crate::is_supported_image(&relative) {
+            return Ok(false);
+        }
+
+        let target = self.dest.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        let mut file = fs::File::create(&target)
+            .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+        io::copy(reader, &mut file).map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+
+        self.extracted += 1;
+        if self.extracted.is_multiple_of(PROGRESS_EVERY) {
+            self.emit_progress();
+        }
+        Ok(true)
+    }
+
+    fn emit_progress(&self) {
+        let _ = self.app.emit(
+            "archive-extract-progress",
+            ExtractProgress {
+                pack_id: self.pack_id.to_string(),
+                extracted: self.extracted,
+            },
+        );
+    }
+}
+
+fn extract_zip(archive_path: &Path, extractor: &mut Extractor) -> Result<(), String> {
+    let file =
+        fs::File::open(archive_path).map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut archive =
+        zip::ZipArchive::new(file).map_err(|e| format!("Failed to read zip archive: {}", e))?;
+
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| format!("Failed to read zip entry {}: {}", index, e))?;
+        if !entry.is_file() {
+            continue;
+        }
+
+        let name = entry.name().to_string();
+        extractor.extract(&name, &mut entry)?;
+    }
+
+    Ok(())
+}
+
+fn extract_7z(archive_path: &Path, extractor: &mut Extractor) -> Result<(), String> {
+    let mut reader = sevenz_rust::SevenZReader::open(archive_path, sevenz_rust::Password::empty())
+        .map_err(|e| format!("Failed to read 7z archive: {}", e))?;
+
+    let mut failure = None;
+    reader
+        .for_each_entries(|entry, data| {
+            if entry.is_directory() {
+                return Ok(true);
+            }
+            match extractor.extract(entry.name(), data) {
+                // Solid archives decode sequentially, so skipped entries
+                // still have to be drained
+                Ok(false) => {
+                    io::copy(data, &mut io::sink())?;
+                    Ok(true)
+                }
+                Ok(true) => Ok(true),
+                Err(e) => {
+                    failure = Some(e);
+                    Ok(false)
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to extract 7z archive: {}", e))?;
+
+    failure.map_or(Ok(()), Err)
+}
+
+// Extract the images from a zip or 7z archive into
+// app_data/archives/<pack_id>/<uuid> and import them like a regular folder.
+#[tauri::command]
+pub async fn import_archive(
+    app: AppHandle,
+    archive_path: String,
+    pack_id: String,
+    thread_count: Option<usize>,
+) -> Result<imports::ImportReport, DrawStackError> {
+    tracing::info!("Importing archive: {}", archive_path);
+
+    let source = Path::new(&archive_path);
+    if !source.is_file() {
+        return Err(DrawStackError::not_found(&archive_path));
+    }
+
+    let running =
+        imports::RunningImport::start(app.state::<imports::ImportControl>().inner(), &pack_id)?;
+
+    let extract: fn(&Path, &mut Extractor) -> Result<(), String> =
+        match crate::extension_lower(source).as_deref() {
+            Some("zip") | Some("cbz") => extract_zip,
+            Some("7z") | Some("cb7") => extract_7z,
+            Some("rar") | Some("cbr") => {
+                return Err(DrawStackError::invalid(
+                    "RAR archives are not supported yet - please extract them first",
+                ))
+            }
+            _ => {
+                return Err(DrawStackError::Unsupported {
+                    path: source.to_path_buf(),
+                })
+            }
+        };
+
+    // Each archive gets a folder of its own: earlier archives imported into
+    // the pack keep their originals in the sibling folders, and nothing left
+    // there is picked up by this import's scan
+    let dest = extraction_dir(&app, &pack_id)?.join(crate::generate_uuid());
+    fs::create_dir_all(&dest).map_err(|e| format!("Failed to create extraction dir: {}", e))?;
+
+    let mut extractor = Extractor {
+        app: &app,
+        pack_id: &pack_id,
+        dest: &dest,
+        extracted: 0,
+    };
+    if let Err(e) = extract(source, &mut extractor) {
+        let _ = fs::remove_dir_all(&dest);
+        return Err(e.into());
+    }
+
+    extractor.emit_progress();
+    tracing::info!("Extracted {} images from archive", extractor.extracted);
+
+    let images = scan::scan_for_images(&dest, &config::load(&app).scan)?;
+    let journal = imports::ImportJournal {
+        pack_id,
+        folder_path: dest.to_string_lossy().to_string(),
+        total: images.len(),
+        processed: 0,
+        skipped: 0,
+        filtered: 0,
+        failed: Vec::new(),
+    };
+    imports::create_journal(&app, &journal, &images)?;
+
+    crate::process_import(&app, running, journal, images, thread_count, None)
+}
@@ -0,0 +1,246 @@
+// Sprite sheets of a pack's thumbnails. A grid of thousands of images loads
+// a few large pages instead of one request per thumbnail, and draws each
+// cell from the index's tile coordinates. Pages live in
+// app_data/atlases/<pack_id>/ and are served as drawstack://atlas/<pack_id>/<page>.
+use image::codecs::webp::WebPEncoder;
+use image::{imageops, Rgba, RgbaImage};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+use crate::catalog;
+use crate::error::DrawStackError;
+
+pub const PAGE_SIZE: u32 = 4096;
+const DEFAULT_TILE_SIZE: u32 = 128;
+const MIN_TILE_SIZE: u32 = 32;
+const MAX_TILE_SIZE: u32 = 512;
+
+const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct AtlasTile {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    // The thumbnail is fitted inside the cell, keeping its aspect ratio
+    pub width: u32,
+    pub height: u32,
+    // Thumbnail path and mtime the tile was drawn from
+    stamp: String,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct AtlasIndex {
+    pub pack_id: String,
+    pub tile_size: u32,
+    pub page_size: u32,
+    pub pages: usize,
+    pub tiles: HashMap<String, AtlasTile>,
+    // Pages redrawn by the last build; the rest were reused as they were
+    #[serde(default)]
+    pub rebuilt_pages: Vec<usize>,
+}
+
+fn atlas_dir(app: &AppHandle, pack_id: &str) -> Result<PathBuf, String> {
+    let app_data = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(app_data.join("atlases").join(pack_id))
+}
+
+fn page_file(dir: &Path, page: usize) -> PathBuf {
+    dir.join(format!("page-{}.webp", page))
+}
+
+// File behind drawstack://atlas/<pack_id>/<page>
+pub fn page_path(app: &AppHandle, pack_id: &str, page: usize) -> Result<PathBuf, String> {
+    Ok(page_file(&atlas_dir(app, pack_id)?, page))
+}
+
+fn stamp(thumbnail: &Path) -> Option<String> {
+    let modified = fs::metadata(thumbnail)
+        .ok()?
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?;
+    Some(format!("{}@{}", thumbnail.display(), modified.as_millis()))
+}
+
+fn load_index(dir: &Path) -> Option<AtlasIndex> {
+    let contents = fs::read_to_string(dir.join(INDEX_FILE)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+// Build or refresh the atlas for `pack_id`. Tiles whose thumbnail hasn't
+// changed keep their place; removed images free their cell for new ones, so
+// only pages that gained, lost or changed a tile are re-encoded. Changing
+// `tile_size` rebuilds everything.
+#[tauri::command]
+pub async fn build_thumbnail_atlas(
+    app: AppHandle,
+    pack_id: String,
+    tile_size: Option<u32>,
+) -> Result<AtlasIndex, DrawStackError> {
+    let tile_size = tile_size
+        .unwrap_or(DEFAULT_TILE_SIZE)
+        .clamp(MIN_TILE_SIZE, MAX_TILE_SIZE);
+    let per_row = PAGE_SIZE / tile_size;
+    let per_page = (per_row * per_row) as usize;
+
+    let conn = catalog::open(&app)?;
+    let pack = catalog::get_pack(&conn, &pack_id, None)?
+        .ok_or_else(|| DrawStackError::invalid(format!("Pack not found: {}", pack_id)))?;
+    let dir = atlas_dir(&app, &pack_id)?;
+    fs::create_dir_all(&dir).map_err(|e| DrawStackError::io("create", &dir, e))?;
+
+    let previous = load_index(&dir)
+        .filter(|index| index.tile_size == tile_size && index.page_size == PAGE_SIZE);
+    // Without a matching index the page files on disk can't be reused
+    let reuse_pages = previous.is_some();
+    let mut old_tiles = previous.map(|index| index.tiles).unwrap_or_default();
+
+    // Keep unchanged tiles where they are; everything else needs drawing
+    let mut tiles: HashMap<String, AtlasTile> = HashMap::new();
+    let mut pending: Vec<(String, PathBuf, String)> = Vec::new();
+    for image in &pack.images {
+        let Some(thumbnail) = image.thumbnail_path.as_ref().map(|p| p.to_path_buf()) else {
+            continue;
+        };
+        let Some(stamp) = stamp(&thumbnail) else {
+            continue;
+        };
+        match old_tiles.remove(&image.id) {
+            Some(tile) if tile.stamp == stamp => {
+                tiles.insert(image.id.clone(), tile);
+            }
+            Some(tile) => {
+                // Redraw in the same cell
+                old_tiles.insert(image.id.clone(), tile);
+                pending.push((image.id.clone(), thumbnail, stamp));
+            }
+            None => pending.push((image.id.clone(), thumbnail, stamp)),
+        }
+    }
+
+    let slot_of = |tile: &AtlasTile| {
+        tile.page * per_page + ((tile.y / tile_size) * per_row + tile.x / tile_size) as usize
+    };
+    let taken: BTreeSet<usize> = tiles.values().map(slot_of).collect();
+    // Cells of removed or changed images are cleared and may be reused
+    let mut cleared: Vec<usize> = old_tiles.values().map(slot_of).collect();
+    cleared.sort_unstable();
+    let mut dirty: BTreeSet<usize> = cleared.iter().map(|slot| slot / per_page).collect();
+
+    let mut free = (0..).filter(|slot| !taken.contains(slot));
+    let mut placed: Vec<(usize, PathBuf)> = Vec::with_capacity(pending.len());
+    for (image_id, thumbnail, stamp) in pending {
+        let slot = free.next().unwrap_or_default();
+        let page = slot / per_page;
+        let cell = (slot % per_page) as u32;
+        dirty.insert(page);
+        tiles.insert(
+            image_id,
+            AtlasTile {
+                page,
+                x: (cell % per_row) * tile_size,
+                y: (cell / per_row) * tile_size,
+                width: 0,
+                height: 0,
+                stamp,
+            },
+        );
+        placed.push((slot, thumbnail));
+    }
+
+    let used_slots = tiles.values().map(slot_of).max().map_or(0, |max| max + 1);
+    let pages = used_slots.div_ceil(per_page);
+    let ids_by_slot: HashMap<usize, String> = tiles
+        .iter()
+        .map(|(id, tile)| (slot_of(tile), id.clone()))
+        .collect();
+
+    for &page in dirty.iter().filter(|&&page| page < pages) {
+        let path = page_file(&dir, page);
+        let mut canvas = reuse_pages
+            .then(|| image::open(&path).ok())
+            .flatten()
+            .map(|img| img.to_rgba8())
+            .filter(|img| img.dimensions() == (PAGE_SIZE, PAGE_SIZE))
+            .unwrap_or_else(|| RgbaImage::new(PAGE_SIZE, PAGE_SIZE));
+
+        let blank = RgbaImage::from_pixel(tile_size, tile_size, Rgba([0, 0, 0, 0]));
+        for &slot in cleared.iter().filter(|slot| *slot / per_page == page) {
+            let cell = (slot % per_page) as u32;
+            imageops::replace(
+                &mut canvas,
+                &blank,
+                ((cell % per_row) * tile_size) as i64,
+                ((cell / per_row) * tile_size) as i64,
+            );
+        }
+
+        for (slot, thumbnail) in placed.iter().filter(|(slot, _)| slot / per_page == page) {
+            let Some(tile) = ids_by_slot.get(slot).and_then(|id| tiles.get_mut(id)) else {
+                continue;
+            };
+            let fitted = match image::open(thumbnail) {
+                Ok(img) => img.thumbnail(tile_size, tile_size).to_rgba8(),
+                Err(e) => {
+                    tracing::warn!("Skipping atlas tile {}: {}", thumbnail.display(), e);
+                    continue;
+                }
+            };
+            imageops::replace(&mut canvas, &fitted, tile.x as i64, tile.y as i64);
+            tile.width = fitted.width();
+            tile.height = fitted.height();
+        }
+
+        let mut encoded = Vec::new();
+        WebPEncoder::new_lossless(&mut encoded)
+            .encode(
+                canvas.as_raw(),
+                PAGE_SIZE,
+                PAGE_SIZE,
+                image::ExtendedColorType::Rgba8,
+            )
+            .map_err(|e| format!("Failed to encode atlas page: {}", e))?;
+        crate::write_atomic(&path, &encoded).map_err(|e| DrawStackError::io("write", &path, e))?;
+    }
+
+    // Pages past the end are left over from a bigger pack
+    let mut extra = pages;
+    while page_file(&dir, extra).exists() {
+        let _ = fs::remove_file(page_file(&dir, extra));
+        extra += 1;
+    }
+
+    // Tiles whose thumbnail couldn't be read were never drawn
+    tiles.retain(|_, tile| tile.width > 0);
+    let index = AtlasIndex {
+        pack_id,
+        tile_size,
+        page_size: PAGE_SIZE,
+        pages,
+        tiles,
+        rebuilt_pages: dirty.into_iter().filter(|&page| page < pages).collect(),
+    };
+    let contents = serde_json::to_vec(&index)
+        .map_err(|e| format!("Failed to serialize atlas index: {}", e))?;
+    let index_path = dir.join(INDEX_FILE);
+    crate::write_atomic(&index_path, &contents)
+        .map_err(|e| DrawStackError::io("write", &index_path, e))?;
+
+    tracing::info!(
+        "Atlas for pack {}: {} tiles on {} pages, {} redrawn",
+        index.pack_id,
+        index.tiles.len(),
+        index.pages,
+        index.rebuilt_pages.len()
+    );
+    Ok(index)
+}
@@ -0,0 +1,230 @@
+// Timer sounds played by the backend. The webview throttles its timers while
+// the window is minimized, so a beep scheduled there arrives late or not at
+// all; these cues are fired by the session thread as intervals end.
+// Playback runs on its own thread, since the audio output can't be moved
+// between threads.
+use std::path::Path;
+use std::sync::{mpsc, Mutex};
+use tauri::{AppHandle, Manager};
+
+use crate::config;
+use crate::error::DrawStackError;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CueSound {
+    Off,
+    // Two quick notes, as the timer page plays between images
+    Chime,
+    // A rising four-note arpeggio, as the timer page plays at the end
+    Fanfare,
+    // An audio file: WAV, MP3, Ogg Vorbis or FLAC
+    File(String),
+}
+
+// Stored in the app config as `audio_cues`
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(default)]
+pub struct AudioCues {
+    // Off until the frontend stops playing its own sounds, so cues aren't
+    // heard twice
+    pub enabled: bool,
+    // 0.0 to 1.0
+    pub volume: f32,
+    // When an image's time runs out, or a break ends
+    pub interval_end: CueSound,
+    // When a session runs through its last image
+    pub session_end: CueSound,
+}
+
+impl Default for AudioCues {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volume: 0.8,
+            interval_end: CueSound::Chime,
+            session_end: CueSound::Fanfare,
+        }
+    }
+}
+
+impl AudioCues {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.volume) {
+            return Err(format!(
+                "Volume must be between 0 and 1, got {}",
+                self.volume
+            ));
+        }
+        for sound in [&self.interval_end, &self.session_end] {
+            if matches!(sound, CueSound::File(path) if path.trim().is_empty()) {
+                return Err("Sound file path can't be empty".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "audio")]
+    fn sound(&self, cue: Cue) -> &CueSound {
+        match cue {
+            Cue::IntervalEnd => &self.interval_end,
+            Cue::SessionEnd => &self.session_end,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Cue {
+    IntervalEnd,
+    SessionEnd,
+}
+
+#[derive(Default)]
+pub struct AudioCuePlayer {
+    sender: Mutex<Option<mpsc::Sender<Cue>>>,
+}
+
+impl AudioCuePlayer {
+    #[cfg(feature = "audio")]
+    pub fn start(&self, app: &AppHandle) {
+        let (sender, receiver) = mpsc::channel();
+        *self.sender.lock().unwrap() = Some(sender);
+        let app = app.clone();
+        let spawned = std::thread::Builder::new()
+            .name("audio-cues".into())
+            .spawn(move || playback::run(&app, receiver));
+        if let Err(e) = spawned {
+            tracing::error!("Failed to start audio cue thread: {}", e);
+        }
+    }
+}
+
+// Play `cue` with the sound the settings pick for it. Returns at once; does
+// nothing when cues are off or this build has no audio.
+pub fn play(app: &AppHandle, cue: Cue) {
+    let sender = app.state::<AudioCuePlayer>().sender.lock().unwrap().clone();
+    if let Some(sender) = sender {
+        let _ = sender.send(cue);
+    }
+}
+
+#[cfg(feature = "audio")]
+mod playback {
+    use rodio::source::{SineWave, Source};
+    use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::Path;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use tauri::AppHandle;
+
+    use super::{Cue, CueSound};
+    use crate::config;
+
+    struct Output {
+        // Sound stops when the stream is dropped
+        _stream: OutputStream,
+        handle: OutputStreamHandle,
+    }
+
+    impl Output {
+        fn open() -> Result<Self, String> {
+            let (stream, handle) = OutputStream::try_default()
+                .map_err(|e| format!("Failed to open audio output: {}", e))?;
+            Ok(Self {
+                _stream: stream,
+                handle,
+            })
+        }
+
+        fn play(&self, sound: &CueSound, volume: f32) -> Result<(), String> {
+            let sink = Sink::try_new(&self.handle)
+                .map_err(|e| format!("Failed to start audio playback: {}", e))?;
+            sink.set_volume(volume);
+            match sound {
+                CueSound::Off => return Ok(()),
+                CueSound::Chime => {
+                    sink.append(tone(800.0, 100, 0.3));
+                    sink.append(tone(1000.0, 200, 0.3));
+                }
+                CueSound::Fanfare => {
+                    for freq in [523.25, 659.25, 783.99] {
+                        sink.append(tone(freq, 150, 0.2));
+                    }
+                    sink.append(tone(1046.5, 400, 0.2));
+                }
+                CueSound::File(path) => {
+                    let file = File::open(crate::paths::extended(Path::new(path)))
+                        .map_err(|e| format!("Failed to open sound {}: {}", path, e))?;
+                    let decoder = Decoder::new(BufReader::new(file))
+                        .map_err(|e| format!("Failed to decode sound {}: {}", path, e))?;
+                    sink.append(decoder);
+                }
+            }
+            // Keeps playing after the sink is dropped, so cues can overlap
+            sink.detach();
+            Ok(())
+        }
+    }
+
+    // A sine note, faded in so it starts without a click
+    fn tone(freq: f32, millis: u64, gain: f32) -> impl Source<Item = f32> + Send {
+        SineWave::new(freq)
+            .take_duration(Duration::from_millis(millis))
+            .amplify(gain)
+            .fade_in(Duration::from_millis(5))
+    }
+
+    // The output is opened at the first cue, so no audio device is held
+    // while cues are off, and reopened after a failure, e.g. when the
+    // headphones it played through are unplugged
+    pub(super) fn run(app: &AppHandle, receiver: mpsc::Receiver<Cue>) {
+        let mut output: Option<Output> = None;
+        for cue in receiver {
+            let cues = config::load(app).audio_cues;
+            let sound = cues.sound(cue);
+            if !cues.enabled || *sound == CueSound::Off {
+                continue;
+            }
+
+            let played = match output.take() {
+                Some(current) => Ok(current),
+                None => Output::open(),
+            }
+            .and_then(|current| current.play(sound, cues.volume).map(|()| current));
+            match played {
+                Ok(current) => output = Some(current),
+                Err(e) => tracing::warn!("Failed to play {:?} cue: {}", cue, e),
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_audio_cues(app: AppHandle) -> AudioCues {
+    config::load(&app).audio_cues
+}
+
+#[tauri::command]
+pub fn set_audio_cues(app: AppHandle, config: AudioCues) -> Result<(), DrawStackError> {
+    config.validate().map_err(DrawStackError::invalid)?;
+    if config.enabled && cfg!(not(feature = "audio")) {
+        return Err(DrawStackError::invalid(
+            "Audio cues aren't available in this build",
+        ));
+    }
+    for sound in [&config.interval_end, &config.session_end] {
+        if let CueSound::File(path) = sound {
+            if !crate::paths::extended(Path::new(path)).is_file() {
+                return Err(DrawStackError::not_found(path));
+            }
+        }
+    }
+
+    crate::config::update(&app, |stored| {
+        stored.audio_cues = config;
+        Ok(())
+    })?;
+    Ok(())
+}
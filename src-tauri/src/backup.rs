@@ -0,0 +1,468 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use zip::write::SimpleFileOptions;
+
+use crate::error::DrawStackError;
+use crate::{archive, catalog, config, logging, storage};
+
+const BACKUP_FORMAT: &str = "drawstack-backup";
+const BACKUP_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+const CATALOG_NAME: &str = "catalog.db";
+
+// Zip folders for files from the app data dir and the library
+const DATA_PREFIX: &str = "data/";
+const LIBRARY_PREFIX: &str = "library/";
+
+// The app data dir's current contents are moved here before a restore
+const PRE_RESTORE_PREFIX: &str = "pre-restore-";
+// Inside the pre-restore folder: library files the restore overwrote
+const REPLACED_LIBRARY_DIR: &str = "replaced-library";
+
+const PROGRESS_EVERY: usize = 25;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+struct BackupManifest {
+    format: String,
+    version: u32,
+    created_at: i64,
+    app_version: String,
+    // Catalog schema version; newer catalogs can't be opened by older builds
+    schema_version: usize,
+    // Where things lived on the machine that made the backup, so stored
+    // paths can be pointed at the new locations on restore
+    app_data_dir: String,
+    library_path: String,
+    includes_library: bool,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct BackupSummary {
+    pub path: String,
+    pub files: usize,
+    pub bytes: u64,
+    pub includes_library: bool,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct RestoreSummary {
+    pub files: usize,
+    pub created_at: i64,
+    // Where the data replaced by the restore was moved
+    pub previous_data_dir: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct BackupProgress {
+    operation: &'static str,
+    done: usize,
+    total: usize,
+}
+
+fn emit_progress(app: &AppHandle, operation: &'static str, done: usize, total: usize) {
+    if done.is_multiple_of(PROGRESS_EVERY) || done == total {
+        let _ = app.emit(
+            "backup-progress",
+            BackupProgress {
+                operation,
+                done,
+                total,
+            },
+        );
+    }
+}
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+// The live database and its WAL are replaced by a consistent snapshot, and
+// leftovers from interrupted writes or earlier restores aren't worth keeping
+fn skip_data_entry(relative: &Path) -> bool {
+    let name = relative.to_string_lossy();
+    name.starts_with(CATALOG_NAME)
+        || name.starts_with(PRE_RESTORE_PREFIX)
+        || name.ends_with(".tmp")
+        || name.ends_with(".part")
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        if dir == root && skip_data_entry(relative) {
+            continue;
+        }
+        if file_type.is_dir() {
+            collect_files(root, &path, files);
+        } else if file_type.is_file() {
+            files.push(path);
+        }
+    }
+}
+
+fn entry_name(prefix: &str, root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    Some(format!(
+        "{}{}",
+        prefix,
+        relative.to_string_lossy().replace('\\', "/")
+    ))
+}
+
+fn add_file<W: Write + io::Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    name: &str,
+    source: &Path,
+) -> Result<(), String> {
+    let mut file = fs::File::open(source)
+        .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+
+    // Images are already compressed; the catalog and JSON files deflate well
+    let method = match crate::extension_lower(source).as_deref() {
+        Some("db" | "json" | "jsonl") => zip::CompressionMethod::Deflated,
+        _ if crate::is_supported_image(source) => zip::CompressionMethod::Stored,
+        _ => zip::CompressionMethod::Deflated,
+    };
+    let options = SimpleFileOptions::default()
+        .compression_method(method)
+        .large_file(true);
+    zip.start_file(name, options)
+        .map_err(|e| format!("Failed to add {} to backup: {}", name, e))?;
+    io::copy(&mut file, zip).map_err(|e| format!("Failed to write {} to backup: {}", name, e))?;
+    Ok(())
+}
+
+// Write config, catalog and thumbnails - everything in the app data dir -
+// and optionally the library originals to one zip at `dest_path`.
+#[tauri::command]
+pub async fn create_backup(
+    app: AppHandle,
+    dest_path: String,
+    include_library: Option<bool>,
+) -> Result<BackupSummary, DrawStackError> {
+    let include_library = include_library.unwrap_or(false);
+    let app_data = app_data_dir(&app)?;
+    let library_dir = PathBuf::from(crate::get_library_path(app.clone())?);
+
+    // VACUUM INTO gives a consistent copy even while other commands write
+    let snapshot = app_data.join(format!("{}.backup.tmp", CATALOG_NAME));
+    let _ = fs::remove_file(&snapshot);
+    catalog::open(&app)?
+        .execute("VACUUM INTO ?1", [snapshot.to_string_lossy().to_string()])
+        .map_err(|e| format!("Failed to snapshot catalog: {}", e))?;
+
+    let mut data_files = Vec::new();
+    collect_files(&app_data, &app_data, &mut data_files);
+    let mut library_files = Vec::new();
+    if include_library {
+        collect_files(&library_dir, &library_dir, &mut library_files);
+    }
+    let total = data_files.len() + library_files.len() + 1;
+
+    let manifest = BackupManifest {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        created_at: catalog::now_unix(),
+        app_version: app.package_info().version.to_string(),
+        schema_version: catalog::schema_version(),
+        app_data_dir: app_data.to_string_lossy().to_string(),
+        library_path: library_dir.to_string_lossy().to_string(),
+        includes_library: include_library,
+    };
+
+    // Written next to the destination and renamed at the end, so a failed
+    // backup never leaves a truncated archive behind
+    let dest = PathBuf::from(&dest_path);
+    let partial = dest.with_extension("zip.part");
+    let file = fs::File::create(&partial).map_err(|e| format!("Failed to create backup: {}", e))?;
+    let mut zip = zip::ZipWriter::new(io::BufWriter::new(file));
+
+    let result = (|| {
+        let manifest_json = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| format!("Failed to serialize backup manifest: {}", e))?;
+        zip.start_file(MANIFEST_NAME, SimpleFileOptions::default())
+            .map_err(|e| format!("Failed to add manifest to backup: {}", e))?;
+        zip.write_all(&manifest_json)
+            .map_err(|e| format!("Failed to write backup manifest: {}", e))?;
+
+        add_file(
+            &mut zip,
+            &format!("{}{}", DATA_PREFIX, CATALOG_NAME),
+            &snapshot,
+        )?;
+        let mut done = 1;
+        emit_progress(&app, "backup", done, total);
+
+        let sources = data_files
+            .iter()
+            .map(|path| (DATA_PREFIX, app_data.as_path(), path))
+            .chain(
+                library_files
+                    .iter()
+                    .map(|path| (LIBRARY_PREFIX, library_dir.as_path(), path)),
+            );
+        for (prefix, root, path) in sources {
+            if let Some(name) = entry_name(prefix, root, path) {
+                add_file(&mut zip, &name, path)?;
+            }
+            done += 1;
+            emit_progress(&app, "backup", done, total);
+        }
+
+        zip.finish()
+            .map_err(|e| format!("Failed to finish backup: {}", e))?
+            .flush()
+            .map_err(|e| format!("Failed to write backup: {}", e))?;
+        Ok::<_, String>(())
+    })();
+    let _ = fs::remove_file(&snapshot);
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    fs::rename(&partial, &dest).map_err(|e| format!("Failed to save backup: {}", e))?;
+    let bytes = fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+
+    tracing::info!("Backed up {} files to {}", total, dest_path);
+    Ok(BackupSummary {
+        path: dest_path,
+        files: total,
+        bytes,
+        includes_library: include_library,
+    })
+}
+
+fn read_manifest<R: Read + io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+) -> Result<BackupManifest, DrawStackError> {
+    let mut contents = String::new();
+    archive
+        .by_name(MANIFEST_NAME)
+        .map_err(|_| DrawStackError::invalid("Not a DrawStack backup: no manifest"))?
+        .read_to_string(&mut contents)
+        .map_err(|e| format!("Failed to read backup manifest: {}", e))?;
+    let manifest: BackupManifest = serde_json::from_str(&contents)
+        .map_err(|e| DrawStackError::invalid(format!("Invalid backup manifest: {}", e)))?;
+
+    if manifest.format != BACKUP_FORMAT {
+        return Err(DrawStackError::invalid("Not a DrawStack backup"));
+    }
+    if manifest.version > BACKUP_VERSION || manifest.schema_version > catalog::schema_version() {
+        return Err(DrawStackError::invalid(format!(
+            "This backup was made by a newer version of DrawStack ({})",
+            manifest.app_version
+        )));
+    }
+    Ok(manifest)
+}
+
+fn extract_to<R: Read + io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    index: usize,
+    target: &Path,
+) -> Result<(), String> {
+    let mut entry = archive
+        .by_index(index)
+        .map_err(|e| format!("Failed to read backup entry: {}", e))?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let mut out = fs::File::create(target)
+        .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    io::copy(&mut entry, &mut out)
+        .map_err(|e| format!("Failed to extract {}: {}", target.display(), e))?;
+    Ok(())
+}
+
+// Entries of the app data dir a restore leaves alone: earlier safety copies,
+// and the logs, which are held open while the app runs
+fn stays_in_place(name: &OsStr) -> bool {
+    name.to_string_lossy().starts_with(PRE_RESTORE_PREFIX) || name == logging::LOG_DIR
+}
+
+// Move everything in the app data dir except `stays_in_place` entries into
+// `dest`. If an entry can't be moved, the ones already moved are put back,
+// so a failure never leaves the app data half-emptied.
+fn move_contents(from: &Path, dest: &Path) -> Result<(), String> {
+    fs::create_dir_all(dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+    let entries = fs::read_dir(from).map_err(|e| format!("Failed to read app data: {}", e))?;
+    let mut moved = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        if stays_in_place(&name) {
+            continue;
+        }
+        if let Err(e) = fs::rename(entry.path(), dest.join(&name)) {
+            for name in moved.iter().rev() {
+                let _ = fs::rename(dest.join(name), from.join(name));
+            }
+            return Err(format!("Failed to move {}: {}", entry.path().display(), e));
+        }
+        moved.push(name);
+    }
+    Ok(())
+}
+
+// Replace the app data with the contents of a backup made by `create_backup`.
+// The backup's catalog is checked before anything is touched, and the data
+// it replaces, library files included, is kept in a `pre-restore-<time>`
+// folder rather than deleted.
+#[tauri::command]
+pub async fn restore_backup(
+    app: AppHandle,
+    path: String,
+) -> Result<RestoreSummary, DrawStackError> {
+    let file = fs::File::open(&path).map_err(|e| DrawStackError::io("open backup", &path, e))?;
+    let mut archive = zip::ZipArchive::new(io::BufReader::new(file))
+        .map_err(|e| DrawStackError::invalid(format!("Not a valid backup archive: {}", e)))?;
+    let manifest = read_manifest(&mut archive)?;
+
+    let app_data = app_data_dir(&app)?;
+    let library_dir = PathBuf::from(crate::get_library_path(app.clone())?);
+    fs::create_dir_all(&app_data).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+
+    // Stage and check the catalog first; a damaged backup fails here with
+    // the current data untouched
+    let catalog_index = archive
+        .index_for_name(&format!("{}{}", DATA_PREFIX, CATALOG_NAME))
+        .ok_or_else(|| DrawStackError::invalid("Backup has no catalog"))?;
+    let staged = app_data.join(format!("{}.restore.tmp", CATALOG_NAME));
+    extract_to(&mut archive, catalog_index, &staged)?;
+    let check: Result<String, _> = rusqlite::Connection::open(&staged)
+        .and_then(|conn| conn.query_row("PRAGMA quick_check", [], |row| row.get(0)));
+    if check.as_deref() != Ok("ok") {
+        let _ = fs::remove_file(&staged);
+        return Err(DrawStackError::invalid(
+            "The catalog in this backup is damaged",
+        ));
+    }
+
+    let previous = app_data.join(format!("{}{}", PRE_RESTORE_PREFIX, catalog::now_unix()));
+    move_contents(&app_data, &previous)?;
+    let staged = previous.join(staged.file_name().unwrap_or_default());
+
+    // Library files the backup overwrites are saved here first, and those it
+    // adds are noted, so a rollback can undo both
+    let replaced_dir = previous.join(REPLACED_LIBRARY_DIR);
+    let mut replaced: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut added: Vec<PathBuf> = Vec::new();
+
+    let total = archive.len();
+    let mut files = 0;
+    let result = (|| {
+        for index in 0..total {
+            let name = archive
+                .by_index(index)
+                .map_err(|e| format!("Failed to read backup entry: {}", e))?
+                .name()
+                .to_string();
+            let mut library_relative = None;
+            let target = if let Some(rest) = name.strip_prefix(DATA_PREFIX) {
+                archive::safe_relative_path(rest)
+                    .filter(|p| !p.starts_with(logging::LOG_DIR))
+                    .map(|p| app_data.join(p))
+            } else if let Some(rest) = name.strip_prefix(LIBRARY_PREFIX) {
+                library_relative = archive::safe_relative_path(rest);
+                library_relative.as_ref().map(|p| library_dir.join(p))
+            } else {
+                None
+            };
+
+            if let Some(target) = target.filter(|t| !name.ends_with('/') && *t != app_data) {
+                if name != format!("{}{}", DATA_PREFIX, CATALOG_NAME) {
+                    if let Some(relative) = &library_relative {
+                        if target.is_file() {
+                            let saved = replaced_dir.join(relative);
+                            if let Some(parent) = saved.parent() {
+                                fs::create_dir_all(parent).map_err(|e| {
+                                    format!("Failed to create {}: {}", parent.display(), e)
+                                })?;
+                            }
+                            fs::copy(&target, &saved).map_err(|e| {
+                                format!("Failed to save {}: {}", target.display(), e)
+                            })?;
+                            replaced.push((target.clone(), saved));
+                        } else {
+                            added.push(target.clone());
+                        }
+                    }
+                    extract_to(&mut archive, index, &target)?;
+                    files += 1;
+                }
+            }
+            emit_progress(&app, "restore", index + 1, total);
+        }
+        fs::rename(&staged, app_data.join(CATALOG_NAME))
+            .map_err(|e| format!("Failed to restore catalog: {}", e))?;
+        files += 1;
+
+        // Stored paths still point at the old machine's folders
+        let mut conn = catalog::open(&app)?;
+        catalog::rewrite_path_prefix(
+            &mut conn,
+            &manifest.app_data_dir,
+            &app_data.to_string_lossy(),
+        )?;
+        if manifest.includes_library {
+            catalog::rewrite_path_prefix(
+                &mut conn,
+                &manifest.library_path,
+                &library_dir.to_string_lossy(),
+            )?;
+        }
+        Ok::<_, String>(())
+    })();
+
+    if let Err(e) = result {
+        // Put the previous data back rather than leave a half-restored mix
+        tracing::error!("Restore failed, rolling back: {}", e);
+        for (target, saved) in &replaced {
+            let _ = fs::copy(saved, target);
+        }
+        for target in &added {
+            let _ = fs::remove_file(target);
+        }
+        let _ = fs::remove_dir_all(&replaced_dir);
+        if let Ok(entries) = fs::read_dir(&app_data) {
+            for entry in entries.flatten() {
+                if !stays_in_place(&entry.file_name()) {
+                    let path = entry.path();
+                    let _ = if path.is_dir() {
+                        fs::remove_dir_all(&path)
+                    } else {
+                        fs::remove_file(&path)
+                    };
+                }
+            }
+        }
+        let _ = move_contents(&previous, &app_data);
+        let _ = fs::remove_dir(&previous);
+        let _ = fs::remove_file(app_data.join(format!("{}.restore.tmp", CATALOG_NAME)));
+        config::invalidate(&app);
+        return Err(e.into());
+    }
+
+    config::invalidate(&app);
+    storage::invalidate(&app);
+    tracing::info!("Restored {} files from {}", files, path);
+    Ok(RestoreSummary {
+        files,
+        created_at: manifest.created_at,
+        previous_data_dir: previous.to_string_lossy().to_string(),
+    })
+}
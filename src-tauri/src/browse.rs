@@ -0,0 +1,138 @@
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use tauri::AppHandle;
+
+use crate::error::DrawStackError;
+use crate::{animation, config, natural, scan};
+use crate::{FolderInfo, ImageInfo};
+
+const DEFAULT_PAGE_SIZE: usize = 500;
+const MAX_PAGE_SIZE: usize = 5000;
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BrowseSort {
+    #[default]
+    Name,
+    // Numbers in names compare by value: "img2" before "img10"
+    Natural,
+    // Newest first
+    Mtime,
+    // Largest first
+    Size,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct BrowsePage {
+    pub path: String,
+    // Subfolders come with the first page only
+    pub folders: Vec<FolderInfo>,
+    pub images: Vec<ImageInfo>,
+    pub offset: usize,
+    pub total_images: usize,
+    pub has_more: bool,
+}
+
+pub struct ImageEntry {
+    info: ImageInfo,
+    modified: Option<SystemTime>,
+    size: u64,
+}
+
+impl ImageEntry {
+    pub fn new(path: &Path, meta: &fs::Metadata) -> Self {
+        let frame_count = animation::frame_count(path);
+        Self {
+            info: ImageInfo {
+                path: path.to_string_lossy().to_string(),
+                filename: file_name(path),
+                is_animated: animation::is_animated(frame_count),
+                frame_count,
+            },
+            modified: meta.modified().ok(),
+            size: meta.len(),
+        }
+    }
+}
+
+// Order a listing in place. Folders have no useful size, so they go by name
+// (naturally, if that was asked for) whatever the image order.
+pub fn sort_listing(
+    folders: &mut [FolderInfo],
+    images: &mut [ImageEntry],
+    sort: BrowseSort,
+) -> Vec<ImageInfo> {
+    match sort {
+        BrowseSort::Natural => folders.sort_by(|a, b| natural::compare(&a.name, &b.name)),
+        _ => folders.sort_by_key(|f| f.name.to_lowercase()),
+    }
+    match sort {
+        BrowseSort::Name => images.sort_by_key(|i| i.info.filename.to_lowercase()),
+        BrowseSort::Natural => {
+            images.sort_by(|a, b| natural::compare(&a.info.filename, &b.info.filename))
+        }
+        BrowseSort::Mtime => images.sort_by_key(|i| std::cmp::Reverse(i.modified)),
+        BrowseSort::Size => images.sort_by_key(|i| std::cmp::Reverse(i.size)),
+    }
+    images.iter().map(|i| i.info.clone()).collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Unknown")
+        .to_string()
+}
+
+// One page of a folder listing. The folder is re-read for every page, but
+// only the requested slice is serialized, so a 30k-image folder doesn't go
+// to the webview as one payload.
+#[tauri::command]
+pub async fn browse_folder_page(
+    app: AppHandle,
+    folder_path: String,
+    offset: usize,
+    limit: Option<usize>,
+    sort: Option<BrowseSort>,
+) -> Result<BrowsePage, DrawStackError> {
+    let path = Path::new(&folder_path);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let entries = fs::read_dir(path).map_err(|e| DrawStackError::io("read directory", path, e))?;
+    let by_content = config::load(&app).scan.detect_by_content;
+
+    let mut folders = Vec::new();
+    let mut images = Vec::new();
+    for entry in entries.flatten() {
+        let entry_path = entry.path();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+
+        if meta.is_dir() {
+            if offset == 0 {
+                folders.push(FolderInfo {
+                    path: entry_path.to_string_lossy().to_string(),
+                    name: file_name(&entry_path),
+                    image_count: crate::count_images_shallow(&entry_path, by_content),
+                });
+            }
+        } else if meta.is_file() && scan::is_listed_image(&entry_path, by_content) {
+            images.push(ImageEntry::new(&entry_path, &meta));
+        }
+    }
+
+    let sorted = sort_listing(&mut folders, &mut images, sort.unwrap_or_default());
+    let total_images = sorted.len();
+    let images: Vec<ImageInfo> = sorted.into_iter().skip(offset).take(limit).collect();
+
+    Ok(BrowsePage {
+        path: folder_path,
+        folders,
+        has_more: offset + images.len() < total_images,
+        images,
+        offset,
+        total_images,
+    })
+}
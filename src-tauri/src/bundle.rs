@@ -0,0 +1,400 @@
+use rusqlite::params;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+
+use crate::error::DrawStackError;
+use crate::paths::StoredPath;
+use crate::{
+    animation, archive, catalog, content_hash, dedupe, exif, tags, thumbnails, ThumbnailInfo,
+};
+
+const BUNDLE_FORMAT: &str = "drawstack-bundle";
+const BUNDLE_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+struct BundleManifest {
+    format: String,
+    version: u32,
+    exported_at: i64,
+    pack: BundlePack,
+    images: Vec<BundleImage>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+struct BundlePack {
+    id: String,
+    name: String,
+    created_at: i64,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+struct BundleImage {
+    id: String,
+    filename: String,
+    relative_path: String,
+    // Entry names inside the zip
+    file: String,
+    thumbnail: Option<String>,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+    #[serde(default)]
+    orientation: Option<u16>,
+    #[serde(default)]
+    captured_at: Option<String>,
+    #[serde(default)]
+    rating: u8,
+    #[serde(default)]
+    favorite: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct BundleExport {
+    pub images: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct BundleImport {
+    pub pack_id: String,
+    pub pack_name: String,
+    pub thumbnails: Vec<ThumbnailInfo>,
+}
+
+fn pack_tags(
+    conn: &rusqlite::Connection,
+    pack_id: &str,
+) -> Result<HashMap<String, Vec<String>>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT it.image_id, tg.name FROM image_tags it
+             JOIN tags tg ON tg.id = it.tag_id
+             JOIN images i ON i.id = it.image_id
+             WHERE i.pack_id = ?1 ORDER BY tg.name",
+        )
+        .map_err(|e| format!("Failed to prepare tag query: {}", e))?;
+
+    let rows = stmt
+        .query_map(params![pack_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read pack tags: {}", e))?;
+
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for (image_id, name) in rows {
+        tags.entry(image_id).or_default().push(name);
+    }
+    Ok(tags)
+}
+
+fn add_file<W: Write + io::Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    name: &str,
+    source: &Path,
+) -> Result<(), String> {
+    let mut file = fs::File::open(source)
+        .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+
+    // Images are already compressed, so deflating them only costs time
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .large_file(true);
+    zip.start_file(name, options)
+        .map_err(|e| format!("Failed to add {} to bundle: {}", name, e))?;
+    io::copy(&mut file, zip).map_err(|e| format!("Failed to write {} to bundle: {}", name, e))?;
+    Ok(())
+}
+
+// Write a pack as a zip holding the images (library copies when present),
+// their thumbnails and a manifest with metadata and tags.
+#[tauri::command]
+pub async fn export_pack(
+    app: AppHandle,
+    pack_id: String,
+    dest_path: String,
+) -> Result<BundleExport, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let pack = catalog::get_pack(&conn, &pack_id, None)?
+        .ok_or_else(|| format!("Pack not found: {}", pack_id))?;
+    let mut tags = pack_tags(&conn, &pack_id)?;
+
+    // Write next to the destination and rename at the end, so a failed
+    // export never leaves a truncated bundle behind
+    let dest = PathBuf::from(&dest_path);
+    let partial = dest.with_extension("drawstack.part");
+    let file = fs::File::create(&partial).map_err(|e| format!("Failed to create bundle: {}", e))?;
+    let mut zip = zip::ZipWriter::new(io::BufWriter::new(file));
+
+    let mut used_names = HashSet::new();
+    let mut images = Vec::new();
+
+    let result = (|| {
+        for image in &pack.images {
+            let source = image
+                .library_path
+                .as_ref()
+                .map(StoredPath::to_path_buf)
+                .filter(|p| p.exists())
+                .unwrap_or_else(|| image.original_path.to_path_buf());
+            if !source.exists() {
+                tracing::warn!("Skipping missing image: {}", image.original_path);
+                continue;
+            }
+
+            let folder = image.relative_path.replace('\\', "/");
+            let mut name = if folder.is_empty() {
+                format!("images/{}", image.filename)
+            } else {
+                format!("images/{}/{}", folder, image.filename)
+            };
+            if !used_names.insert(name.clone()) {
+                name = format!("images/{}_{}", image.id, image.filename);
+                used_names.insert(name.clone());
+            }
+            add_file(&mut zip, &name, &source)?;
+
+            let thumbnail = image
+                .thumbnail_path
+                .as_ref()
+                .filter(|p| **p != image.original_path)
+                .map(StoredPath::to_path_buf)
+                .filter(|p| p.exists() && *p != source)
+                .map(|thumb| -> Result<String, String> {
+                    let ext = crate::extension_lower(&thumb).unwrap_or_else(|| "jpg".to_string());
+                    let thumb_name = format!("thumbnails/{}.{}", image.id, ext);
+                    add_file(&mut zip, &thumb_name, &thumb)?;
+                    Ok(thumb_name)
+                })
+                .transpose()?;
+
+            images.push(BundleImage {
+                id: image.id.clone(),
+                filename: image.filename.clone(),
+                relative_path: image.relative_path.clone(),
+                file: name,
+                thumbnail,
+                width: image.width,
+                height: image.height,
+                orientation: image.orientation,
+                captured_at: image.captured_at.clone(),
+                rating: image.rating,
+                favorite: image.favorite,
+                tags: tags.remove(&image.id).unwrap_or_default(),
+            });
+        }
+
+        let manifest = BundleManifest {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            exported_at: catalog::now_unix(),
+            pack: BundlePack {
+                id: pack.id.clone(),
+                name: pack.name.clone(),
+                created_at: pack.created_at,
+            },
+            images,
+        };
+        let manifest_json = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+        zip.start_file(MANIFEST_NAME, SimpleFileOptions::default())
+            .map_err(|e| format!("Failed to add manifest to bundle: {}", e))?;
+        zip.write_all(&manifest_json)
+            .map_err(|e| format!("Failed to write manifest: {}", e))?;
+        zip.finish()
+            .map_err(|e| format!("Failed to finish bundle: {}", e))?
+            .flush()
+            .map_err(|e| format!("Failed to write bundle: {}", e))?;
+
+        Ok(manifest.images.len())
+    })();
+
+    let image_count = match result {
+        Ok(count) => count,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    fs::rename(&partial, &dest).map_err(|e| format!("Failed to save bundle: {}", e))?;
+    let bytes = fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+
+    tracing::info!("Exported {} images to {}", image_count, dest_path);
+    Ok(BundleExport {
+        images: image_count,
+        bytes,
+    })
+}
+
+fn extract_entry<R: Read + io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+    target: &Path,
+) -> Result<(), String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| format!("Bundle entry {} is missing: {}", name, e))?;
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let mut file = fs::File::create(target)
+        .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    io::copy(&mut entry, &mut file).map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+    Ok(())
+}
+
+// Import a bundle written by `export_pack` as a new pack. Images land in
+// app_data/bundles/<pack_id>; bundled thumbnails are reused as-is.
+#[tauri::command]
+pub async fn import_drawstack_bundle(
+    app: AppHandle,
+    bundle_path: String,
+) -> Result<BundleImport, DrawStackError> {
+    let file = fs::File::open(&bundle_path).map_err(|e| format!("Failed to open bundle: {}", e))?;
+    let mut archive =
+        zip::ZipArchive::new(file).map_err(|e| format!("Failed to read bundle: {}", e))?;
+
+    let manifest: BundleManifest = {
+        let entry = archive
+            .by_name(MANIFEST_NAME)
+            .map_err(|_| "Not a DrawStack bundle: manifest.json is missing".to_string())?;
+        serde_json::from_reader(entry).map_err(|e| format!("Invalid bundle manifest: {}", e))?
+    };
+    if manifest.format != BUNDLE_FORMAT || manifest.version > BUNDLE_VERSION {
+        return Err(DrawStackError::Unsupported {
+            path: PathBuf::from(&bundle_path),
+        });
+    }
+
+    let mut conn = catalog::open(&app)?;
+
+    // The manifest's ids aren't trusted: they end up in file paths. The pack
+    // gets a fresh id and images are identified by their extracted bytes.
+    let pack_id = crate::generate_uuid();
+
+    let app_data = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    let dest = app_data.join("bundles").join(&pack_id);
+    let settings = thumbnails::load_settings(&app);
+
+    let mut thumbnails = Vec::new();
+    // Catalog id of each extracted image, for restoring its rating and tags
+    let mut extracted = Vec::new();
+    for image in &manifest.images {
+        let Some(relative) = archive::safe_relative_path(&image.file) else {
+            continue;
+        };
+        let target = dest.join(relative);
+        if let Err(e) = extract_entry(&mut archive, &image.file, &target) {
+            tracing::warn!("Skipping bundle image {}: {}", image.file, e);
+            continue;
+        }
+        let image_id = match content_hash::content_id(&target) {
+            Ok(image_id) => image_id,
+            Err(e) => {
+                tracing::warn!("Skipping bundle image {}: {}", image.file, e);
+                continue;
+            }
+        };
+        let original_path = StoredPath::from(target.as_path());
+
+        let bundled_thumbnail = image.thumbnail.as_deref().and_then(|name| {
+            let ext = crate::extension_lower(Path::new(name))?;
+            let thumb = thumbnails::shard_dir(&app, &image_id)
+                .ok()?
+                .join(format!("{}.{}", image_id, ext));
+            extract_entry(&mut archive, name, &thumb).ok()?;
+            let hash = image::open(&thumb).ok().map(|img| dedupe::dhash(&img));
+            Some((StoredPath::from(thumb.as_path()), hash))
+        });
+
+        let (thumbnail_path, dhash) = match bundled_thumbnail {
+            Some(bundled) => bundled,
+            None => match crate::generate_fast_thumbnail(&target, &app, &image_id, &settings) {
+                Ok(generated) => (generated.path, Some(generated.dhash)),
+                Err(_) => (original_path.clone(), None),
+            },
+        };
+
+        let metadata = exif::read_metadata(&target);
+        extracted.push((image_id.clone(), image));
+        thumbnails.push(ThumbnailInfo {
+            id: image_id,
+            original_path,
+            thumbnail_path,
+            filename: image.filename.clone(),
+            relative_path: image.relative_path.clone(),
+            dhash: dhash.map(dedupe::to_hex),
+            width: image.width,
+            height: image.height,
+            orientation: image.orientation,
+            captured_at: image.captured_at.clone(),
+            is_animated: animation::is_animated(metadata.frame_count),
+            frame_count: metadata.frame_count,
+            duration_ms: metadata.duration_ms,
+        });
+    }
+
+    let duplicates = catalog::record_import(
+        &app,
+        &mut conn,
+        &pack_id,
+        Some(&manifest.pack.name),
+        Some(&bundle_path),
+        &thumbnails,
+    )?;
+    // Images the catalog already had stay where they are
+    thumbnails.retain(|info| {
+        let path = info.original_path.to_string();
+        let duplicate = duplicates.iter().any(|d| d.path == path);
+        if duplicate {
+            let _ = fs::remove_file(info.original_path.to_path_buf());
+        }
+        !duplicate
+    });
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    for (image_id, image) in &extracted {
+        // Left out as a duplicate
+        if !thumbnails.iter().any(|info| info.id == *image_id) {
+            continue;
+        }
+        tx.execute(
+            "UPDATE images SET rating = ?1, favorite = ?2 WHERE id = ?3",
+            params![image.rating.min(5), image.favorite, image_id],
+        )
+        .map_err(|e| format!("Failed to restore rating for {}: {}", image_id, e))?;
+        tags::tag_images(&tx, std::slice::from_ref(image_id), &image.tags)?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    tracing::info!(
+        "Imported bundle {} as pack {} ({} images)",
+        bundle_path,
+        pack_id,
+        thumbnails.len()
+    );
+
+    Ok(BundleImport {
+        pack_id,
+        pack_name: manifest.pack.name,
+        thumbnails,
+    })
+}
@@ -0,0 +1,912 @@
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::config::AppConfig;
+use crate::error::DrawStackError;
+use crate::imports::{FailureKind, ImportFailure};
+use crate::orientation::Aspect;
+use crate::paths::{self, StoredPath};
+use crate::{config, dedupe, manifest, natural, palette, thumbnails, xmp, ThumbnailInfo};
+
+// Each entry upgrades the schema by one version. Never edit an existing
+// entry once released - append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE packs (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        source_path TEXT,
+        created_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE images (
+        id TEXT PRIMARY KEY,
+        pack_id TEXT NOT NULL REFERENCES packs(id) ON DELETE CASCADE,
+        original_path TEXT NOT NULL,
+        library_path TEXT,
+        filename TEXT NOT NULL,
+        relative_path TEXT NOT NULL DEFAULT '',
+        imported_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_images_pack ON images(pack_id);
+
+    CREATE TABLE thumbnails (
+        image_id TEXT PRIMARY KEY REFERENCES images(id) ON DELETE CASCADE,
+        path TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE tags (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE
+    );
+
+    CREATE TABLE image_tags (
+        image_id TEXT NOT NULL REFERENCES images(id) ON DELETE CASCADE,
+        tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+        PRIMARY KEY (image_id, tag_id)
+    );
+    CREATE INDEX idx_image_tags_tag ON image_tags(tag_id);
+"#,
+    // Perceptual hash for near-duplicate detection, as the signed bit
+    // pattern of the u64 dHash
+    "ALTER TABLE images ADD COLUMN dhash INTEGER;",
+    r#"
+    CREATE TABLE thumbnail_cache (
+        source_path TEXT PRIMARY KEY,
+        mtime INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        image_id TEXT NOT NULL,
+        thumbnail_path TEXT NOT NULL,
+        dhash INTEGER,
+        settings TEXT NOT NULL
+    );
+    CREATE INDEX idx_thumbnail_cache_image ON thumbnail_cache(image_id);
+"#,
+    r#"
+    ALTER TABLE images ADD COLUMN width INTEGER;
+    ALTER TABLE images ADD COLUMN height INTEGER;
+    ALTER TABLE images ADD COLUMN orientation INTEGER;
+    ALTER TABLE images ADD COLUMN captured_at TEXT;
+    CREATE INDEX idx_images_captured_at ON images(captured_at);
+"#,
+    r#"
+    ALTER TABLE images ADD COLUMN rating INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE images ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0;
+    CREATE INDEX idx_images_rating ON images(rating);
+"#,
+    // Full-text index over filename, folder and tags, kept in sync by triggers
+    r#"
+    CREATE VIRTUAL TABLE images_fts USING fts5(
+        image_id UNINDEXED,
+        filename,
+        relative_path,
+        tags,
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+
+    INSERT INTO images_fts (image_id, filename, relative_path, tags)
+    SELECT i.id, i.filename, i.relative_path,
+           COALESCE((SELECT group_concat(tg.name, ' ') FROM image_tags it
+                     JOIN tags tg ON tg.id = it.tag_id WHERE it.image_id = i.id), '')
+    FROM images i;
+
+    CREATE TRIGGER images_fts_insert AFTER INSERT ON images BEGIN
+        INSERT INTO images_fts (image_id, filename, relative_path, tags)
+        VALUES (new.id, new.filename, new.relative_path, '');
+    END;
+
+    CREATE TRIGGER images_fts_delete AFTER DELETE ON images BEGIN
+        DELETE FROM images_fts WHERE image_id = old.id;
+    END;
+
+    CREATE TRIGGER images_fts_update AFTER UPDATE OF filename, relative_path ON images BEGIN
+        UPDATE images_fts SET filename = new.filename, relative_path = new.relative_path
+        WHERE image_id = new.id;
+    END;
+
+    CREATE TRIGGER image_tags_fts_insert AFTER INSERT ON image_tags BEGIN
+        UPDATE images_fts SET tags = COALESCE((SELECT group_concat(tg.name, ' ') FROM image_tags it
+            JOIN tags tg ON tg.id = it.tag_id WHERE it.image_id = new.image_id), '')
+        WHERE image_id = new.image_id;
+    END;
+
+    CREATE TRIGGER image_tags_fts_delete AFTER DELETE ON image_tags BEGIN
+        UPDATE images_fts SET tags = COALESCE((SELECT group_concat(tg.name, ' ') FROM image_tags it
+            JOIN tags tg ON tg.id = it.tag_id WHERE it.image_id = old.image_id), '')
+        WHERE image_id = old.image_id;
+    END;
+
+    CREATE TRIGGER tags_fts_rename AFTER UPDATE OF name ON tags BEGIN
+        UPDATE images_fts SET tags = COALESCE((SELECT group_concat(tg.name, ' ') FROM image_tags it
+            JOIN tags tg ON tg.id = it.tag_id WHERE it.image_id = images_fts.image_id), '')
+        WHERE image_id IN (SELECT image_id FROM image_tags WHERE tag_id = new.id);
+    END;
+"#,
+    r#"
+    CREATE TABLE smart_collections (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        rule TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+"#,
+    // Practice history. image_id is deliberately not a foreign key so the
+    // history survives images being removed from the library.
+    r#"
+    CREATE TABLE practice_sessions (
+        id TEXT PRIMARY KEY,
+        pack_id TEXT,
+        started_at INTEGER NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        completed INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_practice_sessions_started ON practice_sessions(started_at);
+
+    CREATE TABLE practice_images (
+        session_id TEXT NOT NULL REFERENCES practice_sessions(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        image_id TEXT NOT NULL,
+        shown_ms INTEGER NOT NULL,
+        skipped INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (session_id, position)
+    );
+    CREATE INDEX idx_practice_images_image ON practice_images(image_id);
+"#,
+    r#"
+    ALTER TABLE images ADD COLUMN last_shown_at INTEGER;
+    ALTER TABLE images ADD COLUMN times_shown INTEGER NOT NULL DEFAULT 0;
+    CREATE INDEX idx_images_last_shown ON images(last_shown_at);
+"#,
+    // Images made from another one by flipping, rotating or cropping
+    r#"
+    ALTER TABLE images ADD COLUMN derived_from TEXT REFERENCES images(id) ON DELETE SET NULL;
+    CREATE INDEX idx_images_derived_from ON images(derived_from);
+"#,
+    r#"
+    CREATE TABLE image_palettes (
+        image_id TEXT NOT NULL REFERENCES images(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        r INTEGER NOT NULL,
+        g INTEGER NOT NULL,
+        b INTEGER NOT NULL,
+        proportion REAL NOT NULL,
+        PRIMARY KEY (image_id, position)
+    );
+"#,
+    // Source file size and mtime (unix seconds) for sorting; unknown for
+    // images imported before this version
+    r#"
+    ALTER TABLE images ADD COLUMN file_size INTEGER;
+    ALTER TABLE images ADD COLUMN modified_at INTEGER;
+"#,
+    // Frames in a GIF or WebP, so the viewer can badge and play animations
+    r#"
+    ALTER TABLE images ADD COLUMN frame_count INTEGER;
+"#,
+    // Clip length of video references, in milliseconds
+    r#"
+    ALTER TABLE images ADD COLUMN duration_ms INTEGER;
+"#,
+    // Portrait/landscape/square and a megapixel bucket (see orientation.rs),
+    // derived from the display dimensions so every insert path fills them in
+    r#"
+    ALTER TABLE images ADD COLUMN aspect TEXT GENERATED ALWAYS AS (
+        CASE
+            WHEN width IS NULL OR height IS NULL OR width = 0 OR height = 0 THEN NULL
+            WHEN abs(width - height) * 20 <= max(width, height) THEN 'square'
+            WHEN width > height THEN 'landscape'
+            ELSE 'portrait'
+        END
+    ) VIRTUAL;
+    ALTER TABLE images ADD COLUMN megapixel_bucket INTEGER GENERATED ALWAYS AS (
+        CASE
+            WHEN width IS NULL OR height IS NULL THEN NULL
+            WHEN width * height < 2000000 THEN 0
+            WHEN width * height < 8000000 THEN 1
+            WHEN width * height < 20000000 THEN 2
+            ELSE 3
+        END
+    ) VIRTUAL;
+    CREATE INDEX idx_images_aspect ON images(aspect, megapixel_bucket);
+"#,
+    // Free-form content label such as "nsfw"; a flagged pack flags all its
+    // images. NULL means unflagged.
+    r#"
+    ALTER TABLE images ADD COLUMN content_flag TEXT;
+    ALTER TABLE packs ADD COLUMN content_flag TEXT;
+"#,
+    // Set when the library watcher sees an image's library copy deleted
+    // outside the app, cleared if it reappears
+    r#"
+    ALTER TABLE images ADD COLUMN missing_since INTEGER;
+"#,
+    // Link an image was downloaded from
+    r#"
+    ALTER TABLE images ADD COLUMN source_url TEXT;
+"#,
+    // Counter the quick-find index polls to learn the names or thumbnails
+    // it holds have changed
+    r#"
+    CREATE TABLE name_index_version (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        version INTEGER NOT NULL
+    );
+    INSERT INTO name_index_version (id, version) VALUES (1, 0);
+
+    CREATE TRIGGER name_index_images_insert AFTER INSERT ON images BEGIN
+        UPDATE name_index_version SET version = version + 1;
+    END;
+    CREATE TRIGGER name_index_images_delete AFTER DELETE ON images BEGIN
+        UPDATE name_index_version SET version = version + 1;
+    END;
+    CREATE TRIGGER name_index_images_update
+    AFTER UPDATE OF filename, relative_path, pack_id ON images BEGIN
+        UPDATE name_index_version SET version = version + 1;
+    END;
+    CREATE TRIGGER name_index_thumbnails_insert AFTER INSERT ON thumbnails BEGIN
+        UPDATE name_index_version SET version = version + 1;
+    END;
+    CREATE TRIGGER name_index_thumbnails_update AFTER UPDATE OF path ON thumbnails BEGIN
+        UPDATE name_index_version SET version = version + 1;
+    END;
+"#,
+    // Time spent on pomodoro breaks; duration_ms leaves it out
+    r#"
+    ALTER TABLE practice_sessions ADD COLUMN break_ms INTEGER NOT NULL DEFAULT 0;
+"#,
+];
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct CatalogImage {
+    pub id: String,
+    pub pack_id: String,
+    pub original_path: StoredPath,
+    pub library_path: Option<StoredPath>,
+    pub thumbnail_path: Option<StoredPath>,
+    pub filename: String,
+    pub relative_path: String,
+    pub imported_at: i64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub orientation: Option<u16>,
+    pub aspect: Option<Aspect>,
+    pub megapixel_bucket: Option<u8>,
+    // The image's own flag, or its pack's
+    pub content_flag: Option<String>,
+    // Unix seconds since the library copy went missing, if it has
+    pub missing_since: Option<i64>,
+    pub source_url: Option<String>,
+    pub captured_at: Option<String>,
+    pub rating: u8,
+    pub favorite: bool,
+    pub derived_from: Option<String>,
+    pub is_animated: bool,
+    pub frame_count: Option<u32>,
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSort {
+    #[default]
+    Name,
+    // Numbers in names compare by value: "img2" before "img10"
+    Natural,
+    // Newest first
+    Mtime,
+    // Largest first
+    Size,
+}
+
+impl ImageSort {
+    pub fn order_by(self) -> &'static str {
+        match self {
+            Self::Name => "i.relative_path COLLATE NOCASE, i.filename COLLATE NOCASE",
+            Self::Natural => "i.relative_path COLLATE NATURAL, i.filename COLLATE NATURAL",
+            Self::Mtime => "i.modified_at DESC NULLS LAST, i.filename COLLATE NATURAL",
+            Self::Size => "i.file_size DESC NULLS LAST, i.filename COLLATE NATURAL",
+        }
+    }
+}
+
+// ORDER BY terms for a list command: the requested sort, or the command's
+// own order when none was asked for
+pub fn order_by(sort: Option<ImageSort>, default: &'static str) -> &'static str {
+    sort.map_or(default, ImageSort::order_by)
+}
+
+impl CatalogImage {
+    // Prefer the library copy; the original may have moved since import
+    pub fn source_path(&self) -> PathBuf {
+        self.library_path
+            .as_ref()
+            .map(StoredPath::to_path_buf)
+            .filter(|p| p.exists())
+            .unwrap_or_else(|| self.original_path.to_path_buf())
+    }
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct PackRecord {
+    pub id: String,
+    pub name: String,
+    pub source_path: Option<String>,
+    pub created_at: i64,
+    pub content_flag: Option<String>,
+    pub images: Vec<CatalogImage>,
+}
+
+pub fn now_unix() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// Open the catalog database in app_data, creating and migrating it as needed.
+// Shared by every connection `open` hands out. SQLite already serializes
+// writers across connections; this only keeps two commands from running the
+// schema migrations at the same time on a fresh catalog.
+#[derive(Default)]
+pub struct CatalogState {
+    migration: Mutex<()>,
+}
+
+pub fn open(app: &AppHandle) -> Result<Connection, String> {
+    let app_data = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+
+    fs::create_dir_all(&app_data).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+
+    let mut conn = Connection::open(app_data.join("catalog.db"))
+        .map_err(|e| format!("Failed to open catalog: {}", e))?;
+    // Take the write lock when a transaction starts. A deferred transaction
+    // that later tries to write while another connection holds the lock
+    // fails with SQLITE_BUSY at once instead of waiting out busy_timeout.
+    conn.set_transaction_behavior(TransactionBehavior::Immediate);
+
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         PRAGMA foreign_keys = ON;
+         PRAGMA busy_timeout = 5000;",
+    )
+    .map_err(|e| format!("Failed to configure catalog: {}", e))?;
+
+    conn.create_collation("NATURAL", natural::compare)
+        .map_err(|e| format!("Failed to configure catalog: {}", e))?;
+
+    let state = app.state::<CatalogState>();
+    let _guard = state.migration.lock().unwrap();
+    migrate(&conn)?;
+    Ok(conn)
+}
+
+// Schema version a fully migrated catalog has, as stored in user_version
+pub fn schema_version() -> usize {
+    MIGRATIONS.len()
+}
+
+// Every column holding an absolute file path, as (table, column)
+const PATH_COLUMNS: &[(&str, &str)] = &[
+    ("packs", "source_path"),
+    ("images", "original_path"),
+    ("images", "library_path"),
+    ("thumbnails", "path"),
+    ("thumbnail_cache", "source_path"),
+    ("thumbnail_cache", "thumbnail_path"),
+];
+
+// Point every stored path inside the folder `old_prefix` at the same place
+// under `new_prefix`. Returns the number of values changed.
+pub fn rewrite_path_prefix(
+    conn: &mut Connection,
+    old_prefix: &str,
+    new_prefix: &str,
+) -> Result<usize, String> {
+    let old_prefix = old_prefix.trim_end_matches(['/', '\\']);
+    let new_prefix = new_prefix.trim_end_matches(['/', '\\']);
+    if old_prefix.is_empty() || old_prefix == new_prefix {
+        return Ok(0);
+    }
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    let mut changed = 0;
+    for (table, column) in PATH_COLUMNS {
+        changed += tx
+            .execute(
+                &format!(
+                    "UPDATE OR IGNORE {table} SET {column} = ?2 || substr({column}, length(?1) + 1)
+                     WHERE {column} = ?1
+                        OR substr({column}, 1, length(?1) + 1) IN (?1 || '/', ?1 || '\\')"
+                ),
+                params![old_prefix, new_prefix],
+            )
+            .map_err(|e| format!("Failed to update {}.{}: {}", table, column, e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+    Ok(changed)
+}
+
+fn migrate(conn: &Connection) -> Result<(), String> {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read catalog version: {}", e))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(&format!(
+            "BEGIN;\n{}\nPRAGMA user_version = {};\nCOMMIT;",
+            migration,
+            index + 1
+        ))
+        .map_err(|e| format!("Failed to migrate catalog to version {}: {}", index + 1, e))?;
+    }
+
+    Ok(())
+}
+
+pub fn map_image(row: &rusqlite::Row) -> rusqlite::Result<CatalogImage> {
+    Ok(CatalogImage {
+        id: row.get("id")?,
+        pack_id: row.get("pack_id")?,
+        original_path: row.get("original_path")?,
+        library_path: row.get("library_path")?,
+        thumbnail_path: row.get("thumbnail_path")?,
+        filename: row.get("filename")?,
+        relative_path: row.get("relative_path")?,
+        imported_at: row.get("imported_at")?,
+        width: row.get("width")?,
+        height: row.get("height")?,
+        orientation: row.get("orientation")?,
+        aspect: row
+            .get::<_, Option<String>>("aspect")?
+            .as_deref()
+            .and_then(Aspect::parse),
+        megapixel_bucket: row.get("megapixel_bucket")?,
+        content_flag: row.get("content_flag")?,
+        missing_since: row.get("missing_since")?,
+        source_url: row.get("source_url")?,
+        captured_at: row.get("captured_at")?,
+        rating: row.get("rating")?,
+        favorite: row.get("favorite")?,
+        derived_from: row.get("derived_from")?,
+        is_animated: crate::animation::is_animated(row.get("frame_count")?),
+        frame_count: row.get("frame_count")?,
+        duration_ms: row.get("duration_ms")?,
+    })
+}
+
+// Column list matching `map_image`, for queries joining images to thumbnails.
+pub const IMAGE_COLUMNS: &str = "i.id, i.pack_id, i.original_path, i.library_path, \
+     t.path AS thumbnail_path, i.filename, i.relative_path, i.imported_at, \
+     i.width, i.height, i.orientation, i.aspect, i.megapixel_bucket, i.captured_at, \
+     i.rating, i.favorite, i.derived_from, i.frame_count, i.duration_ms, i.missing_since, \
+     i.source_url, \
+     COALESCE(i.content_flag, (SELECT p.content_flag FROM packs p WHERE p.id = i.pack_id)) \
+     AS content_flag";
+
+// Image IDs are content hashes, so a file already in the catalog under
+// another path, or in another pack, would take over the existing row. Those
+// are left out and returned. A file whose old path in the same pack is gone
+// counts as moved and is updated.
+pub fn insert_images(
+    conn: &mut Connection,
+    pack_id: &str,
+    pack_name: Option<&str>,
+    source_path: Option<&str>,
+    images: &[ThumbnailInfo],
+) -> Result<Vec<ImportFailure>, String> {
+    let now = now_unix();
+    let mut duplicates = Vec::new();
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+
+    tx.execute(
+        "INSERT OR IGNORE INTO packs (id, name, source_path, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![pack_id, pack_name.unwrap_or(pack_id), source_path, now],
+    )
+    .map_err(|e| format!("Failed to insert pack: {}", e))?;
+
+    {
+        let mut insert_image = tx
+            .prepare(
+                // An upsert rather than INSERT OR REPLACE: replacing deletes the
+                // row, which would cascade away the image's tags on re-import
+                "INSERT INTO images
+                    (id, pack_id, original_path, filename, relative_path, imported_at, dhash,
+                     width, height, orientation, captured_at, file_size, modified_at,
+                     frame_count, duration_ms)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+                 ON CONFLICT(id) DO UPDATE SET
+                    original_path = excluded.original_path,
+                    filename = excluded.filename,
+                    relative_path = excluded.relative_path,
+                    dhash = excluded.dhash,
+                    width = excluded.width,
+                    height = excluded.height,
+                    orientation = excluded.orientation,
+                    captured_at = excluded.captured_at,
+                    file_size = excluded.file_size,
+                    modified_at = excluded.modified_at,
+                    frame_count = excluded.frame_count,
+                    duration_ms = excluded.duration_ms",
+            )
+            .map_err(|e| format!("Failed to prepare image insert: {}", e))?;
+        let mut insert_thumbnail = tx
+            .prepare(
+                "INSERT OR REPLACE INTO thumbnails (image_id, path, created_at) VALUES (?1, ?2, ?3)",
+            )
+            .map_err(|e| format!("Failed to prepare thumbnail insert: {}", e))?;
+
+        let mut existing = tx
+            .prepare("SELECT pack_id, original_path FROM images WHERE id = ?1")
+            .map_err(|e| format!("Failed to prepare image lookup: {}", e))?;
+
+        for image in images {
+            let found: Option<(String, StoredPath)> = existing
+                .query_row(params![image.id], |row| Ok((row.get(0)?, row.get(1)?)))
+                .optional()
+                .map_err(|e| format!("Failed to look up image {}: {}", image.id, e))?;
+            if let Some((existing_pack, existing_path)) = found {
+                let moved = existing_path != image.original_path
+                    && !paths::extended(&existing_path.to_path_buf()).exists();
+                if existing_pack != pack_id || (existing_path != image.original_path && !moved) {
+                    duplicates.push(ImportFailure {
+                        path: image.original_path.to_string(),
+                        reason: format!("Same file as {} in pack {}", existing_path, existing_pack),
+                        kind: FailureKind::Duplicate,
+                    });
+                    continue;
+                }
+            }
+
+            let meta = fs::metadata(paths::extended(&image.original_path.to_path_buf())).ok();
+            let modified_at = meta
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64);
+
+            insert_image
+                .execute(params![
+                    image.id,
+                    pack_id,
+                    image.original_path,
+                    image.filename,
+                    image.relative_path,
+                    now,
+                    image
+                        .dhash
+                        .as_deref()
+                        .and_then(dedupe::from_hex)
+                        .map(|hash| hash as i64),
+                    image.width,
+                    image.height,
+                    image.orientation,
+                    image.captured_at,
+                    meta.as_ref().map(|m| m.len() as i64),
+                    modified_at,
+                    image.frame_count,
+                    image.duration_ms
+                ])
+                .map_err(|e| format!("Failed to insert image {}: {}", image.id, e))?;
+
+            insert_thumbnail
+                .execute(params![image.id, image.thumbnail_path, now])
+                .map_err(|e| format!("Failed to insert thumbnail {}: {}", image.id, e))?;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    if !duplicates.is_empty() {
+        tracing::info!(
+            "Left {} duplicate images out of pack {}",
+            duplicates.len(),
+            pack_id
+        );
+    }
+    Ok(duplicates)
+}
+
+// The optional import steps the settings turn on. Their failures only cost
+// a warning; the images are already in the catalog.
+fn run_import_hooks(
+    conn: &mut Connection,
+    config: &AppConfig,
+    pack_id: &str,
+    images: &[ThumbnailInfo],
+) {
+    if config.extract_palettes {
+        if let Err(e) = palette::store_for_thumbnails(conn, images) {
+            tracing::warn!("Failed to extract palettes for pack {}: {}", pack_id, e);
+        }
+    }
+    if config.read_xmp_sidecars {
+        if let Err(e) = xmp::apply_sidecars(conn, images) {
+            tracing::warn!("Failed to read XMP sidecars for pack {}: {}", pack_id, e);
+        }
+    }
+}
+
+// `insert_images` followed by the import hooks (palettes, XMP sidecars) and
+// the high-quality thumbnail upgrades, which need the rows to exist. Every
+// import path records its images through here. Returns the files left out
+// as duplicates.
+pub fn record_import(
+    app: &AppHandle,
+    conn: &mut Connection,
+    pack_id: &str,
+    pack_name: Option<&str>,
+    source_path: Option<&str>,
+    images: &[ThumbnailInfo],
+) -> Result<Vec<ImportFailure>, String> {
+    let duplicates = insert_images(conn, pack_id, pack_name, source_path, images)?;
+    let recorded: Vec<ThumbnailInfo> = images
+        .iter()
+        .filter(|image| {
+            !duplicates
+                .iter()
+                .any(|d| d.path == image.original_path.to_string())
+        })
+        .cloned()
+        .collect();
+    run_import_hooks(conn, &config::load(app), pack_id, &recorded);
+    thumbnails::queue_upgrades(app, &recorded);
+    Ok(duplicates)
+}
+
+pub fn get_image(conn: &Connection, image_id: &str) -> Result<CatalogImage, DrawStackError> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id WHERE i.id = ?1",
+            IMAGE_COLUMNS
+        ),
+        params![image_id],
+        map_image,
+    )
+    .optional()?
+    .ok_or_else(|| DrawStackError::invalid(format!("Image not found: {}", image_id)))
+}
+
+pub fn get_pack(
+    conn: &Connection,
+    pack_id: &str,
+    sort: Option<ImageSort>,
+) -> Result<Option<PackRecord>, String> {
+    let pack = conn
+        .query_row(
+            "SELECT id, name, source_path, created_at, content_flag FROM packs WHERE id = ?1",
+            params![pack_id],
+            |row| {
+                Ok(PackRecord {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    source_path: row.get(2)?,
+                    created_at: row.get(3)?,
+                    content_flag: row.get(4)?,
+                    images: Vec::new(),
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read pack: {}", e))?;
+
+    let Some(mut pack) = pack else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id
+             WHERE i.pack_id = ?1 ORDER BY {}",
+            IMAGE_COLUMNS,
+            order_by(sort, "i.relative_path, i.filename")
+        ))
+        .map_err(|e| format!("Failed to prepare pack query: {}", e))?;
+
+    pack.images = stmt
+        .query_map(params![pack_id], map_image)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read pack images: {}", e))?;
+
+    Ok(Some(pack))
+}
+
+#[tauri::command]
+pub async fn catalog_insert_images(
+    app: AppHandle,
+    pack_id: String,
+    pack_name: Option<String>,
+    source_path: Option<String>,
+    images: Vec<ThumbnailInfo>,
+) -> Result<usize, DrawStackError> {
+    let mut conn = open(&app)?;
+    let duplicates = record_import(
+        &app,
+        &mut conn,
+        &pack_id,
+        pack_name.as_deref(),
+        source_path.as_deref(),
+        &images,
+    )?;
+    manifest::refresh(&app, [pack_id.as_str()]);
+    Ok(images.len() - duplicates.len())
+}
+
+#[tauri::command]
+pub async fn catalog_get_pack(
+    app: AppHandle,
+    pack_id: String,
+    sort: Option<ImageSort>,
+) -> Result<Option<PackRecord>, DrawStackError> {
+    let conn = open(&app)?;
+    Ok(get_pack(&conn, &pack_id, sort)?)
+}
+
+#[tauri::command]
+pub async fn catalog_delete_pack(app: AppHandle, pack_id: String) -> Result<usize, DrawStackError> {
+    let conn = open(&app)?;
+
+    let image_count: usize = conn
+        .query_row(
+            "SELECT COUNT(*) FROM images WHERE pack_id = ?1",
+            params![pack_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count pack images: {}", e))?;
+
+    // Images, thumbnails and tag links cascade from the pack row
+    conn.execute("DELETE FROM packs WHERE id = ?1", params![pack_id])
+        .map_err(|e| format!("Failed to delete pack: {}", e))?;
+    manifest::refresh(&app, [pack_id.as_str()]);
+
+    Ok(image_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::testing;
+    use std::path::Path;
+
+    fn test_catalog() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+        conn.create_collation("NATURAL", natural::compare).unwrap();
+        migrate(&conn).unwrap();
+        conn
+    }
+
+    fn image_info(id: &str, path: &Path) -> ThumbnailInfo {
+        ThumbnailInfo {
+            id: id.to_string(),
+            original_path: StoredPath::from(path),
+            thumbnail_path: StoredPath::from(path.with_extension("thumb.webp").as_path()),
+            filename: path.file_name().unwrap().to_string_lossy().to_string(),
+            relative_path: String::new(),
+            dhash: None,
+            width: None,
+            height: None,
+            orientation: None,
+            captured_at: None,
+            is_animated: false,
+            frame_count: None,
+            duration_ms: None,
+        }
+    }
+
+    fn pack_of(conn: &Connection, image_id: &str) -> String {
+        conn.query_row(
+            "SELECT pack_id FROM images WHERE id = ?1",
+            params![image_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn same_file_in_two_packs_is_reported_as_duplicate() {
+        let root = testing::scratch_dir("import-duplicates");
+        let image = root.join("pose.png");
+        testing::write_png(&image);
+        let mut conn = test_catalog();
+
+        let first = [image_info("pose", &image)];
+        assert!(insert_images(&mut conn, "first", None, None, &first)
+            .unwrap()
+            .is_empty());
+
+        // Re-importing into the same pack updates the row in place
+        assert!(insert_images(&mut conn, "first", None, None, &first)
+            .unwrap()
+            .is_empty());
+
+        let duplicates = insert_images(&mut conn, "second", None, None, &first).unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].kind, FailureKind::Duplicate);
+        assert_eq!(pack_of(&conn, "pose"), "first");
+
+        // A byte-identical copy in the same pack doesn't replace the original
+        let copy = root.join("pose copy.png");
+        fs::copy(&image, &copy).unwrap();
+        let duplicates =
+            insert_images(&mut conn, "first", None, None, &[image_info("pose", &copy)]).unwrap();
+        assert_eq!(duplicates.len(), 1);
+
+        // Once the original is gone the copy counts as the file moving
+        fs::remove_file(&image).unwrap();
+        assert!(
+            insert_images(&mut conn, "first", None, None, &[image_info("pose", &copy)])
+                .unwrap()
+                .is_empty()
+        );
+        let stored: StoredPath = conn
+            .query_row(
+                "SELECT original_path FROM images WHERE id = 'pose'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(stored.to_path_buf(), copy);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn import_applies_xmp_sidecars() {
+        let root = testing::scratch_dir("import-xmp");
+        let image = root.join("pose.png");
+        testing::write_png(&image);
+        fs::write(
+            root.join("pose.xmp"),
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/" xmp:Rating="4">
+   <dc:subject><rdf:Bag><rdf:li>gesture</rdf:li></rdf:Bag></dc:subject>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#,
+        )
+        .unwrap();
+
+        let mut conn = test_catalog();
+        let images = [image_info("pose", &image)];
+        insert_images(&mut conn, "pack", None, None, &images).unwrap();
+        let config = AppConfig {
+            read_xmp_sidecars: true,
+            ..AppConfig::default()
+        };
+        run_import_hooks(&mut conn, &config, "pack", &images);
+
+        let rating: Option<u8> = conn
+            .query_row("SELECT rating FROM images WHERE id = 'pose'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(rating, Some(4));
+        let tag: String = conn
+            .query_row(
+                "SELECT tg.name FROM image_tags it JOIN tags tg ON tg.id = it.tag_id
+                 WHERE it.image_id = 'pose'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tag, "gesture");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
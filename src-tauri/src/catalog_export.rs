@@ -0,0 +1,466 @@
+// Catalog metadata as CSV or JSON, for spreadsheets and other asset
+// managers. Exports list every image; imports merge tags, ratings and
+// favorites back onto images matched by ID or original path.
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::catalog::{self, CatalogImage};
+use crate::error::DrawStackError;
+use crate::tags;
+
+const CATALOG_FORMAT: &str = "drawstack-catalog";
+const CATALOG_VERSION: u32 = 1;
+// Separates tags inside the CSV `tags` column
+const TAG_SEPARATOR: char = ';';
+const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "pack_id",
+    "pack_name",
+    "filename",
+    "relative_path",
+    "original_path",
+    "library_path",
+    "width",
+    "height",
+    "rating",
+    "favorite",
+    "tags",
+    "source_url",
+    "imported_at",
+];
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CatalogFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct CatalogFile {
+    format: &'static str,
+    version: u32,
+    exported_at: i64,
+    images: Vec<CatalogRecord>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct CatalogRecord {
+    id: String,
+    pack_id: String,
+    pack_name: String,
+    filename: String,
+    relative_path: String,
+    original_path: String,
+    library_path: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    rating: u8,
+    favorite: bool,
+    tags: Vec<String>,
+    source_url: Option<String>,
+    imported_at: i64,
+}
+
+impl CatalogRecord {
+    fn csv_row(&self) -> Vec<String> {
+        let number = |n: Option<u32>| n.map(|n| n.to_string()).unwrap_or_default();
+        vec![
+            self.id.clone(),
+            self.pack_id.clone(),
+            self.pack_name.clone(),
+            self.filename.clone(),
+            self.relative_path.clone(),
+            self.original_path.clone(),
+            self.library_path.clone().unwrap_or_default(),
+            number(self.width),
+            number(self.height),
+            self.rating.to_string(),
+            self.favorite.to_string(),
+            self.tags.join(&format!("{} ", TAG_SEPARATOR)),
+            self.source_url.clone().unwrap_or_default(),
+            self.imported_at.to_string(),
+        ]
+    }
+}
+
+// One image's metadata as read back from a file. Only `id` or
+// `original_path` is needed to find the image; missing fields are left alone.
+#[derive(Debug, serde::Deserialize, Default)]
+struct MetadataRow {
+    id: Option<String>,
+    original_path: Option<String>,
+    rating: Option<u8>,
+    favorite: Option<bool>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+// Exported envelope, or a bare array of rows
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum MetadataFile {
+    Catalog { images: Vec<MetadataRow> },
+    Rows(Vec<MetadataRow>),
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct CatalogExport {
+    pub images: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct MetadataImport {
+    pub matched: usize,
+    pub unmatched: usize,
+    pub tags_added: usize,
+    pub ratings_updated: usize,
+    pub favorites_updated: usize,
+    // Rows that couldn't be read, e.g. a rating of 7
+    pub invalid: usize,
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_line(fields: &[String]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|f| csv_field(f))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+// RFC 4180 records: quoted fields may hold commas, quotes and line breaks
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("Unterminated quoted field in CSV".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    // Blank lines carry nothing
+    records.retain(|r| !(r.len() == 1 && r[0].trim().is_empty()));
+    Ok(records)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Some(true),
+        "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+// Rows from a CSV with a header line. Columns are matched by name, so files
+// edited in a spreadsheet may reorder or drop them. Returns the rows and the
+// number of lines with unreadable values.
+fn csv_rows(text: &str) -> Result<(Vec<MetadataRow>, usize), String> {
+    let mut records = parse_csv(text)?.into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or("The CSV file is empty")?
+        .iter()
+        .map(|h| h.trim().to_ascii_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let (id, path) = (column("id"), column("original_path"));
+    if id.is_none() && path.is_none() {
+        return Err("The CSV needs an id or original_path column".to_string());
+    }
+    let (rating, favorite, tag_list) = (column("rating"), column("favorite"), column("tags"));
+
+    let mut rows = Vec::new();
+    let mut invalid = 0;
+    for record in records {
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+        };
+        let rating = match cell(rating).map(str::parse::<u8>) {
+            Some(Ok(value)) => Some(value),
+            Some(Err(_)) => {
+                invalid += 1;
+                continue;
+            }
+            None => None,
+        };
+        let favorite = match cell(favorite).map(parse_bool) {
+            Some(Some(value)) => Some(value),
+            Some(None) => {
+                invalid += 1;
+                continue;
+            }
+            None => None,
+        };
+        rows.push(MetadataRow {
+            id: cell(id).map(str::to_string),
+            original_path: cell(path).map(str::to_string),
+            rating,
+            favorite,
+            tags: cell(tag_list)
+                .map(|t| t.split(TAG_SEPARATOR).map(str::to_string).collect())
+                .unwrap_or_default(),
+        });
+    }
+    Ok((rows, invalid))
+}
+
+fn image_tags(conn: &Connection) -> Result<HashMap<String, Vec<String>>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT it.image_id, tg.name FROM image_tags it
+             JOIN tags tg ON tg.id = it.tag_id ORDER BY tg.name",
+        )
+        .map_err(|e| format!("Failed to prepare tag query: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read tags: {}", e))?;
+
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for (image_id, name) in rows {
+        tags.entry(image_id).or_default().push(name);
+    }
+    Ok(tags)
+}
+
+fn pack_names(conn: &Connection) -> Result<HashMap<String, String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name FROM packs")
+        .map_err(|e| format!("Failed to prepare pack query: {}", e))?;
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect::<rusqlite::Result<HashMap<_, _>>>())
+        .map_err(|e| format!("Failed to read packs: {}", e))
+}
+
+fn all_images(conn: &Connection) -> Result<Vec<CatalogImage>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id
+             ORDER BY i.pack_id, i.relative_path, i.filename",
+            catalog::IMAGE_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+    stmt.query_map([], catalog::map_image)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read images: {}", e))
+}
+
+// Write every catalog image, with its pack, tags and rating, to `dest` as
+// CSV (tags joined by `;`) or JSON.
+#[tauri::command]
+pub async fn export_catalog(
+    app: AppHandle,
+    format: CatalogFormat,
+    dest: String,
+) -> Result<CatalogExport, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let mut tags = image_tags(&conn)?;
+    let packs = pack_names(&conn)?;
+
+    let records: Vec<CatalogRecord> = all_images(&conn)?
+        .into_iter()
+        .map(|image| CatalogRecord {
+            pack_name: packs.get(&image.pack_id).cloned().unwrap_or_default(),
+            tags: tags.remove(&image.id).unwrap_or_default(),
+            id: image.id,
+            pack_id: image.pack_id,
+            filename: image.filename,
+            relative_path: image.relative_path,
+            original_path: image.original_path.to_string(),
+            library_path: image.library_path.map(|p| p.to_string()),
+            width: image.width,
+            height: image.height,
+            rating: image.rating,
+            favorite: image.favorite,
+            source_url: image.source_url,
+            imported_at: image.imported_at,
+        })
+        .collect();
+    let count = records.len();
+
+    let contents = match format {
+        CatalogFormat::Csv => {
+            let header: Vec<String> = CSV_COLUMNS.iter().map(|c| c.to_string()).collect();
+            let mut csv = csv_line(&header);
+            for record in &records {
+                csv.push_str(&csv_line(&record.csv_row()));
+            }
+            csv.into_bytes()
+        }
+        CatalogFormat::Json => serde_json::to_vec_pretty(&CatalogFile {
+            format: CATALOG_FORMAT,
+            version: CATALOG_VERSION,
+            exported_at: catalog::now_unix(),
+            images: records,
+        })
+        .map_err(|e| format!("Failed to serialize catalog: {}", e))?,
+    };
+
+    let dest = Path::new(&dest);
+    crate::write_atomic(dest, &contents)
+        .map_err(|e| DrawStackError::io("write catalog export", dest, e))?;
+
+    tracing::info!("Exported {} catalog entries to {}", count, dest.display());
+    Ok(CatalogExport {
+        images: count,
+        bytes: contents.len() as u64,
+    })
+}
+
+// Merge tags, ratings and favorites from a CSV or JSON file (as written by
+// `export_catalog`, or edited elsewhere) into the catalog. Tags are only
+// added, never removed. Images are matched by ID, then by original path.
+#[tauri::command]
+pub async fn import_catalog_metadata(
+    app: AppHandle,
+    path: String,
+) -> Result<MetadataImport, DrawStackError> {
+    let source = Path::new(&path);
+    let text = fs::read_to_string(source)
+        .map_err(|e| DrawStackError::io("read catalog metadata", source, e))?;
+
+    let is_json = crate::extension_lower(source).as_deref() == Some("json")
+        || text.trim_start().starts_with(['{', '[']);
+    let (rows, invalid) = if is_json {
+        let file: MetadataFile = serde_json::from_str(&text)
+            .map_err(|e| DrawStackError::invalid(format!("Invalid catalog JSON: {}", e)))?;
+        let rows = match file {
+            MetadataFile::Catalog { images } => images,
+            MetadataFile::Rows(rows) => rows,
+        };
+        (rows, 0)
+    } else {
+        csv_rows(&text).map_err(DrawStackError::invalid)?
+    };
+
+    let mut conn = catalog::open(&app)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+
+    let mut report = MetadataImport {
+        invalid,
+        ..MetadataImport::default()
+    };
+    for row in rows {
+        if row.rating.is_some_and(|r| r > 5) {
+            report.invalid += 1;
+            continue;
+        }
+
+        let by_id = row
+            .id
+            .as_deref()
+            .map(|id| {
+                tx.query_row("SELECT id FROM images WHERE id = ?1", params![id], |r| {
+                    r.get::<_, String>(0)
+                })
+                .optional()
+            })
+            .transpose()
+            .map_err(|e| format!("Failed to look up image: {}", e))?
+            .flatten();
+        let image_id = match by_id {
+            Some(id) => Some(id),
+            None => row
+                .original_path
+                .as_deref()
+                .map(|path| {
+                    tx.query_row(
+                        "SELECT id FROM images WHERE original_path = ?1 LIMIT 1",
+                        params![path],
+                        |r| r.get::<_, String>(0),
+                    )
+                    .optional()
+                })
+                .transpose()
+                .map_err(|e| format!("Failed to look up image: {}", e))?
+                .flatten(),
+        };
+        let Some(image_id) = image_id else {
+            report.unmatched += 1;
+            continue;
+        };
+        report.matched += 1;
+
+        if let Some(rating) = row.rating {
+            report.ratings_updated += tx
+                .execute(
+                    "UPDATE images SET rating = ?1 WHERE id = ?2 AND rating != ?1",
+                    params![rating, image_id],
+                )
+                .map_err(|e| format!("Failed to set rating: {}", e))?;
+        }
+        if let Some(favorite) = row.favorite {
+            report.favorites_updated += tx
+                .execute(
+                    "UPDATE images SET favorite = ?1 WHERE id = ?2 AND favorite != ?1",
+                    params![favorite, image_id],
+                )
+                .map_err(|e| format!("Failed to set favorite: {}", e))?;
+        }
+        if !row.tags.is_empty() {
+            report.tags_added += tags::tag_images(&tx, &[image_id], &row.tags)?;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    tracing::info!(
+        "Merged catalog metadata from {}: {} matched, {} unmatched, {} invalid",
+        path,
+        report.matched,
+        report.unmatched,
+        report.invalid
+    );
+    Ok(report)
+}
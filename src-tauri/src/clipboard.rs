@@ -0,0 +1,77 @@
+use arboard::{Clipboard, ImageData};
+use std::borrow::Cow;
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+
+use crate::catalog;
+use crate::error::DrawStackError;
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardContent {
+    // Decoded pixels, for pasting straight into a canvas
+    #[default]
+    Image,
+    // The original's path as text
+    Path,
+    // The file itself, as a file manager copy would put it
+    File,
+}
+
+// On X11 the clipboard is served by whoever set it, so one handle is kept
+// for the app's lifetime instead of being dropped after each copy.
+#[derive(Default)]
+pub struct ClipboardState {
+    clipboard: Mutex<Option<Clipboard>>,
+}
+
+fn clipboard_error(e: arboard::Error) -> DrawStackError {
+    DrawStackError::Other(format!("Failed to access the clipboard: {}", e))
+}
+
+#[tauri::command]
+pub async fn copy_image_to_clipboard(
+    app: AppHandle,
+    state: State<'_, ClipboardState>,
+    image_id: String,
+    content: Option<ClipboardContent>,
+) -> Result<(), DrawStackError> {
+    let image = catalog::get_image(&catalog::open(&app)?, &image_id)?;
+    let path = image.source_path().to_path_buf();
+    if !path.exists() {
+        return Err(DrawStackError::not_found(&path));
+    }
+
+    // Decode before taking the lock so a large image doesn't hold up other copies
+    let content = content.unwrap_or_default();
+    let pixels = match content {
+        ClipboardContent::Image => {
+            let rgba = crate::decode_image(&path)?.to_rgba8();
+            Some(ImageData {
+                width: rgba.width() as usize,
+                height: rgba.height() as usize,
+                bytes: Cow::Owned(rgba.into_raw()),
+            })
+        }
+        _ => None,
+    };
+
+    let mut guard = state.clipboard.lock().unwrap();
+    let clipboard = match guard.as_mut() {
+        Some(clipboard) => clipboard,
+        None => guard.insert(Clipboard::new().map_err(clipboard_error)?),
+    };
+
+    if let Some(pixels) = pixels {
+        return clipboard.set_image(pixels).map_err(clipboard_error);
+    }
+    if content == ClipboardContent::Path {
+        return clipboard
+            .set_text(path.to_string_lossy())
+            .map_err(clipboard_error);
+    }
+    match clipboard.set().file_list(&[&path]) {
+        Err(arboard::Error::ClipboardNotSupported) => Err(DrawStackError::Unsupported { path }),
+        result => result.map_err(clipboard_error),
+    }
+}
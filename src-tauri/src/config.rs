@@ -0,0 +1,267 @@
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tauri::{AppHandle, Manager};
+
+use crate::audio_cues::AudioCues;
+use crate::error::DrawStackError;
+use crate::logging::LogLevel;
+use crate::maintenance::MaintenanceSettings;
+use crate::quota::StorageQuota;
+use crate::roots::LibraryRoot;
+use crate::scan::ScanOptions;
+use crate::session_presets::SessionPreset;
+use crate::thumbnails::ThumbnailSettings;
+
+const CONFIG_FILE: &str = "config.json";
+
+// Bump when a setting changes shape and add a step to `migrate`.
+pub const CONFIG_VERSION: u32 = 2;
+
+// The parsed config, shared by every command. Readers run concurrently off
+// the cached copy; `update` holds the write lock across its whole
+// read-modify-write cycle, so two commands changing different settings at
+// once can't drop each other's change.
+#[derive(Default)]
+pub struct ConfigState {
+    cached: RwLock<Option<AppConfig>>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(default)]
+pub struct AppConfig {
+    pub version: u32,
+    // Library locations; empty means the default folder in Documents
+    pub library_roots: Vec<LibraryRoot>,
+    pub thumbnail_settings: ThumbnailSettings,
+    pub theme: Option<String>,
+    // Extra folders the file commands may touch besides app data and the
+    // library, e.g. import sources the user picked
+    pub allowed_roots: Vec<String>,
+    // Store each image's dominant colors as it's added to the catalog, for
+    // palette search
+    pub extract_palettes: bool,
+    // Take ratings and keywords from `.xmp` sidecars next to the originals
+    // as images are added to the catalog
+    pub read_xmp_sidecars: bool,
+    // Symlink, depth and ignore rules for folder scans
+    pub scan: ScanOptions,
+    // Library size limit and eviction policy
+    pub storage_quota: StorageQuota,
+    // Verbosity of the log file in app_data/logs
+    pub log_level: LogLevel,
+    // Schedule and tasks of the background maintenance pass
+    pub maintenance: MaintenanceSettings,
+    // Keep import timings in memory for `get_performance_metrics`
+    pub performance_metrics: bool,
+    // Saved class schedules; the standard presets are built in, not stored
+    pub session_presets: Vec<SessionPreset>,
+    // Sounds the session timer plays at the end of each interval
+    pub audio_cues: AudioCues,
+    // Keys this build doesn't know about - written by the frontend or a newer
+    // version - are carried through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            library_roots: Vec::new(),
+            thumbnail_settings: ThumbnailSettings::default(),
+            theme: None,
+            allowed_roots: Vec::new(),
+            extract_palettes: false,
+            read_xmp_sidecars: false,
+            scan: ScanOptions::default(),
+            storage_quota: StorageQuota::default(),
+            log_level: LogLevel::default(),
+            maintenance: MaintenanceSettings::default(),
+            performance_metrics: false,
+            session_presets: Vec::new(),
+            audio_cues: AudioCues::default(),
+            extra: Map::new(),
+        }
+    }
+}
+
+fn config_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(app_dir.join(CONFIG_FILE))
+}
+
+// Upgrade an older config in place. Files written before versioning have no
+// `version` key; version 1 only introduced that key. Version 2 replaced the
+// single `library_path` with a list of library roots.
+fn migrate(config: &mut Map<String, Value>) {
+    let version = config.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version < 2 {
+        if let Some(Value::String(path)) = config.remove("library_path") {
+            config.insert(
+                "library_roots".to_string(),
+                serde_json::json!([{ "id": "primary", "label": "Library", "path": path, "priority": 0 }]),
+            );
+        }
+    }
+    config.insert("version".to_string(), Value::from(CONFIG_VERSION));
+}
+
+fn parse(contents: &str) -> Result<AppConfig, String> {
+    let mut raw: Map<String, Value> =
+        serde_json::from_str(contents).map_err(|e| format!("Invalid config: {}", e))?;
+    migrate(&mut raw);
+    serde_json::from_value(Value::Object(raw)).map_err(|e| format!("Invalid config: {}", e))
+}
+
+fn load_unlocked(app: &AppHandle) -> Result<AppConfig, String> {
+    let path = config_path(app)?;
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Ok(AppConfig::default());
+    };
+
+    Ok(parse(&contents).unwrap_or_else(|e| {
+        // Keep the unreadable file around rather than silently overwriting
+        // it on the next save
+        tracing::warn!("{} - using defaults", e);
+        let _ = fs::copy(&path, path.with_extension("json.corrupt"));
+        AppConfig::default()
+    }))
+}
+
+// Written atomically, so a crash mid-write leaves either the old or the new
+// config, never a truncated one.
+fn save_unlocked(app: &AppHandle, config: &AppConfig) -> Result<(), String> {
+    let path = config_path(app)?;
+    if let Some(app_dir) = path.parent() {
+        fs::create_dir_all(app_dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    }
+
+    let contents = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    crate::write_atomic(&path, contents.as_bytes())
+        .map_err(|e| format!("Failed to save config: {}", e))
+}
+
+pub fn load(app: &AppHandle) -> AppConfig {
+    let state = app.state::<ConfigState>();
+    if let Some(config) = state.cached.read().unwrap().as_ref() {
+        return config.clone();
+    }
+
+    let mut cached = state.cached.write().unwrap();
+    // Another thread may have filled the cache while we waited
+    if let Some(config) = cached.as_ref() {
+        return config.clone();
+    }
+    let config = load_unlocked(app).unwrap_or_default();
+    *cached = Some(config.clone());
+    config
+}
+
+// Drop the cached config after config.json is replaced on disk, e.g. by a
+// backup restore, so the next `load` reads the new file.
+pub fn invalidate(app: &AppHandle) {
+    *app.state::<ConfigState>().cached.write().unwrap() = None;
+}
+
+// Apply `change` to the current config and persist it atomically. The cache
+// only changes once the file is written, so a failed save leaves both as
+// they were.
+pub fn update<F>(app: &AppHandle, change: F) -> Result<AppConfig, DrawStackError>
+where
+    F: FnOnce(&mut AppConfig) -> Result<(), DrawStackError>,
+{
+    let state = app.state::<ConfigState>();
+    let mut cached = state.cached.write().unwrap();
+    let mut config = match cached.as_ref() {
+        Some(config) => config.clone(),
+        None => load_unlocked(app)?,
+    };
+    change(&mut config)?;
+    config.version = CONFIG_VERSION;
+    save_unlocked(app, &config)?;
+    *cached = Some(config.clone());
+    Ok(config)
+}
+
+// JSON merge patch (RFC 7386): objects merge recursively, null removes a key.
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        return;
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_config(app: AppHandle) -> AppConfig {
+    load(&app)
+}
+
+// Keys that widen what the file commands may touch. They change only
+// through the root commands, which insist on a dialog-picked folder.
+const PROTECTED_KEYS: &[&str] = &["allowed_roots", "library_roots", "library_path"];
+
+// Merge `patch` into the stored config and return the result. Known settings
+// are type-checked, so a bad value is rejected instead of being saved.
+#[tauri::command]
+pub fn update_config(app: AppHandle, patch: Value) -> Result<AppConfig, DrawStackError> {
+    let Some(keys) = patch.as_object() else {
+        return Err(DrawStackError::invalid("Config update must be an object"));
+    };
+    if let Some(key) = PROTECTED_KEYS.iter().find(|key| keys.contains_key(**key)) {
+        return Err(DrawStackError::invalid(format!(
+            "{} can't be changed with update_config",
+            key
+        )));
+    }
+
+    update(&app, |config| {
+        let mut merged =
+            serde_json::to_value(&*config).map_err(|e| format!("Failed to read config: {}", e))?;
+        merge_patch(&mut merged, patch);
+
+        let updated: AppConfig = serde_json::from_value(merged)
+            .map_err(|e| DrawStackError::invalid(format!("Invalid config value: {}", e)))?;
+        updated
+            .thumbnail_settings
+            .validate()
+            .map_err(DrawStackError::invalid)?;
+        updated.scan.validate().map_err(DrawStackError::invalid)?;
+        updated
+            .storage_quota
+            .validate()
+            .map_err(DrawStackError::invalid)?;
+        updated
+            .maintenance
+            .validate()
+            .map_err(DrawStackError::invalid)?;
+        crate::session_presets::validate_all(&updated.session_presets)
+            .map_err(DrawStackError::invalid)?;
+        updated
+            .audio_cues
+            .validate()
+            .map_err(DrawStackError::invalid)?;
+
+        *config = updated;
+        Ok(())
+    })
+}
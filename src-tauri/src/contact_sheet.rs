@@ -0,0 +1,347 @@
+// Printable overview of one or more packs: a grid of thumbnails with their
+// filenames, laid out with pdfium and saved as a PDF or rendered to PNG pages.
+use image::DynamicImage;
+use pdfium_render::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+use crate::catalog::{self, CatalogImage};
+use crate::error::DrawStackError;
+use crate::{pdf, variants};
+
+// PDF coordinates are in points
+const POINTS_PER_INCH: f32 = 72.0;
+const MARGIN: f32 = 36.0;
+const HEADER_HEIGHT: f32 = 28.0;
+const TITLE_SIZE: f32 = 14.0;
+const CAPTION_SIZE: f32 = 7.0;
+const CAPTION_HEIGHT: f32 = 12.0;
+const CELL_PADDING: f32 = 4.0;
+// Embedded thumbnails are downscaled to this resolution at their printed size
+const IMAGE_DPI: f32 = 200.0;
+// Size for decoding images that have no thumbnail on disk
+const FALLBACK_SIZE: u32 = 512;
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SheetFormat {
+    #[default]
+    Pdf,
+    Png,
+}
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Paper {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl Paper {
+    // Portrait width and height in points
+    fn size(self) -> (f32, f32) {
+        match self {
+            Self::A4 => (595.0, 842.0),
+            Self::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+#[serde(default)]
+pub struct SheetLayout {
+    pub columns: u32,
+    pub rows: u32,
+    pub paper: Paper,
+    pub landscape: bool,
+    pub format: SheetFormat,
+    // Resolution of PNG pages
+    pub dpi: u32,
+    pub captions: bool,
+}
+
+impl Default for SheetLayout {
+    fn default() -> Self {
+        Self {
+            columns: 5,
+            rows: 6,
+            paper: Paper::A4,
+            landscape: false,
+            format: SheetFormat::Pdf,
+            dpi: 150,
+            captions: true,
+        }
+    }
+}
+
+impl SheetLayout {
+    fn validate(&self) -> Result<(), DrawStackError> {
+        if !(1..=12).contains(&self.columns) || !(1..=16).contains(&self.rows) {
+            return Err(DrawStackError::invalid(
+                "Contact sheets take 1 to 12 columns and 1 to 16 rows",
+            ));
+        }
+        if !(72..=600).contains(&self.dpi) {
+            return Err(DrawStackError::invalid(format!(
+                "Sheet resolution must be between 72 and 600 dpi, got {}",
+                self.dpi
+            )));
+        }
+        Ok(())
+    }
+
+    fn page_size(&self) -> (f32, f32) {
+        let (width, height) = self.paper.size();
+        if self.landscape {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    fn per_page(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ContactSheet {
+    pub pages: usize,
+    pub images: usize,
+    // The PDF, or one PNG per page
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct ContactSheetProgress {
+    page: usize,
+    total: usize,
+}
+
+// One page of the sheet: a pack's name and up to `per_page` of its images
+struct SheetPage<'a> {
+    title: String,
+    images: &'a [CatalogImage],
+}
+
+fn load_thumbnail(image: &CatalogImage) -> Option<DynamicImage> {
+    image
+        .thumbnail_path
+        .as_ref()
+        .and_then(|path| image::open(path.to_path_buf()).ok())
+        .or_else(|| variants::load_scaled(&image.source_path(), FALLBACK_SIZE).ok())
+}
+
+// Cut a caption down to roughly what fits in `width` points. Helvetica
+// averages about half an em per character.
+fn fit_caption(text: &str, width: f32) -> String {
+    let max_chars = ((width / (CAPTION_SIZE * 0.5)) as usize).max(4);
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max_chars - 3).collect();
+    format!("{}...", kept)
+}
+
+fn draw_page(
+    document: &mut PdfDocument,
+    font: PdfFontToken,
+    layout: &SheetLayout,
+    sheet: &SheetPage,
+) -> Result<(), String> {
+    let (page_width, page_height) = layout.page_size();
+    let mut page = document
+        .pages_mut()
+        .create_page_at_end(PdfPagePaperSize::new_custom(
+            PdfPoints::new(page_width),
+            PdfPoints::new(page_height),
+        ))
+        .map_err(|e| format!("Failed to add sheet page: {}", e))?;
+    let objects = page.objects_mut();
+
+    objects
+        .create_text_object(
+            PdfPoints::new(MARGIN),
+            PdfPoints::new(page_height - MARGIN - TITLE_SIZE),
+            &sheet.title,
+            font,
+            PdfPoints::new(TITLE_SIZE),
+        )
+        .map_err(|e| format!("Failed to write sheet title: {}", e))?;
+
+    let grid_top = page_height - MARGIN - HEADER_HEIGHT;
+    let cell_width = (page_width - 2.0 * MARGIN) / layout.columns as f32;
+    let cell_height = (grid_top - MARGIN) / layout.rows as f32;
+    let caption_height = if layout.captions { CAPTION_HEIGHT } else { 0.0 };
+    let box_width = cell_width - 2.0 * CELL_PADDING;
+    let box_height = cell_height - 2.0 * CELL_PADDING - caption_height;
+
+    for (i, image) in sheet.images.iter().enumerate() {
+        let column = (i as u32 % layout.columns) as f32;
+        let row = (i as u32 / layout.columns) as f32;
+        let left = MARGIN + column * cell_width;
+        let top = grid_top - row * cell_height;
+
+        if let Some(thumbnail) = load_thumbnail(image) {
+            let scale =
+                (box_width / thumbnail.width() as f32).min(box_height / thumbnail.height() as f32);
+            let width = thumbnail.width() as f32 * scale;
+            let height = thumbnail.height() as f32 * scale;
+            let max_pixels = |points: f32| ((points / POINTS_PER_INCH) * IMAGE_DPI).ceil() as u32;
+            let thumbnail = thumbnail.thumbnail(max_pixels(width), max_pixels(height));
+
+            objects
+                .create_image_object(
+                    PdfPoints::new(left + CELL_PADDING + (box_width - width) / 2.0),
+                    PdfPoints::new(top - CELL_PADDING - box_height + (box_height - height) / 2.0),
+                    &thumbnail,
+                    Some(PdfPoints::new(width)),
+                    Some(PdfPoints::new(height)),
+                )
+                .map_err(|e| format!("Failed to place {}: {}", image.filename, e))?;
+        } else {
+            tracing::warn!("No preview for {} on the contact sheet", image.id);
+        }
+
+        if layout.captions {
+            objects
+                .create_text_object(
+                    PdfPoints::new(left + CELL_PADDING),
+                    PdfPoints::new(top - cell_height + CELL_PADDING + 2.0),
+                    fit_caption(&image.filename, box_width),
+                    font,
+                    PdfPoints::new(CAPTION_SIZE),
+                )
+                .map_err(|e| format!("Failed to write caption: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+// `sheet.png` -> `sheet-001.png`, `sheet-002.png`, ... for multi-page output
+fn page_paths(dest: &Path, pages: usize) -> Vec<PathBuf> {
+    if pages == 1 {
+        return vec![dest.to_path_buf()];
+    }
+    let stem = dest
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "contact-sheet".to_string());
+    let digits = pages.to_string().len().max(3);
+    (1..=pages)
+        .map(|page| dest.with_file_name(format!("{}-{:0width$}.png", stem, page, width = digits)))
+        .collect()
+}
+
+// Render a contact sheet of `pack_ids`, in order, to `dest`. Every pack
+// starts on a new page titled with its name.
+#[tauri::command]
+pub async fn export_contact_sheet(
+    app: AppHandle,
+    pack_ids: Vec<String>,
+    layout: Option<SheetLayout>,
+    dest: String,
+) -> Result<ContactSheet, DrawStackError> {
+    let layout = layout.unwrap_or_default();
+    layout.validate()?;
+    if pack_ids.is_empty() {
+        return Err(DrawStackError::invalid("No packs selected"));
+    }
+
+    let conn = catalog::open(&app)?;
+    let mut packs = Vec::new();
+    for pack_id in &pack_ids {
+        let pack = catalog::get_pack(&conn, pack_id, None)?
+            .ok_or_else(|| format!("Pack not found: {}", pack_id))?;
+        packs.push(pack);
+    }
+
+    let per_page = layout.per_page();
+    let mut sheets = Vec::new();
+    for pack in &packs {
+        let count = pack.images.len();
+        for (i, chunk) in pack.images.chunks(per_page).enumerate() {
+            let first = i * per_page + 1;
+            sheets.push(SheetPage {
+                title: format!(
+                    "{} ({}-{} of {})",
+                    pack.name,
+                    first,
+                    first + chunk.len() - 1,
+                    count
+                ),
+                images: chunk,
+            });
+        }
+    }
+    if sheets.is_empty() {
+        return Err(DrawStackError::invalid("The selected packs have no images"));
+    }
+
+    let pdfium = pdf::load_pdfium(&app)?;
+    let mut document = pdfium
+        .create_new_pdf()
+        .map_err(|e| format!("Failed to create contact sheet: {}", e))?;
+    let font = document.fonts_mut().helvetica();
+
+    let total = sheets.len();
+    for (i, sheet) in sheets.iter().enumerate() {
+        draw_page(&mut document, font, &layout, sheet)?;
+        let _ = app.emit(
+            "contact-sheet-progress",
+            ContactSheetProgress { page: i + 1, total },
+        );
+    }
+
+    let dest = PathBuf::from(&dest);
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| DrawStackError::io("create export directory", parent, e))?;
+    }
+
+    let paths = match layout.format {
+        SheetFormat::Pdf => {
+            // Save beside the destination first, so a failure never leaves
+            // a truncated PDF behind
+            let partial = dest.with_extension("pdf.part");
+            document
+                .save_to_file(&partial)
+                .map_err(|e| format!("Failed to save contact sheet: {}", e))?;
+            fs::rename(&partial, &dest)
+                .map_err(|e| DrawStackError::io("save contact sheet", &dest, e))?;
+            vec![dest.clone()]
+        }
+        SheetFormat::Png => {
+            let render_config =
+                PdfRenderConfig::new().scale_page_by_factor(layout.dpi as f32 / POINTS_PER_INCH);
+            let paths = page_paths(&dest, total);
+            for (page, path) in document.pages().iter().zip(&paths) {
+                page.render_with_config(&render_config)
+                    .map_err(|e| format!("Failed to render contact sheet: {}", e))?
+                    .as_image()
+                    .to_rgb8()
+                    .save_with_format(path, image::ImageFormat::Png)
+                    .map_err(|e| format!("Failed to save contact sheet: {}", e))?;
+            }
+            paths
+        }
+    };
+
+    let images = sheets.iter().map(|s| s.images.len()).sum();
+    tracing::info!(
+        "Exported a {} page contact sheet of {} images to {}",
+        total,
+        images,
+        dest.display()
+    );
+    Ok(ContactSheet {
+        pages: total,
+        images,
+        paths: paths
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect(),
+    })
+}
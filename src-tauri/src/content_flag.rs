@@ -0,0 +1,71 @@
+// Content labels for images and whole packs, e.g. "nsfw", so they can be kept
+// off screen while streaming. Commands taking `exclude_flagged` filter in the
+// query, so flagged images never reach the webview.
+use rusqlite::params;
+use tauri::AppHandle;
+
+use crate::catalog;
+use crate::error::DrawStackError;
+
+const MAX_FLAG_LEN: usize = 64;
+
+// SQL condition on `images i` matching images with no flag of their own and
+// no flagged pack
+pub const UNFLAGGED: &str = "i.content_flag IS NULL \
+     AND i.pack_id NOT IN (SELECT id FROM packs WHERE content_flag IS NOT NULL)";
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct FlagUpdate {
+    pub images: usize,
+    pub packs: usize,
+}
+
+// Set `flag` on every image in `image_ids` and every pack in `pack_ids`, or
+// clear it when `flag` is empty or missing. Unknown IDs are skipped.
+#[tauri::command]
+pub async fn set_content_flag(
+    app: AppHandle,
+    image_ids: Option<Vec<String>>,
+    pack_ids: Option<Vec<String>>,
+    flag: Option<String>,
+) -> Result<FlagUpdate, DrawStackError> {
+    let flag = flag
+        .map(|f| f.trim().to_lowercase())
+        .filter(|f| !f.is_empty());
+    if flag.as_ref().is_some_and(|f| f.len() > MAX_FLAG_LEN) {
+        return Err(DrawStackError::invalid(format!(
+            "Content flags can be at most {} characters",
+            MAX_FLAG_LEN
+        )));
+    }
+
+    let mut conn = catalog::open(&app)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+
+    let mut update = FlagUpdate {
+        images: 0,
+        packs: 0,
+    };
+    for image_id in image_ids.unwrap_or_default() {
+        update.images += tx
+            .execute(
+                "UPDATE images SET content_flag = ?1 WHERE id = ?2",
+                params![flag, image_id],
+            )
+            .map_err(|e| format!("Failed to flag image {}: {}", image_id, e))?;
+    }
+    for pack_id in pack_ids.unwrap_or_default() {
+        update.packs += tx
+            .execute(
+                "UPDATE packs SET content_flag = ?1 WHERE id = ?2",
+                params![flag, pack_id],
+            )
+            .map_err(|e| format!("Failed to flag pack {}: {}", pack_id, e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+    Ok(update)
+}
@@ -0,0 +1,89 @@
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::error::DrawStackError;
+use crate::{config, scan};
+
+// Hex digits of the blake3 digest used as an image ID. 128 bits is plenty to
+// avoid accidental collisions while keeping file names short.
+const ID_HEX_LEN: usize = 32;
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub paths: Vec<String>,
+}
+
+// Stable image ID derived from the file's bytes, so importing the same file
+// twice yields the same ID.
+pub fn content_id(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(fs::File::open(crate::paths::extended(path))?)?;
+    let mut id = hasher.finalize().to_hex().to_string();
+    id.truncate(ID_HEX_LEN);
+    Ok(id)
+}
+
+// Report files under `folder_path` that have identical contents
+#[tauri::command]
+pub async fn find_duplicates(
+    app: AppHandle,
+    folder_path: String,
+) -> Result<Vec<DuplicateGroup>, DrawStackError> {
+    let images = scan::scan_for_images(Path::new(&folder_path), &config::load(&app).scan)?;
+
+    // Only files of equal size can be identical, so skip hashing unique sizes
+    let mut by_size: HashMap<u64, Vec<_>> = HashMap::new();
+    for path in images {
+        if let Ok(meta) = fs::metadata(&path) {
+            by_size.entry(meta.len()).or_default().push(path);
+        }
+    }
+
+    let candidates: Vec<_> = by_size
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .flat_map(|(size, paths)| paths.into_iter().map(move |p| (size, p)))
+        .collect();
+
+    let hashed: Vec<(String, u64, String)> = candidates
+        .par_iter()
+        .filter_map(|(size, path)| {
+            content_id(path)
+                .ok()
+                .map(|hash| (hash, *size, path.to_string_lossy().to_string()))
+        })
+        .collect();
+
+    let mut groups: HashMap<String, DuplicateGroup> = HashMap::new();
+    for (hash, size, path) in hashed {
+        groups
+            .entry(hash.clone())
+            .or_insert_with(|| DuplicateGroup {
+                hash,
+                size,
+                paths: Vec::new(),
+            })
+            .paths
+            .push(path);
+    }
+
+    let mut duplicates: Vec<DuplicateGroup> = groups
+        .into_values()
+        .filter(|group| group.paths.len() > 1)
+        .map(|mut group| {
+            group.paths.sort();
+            group
+        })
+        .collect();
+
+    // Biggest space savings first
+    duplicates.sort_by_key(|group| std::cmp::Reverse(group.size * (group.paths.len() as u64 - 1)));
+
+    Ok(duplicates)
+}
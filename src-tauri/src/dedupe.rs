@@ -0,0 +1,189 @@
+// Perceptual hashing for near-duplicate detection. Unlike content hashes,
+// dHashes stay close for resized, recompressed or lightly cropped copies.
+use image::{imageops::FilterType, DynamicImage};
+use rusqlite::params;
+use std::collections::HashMap;
+use tauri::AppHandle;
+
+use crate::catalog;
+use crate::error::DrawStackError;
+use crate::jobs::JobHandle;
+use crate::paths::StoredPath;
+
+// Above this many differing bits almost everything starts to "match"
+const MAX_THRESHOLD: u32 = 16;
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct SimilarImage {
+    pub id: String,
+    pub pack_id: String,
+    pub filename: String,
+    pub original_path: StoredPath,
+    pub thumbnail_path: Option<StoredPath>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct SimilarCluster {
+    pub images: Vec<SimilarImage>,
+}
+
+// 64-bit difference hash: compare each pixel with its right neighbour on a
+// 9x8 grayscale downscale.
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+    hash
+}
+
+pub fn to_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+pub fn from_hex(hex: &str) -> Option<u64> {
+    u64::from_str_radix(hex, 16).ok()
+}
+
+struct DisjointSet {
+    parent: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b] = a;
+        }
+    }
+}
+
+// Group hashes that are within `threshold` bits of each other. Splitting the
+// hash into threshold + 1 chunks means any two matches must agree exactly on
+// at least one chunk, so only hashes sharing a chunk are compared.
+fn cluster(hashes: &[u64], threshold: u32) -> Vec<Vec<usize>> {
+    let chunks = threshold as usize + 1;
+    let mut sets = DisjointSet::new(hashes.len());
+
+    for chunk in 0..chunks {
+        let start = chunk * 64 / chunks;
+        let end = (chunk + 1) * 64 / chunks;
+        let mask = if end - start == 64 {
+            u64::MAX
+        } else {
+            ((1u64 << (end - start)) - 1) << start
+        };
+
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, hash) in hashes.iter().enumerate() {
+            buckets.entry(hash & mask).or_default().push(i);
+        }
+
+        for bucket in buckets.values().filter(|b| b.len() > 1) {
+            for (n, &a) in bucket.iter().enumerate() {
+                for &b in &bucket[n + 1..] {
+                    if (hashes[a] ^ hashes[b]).count_ones() <= threshold {
+                        sets.union(a, b);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..hashes.len() {
+        let root = sets.find(i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    groups.into_values().filter(|g| g.len() > 1).collect()
+}
+
+#[tauri::command]
+pub async fn find_similar_images(
+    app: AppHandle,
+    threshold: u32,
+) -> Result<Vec<SimilarCluster>, DrawStackError> {
+    find_similar(&app, threshold, None)
+}
+
+pub fn find_similar(
+    app: &AppHandle,
+    threshold: u32,
+    job: Option<&JobHandle>,
+) -> Result<Vec<SimilarCluster>, DrawStackError> {
+    if threshold > MAX_THRESHOLD {
+        return Err(DrawStackError::invalid(format!(
+            "Threshold must be at most {}",
+            MAX_THRESHOLD
+        )));
+    }
+
+    let conn = catalog::open(app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.id, i.pack_id, i.filename, i.original_path, t.path, i.dhash
+             FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id
+             WHERE i.dhash IS NOT NULL",
+        )
+        .map_err(|e| format!("Failed to prepare similarity query: {}", e))?;
+
+    let rows: Vec<(SimilarImage, i64)> = stmt
+        .query_map(params![], |row| {
+            Ok((
+                SimilarImage {
+                    id: row.get(0)?,
+                    pack_id: row.get(1)?,
+                    filename: row.get(2)?,
+                    original_path: row.get(3)?,
+                    thumbnail_path: row.get(4)?,
+                },
+                row.get(5)?,
+            ))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read image hashes: {}", e))?;
+
+    // Hashes are stored as the signed bit pattern of the u64
+    let hashes: Vec<u64> = rows.iter().map(|(_, hash)| *hash as u64).collect();
+
+    if let Some(job) = job {
+        if job.is_cancelled() {
+            return Err(job.cancelled_error());
+        }
+        job.progress(0, hashes.len(), Some("Comparing image hashes".to_string()));
+    }
+
+    let mut clusters: Vec<SimilarCluster> = cluster(&hashes, threshold)
+        .into_iter()
+        .map(|members| SimilarCluster {
+            images: members.into_iter().map(|i| rows[i].0.clone()).collect(),
+        })
+        .collect();
+
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.images.len()));
+    if let Some(job) = job {
+        job.progress(hashes.len(), hashes.len(), None);
+    }
+    Ok(clusters)
+}
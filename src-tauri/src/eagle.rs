@@ -0,0 +1,328 @@
+// Import an Eagle (eagle.cool) library. Eagle keeps each item in
+// `images/<id>.info/` with the file, a `_thumbnail.png` preview and a
+// `metadata.json`; the folder tree lives in the library's own metadata.json.
+// Top-level Eagle folders become packs, nested folders their subfolders.
+use rayon::prelude::*;
+use rusqlite::params;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::{AppHandle, Emitter};
+
+use crate::error::DrawStackError;
+use crate::paths::StoredPath;
+use crate::{
+    animation, catalog, content_hash, dedupe, exif, manifest, storage, tags, thumbnails,
+    ThumbnailInfo,
+};
+
+// Pack for items that aren't in any Eagle folder
+const UNFILED_PACK: &str = "eagle-unfiled";
+const UNFILED_NAME: &str = "Eagle (unfiled)";
+
+#[derive(Debug, serde::Deserialize, Default)]
+#[serde(default)]
+struct LibraryMetadata {
+    folders: Vec<EagleFolder>,
+}
+
+#[derive(Debug, serde::Deserialize, Default)]
+#[serde(default)]
+struct EagleFolder {
+    id: String,
+    name: String,
+    children: Vec<EagleFolder>,
+}
+
+#[derive(Debug, serde::Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct EagleItem {
+    name: String,
+    ext: String,
+    tags: Vec<String>,
+    folders: Vec<String>,
+    is_deleted: bool,
+    url: Option<String>,
+    star: Option<u8>,
+}
+
+// Where an Eagle folder ends up: its top-level folder's pack and the path
+// below that
+#[derive(Debug, Clone)]
+struct FolderTarget {
+    pack_id: String,
+    pack_name: String,
+    relative_path: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct EaglePack {
+    pub pack_id: String,
+    pub name: String,
+    pub images: usize,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct EagleImport {
+    pub packs: Vec<EaglePack>,
+    pub images: usize,
+    // Deleted, unsupported or unreadable items, and ones already in the
+    // catalog
+    pub skipped: usize,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct EagleImportProgress {
+    done: usize,
+    total: usize,
+}
+
+// Map every folder ID to its pack. Pack IDs derive from the top-level
+// folder's ID, so importing the same library again updates those packs.
+fn folder_targets(
+    folders: &[EagleFolder],
+    top: Option<&FolderTarget>,
+    targets: &mut HashMap<String, FolderTarget>,
+) {
+    for folder in folders {
+        let target = match top {
+            None => FolderTarget {
+                pack_id: format!("eagle-{}", folder.id),
+                pack_name: folder.name.clone(),
+                relative_path: String::new(),
+            },
+            Some(parent) => FolderTarget {
+                relative_path: if parent.relative_path.is_empty() {
+                    folder.name.clone()
+                } else {
+                    format!("{}/{}", parent.relative_path, folder.name)
+                },
+                ..parent.clone()
+            },
+        };
+        folder_targets(&folder.children, Some(&target), targets);
+        targets.insert(folder.id.clone(), target);
+    }
+}
+
+// The item's file: anything in its folder other than the metadata and the
+// preview, preferring the extension Eagle recorded
+fn item_file(info_dir: &Path, item: &EagleItem) -> Option<PathBuf> {
+    let named = info_dir.join(format!("{}.{}", item.name, item.ext));
+    if named.is_file() {
+        return Some(named);
+    }
+    fs::read_dir(info_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            crate::extension_lower(path).as_deref() == Some(item.ext.to_lowercase().as_str())
+                && !path
+                    .file_stem()
+                    .is_some_and(|s| s.to_string_lossy().ends_with("_thumbnail"))
+        })
+}
+
+// Build the catalog entry, generating our thumbnail from Eagle's preview
+// when there is one rather than decoding the full image
+fn item_info(
+    app: &AppHandle,
+    info_dir: &Path,
+    file: &Path,
+    item: &EagleItem,
+    target: &FolderTarget,
+    settings: &thumbnails::ThumbnailSettings,
+) -> Option<ThumbnailInfo> {
+    let image_id = content_hash::content_id(file).ok()?;
+    let original_path = StoredPath::from(file);
+    let metadata = exif::read_metadata(file);
+
+    let preview = info_dir.join(format!("{}_thumbnail.png", item.name));
+    let from_preview = preview
+        .is_file()
+        .then(|| crate::generate_fast_thumbnail(&preview, app, &image_id, settings).ok())
+        .flatten();
+    let thumbnail = from_preview
+        .or_else(|| crate::generate_fast_thumbnail(file, app, &image_id, settings).ok());
+
+    let extension = if item.ext.is_empty() {
+        String::new()
+    } else {
+        format!(".{}", item.ext)
+    };
+    Some(ThumbnailInfo {
+        id: image_id,
+        thumbnail_path: thumbnail
+            .as_ref()
+            .map(|t| t.path.clone())
+            .unwrap_or_else(|| original_path.clone()),
+        original_path,
+        filename: format!("{}{}", item.name, extension),
+        relative_path: target.relative_path.clone(),
+        dhash: thumbnail.map(|t| dedupe::to_hex(t.dhash)),
+        width: metadata.width,
+        height: metadata.height,
+        orientation: metadata.orientation,
+        captured_at: metadata.captured_at,
+        is_animated: animation::is_animated(metadata.frame_count),
+        frame_count: metadata.frame_count,
+        duration_ms: metadata.duration_ms,
+    })
+}
+
+// Bring an Eagle library into the catalog with its folders, tags, star
+// ratings and source URLs. Files stay inside the Eagle library; items in
+// several folders land in the first one.
+#[tauri::command]
+pub async fn import_from_eagle(
+    app: AppHandle,
+    library_path: String,
+) -> Result<EagleImport, DrawStackError> {
+    let library = Path::new(&library_path);
+    let images_dir = library.join("images");
+    let metadata_path = library.join("metadata.json");
+    if !images_dir.is_dir() || !metadata_path.is_file() {
+        return Err(DrawStackError::invalid(format!(
+            "Not an Eagle library: {}",
+            library_path
+        )));
+    }
+
+    let metadata: LibraryMetadata = fs::read_to_string(&metadata_path)
+        .map_err(|e| DrawStackError::io("read Eagle metadata", &metadata_path, e))
+        .and_then(|text| {
+            serde_json::from_str(&text)
+                .map_err(|e| DrawStackError::invalid(format!("Invalid Eagle metadata: {}", e)))
+        })?;
+    let mut targets = HashMap::new();
+    folder_targets(&metadata.folders, None, &mut targets);
+    let unfiled = FolderTarget {
+        pack_id: UNFILED_PACK.to_string(),
+        pack_name: UNFILED_NAME.to_string(),
+        relative_path: String::new(),
+    };
+
+    let info_dirs: Vec<PathBuf> = fs::read_dir(&images_dir)
+        .map_err(|e| DrawStackError::io("read Eagle library", &images_dir, e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && crate::extension_lower(path).as_deref() == Some("info"))
+        .collect();
+
+    let total = info_dirs.len();
+    let done = AtomicUsize::new(0);
+    let settings = thumbnails::load_settings(&app);
+    let items: Vec<(ThumbnailInfo, &FolderTarget, EagleItem)> = info_dirs
+        .par_iter()
+        .filter_map(|info_dir| {
+            let item = fs::read_to_string(info_dir.join("metadata.json"))
+                .ok()
+                .and_then(|text| serde_json::from_str::<EagleItem>(&text).ok())
+                .filter(|item| !item.is_deleted);
+            let result = item.and_then(|item| {
+                let file = item_file(info_dir, &item).filter(|f| crate::is_supported_image(f))?;
+                let target = item
+                    .folders
+                    .iter()
+                    .find_map(|id| targets.get(id))
+                    .unwrap_or(&unfiled);
+                let info = item_info(&app, info_dir, &file, &item, target, &settings)?;
+                Some((info, target, item))
+            });
+
+            let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+            if finished.is_multiple_of(50) || finished == total {
+                let _ = app.emit(
+                    "eagle-import-progress",
+                    EagleImportProgress {
+                        done: finished,
+                        total,
+                    },
+                );
+            }
+            result
+        })
+        .collect();
+    let mut skipped = total - items.len();
+
+    // One catalog insert per pack, in the order packs were first seen
+    let mut packs: Vec<(&FolderTarget, Vec<ThumbnailInfo>)> = Vec::new();
+    let mut extras = Vec::new();
+    for (info, target, item) in items {
+        extras.push((info.id.clone(), item));
+        match packs.iter_mut().find(|(t, _)| t.pack_id == target.pack_id) {
+            Some((_, infos)) => infos.push(info),
+            None => packs.push((target, vec![info])),
+        }
+    }
+
+    let mut conn = catalog::open(&app)?;
+    let mut imported = Vec::new();
+    let mut duplicate_ids = HashSet::new();
+    for (target, infos) in &packs {
+        let duplicates = catalog::record_import(
+            &app,
+            &mut conn,
+            &target.pack_id,
+            Some(&target.pack_name),
+            Some(&library_path),
+            infos,
+        )?;
+        for info in infos {
+            let path = info.original_path.to_string();
+            if duplicates.iter().any(|d| d.path == path) {
+                duplicate_ids.insert(info.id.clone());
+            }
+        }
+        imported.push(EaglePack {
+            pack_id: target.pack_id.clone(),
+            name: target.pack_name.clone(),
+            images: infos.len() - duplicates.len(),
+        });
+    }
+    // Items the catalog already had keep their own rating and tags
+    extras.retain(|(image_id, _)| !duplicate_ids.contains(image_id));
+    skipped += duplicate_ids.len();
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    for (image_id, item) in &extras {
+        if let Some(star) = item.star.filter(|s| (1..=5).contains(s)) {
+            tx.execute(
+                "UPDATE images SET rating = ?1 WHERE id = ?2",
+                params![star, image_id],
+            )
+            .map_err(|e| format!("Failed to set rating: {}", e))?;
+        }
+        if let Some(url) = item.url.as_deref().filter(|u| !u.is_empty()) {
+            tx.execute(
+                "UPDATE images SET source_url = ?1 WHERE id = ?2",
+                params![url, image_id],
+            )
+            .map_err(|e| format!("Failed to record source URL: {}", e))?;
+        }
+        if !item.tags.is_empty() {
+            tags::tag_images(&tx, std::slice::from_ref(image_id), &item.tags)?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+    manifest::refresh(&app, imported.iter().map(|p| p.pack_id.as_str()));
+    storage::invalidate(&app);
+
+    tracing::info!(
+        "Imported {} Eagle items into {} packs from {} ({} skipped)",
+        extras.len(),
+        imported.len(),
+        library_path,
+        skipped
+    );
+    Ok(EagleImport {
+        packs: imported,
+        images: extras.len(),
+        skipped,
+    })
+}
@@ -0,0 +1,140 @@
+use serde::ser::SerializeStruct;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Error returned by every command. It reaches the webview as
+// `{ code, message, path }` so the UI can branch on `code` instead of
+// parsing message text.
+#[derive(Debug, thiserror::Error)]
+pub enum DrawStackError {
+    #[error("Not found: {}", .path.display())]
+    NotFound { path: PathBuf },
+
+    #[error("Permission denied: {}", .path.display())]
+    PermissionDenied { path: PathBuf },
+
+    #[error("Already exists: {}", .path.display())]
+    AlreadyExists { path: PathBuf },
+
+    #[error("Access outside the allowed folders: {}", .path.display())]
+    OutOfScope { path: PathBuf },
+
+    #[error("Failed to {action} {}: {source}", .path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+
+    #[error("Failed to decode {}: {message}", .path.display())]
+    Decode { path: PathBuf, message: String },
+
+    #[error("Unsupported format: {}", .path.display())]
+    Unsupported { path: PathBuf },
+
+    #[error("{0}")]
+    InvalidInput(String),
+
+    #[error("{0}")]
+    Busy(String),
+
+    #[error("Catalog error: {0}")]
+    Catalog(#[from] rusqlite::Error),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl DrawStackError {
+    // Classify an I/O failure on `path`, so missing files and permission
+    // problems get their own codes.
+    pub fn io(action: &'static str, path: impl AsRef<Path>, source: io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        match source.kind() {
+            io::ErrorKind::NotFound => Self::NotFound { path },
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied { path },
+            io::ErrorKind::AlreadyExists => Self::AlreadyExists { path },
+            _ => Self::Io {
+                action,
+                path,
+                source,
+            },
+        }
+    }
+
+    pub fn not_found(path: impl AsRef<Path>) -> Self {
+        Self::NotFound {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn decode(path: impl AsRef<Path>, message: impl ToString) -> Self {
+        Self::Decode {
+            path: path.as_ref().to_path_buf(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::InvalidInput(message.into())
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound { .. } => "not_found",
+            Self::PermissionDenied { .. } => "permission_denied",
+            Self::AlreadyExists { .. } => "already_exists",
+            Self::OutOfScope { .. } => "out_of_scope",
+            Self::Io { .. } => "io",
+            Self::Decode { .. } => "decode_failed",
+            Self::Unsupported { .. } => "unsupported_format",
+            Self::InvalidInput(_) => "invalid_input",
+            Self::Busy(_) => "busy",
+            Self::Catalog(_) => "catalog",
+            Self::Other(_) => "error",
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::NotFound { path }
+            | Self::PermissionDenied { path }
+            | Self::AlreadyExists { path }
+            | Self::OutOfScope { path }
+            | Self::Io { path, .. }
+            | Self::Decode { path, .. }
+            | Self::Unsupported { path } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+// Most internal helpers still build their errors with `format!`; those come
+// through as the generic code.
+impl From<String> for DrawStackError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl From<&str> for DrawStackError {
+    fn from(message: &str) -> Self {
+        Self::Other(message.to_string())
+    }
+}
+
+impl From<DrawStackError> for String {
+    fn from(error: DrawStackError) -> Self {
+        error.to_string()
+    }
+}
+
+impl serde::Serialize for DrawStackError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("DrawStackError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("path", &self.path().map(|p| p.to_string_lossy()))?;
+        state.end()
+    }
+}
@@ -0,0 +1,156 @@
+use ::exif::{DateTime, In, Reader, Tag, Value};
+use image::DynamicImage;
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+#[derive(Debug, Default, Clone)]
+pub struct ImageMetadata {
+    // Display dimensions, i.e. already swapped for rotated orientations
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    // EXIF orientation, 1-8
+    pub orientation: Option<u16>,
+    // DateTimeOriginal as `YYYY-MM-DDTHH:MM:SS`, camera local time
+    pub captured_at: Option<String>,
+    // Frames in a GIF or WebP
+    pub frame_count: Option<u32>,
+    // Length of a video clip
+    pub duration_ms: Option<u64>,
+}
+
+fn read_exif(path: &Path) -> Option<::exif::Exif> {
+    let file = fs::File::open(crate::paths::extended(path)).ok()?;
+    Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()
+}
+
+fn orientation_of(exif: &::exif::Exif) -> Option<u16> {
+    exif.get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .filter(|o| (1..=8).contains(o))
+        .map(|o| o as u16)
+}
+
+fn captured_at_of(exif: &::exif::Exif) -> Option<String> {
+    let field = exif.get_field(Tag::DateTimeOriginal, In::PRIMARY)?;
+    let Value::Ascii(ref values) = field.value else {
+        return None;
+    };
+    let dt = DateTime::from_ascii(values.first()?).ok()?;
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+    ))
+}
+
+fn exif_dimensions(exif: &::exif::Exif) -> Option<(u32, u32)> {
+    let width = exif
+        .get_field(Tag::PixelXDimension, In::PRIMARY)?
+        .value
+        .get_uint(0)?;
+    let height = exif
+        .get_field(Tag::PixelYDimension, In::PRIMARY)?
+        .value
+        .get_uint(0)?;
+    Some((width, height))
+}
+
+// Header-only read of the metadata shown and sorted on in the library
+pub fn read_metadata(path: &Path) -> ImageMetadata {
+    #[cfg(feature = "video")]
+    if let Some(video) = crate::video::probe(path) {
+        return ImageMetadata {
+            width: video.width,
+            height: video.height,
+            duration_ms: video.duration_ms,
+            ..ImageMetadata::default()
+        };
+    }
+
+    let exif = read_exif(path);
+    let orientation = exif.as_ref().and_then(orientation_of);
+
+    // Format from the content, so misnamed and extensionless files measure
+    let dimensions = image::ImageReader::open(crate::paths::extended(path))
+        .and_then(|reader| reader.with_guessed_format())
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok())
+        .or_else(|| exif.as_ref().and_then(exif_dimensions))
+        .map(|(w, h)| {
+            if matches!(orientation, Some(5..=8)) {
+                (h, w)
+            } else {
+                (w, h)
+            }
+        });
+
+    // Layered documents aren't readable by `image`, but their headers are
+    #[cfg(feature = "psd")]
+    let dimensions = dimensions.or_else(|| crate::psd::dimensions(path));
+
+    ImageMetadata {
+        width: dimensions.map(|(w, _)| w),
+        height: dimensions.map(|(_, h)| h),
+        orientation,
+        captured_at: exif.as_ref().and_then(captured_at_of),
+        frame_count: crate::animation::frame_count(path),
+        duration_ms: None,
+    }
+}
+
+// Cameras pad a 3:2 photo's 4:3 preview with black bars; a preview whose
+// shape differs from the image by more than this is not used
+const MAX_PREVIEW_ASPECT_DRIFT: f32 = 0.02;
+
+// The JPEG preview stored in the EXIF thumbnail IFD, upright, if its longest
+// edge is at least `min_size` and it has the image's proportions
+pub fn embedded_thumbnail(path: &Path, min_size: u32) -> Option<DynamicImage> {
+    let exif = read_exif(path)?;
+    let offset = exif
+        .get_field(Tag::JPEGInterchangeFormat, In::THUMBNAIL)?
+        .value
+        .get_uint(0)? as usize;
+    let len = exif
+        .get_field(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL)?
+        .value
+        .get_uint(0)? as usize;
+    let data = exif.buf().get(offset..offset.checked_add(len)?)?;
+
+    let preview = image::load_from_memory_with_format(data, image::ImageFormat::Jpeg).ok()?;
+    if preview.width().max(preview.height()) < min_size {
+        return None;
+    }
+    let (width, height) = image::image_dimensions(path)
+        .ok()
+        .or_else(|| exif_dimensions(&exif))?;
+    let aspect = |w: u32, h: u32| w as f32 / h.max(1) as f32;
+    let drift = (aspect(preview.width(), preview.height()) / aspect(width, height) - 1.0).abs();
+    if drift > MAX_PREVIEW_ASPECT_DRIFT {
+        return None;
+    }
+
+    Some(match orientation_of(&exif) {
+        Some(orientation) => apply_orientation(preview, orientation),
+        None => preview,
+    })
+}
+
+pub fn read_orientation(path: &Path) -> Option<u16> {
+    read_exif(path).as_ref().and_then(orientation_of)
+}
+
+// Rotate/flip an image so it displays the way the camera was held
+pub fn apply_orientation(img: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
@@ -0,0 +1,303 @@
+// Copy images out of the library under readable names, e.g. for class
+// handouts. Names come from a template; images are copied byte for byte
+// unless they have to be scaled down or converted.
+use image::imageops::FilterType;
+use rusqlite::params;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+use crate::catalog::{self, CatalogImage};
+use crate::error::DrawStackError;
+use crate::transforms;
+
+pub const DEFAULT_TEMPLATE: &str = "{pack}_{index}_{original_name}";
+const MAX_TEMPLATE_LEN: usize = 200;
+const MIN_DIMENSION: u32 = 16;
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ExportedImage {
+    pub image_id: String,
+    pub path: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ExportFailure {
+    pub image_id: String,
+    pub error: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ExportReport {
+    pub exported: Vec<ExportedImage>,
+    pub failed: Vec<ExportFailure>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct ExportProgress {
+    done: usize,
+    total: usize,
+}
+
+// One piece of a parsed filename template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Pack,
+    Index,
+    OriginalName,
+    Id,
+    Rating,
+}
+
+fn parse_template(template: &str) -> Result<Vec<Part>, DrawStackError> {
+    if template.trim().is_empty() || template.len() > MAX_TEMPLATE_LEN {
+        return Err(DrawStackError::invalid(format!(
+            "Filename templates must be 1 to {} characters",
+            MAX_TEMPLATE_LEN
+        )));
+    }
+
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        if open > 0 {
+            parts.push(Part::Text(rest[..open].to_string()));
+        }
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| DrawStackError::invalid("Unclosed { in filename template"))?;
+        let part = match &rest[open + 1..open + close] {
+            "pack" => Part::Pack,
+            "index" => Part::Index,
+            "original_name" => Part::OriginalName,
+            "id" => Part::Id,
+            "rating" => Part::Rating,
+            other => {
+                return Err(DrawStackError::invalid(format!(
+                    "Unknown template field: {{{}}}",
+                    other
+                )))
+            }
+        };
+        parts.push(part);
+        rest = &rest[open + close + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest.to_string()));
+    }
+    Ok(parts)
+}
+
+// Characters Windows (or any other target) won't take in a file name
+fn sanitize(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.').to_string();
+    if cleaned.is_empty() {
+        "image".to_string()
+    } else {
+        cleaned
+    }
+}
+
+// File stem for one image. `index` is 1-based and zero padded to the width
+// of the largest index, so exports sort in order.
+fn render(parts: &[Part], image: &CatalogImage, pack: &str, index: usize, total: usize) -> String {
+    let width = total.to_string().len();
+    let mut name = String::new();
+    for part in parts {
+        match part {
+            Part::Text(text) => name.push_str(text),
+            Part::Pack => name.push_str(pack),
+            Part::Index => name.push_str(&format!("{:0width$}", index, width = width)),
+            Part::OriginalName => name.push_str(
+                &Path::new(&image.filename)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| image.id.clone()),
+            ),
+            Part::Id => name.push_str(&image.id),
+            Part::Rating => name.push_str(&image.rating.to_string()),
+        }
+    }
+    sanitize(&name)
+}
+
+// `stem.ext`, or `stem (2).ext` and up when that name is already taken in
+// this export or on disk
+fn unique_path(
+    dest_dir: &Path,
+    stem: &str,
+    extension: &str,
+    used: &mut HashSet<String>,
+) -> PathBuf {
+    let mut counter = 1;
+    loop {
+        let name = if counter == 1 {
+            format!("{}.{}", stem, extension)
+        } else {
+            format!("{} ({}).{}", stem, counter, extension)
+        };
+        let path = dest_dir.join(&name);
+        if !path.exists() && used.insert(name.to_lowercase()) {
+            return path;
+        }
+        counter += 1;
+    }
+}
+
+fn pack_name(
+    conn: &rusqlite::Connection,
+    cache: &mut HashMap<String, String>,
+    pack_id: &str,
+) -> String {
+    cache
+        .entry(pack_id.to_string())
+        .or_insert_with(|| {
+            conn.query_row(
+                "SELECT name FROM packs WHERE id = ?1",
+                params![pack_id],
+                |row| row.get::<_, String>(0),
+            )
+            .unwrap_or_else(|_| pack_id.to_string())
+        })
+        .clone()
+}
+
+fn export_one(
+    image: &CatalogImage,
+    dest_dir: &Path,
+    stem: &str,
+    max_dimension: Option<u32>,
+    format: Option<ExportFormat>,
+    used: &mut HashSet<String>,
+) -> Result<PathBuf, DrawStackError> {
+    let source = image.source_path();
+    if !source.exists() {
+        return Err(DrawStackError::not_found(source));
+    }
+
+    let source_extension = crate::extension_lower(&source).unwrap_or_default();
+    let too_large = max_dimension.is_some_and(|max| {
+        image
+            .width
+            .zip(image.height)
+            .is_none_or(|(w, h)| w > max || h > max)
+    });
+    let converts = format.is_some_and(|f| match f {
+        ExportFormat::Jpeg => !matches!(source_extension.as_str(), "jpg" | "jpeg"),
+        _ => source_extension != f.extension(),
+    });
+
+    if !too_large && !converts {
+        let target = unique_path(dest_dir, stem, &source_extension, used);
+        fs::copy(&source, &target).map_err(|e| DrawStackError::io("copy", &source, e))?;
+        return Ok(target);
+    }
+
+    // Re-encoding; formats we can't write (RAW, JXL, ...) fall back to PNG
+    let mut img = crate::decode_image(&source)?;
+    if let Some(max) = max_dimension {
+        if img.width() > max || img.height() > max {
+            img = img.resize(max, max, FilterType::Lanczos3);
+        }
+    }
+    let extension = match format {
+        Some(format) => format.extension(),
+        None => transforms::output_extension(&source),
+    };
+    let target = unique_path(dest_dir, stem, extension, used);
+    transforms::save(&img, &target)?;
+    Ok(target)
+}
+
+// Copy `image_ids`, in order, into `dest_dir` named by `template` (see
+// `parse_template` for the fields). Images larger than `max_dimension` are
+// scaled down and `format` converts everything else. Each image succeeds or
+// fails on its own; `export-progress` is emitted as they finish.
+#[tauri::command]
+pub async fn export_images(
+    app: AppHandle,
+    image_ids: Vec<String>,
+    dest_dir: String,
+    template: Option<String>,
+    max_dimension: Option<u32>,
+    format: Option<ExportFormat>,
+) -> Result<ExportReport, DrawStackError> {
+    let parts = parse_template(template.as_deref().unwrap_or(DEFAULT_TEMPLATE))?;
+    if max_dimension.is_some_and(|max| max < MIN_DIMENSION) {
+        return Err(DrawStackError::invalid(format!(
+            "Maximum dimension must be at least {} pixels",
+            MIN_DIMENSION
+        )));
+    }
+
+    let dest_dir = PathBuf::from(&dest_dir);
+    fs::create_dir_all(&dest_dir)
+        .map_err(|e| DrawStackError::io("create export directory", &dest_dir, e))?;
+
+    let conn = catalog::open(&app)?;
+    let total = image_ids.len();
+    let mut pack_names = HashMap::new();
+    let mut used = HashSet::new();
+    let mut report = ExportReport {
+        exported: Vec::new(),
+        failed: Vec::new(),
+    };
+
+    for (i, image_id) in image_ids.into_iter().enumerate() {
+        let result = catalog::get_image(&conn, &image_id).and_then(|image| {
+            let pack = pack_name(&conn, &mut pack_names, &image.pack_id);
+            let stem = render(&parts, &image, &pack, i + 1, total);
+            export_one(&image, &dest_dir, &stem, max_dimension, format, &mut used)
+        });
+        match result {
+            Ok(path) => report.exported.push(ExportedImage {
+                image_id,
+                path: path.to_string_lossy().to_string(),
+            }),
+            Err(e) => {
+                tracing::warn!("Failed to export {}: {}", image_id, e);
+                report.failed.push(ExportFailure {
+                    image_id,
+                    error: e.to_string(),
+                });
+            }
+        }
+        let _ = app.emit("export-progress", ExportProgress { done: i + 1, total });
+    }
+
+    tracing::info!(
+        "Exported {} of {} images to {}",
+        report.exported.len(),
+        total,
+        dest_dir.display()
+    );
+    Ok(report)
+}
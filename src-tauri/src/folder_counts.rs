@@ -0,0 +1,164 @@
+// Image counts for the folder picker, which asks for one per subfolder as
+// soon as a folder is listed. Walking a large tree on a network share takes
+// seconds, so counts stop at a limit and show as "5000+", stay cached until a
+// folder in the tree changes, and are dropped when the user browses away.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+use crate::config;
+use crate::error::DrawStackError;
+use crate::scan::{self, ScanOptions};
+
+const DEFAULT_LIMIT: usize = 5000;
+// Past this the cache is cleared rather than tracking which entry is oldest
+const MAX_CACHED: usize = 4096;
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct FolderImageCount {
+    pub count: usize,
+    // Counting stopped at the limit, so there may be more
+    pub capped: bool,
+    // Served from the cache without walking the folder
+    pub cached: bool,
+}
+
+struct CachedCount {
+    options: ScanOptions,
+    count: usize,
+    capped: bool,
+    folders: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl CachedCount {
+    // Adding or removing an image changes its folder's modification time,
+    // so the count holds while every folder walked still has its old one
+    fn is_current(&self, options: &ScanOptions) -> bool {
+        self.options == *options
+            && self
+                .folders
+                .iter()
+                .all(|(folder, modified)| scan::modified(folder) == *modified)
+    }
+
+    // The answer for `limit`, if this count settles it
+    fn answer(&self, limit: usize) -> Option<FolderImageCount> {
+        if self.count >= limit {
+            return Some(FolderImageCount {
+                count: limit,
+                capped: true,
+                cached: true,
+            });
+        }
+        (!self.capped).then_some(FolderImageCount {
+            count: self.count,
+            capped: false,
+            cached: true,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct FolderCounts {
+    cache: Mutex<HashMap<PathBuf, Arc<CachedCount>>>,
+    // Bumped whenever the user browses to another folder; counts started
+    // before that give up
+    generation: AtomicU64,
+}
+
+impl FolderCounts {
+    // Cancel every count in progress. Returns the new generation.
+    pub fn navigate(&self) -> u64 {
+        self.generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::SeqCst) == generation
+    }
+
+    fn count(
+        &self,
+        path: &Path,
+        options: &ScanOptions,
+        limit: usize,
+    ) -> Result<FolderImageCount, DrawStackError> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        // Checked outside the lock, as it stats every folder of the tree
+        let cached = self.cache.lock().unwrap().get(path).cloned();
+        if let Some(cached) = cached {
+            if let Some(answer) = cached.answer(limit).filter(|_| cached.is_current(options)) {
+                return Ok(answer);
+            }
+        }
+
+        let counted = scan::count_images(path, options, limit, &|| !self.is_current(generation))?;
+        let answer = FolderImageCount {
+            count: counted.count,
+            capped: counted.capped,
+            cached: false,
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED {
+            cache.clear();
+        }
+        cache.insert(
+            path.to_path_buf(),
+            Arc::new(CachedCount {
+                options: options.clone(),
+                count: counted.count,
+                capped: counted.capped,
+                folders: counted.folders,
+            }),
+        );
+        Ok(answer)
+    }
+}
+
+fn count_blocking(
+    app: &AppHandle,
+    folder_path: &str,
+    limit: usize,
+) -> Result<FolderImageCount, DrawStackError> {
+    let options = config::load(app).scan;
+    app.state::<FolderCounts>()
+        .count(Path::new(folder_path), &options, limit)
+}
+
+// Images an import of `folder_path` would pick up, counted up to `limit`
+// (default 5000). Errs if the user browses elsewhere before it finishes.
+#[tauri::command]
+pub async fn count_folder_images_bounded(
+    app: AppHandle,
+    folder_path: String,
+    limit: Option<usize>,
+) -> Result<FolderImageCount, DrawStackError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 {
+        return Err(DrawStackError::invalid("limit must be above 0"));
+    }
+    tauri::async_runtime::spawn_blocking(move || count_blocking(&app, &folder_path, limit))
+        .await
+        .map_err(|e| format!("Folder count failed: {}", e))?
+}
+
+// The full count, without a limit
+#[tauri::command]
+pub async fn count_folder_images(
+    app: AppHandle,
+    folder_path: String,
+) -> Result<usize, DrawStackError> {
+    tauri::async_runtime::spawn_blocking(move || count_blocking(&app, &folder_path, usize::MAX))
+        .await
+        .map_err(|e| format!("Folder count failed: {}", e))?
+        .map(|counted| counted.count)
+}
+
+// Abandon the counts in progress, e.g. when the picker closes
+#[tauri::command]
+pub fn cancel_folder_counts(app: AppHandle) {
+    app.state::<FolderCounts>().navigate();
+}
@@ -0,0 +1,348 @@
+// Sorted, grouped and paged image listings, so the grid never has to pull a
+// 100k-row library into JavaScript to order it. Filtering reuses the search
+// conditions; groups come back with their sizes and offsets so the grid can
+// draw headers for pages it hasn't fetched yet.
+use rusqlite::{params_from_iter, types::Value, Connection};
+use std::collections::HashMap;
+use tauri::AppHandle;
+
+use crate::catalog::{self, CatalogImage};
+use crate::error::DrawStackError;
+use crate::search::{self, SearchFilters};
+
+const DEFAULT_PAGE_SIZE: usize = 200;
+const MAX_PAGE_SIZE: usize = 1000;
+
+// Capture day, or import day for images without EXIF dates
+const DAY_EXPR: &str = "COALESCE(substr(i.captured_at, 1, 10), \
+     date(i.imported_at, 'unixepoch', 'localtime'))";
+
+#[derive(Debug, serde::Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    Imported,
+    Captured,
+    // Natural order: "img2" before "img10"
+    Filename,
+    Size,
+    Rating,
+    // Shuffled, repeatably for the same seed
+    Random,
+}
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, serde::Deserialize, Clone, Copy)]
+pub struct QuerySort {
+    pub field: SortField,
+    // Defaults to ascending for filenames and descending (newest, largest,
+    // best rated first) for everything else
+    pub dir: Option<SortDirection>,
+    // For `random`; one is picked and returned when absent. Pass it back
+    // with the next page to keep the same order.
+    pub seed: Option<u32>,
+}
+
+impl Default for QuerySort {
+    fn default() -> Self {
+        Self {
+            field: SortField::Imported,
+            dir: None,
+            seed: None,
+        }
+    }
+}
+
+impl QuerySort {
+    fn direction(&self) -> &'static str {
+        let default = match self.field {
+            SortField::Filename => SortDirection::Asc,
+            _ => SortDirection::Desc,
+        };
+        match self.dir.unwrap_or(default) {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+
+    // ORDER BY terms within a group. Ties fall back to filename, then ID so
+    // pages never overlap.
+    fn order_by(&self) -> String {
+        let dir = self.direction();
+        let primary = match self.field {
+            SortField::Imported => format!("i.imported_at {}", dir),
+            SortField::Captured => format!("i.captured_at {} NULLS LAST", dir),
+            SortField::Filename => format!("i.filename COLLATE NATURAL {}", dir),
+            SortField::Size => format!("i.file_size {} NULLS LAST", dir),
+            SortField::Rating => format!("i.rating {}", dir),
+            SortField::Random => return "i.id".to_string(),
+        };
+        format!("{}, i.filename COLLATE NATURAL, i.id", primary)
+    }
+}
+
+#[derive(Debug, serde::Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    // Pack and subfolder, in name order
+    Folder,
+    // Pack, in name order
+    Pack,
+    // Capture day (import day without one), newest first
+    Date,
+}
+
+impl GroupBy {
+    fn key(self) -> String {
+        match self {
+            Self::Folder => "i.pack_id || '/' || i.relative_path".to_string(),
+            Self::Pack => "i.pack_id".to_string(),
+            Self::Date => DAY_EXPR.to_string(),
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            Self::Folder => "CASE WHEN i.relative_path = '' THEN pk.name \
+                 ELSE pk.name || '/' || i.relative_path END"
+                .to_string(),
+            Self::Pack => "pk.name".to_string(),
+            Self::Date => DAY_EXPR.to_string(),
+        }
+    }
+
+    // Leading ORDER BY terms, keeping each group's images together
+    fn order_by(self) -> String {
+        match self {
+            Self::Folder => {
+                "pk.name COLLATE NATURAL, i.pack_id, i.relative_path COLLATE NATURAL".to_string()
+            }
+            Self::Pack => "pk.name COLLATE NATURAL, i.pack_id".to_string(),
+            Self::Date => format!("{} DESC", DAY_EXPR),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct QueryPage {
+    pub page: usize,
+    pub page_size: Option<usize>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ImageGroup {
+    pub key: String,
+    pub label: String,
+    pub count: usize,
+    // Position of the group's first image in the full listing
+    pub start: usize,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ImageQueryResults {
+    pub images: Vec<CatalogImage>,
+    // Every group in the listing, not only this page's; empty when ungrouped
+    pub groups: Vec<ImageGroup>,
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+    // The seed a random order used
+    pub seed: Option<u32>,
+}
+
+const FROM: &str = "FROM images i JOIN packs pk ON pk.id = i.pack_id";
+
+fn groups(
+    conn: &Connection,
+    group_by: GroupBy,
+    where_clause: &str,
+    values: &[Value],
+) -> Result<Vec<ImageGroup>, DrawStackError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} AS key, {}, COUNT(*) {} {} GROUP BY key ORDER BY {}",
+            group_by.key(),
+            group_by.label(),
+            FROM,
+            where_clause,
+            group_by.order_by()
+        ))
+        .map_err(|e| format!("Failed to prepare group query: {}", e))?;
+    let rows = stmt
+        .query_map(params_from_iter(values.iter()), |row| {
+            Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<(String, String, usize)>>>())
+        .map_err(|e| format!("Failed to group images: {}", e))?;
+
+    let mut start = 0;
+    Ok(rows
+        .into_iter()
+        .map(|(key, label, count)| {
+            let group = ImageGroup {
+                key,
+                label,
+                count,
+                start,
+            };
+            start += count;
+            group
+        })
+        .collect())
+}
+
+// IDs for one page of a seeded shuffle. SQLite has no seeded random, so the
+// IDs are shuffled here, each group on its own.
+fn random_page_ids(
+    conn: &Connection,
+    group_by: Option<GroupBy>,
+    where_clause: &str,
+    values: &[Value],
+    seed: u32,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<String>, DrawStackError> {
+    let (key, order) = match group_by {
+        Some(group_by) => (group_by.key(), format!("{}, i.id", group_by.order_by())),
+        None => ("''".to_string(), "i.id".to_string()),
+    };
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT i.id, {} {} {} ORDER BY {}",
+            key, FROM, where_clause, order
+        ))
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+    let rows = stmt
+        .query_map(params_from_iter(values.iter()), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to list images: {}", e))?;
+
+    let mut rng = fastrand::Rng::with_seed(u64::from(seed));
+    let mut ids = Vec::with_capacity(rows.len());
+    for group in rows.chunk_by(|a, b| a.1 == b.1) {
+        let start = ids.len();
+        ids.extend(group.iter().map(|(id, _)| id.clone()));
+        rng.shuffle(&mut ids[start..]);
+    }
+    Ok(ids.into_iter().skip(offset).take(limit).collect())
+}
+
+fn images_by_id(conn: &Connection, ids: Vec<String>) -> Result<Vec<CatalogImage>, DrawStackError> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id
+             WHERE i.id IN ({})",
+            catalog::IMAGE_COLUMNS,
+            placeholders
+        ))
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+    let mut found: HashMap<String, CatalogImage> = stmt
+        .query_map(params_from_iter(ids.iter()), catalog::map_image)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read images: {}", e))?
+        .into_iter()
+        .map(|image| (image.id.clone(), image))
+        .collect();
+    Ok(ids.iter().filter_map(|id| found.remove(id)).collect())
+}
+
+// One page of the images matching `filters`, ordered by `sort` within
+// `group_by` groups. The sort, page and page_size fields of `filters` are
+// ignored in favour of `sort` and `page`.
+#[tauri::command]
+pub async fn query_images(
+    app: AppHandle,
+    sort: Option<QuerySort>,
+    group_by: Option<GroupBy>,
+    page: Option<QueryPage>,
+    filters: Option<SearchFilters>,
+) -> Result<ImageQueryResults, DrawStackError> {
+    let sort = sort.unwrap_or_default();
+    let page = page.unwrap_or_default();
+    let page_size = page
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = page.page * page_size;
+
+    let (conditions, values) = search::build_conditions("", &filters.unwrap_or_default());
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let conn = catalog::open(&app)?;
+
+    let total: usize = conn
+        .query_row(
+            &format!("SELECT COUNT(*) {} {}", FROM, where_clause),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count images: {}", e))?;
+
+    let groups = match group_by {
+        Some(group_by) => groups(&conn, group_by, &where_clause, &values)?,
+        None => Vec::new(),
+    };
+
+    let (images, seed) = if matches!(sort.field, SortField::Random) {
+        let seed = sort.seed.unwrap_or_else(|| fastrand::u32(..));
+        let ids = random_page_ids(
+            &conn,
+            group_by,
+            &where_clause,
+            &values,
+            seed,
+            offset,
+            page_size,
+        )?;
+        (images_by_id(&conn, ids)?, Some(seed))
+    } else {
+        let order_by = match group_by {
+            Some(group_by) => format!("{}, {}", group_by.order_by(), sort.order_by()),
+            None => sort.order_by(),
+        };
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} {} LEFT JOIN thumbnails t ON t.image_id = i.id
+                 {} ORDER BY {} LIMIT ? OFFSET ?",
+                catalog::IMAGE_COLUMNS,
+                FROM,
+                where_clause,
+                order_by
+            ))
+            .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+        let paged = values.iter().cloned().chain([
+            Value::Integer(page_size as i64),
+            Value::Integer(offset as i64),
+        ]);
+        let images = stmt
+            .query_map(params_from_iter(paged), catalog::map_image)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to query images: {}", e))?;
+        (images, None)
+    };
+
+    Ok(ImageQueryResults {
+        images,
+        groups,
+        total,
+        page: page.page,
+        page_size,
+        seed,
+    })
+}
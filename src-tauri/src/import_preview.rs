@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::{Instant, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::error::DrawStackError;
+use crate::{catalog, config, format_bytes, scan, thumbnails};
+
+// Images decoded to time thumbnail generation on this machine
+const TIMING_SAMPLES: usize = 8;
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ExtensionCount {
+    pub extension: String,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ImportPreview {
+    pub folder_path: String,
+    pub total_images: usize,
+    pub total_bytes: u64,
+    pub total_formatted: String,
+    pub extensions: Vec<ExtensionCount>,
+    pub folder_count: usize,
+    // Levels of folders below the import root
+    pub max_depth: usize,
+    pub deepest_folder: Option<String>,
+    // Unchanged files seen by an earlier import (same path, size and mtime)
+    pub already_imported: usize,
+    // Files elsewhere with the same name and size as an imported one
+    pub probable_duplicates: usize,
+    pub estimated_thumbnail_secs: f32,
+}
+
+struct SourceFile {
+    size: u64,
+    mtime_ms: i64,
+}
+
+fn stat(path: &Path) -> Option<SourceFile> {
+    let meta = fs::metadata(path).ok()?;
+    let mtime_ms = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as i64);
+    Some(SourceFile {
+        size: meta.len(),
+        mtime_ms,
+    })
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+// Average seconds to decode and resize one image, from a spread of samples
+fn sample_thumbnail_secs(images: &[&Path], settings: &thumbnails::ThumbnailSettings) -> f32 {
+    if images.is_empty() {
+        return 0.0;
+    }
+    let step = (images.len() / TIMING_SAMPLES).max(1);
+    let samples: Vec<&Path> = images
+        .iter()
+        .step_by(step)
+        .take(TIMING_SAMPLES)
+        .copied()
+        .collect();
+
+    let start = Instant::now();
+    for path in &samples {
+        if let Ok(img) = crate::decode_image(path) {
+            let _ = settings.resize(&img);
+        }
+    }
+    start.elapsed().as_secs_f32() / samples.len() as f32
+}
+
+// Describe what importing `folder_path` would do, without generating
+// thumbnails or touching the catalog.
+#[tauri::command]
+pub async fn preview_import(
+    app: AppHandle,
+    folder_path: String,
+) -> Result<ImportPreview, DrawStackError> {
+    let root = Path::new(&folder_path);
+    let images = scan::scan_for_images(root, &config::load(&app).scan)?;
+
+    // What earlier imports saw, from the thumbnail cache
+    let conn = catalog::open(&app)?;
+    let mut stmt = conn
+        .prepare("SELECT source_path, mtime, size FROM thumbnail_cache")
+        .map_err(|e| format!("Failed to prepare thumbnail cache query: {}", e))?;
+    let known: Vec<(String, i64, i64)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read thumbnail cache: {}", e))?;
+    let by_path: HashMap<&str, (i64, i64)> = known
+        .iter()
+        .map(|(path, mtime, size)| (path.as_str(), (*mtime, *size)))
+        .collect();
+    let by_name: HashSet<(String, i64)> = known
+        .iter()
+        .map(|(path, _, size)| (file_name(path), *size))
+        .collect();
+
+    let mut extensions: HashMap<String, ExtensionCount> = HashMap::new();
+    let mut folders: HashSet<&Path> = HashSet::new();
+    let mut total_bytes = 0;
+    let mut max_depth = 0;
+    let mut deepest_folder = None;
+    let mut already_imported = 0;
+    let mut probable_duplicates = 0;
+    let mut to_render = Vec::new();
+
+    for path in &images {
+        let Some(file) = stat(path) else {
+            continue;
+        };
+        total_bytes += file.size;
+
+        let extension = crate::extension_lower(path).unwrap_or_default();
+        let entry = extensions
+            .entry(extension.clone())
+            .or_insert(ExtensionCount {
+                extension,
+                count: 0,
+                bytes: 0,
+            });
+        entry.count += 1;
+        entry.bytes += file.size;
+
+        if let Some(parent) = path.parent() {
+            let depth = parent
+                .strip_prefix(root)
+                .map_or(0, |p| p.components().count());
+            if depth > max_depth || deepest_folder.is_none() {
+                max_depth = depth;
+                deepest_folder = Some(parent.to_string_lossy().to_string());
+            }
+            folders.insert(parent);
+        }
+
+        let path_str = path.to_string_lossy();
+        if by_path.get(path_str.as_ref()) == Some(&(file.mtime_ms, file.size as i64)) {
+            already_imported += 1;
+            continue;
+        }
+        if by_name.contains(&(file_name(&path_str), file.size as i64)) {
+            probable_duplicates += 1;
+        }
+        to_render.push(path.as_path());
+    }
+
+    // Cached files are reused, so only the rest cost decode time, spread
+    // across the thumbnail pool
+    let settings = thumbnails::load_settings(&app);
+    let per_image = sample_thumbnail_secs(&to_render, &settings);
+    let workers = rayon::current_num_threads().max(1) as f32;
+    let estimated_thumbnail_secs = per_image * to_render.len() as f32 / workers;
+
+    let mut extensions: Vec<ExtensionCount> = extensions.into_values().collect();
+    extensions.sort_by(|a, b| b.count.cmp(&a.count).then(a.extension.cmp(&b.extension)));
+
+    Ok(ImportPreview {
+        folder_path,
+        total_images: images.len(),
+        total_bytes,
+        total_formatted: format_bytes(total_bytes),
+        extensions,
+        folder_count: folders.len(),
+        max_depth,
+        deepest_folder,
+        already_imported,
+        probable_duplicates,
+        estimated_thumbnail_secs,
+    })
+}
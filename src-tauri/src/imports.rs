@@ -0,0 +1,577 @@
+use image::ImageReader;
+use rayon::prelude::*;
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::error::DrawStackError;
+use crate::paths::{self, StoredPath};
+
+// Tracks which imports are running and which have been asked to pause.
+// Pausing takes effect at the next batch boundary.
+#[derive(Default)]
+pub struct ImportControl {
+    inner: Mutex<ImportFlags>,
+}
+
+#[derive(Default)]
+struct ImportFlags {
+    running: HashSet<String>,
+    paused: HashSet<String>,
+}
+
+impl ImportControl {
+    // Returns false if an import for this pack is already running.
+    pub fn begin(&self, pack_id: &str) -> bool {
+        let mut flags = self.inner.lock().unwrap();
+        flags.paused.remove(pack_id);
+        flags.running.insert(pack_id.to_string())
+    }
+
+    pub fn finish(&self, pack_id: &str) {
+        let mut flags = self.inner.lock().unwrap();
+        flags.running.remove(pack_id);
+        flags.paused.remove(pack_id);
+    }
+
+    pub fn is_paused(&self, pack_id: &str) -> bool {
+        self.inner.lock().unwrap().paused.contains(pack_id)
+    }
+
+    fn is_running(&self, pack_id: &str) -> bool {
+        self.inner.lock().unwrap().running.contains(pack_id)
+    }
+
+    pub fn any_running(&self) -> bool {
+        !self.inner.lock().unwrap().running.is_empty()
+    }
+
+    pub fn set_paused(&self, pack_id: &str, paused: bool) {
+        let mut flags = self.inner.lock().unwrap();
+        if paused {
+            flags.paused.insert(pack_id.to_string());
+        } else {
+            flags.paused.remove(pack_id);
+        }
+    }
+}
+
+// Clears the running flag however the import loop exits.
+pub struct RunningImport<'a> {
+    control: &'a ImportControl,
+    pack_id: String,
+}
+
+impl<'a> RunningImport<'a> {
+    pub fn start(control: &'a ImportControl, pack_id: &str) -> Result<Self, DrawStackError> {
+        if !control.begin(pack_id) {
+            return Err(DrawStackError::Busy(format!(
+                "An import is already running for pack {}",
+                pack_id
+            )));
+        }
+        Ok(Self {
+            control,
+            pack_id: pack_id.to_string(),
+        })
+    }
+}
+
+impl Drop for RunningImport<'_> {
+    fn drop(&mut self) {
+        self.control.finish(&self.pack_id);
+    }
+}
+
+// Progress record for an import, rewritten after every batch. The file list
+// itself is written once alongside it so large imports stay cheap to journal.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct ImportJournal {
+    pub pack_id: String,
+    pub folder_path: String,
+    pub total: usize,
+    pub processed: usize,
+    // Files left out because the catalog already has them, unchanged
+    #[serde(default)]
+    pub skipped: usize,
+    // Files left out by the import's filters
+    #[serde(default)]
+    pub filtered: usize,
+    // Files imported without a thumbnail, or left out because their source
+    // was unreachable, so far; kept across a resume
+    #[serde(default)]
+    pub failed: Vec<ImportFailure>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    // Decoding or saving failed; the catalog shows the original in its place
+    #[default]
+    Thumbnail,
+    // The file couldn't be read at all - a network share that dropped out
+    // or stalled - and wasn't added to the catalog
+    Unreachable,
+    // The same bytes are already in the catalog at another path, or in
+    // another pack; the file wasn't added
+    Duplicate,
+}
+
+// A file the import couldn't make a thumbnail for, with the error
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct ImportFailure {
+    pub path: String,
+    pub reason: String,
+    #[serde(default)]
+    pub kind: FailureKind,
+}
+
+// What an import command returns: how many files got a thumbnail and which
+// didn't, and why. Covers the whole import, including runs before a resume.
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ImportReport {
+    pub succeeded: usize,
+    pub skipped: Vec<ImportFailure>,
+    // Entries of `skipped` whose source couldn't be read
+    pub unreachable: usize,
+    // Entries of `skipped` the catalog already had
+    pub duplicates: usize,
+}
+
+impl ImportJournal {
+    fn count(&self, kind: FailureKind) -> usize {
+        self.failed
+            .iter()
+            .filter(|failure| failure.kind == kind)
+            .count()
+    }
+
+    pub fn unreachable(&self) -> usize {
+        self.count(FailureKind::Unreachable)
+    }
+
+    pub fn duplicates(&self) -> usize {
+        self.count(FailureKind::Duplicate)
+    }
+
+    pub fn report(&self) -> ImportReport {
+        ImportReport {
+            succeeded: self.processed - self.failed.len(),
+            skipped: self.failed.clone(),
+            unreachable: self.unreachable(),
+            duplicates: self.duplicates(),
+        }
+    }
+}
+
+// Optional limits for what an import takes in, to keep icons, UI sprites and
+// screenshots out of a pack. Dimensions are as displayed, after EXIF rotation.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ImportFilters {
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub min_bytes: Option<u64>,
+    // Width divided by height, e.g. 0.5..=2.0 drops long strips and banners
+    pub min_aspect: Option<f32>,
+    pub max_aspect: Option<f32>,
+}
+
+impl ImportFilters {
+    pub fn validate(&self) -> Result<(), String> {
+        for aspect in [self.min_aspect, self.max_aspect].into_iter().flatten() {
+            if !aspect.is_finite() || aspect <= 0.0 {
+                return Err("Aspect ratio limits must be positive".to_string());
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_aspect, self.max_aspect) {
+            if min > max {
+                return Err("min_aspect can't be larger than max_aspect".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn needs_dimensions(&self) -> bool {
+        self.min_width.is_some()
+            || self.min_height.is_some()
+            || self.min_aspect.is_some()
+            || self.max_aspect.is_some()
+    }
+
+    // Files whose header can't be read (RAW, PSD and the like) pass the
+    // dimension checks rather than being dropped unseen
+    fn accepts(&self, path: &Path) -> bool {
+        if let Some(min_bytes) = self.min_bytes {
+            if fs::metadata(paths::extended(path)).map_or(0, |m| m.len()) < min_bytes {
+                return false;
+            }
+        }
+        if !self.needs_dimensions() {
+            return true;
+        }
+
+        // Only the header is read - the pixels are never decoded
+        let Some((width, height)) = ImageReader::open(paths::extended(path))
+            .and_then(|r| r.with_guessed_format())
+            .ok()
+            .and_then(|r| r.into_dimensions().ok())
+        else {
+            return true;
+        };
+        let (width, height) = if matches!(crate::exif::read_orientation(path), Some(5..=8)) {
+            (height, width)
+        } else {
+            (width, height)
+        };
+
+        let aspect = width as f32 / height.max(1) as f32;
+        self.min_width.is_none_or(|min| width >= min)
+            && self.min_height.is_none_or(|min| height >= min)
+            && self.min_aspect.is_none_or(|min| aspect >= min)
+            && self.max_aspect.is_none_or(|max| aspect <= max)
+    }
+}
+
+// Drop files `filters` rejects, returning the rest and how many were dropped
+pub fn apply_filters(images: Vec<PathBuf>, filters: &ImportFilters) -> (Vec<PathBuf>, usize) {
+    if filters.min_bytes.is_none() && !filters.needs_dimensions() {
+        return (images, 0);
+    }
+    let total = images.len();
+    let kept: Vec<PathBuf> = images
+        .into_par_iter()
+        .filter(|path| filters.accepts(path))
+        .collect();
+    let filtered = total - kept.len();
+    (kept, filtered)
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ImportPaused {
+    pub pack_id: String,
+    pub processed: usize,
+    pub total: usize,
+    // Set when the import paused itself, e.g. because its source went away
+    pub reason: Option<String>,
+}
+
+// Emitted as `import-slow-source` when an import lowers its concurrency
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct SlowSource {
+    pub pack_id: String,
+    pub threads: usize,
+}
+
+// Counts only, emitted as `import-progress` at most every PROGRESS_INTERVAL.
+// The thumbnails themselves follow in the less frequent `import-batch`.
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ImportProgress {
+    pub pack_id: String,
+    pub processed: usize,
+    pub total: usize,
+    pub percent: f32,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+// Shared by the thumbnail workers so only one of them emits per interval
+#[derive(Default)]
+pub struct ProgressThrottle {
+    last: Mutex<Option<Instant>>,
+}
+
+impl ProgressThrottle {
+    // True at most once per PROGRESS_INTERVAL
+    pub fn ready(&self) -> bool {
+        let mut last = self.last.lock().unwrap();
+        if last.is_some_and(|t| t.elapsed() < PROGRESS_INTERVAL) {
+            return false;
+        }
+        *last = Some(Instant::now());
+        true
+    }
+}
+
+// Emitted as `import-complete` once every batch has been sent
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ImportSummary {
+    pub pack_id: String,
+    pub total: usize,
+    pub imported: usize,
+    pub skipped: usize,
+    pub filtered: usize,
+    pub failed: Vec<ImportFailure>,
+    // Entries of `failed` whose source couldn't be read
+    pub unreachable: usize,
+    // Entries of `failed` the catalog already had
+    pub duplicates: usize,
+}
+
+// Split a fresh scan of `folder` into the files the catalog doesn't have yet,
+// or has with a different size or mtime, and a count of the unchanged ones.
+// Re-importing a folder then only thumbnails what actually changed.
+pub fn drop_unchanged(
+    conn: &Connection,
+    folder: &Path,
+    images: Vec<PathBuf>,
+) -> Result<(Vec<PathBuf>, usize), String> {
+    // Unicode paths are matched on their text. Other paths are stored as
+    // blobs, so those are all read and matched here.
+    let prefix = folder.to_str().map(|p| p.trim_end_matches(['/', '\\']));
+    let mut stmt = conn
+        .prepare(
+            "SELECT original_path, file_size, modified_at FROM images
+             WHERE typeof(original_path) = 'blob'
+                OR substr(original_path, 1, length(?1) + 1) IN (?1 || '/', ?1 || '\\')",
+        )
+        .map_err(|e| format!("Failed to prepare catalog query: {}", e))?;
+    let known: HashMap<PathBuf, (Option<i64>, Option<i64>)> = stmt
+        .query_map([prefix], |row| {
+            Ok((row.get::<_, StoredPath>(0)?, (row.get(1)?, row.get(2)?)))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read catalog images: {}", e))?
+        .into_iter()
+        .map(|(path, stamp)| (PathBuf::from(path), stamp))
+        .filter(|(path, _)| path.starts_with(folder))
+        .collect();
+    if known.is_empty() {
+        return Ok((images, 0));
+    }
+
+    let total = images.len();
+    let changed: Vec<PathBuf> = images
+        .into_iter()
+        .filter(|path| {
+            let Some(&(Some(size), Some(modified_at))) = known.get(path) else {
+                return true;
+            };
+            // The catalog stores whole seconds
+            let Ok(meta) = fs::metadata(paths::extended(path)) else {
+                return true;
+            };
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64);
+            meta.len() as i64 != size || modified != Some(modified_at)
+        })
+        .collect();
+    let skipped = total - changed.len();
+    Ok((changed, skipped))
+}
+
+fn imports_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(app_data.join("imports"))
+}
+
+// Pack IDs come from the webview, so keep them from escaping the imports dir.
+fn journal_stem(pack_id: &str) -> String {
+    pack_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn journal_paths(app: &AppHandle, pack_id: &str) -> Result<(PathBuf, PathBuf), String> {
+    let dir = imports_dir(app)?;
+    let stem = journal_stem(pack_id);
+    Ok((
+        dir.join(format!("{}.json", stem)),
+        dir.join(format!("{}.files.json", stem)),
+    ))
+}
+
+pub fn create_journal(
+    app: &AppHandle,
+    journal: &ImportJournal,
+    files: &[PathBuf],
+) -> Result<(), String> {
+    fs::create_dir_all(imports_dir(app)?)
+        .map_err(|e| format!("Failed to create imports dir: {}", e))?;
+
+    let (_, files_path) = journal_paths(app, &journal.pack_id)?;
+    let files: Vec<StoredPath> = files
+        .iter()
+        .map(|p| StoredPath::from(p.as_path()))
+        .collect();
+    let contents = serde_json::to_string(&files)
+        .map_err(|e| format!("Failed to serialize import file list: {}", e))?;
+    fs::write(&files_path, contents)
+        .map_err(|e| format!("Failed to write import file list: {}", e))?;
+
+    update_journal(app, journal)
+}
+
+pub fn update_journal(app: &AppHandle, journal: &ImportJournal) -> Result<(), String> {
+    let (journal_path, _) = journal_paths(app, &journal.pack_id)?;
+    let contents = serde_json::to_string_pretty(journal)
+        .map_err(|e| format!("Failed to serialize import journal: {}", e))?;
+    fs::write(&journal_path, contents).map_err(|e| format!("Failed to write import journal: {}", e))
+}
+
+fn load_journal(app: &AppHandle, pack_id: &str) -> Result<(ImportJournal, Vec<PathBuf>), String> {
+    let (journal_path, files_path) = journal_paths(app, pack_id)?;
+
+    let journal: ImportJournal = fs::read_to_string(&journal_path)
+        .map_err(|_| format!("No interrupted import found for pack {}", pack_id))
+        .and_then(|s| {
+            serde_json::from_str(&s).map_err(|e| format!("Corrupt import journal: {}", e))
+        })?;
+
+    let files: Vec<StoredPath> = fs::read_to_string(&files_path)
+        .map_err(|e| format!("Failed to read import file list: {}", e))
+        .and_then(|s| {
+            serde_json::from_str(&s).map_err(|e| format!("Corrupt import file list: {}", e))
+        })?;
+
+    let remaining = files
+        .into_iter()
+        .skip(journal.processed)
+        .map(PathBuf::from)
+        .collect();
+
+    Ok((journal, remaining))
+}
+
+pub fn remove_journal(app: &AppHandle, pack_id: &str) {
+    if let Ok((journal_path, files_path)) = journal_paths(app, pack_id) {
+        let _ = fs::remove_file(journal_path);
+        let _ = fs::remove_file(files_path);
+    }
+}
+
+#[tauri::command]
+pub fn pause_import(app: AppHandle, pack_id: String) -> Result<(), DrawStackError> {
+    let control = app.state::<ImportControl>();
+    if !control.is_running(&pack_id) {
+        return Err(DrawStackError::invalid(format!(
+            "No import is running for pack {}",
+            pack_id
+        )));
+    }
+    control.set_paused(&pack_id, true);
+    Ok(())
+}
+
+// Returns None when a pause was simply withdrawn and the running import
+// carries on; its own command reports the result.
+#[tauri::command]
+pub async fn resume_import(
+    app: AppHandle,
+    pack_id: String,
+    thread_count: Option<usize>,
+) -> Result<Option<ImportReport>, DrawStackError> {
+    // A pause that hasn't reached a batch boundary yet can simply be withdrawn
+    {
+        let control = app.state::<ImportControl>();
+        if control.is_running(&pack_id) {
+            control.set_paused(&pack_id, false);
+            return Ok(None);
+        }
+    }
+
+    let running = RunningImport::start(app.state::<ImportControl>().inner(), &pack_id)?;
+    let (journal, remaining) = load_journal(&app, &pack_id)?;
+    tracing::info!(
+        "Resuming import for pack {} ({} of {} images remaining)",
+        pack_id,
+        remaining.len(),
+        journal.total
+    );
+
+    crate::process_import(&app, running, journal, remaining, thread_count, None).map(Some)
+}
+
+#[tauri::command]
+pub fn list_pending_imports(app: AppHandle) -> Result<Vec<ImportJournal>, DrawStackError> {
+    let dir = imports_dir(&app)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read imports dir: {}", e))?;
+
+    let mut pending = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if !name.ends_with(".json") || name.ends_with(".files.json") {
+            continue;
+        }
+        if let Some(journal) = read_journal_file(&path) {
+            pending.push(journal);
+        }
+    }
+
+    Ok(pending)
+}
+
+fn read_journal_file(path: &Path) -> Option<ImportJournal> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::testing;
+
+    #[cfg(unix)]
+    #[test]
+    fn unchanged_non_unicode_files_are_skipped() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = testing::scratch_dir("drop-unchanged");
+        // Latin-1 "é", invalid as UTF-8
+        let image = root.join(OsStr::from_bytes(b"caf\xe9.png"));
+        testing::write_png(&image);
+        let meta = fs::metadata(&image).unwrap();
+        let modified = meta
+            .modified()
+            .unwrap()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE images (original_path TEXT, file_size INTEGER, modified_at INTEGER)",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO images VALUES (?1, ?2, ?3)",
+            rusqlite::params![
+                StoredPath::from(image.as_path()),
+                meta.len() as i64,
+                modified
+            ],
+        )
+        .unwrap();
+
+        let (changed, skipped) = drop_unchanged(&conn, &root, vec![image]).unwrap();
+        assert!(changed.is_empty());
+        assert_eq!(skipped, 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+use rayon::prelude::*;
+use rusqlite::params;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::catalog::{self, CatalogImage};
+use crate::error::DrawStackError;
+use crate::paths::StoredPath;
+use crate::{library, manifest, roots, storage, thumbnails, ThumbnailInfo};
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct MissingFile {
+    pub image_id: String,
+    pub path: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct LibraryRepairs {
+    pub regenerated_thumbnails: usize,
+    pub removed_thumbnails: usize,
+    pub trashed_library_files: usize,
+    // Library copies forgotten because only the original is left
+    pub cleared_library_paths: usize,
+}
+
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct LibraryReport {
+    pub checked_images: usize,
+    // Neither the original nor a library copy exists; see `relink_originals`
+    pub missing_originals: Vec<MissingFile>,
+    // The catalog points at a library copy that is gone
+    pub missing_library_copies: Vec<MissingFile>,
+    // Image IDs whose thumbnail row or file is missing
+    pub broken_thumbnails: Vec<String>,
+    // Files no catalog image refers to
+    pub orphaned_thumbnails: Vec<String>,
+    pub orphaned_library_files: Vec<String>,
+    pub repairs: Option<LibraryRepairs>,
+    pub errors: Vec<String>,
+}
+
+fn all_images(conn: &rusqlite::Connection) -> Result<Vec<CatalogImage>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id",
+            catalog::IMAGE_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+    stmt.query_map([], catalog::map_image)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read images: {}", e))
+}
+
+// Cross-check the catalog against the library folder and the thumbnails
+// dir. With `repair`, broken thumbnails are regenerated, orphaned thumbnails
+// deleted, orphaned library files sent to the trash and dangling library
+// paths cleared. Missing originals are only reported.
+#[tauri::command]
+pub async fn verify_library(
+    app: AppHandle,
+    repair: Option<bool>,
+) -> Result<LibraryReport, DrawStackError> {
+    let repair = repair.unwrap_or(false);
+    let conn = catalog::open(&app)?;
+    let images = all_images(&conn)?;
+    let mut report = LibraryReport {
+        checked_images: images.len(),
+        ..LibraryReport::default()
+    };
+
+    let mut broken = Vec::new();
+    for image in &images {
+        let original_exists = image.original_path.to_path_buf().exists();
+        match &image.library_path {
+            Some(copy) if !copy.to_path_buf().exists() => {
+                report.missing_library_copies.push(MissingFile {
+                    image_id: image.id.clone(),
+                    path: copy.to_string(),
+                });
+                if !original_exists {
+                    report.missing_originals.push(MissingFile {
+                        image_id: image.id.clone(),
+                        path: image.original_path.to_string(),
+                    });
+                }
+            }
+            None if !original_exists => report.missing_originals.push(MissingFile {
+                image_id: image.id.clone(),
+                path: image.original_path.to_string(),
+            }),
+            _ => {}
+        }
+
+        if !image
+            .thumbnail_path
+            .as_ref()
+            .is_some_and(|thumbnail| thumbnail.to_path_buf().exists())
+        {
+            report.broken_thumbnails.push(image.id.clone());
+            broken.push(image);
+        }
+    }
+
+    let ids: HashSet<&str> = images.iter().map(|i| i.id.as_str()).collect();
+    let orphaned_thumbnails: Vec<PathBuf> = thumbnails::thumbnail_files(&app)?
+        .into_iter()
+        .map(|(path, _)| path)
+        .filter(|path| thumbnails::thumbnail_owner(path).is_none_or(|id| !ids.contains(id)))
+        .collect();
+
+    let referenced: HashSet<PathBuf> = images
+        .iter()
+        .filter_map(|i| i.library_path.as_ref().map(StoredPath::to_path_buf))
+        .collect();
+    let mut orphaned_library_files: Vec<PathBuf> = Vec::new();
+    for library_dir in roots::root_dirs(&app)? {
+        if !library_dir.is_dir() {
+            continue;
+        }
+        orphaned_library_files.extend(
+            library::collect_files(&library_dir)?
+                .into_iter()
+                .map(|(path, _)| path)
+                .filter(|path| !referenced.contains(path))
+                .filter(|path| !path.starts_with(library_dir.join(manifest::MANIFEST_DIR))),
+        );
+    }
+
+    report.orphaned_thumbnails = orphaned_thumbnails
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    report.orphaned_library_files = orphaned_library_files
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+
+    tracing::info!(
+        "Verified {} images: {} missing originals, {} missing library copies, {} broken thumbnails, {} orphaned thumbnails, {} orphaned library files",
+        report.checked_images,
+        report.missing_originals.len(),
+        report.missing_library_copies.len(),
+        report.broken_thumbnails.len(),
+        report.orphaned_thumbnails.len(),
+        report.orphaned_library_files.len()
+    );
+
+    if !repair {
+        return Ok(report);
+    }
+
+    let mut repairs = LibraryRepairs::default();
+
+    for missing in &report.missing_library_copies {
+        let cleared = conn
+            .execute(
+                "UPDATE images SET library_path = NULL WHERE id = ?1 AND library_path = ?2",
+                params![missing.image_id, missing.path],
+            )
+            .map_err(|e| format!("Failed to update catalog: {}", e))?;
+        repairs.cleared_library_paths += cleared;
+    }
+
+    // Anything still without a readable source can't get a thumbnail
+    let settings = thumbnails::load_settings(&app);
+    let pool = crate::build_thumbnail_pool(None)?;
+    let regenerated: Vec<ThumbnailInfo> = pool.install(|| {
+        broken
+            .par_iter()
+            .filter(|image| image.source_path().exists())
+            .filter_map(|image| thumbnails::regenerate_one(&app, image, &settings))
+            .collect()
+    });
+    thumbnails::record_regenerated(&app, &regenerated)?;
+    repairs.regenerated_thumbnails = regenerated.len();
+
+    for path in &orphaned_thumbnails {
+        match std::fs::remove_file(path) {
+            Ok(()) => repairs.removed_thumbnails += 1,
+            Err(e) => report
+                .errors
+                .push(format!("Failed to remove {}: {}", path.display(), e)),
+        }
+    }
+    for path in &orphaned_library_files {
+        match library::trash_file(path) {
+            Ok(trashed) => repairs.trashed_library_files += usize::from(trashed),
+            Err(e) => report.errors.push(e),
+        }
+    }
+
+    storage::invalidate(&app);
+    report.repairs = Some(repairs);
+    Ok(report)
+}
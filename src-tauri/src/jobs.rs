@@ -0,0 +1,288 @@
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DrawStackError;
+use crate::{catalog, dedupe, imports, quota, storage, thumbnails};
+
+// Finished jobs kept around for `list_jobs`
+const KEEP_FINISHED: usize = 50;
+
+// Work that can be queued. Each variant maps onto an existing feature, run
+// with a `JobHandle` for progress and cancellation.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobRequest {
+    Import {
+        folder_path: String,
+        pack_id: String,
+        thread_count: Option<usize>,
+        filters: Option<imports::ImportFilters>,
+    },
+    RegenerateThumbnails {
+        pack_id: String,
+        thread_count: Option<usize>,
+    },
+    FindSimilar {
+        threshold: u32,
+    },
+    StorageScan,
+    EnforceStorageQuota {
+        evict: Option<bool>,
+    },
+}
+
+#[derive(Debug, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+// Payload of every `job-progress` event and entry of `list_jobs`
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct JobInfo {
+    pub id: String,
+    pub request: JobRequest,
+    pub state: JobState,
+    pub done: usize,
+    pub total: usize,
+    pub message: Option<String>,
+    pub result: Option<Value>,
+    // Serialized DrawStackError: { code, message, path }
+    pub error: Option<Value>,
+    pub created_at: i64,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    cancelled: Arc<AtomicBool>,
+}
+
+// Progress and cancellation for the job currently running. Long-running
+// features take an `Option<&JobHandle>` so they work the same called
+// directly from a command.
+pub struct JobHandle {
+    app: AppHandle,
+    id: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl JobHandle {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn progress(&self, done: usize, total: usize, message: Option<String>) {
+        let queue = self.app.state::<JobQueue>();
+        queue.update(&self.app, &self.id, |info| {
+            info.done = done;
+            info.total = total;
+            if message.is_some() {
+                info.message = message;
+            }
+        });
+    }
+
+    pub fn cancelled_error(&self) -> DrawStackError {
+        DrawStackError::Other(format!("Job {} was cancelled", self.id))
+    }
+}
+
+// Jobs run one at a time in the order they were queued, on a single worker
+// thread. Most of them already fan out over rayon internally.
+#[derive(Default)]
+pub struct JobQueue {
+    jobs: Mutex<Vec<JobEntry>>,
+    wake: Condvar,
+}
+
+impl JobQueue {
+    pub fn start(&self, app: &AppHandle) {
+        let app = app.clone();
+        std::thread::Builder::new()
+            .name("job-worker".into())
+            .spawn(move || {
+                let queue = app.state::<JobQueue>();
+                loop {
+                    let (id, request, cancelled) = queue.next(&app);
+                    let handle = JobHandle {
+                        app: app.clone(),
+                        id: id.clone(),
+                        cancelled,
+                    };
+                    let outcome = run(&app, request, &handle);
+                    queue.finish(&app, &id, handle.is_cancelled(), outcome);
+                }
+            })
+            .expect("failed to spawn job worker");
+    }
+
+    // Block until a job is queued, mark it running and hand it out
+    fn next(&self, app: &AppHandle) -> (String, JobRequest, Arc<AtomicBool>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        loop {
+            if let Some(entry) = jobs.iter_mut().find(|e| e.info.state == JobState::Queued) {
+                entry.info.state = JobState::Running;
+                let _ = app.emit("job-progress", entry.info.clone());
+                return (
+                    entry.info.id.clone(),
+                    entry.info.request.clone(),
+                    entry.cancelled.clone(),
+                );
+            }
+            jobs = self.wake.wait(jobs).unwrap();
+        }
+    }
+
+    fn update(&self, app: &AppHandle, id: &str, change: impl FnOnce(&mut JobInfo)) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(entry) = jobs.iter_mut().find(|e| e.info.id == id) {
+            change(&mut entry.info);
+            let _ = app.emit("job-progress", entry.info.clone());
+        }
+    }
+
+    fn finish(
+        &self,
+        app: &AppHandle,
+        id: &str,
+        cancelled: bool,
+        outcome: Result<Value, DrawStackError>,
+    ) {
+        self.update(app, id, |info| {
+            match outcome {
+                Ok(result) => {
+                    info.state = JobState::Completed;
+                    info.result = Some(result);
+                }
+                Err(_) if cancelled => info.state = JobState::Cancelled,
+                Err(e) => {
+                    tracing::error!("Job {} failed: {}", id, e);
+                    info.state = JobState::Failed;
+                    info.error = serde_json::to_value(&e).ok();
+                }
+            }
+            if cancelled && info.state == JobState::Completed {
+                // Finished before it noticed the cancel; keep the result
+                info.message = Some("Completed before cancellation".to_string());
+            }
+        });
+        self.prune();
+    }
+
+    fn prune(&self) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let finished = jobs
+            .iter()
+            .filter(|e| !matches!(e.info.state, JobState::Queued | JobState::Running))
+            .count();
+        let mut excess = finished.saturating_sub(KEEP_FINISHED);
+        jobs.retain(|e| {
+            let finished = !matches!(e.info.state, JobState::Queued | JobState::Running);
+            if finished && excess > 0 {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+}
+
+fn to_value<T: serde::Serialize>(
+    result: Result<T, DrawStackError>,
+) -> Result<Value, DrawStackError> {
+    serde_json::to_value(result?)
+        .map_err(|e| format!("Failed to serialize job result: {}", e).into())
+}
+
+fn run(app: &AppHandle, request: JobRequest, job: &JobHandle) -> Result<Value, DrawStackError> {
+    match request {
+        JobRequest::Import {
+            folder_path,
+            pack_id,
+            thread_count,
+            filters,
+        } => to_value(crate::start_import(
+            app,
+            folder_path,
+            pack_id,
+            thread_count,
+            filters,
+            Some(job),
+        )),
+        JobRequest::RegenerateThumbnails {
+            pack_id,
+            thread_count,
+        } => to_value(thumbnails::regenerate(
+            app,
+            &pack_id,
+            thread_count,
+            Some(job),
+        )),
+        JobRequest::FindSimilar { threshold } => {
+            to_value(dedupe::find_similar(app, threshold, Some(job)))
+        }
+        JobRequest::StorageScan => to_value(storage::scan(app, Some(job))),
+        JobRequest::EnforceStorageQuota { evict } => {
+            to_value(quota::enforce(app, evict, Some(job)))
+        }
+    }
+}
+
+#[tauri::command]
+pub fn enqueue_job(app: AppHandle, request: JobRequest) -> JobInfo {
+    let info = JobInfo {
+        id: crate::generate_uuid(),
+        request,
+        state: JobState::Queued,
+        done: 0,
+        total: 0,
+        message: None,
+        result: None,
+        error: None,
+        created_at: catalog::now_unix(),
+    };
+
+    let queue = app.state::<JobQueue>();
+    queue.jobs.lock().unwrap().push(JobEntry {
+        info: info.clone(),
+        cancelled: Arc::new(AtomicBool::new(false)),
+    });
+    queue.wake.notify_one();
+    let _ = app.emit("job-progress", info.clone());
+    info
+}
+
+// Queued jobs are dropped straight away; a running job stops at its next
+// checkpoint and is reported as cancelled.
+#[tauri::command]
+pub fn cancel_job(app: AppHandle, job_id: String) -> Result<(), DrawStackError> {
+    let queue = app.state::<JobQueue>();
+    let mut jobs = queue.jobs.lock().unwrap();
+    let entry = jobs
+        .iter_mut()
+        .find(|e| e.info.id == job_id)
+        .ok_or_else(|| DrawStackError::invalid(format!("Job not found: {}", job_id)))?;
+
+    match entry.info.state {
+        JobState::Queued => {
+            entry.info.state = JobState::Cancelled;
+            let _ = app.emit("job-progress", entry.info.clone());
+        }
+        JobState::Running => entry.cancelled.store(true, Ordering::Relaxed),
+        _ => {}
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_jobs(app: AppHandle) -> Vec<JobInfo> {
+    let queue = app.state::<JobQueue>();
+    let jobs = queue.jobs.lock().unwrap();
+    jobs.iter().map(|e| e.info.clone()).collect()
+}
@@ -1,11 +1,253 @@
-use image::{imageops::FilterType, ImageReader};
+use image::ImageReader;
+use rayon::prelude::*;
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 use tauri::{AppHandle, Emitter, Manager};
 use uuid::Uuid;
 
+use error::DrawStackError;
+use paths::StoredPath;
+
+mod animation;
+mod archive;
+mod atlas;
+mod audio_cues;
+mod backup;
+mod browse;
+mod bundle;
+mod catalog;
+mod catalog_export;
+mod clipboard;
+mod config;
+#[cfg(feature = "pdf")]
+mod contact_sheet;
+mod content_flag;
+mod content_hash;
+mod dedupe;
+mod eagle;
+mod error;
+mod exif;
+mod export;
+mod folder_counts;
+mod image_query;
+mod import_preview;
+mod imports;
+mod integrity;
+mod jobs;
+mod library;
+mod logging;
+mod maintenance;
+mod manifest;
+mod metrics;
+#[cfg(feature = "drag")]
+mod native_drag;
+mod natural;
+mod orientation;
+mod pack_stats;
+mod packs;
+mod palette;
+mod paths;
+#[cfg(feature = "pdf")]
+mod pdf;
+mod picker;
+mod practice;
+mod protocol;
+#[cfg(feature = "psd")]
+mod psd;
+mod quick_find;
+mod quota;
+mod ratings;
+#[cfg(feature = "raw")]
+mod raw;
+mod relink;
+mod remote;
+mod resize;
+mod roots;
+mod scan;
+mod scope;
+mod search;
+mod session;
+mod session_presets;
+mod smart_collections;
+mod sniff;
+mod source_io;
+mod storage;
+mod tags;
+mod thumbnail_cache;
+mod thumbnails;
+mod transforms;
+mod url_import;
+mod variants;
+#[cfg(feature = "video")]
+mod video;
+mod watcher;
+mod xmp;
+
 static VALID_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif", "bmp"];
 
+fn extension_lower(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+}
+
+// Case-insensitive membership test that doesn't allocate, for checks run on
+// every file of a scan
+fn extension_in(ext: &str, extensions: &[&str]) -> bool {
+    extensions
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(ext))
+}
+
+// Whether `ext`, in any case, is a supported image extension, including
+// formats whose decoders are optional cargo features
+fn is_supported_extension(ext: &str) -> bool {
+    #[cfg(feature = "raw")]
+    if raw::is_raw_extension(ext) {
+        return true;
+    }
+
+    #[cfg(feature = "avif")]
+    if ext.eq_ignore_ascii_case("avif") {
+        return true;
+    }
+
+    #[cfg(feature = "jxl")]
+    if ext.eq_ignore_ascii_case("jxl") {
+        return true;
+    }
+
+    #[cfg(feature = "video")]
+    if video::is_video_extension(ext) {
+        return true;
+    }
+
+    #[cfg(feature = "psd")]
+    if psd::is_psd_extension(ext) {
+        return true;
+    }
+
+    extension_in(ext, VALID_EXTENSIONS)
+}
+
+// Judged by name alone; see `is_image_file` for files that may be misnamed
+fn is_supported_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(is_supported_extension)
+}
+
+// A supported image by name or, failing that, by content. Costs a header
+// read for files with other names.
+fn is_image_file(path: &Path) -> bool {
+    is_supported_image(path) || sniff::image_type(path).is_some()
+}
+
+// Decode an image upright, honoring its EXIF orientation, so everything
+// rendered from it matches how the photo was taken
+fn decode_image(path: &Path) -> Result<image::DynamicImage, DrawStackError> {
+    let img = decode_image_raw(path).map_err(|e| DrawStackError::decode(path, e))?;
+    Ok(match exif::read_orientation(path) {
+        Some(orientation) => exif::apply_orientation(img, orientation),
+        None => img,
+    })
+}
+
+// Largest image the decoders will take on. A malformed header or a huge
+// scan would otherwise allocate gigabytes mid-import; such files fail with a
+// clear error and show up in the import report instead.
+const MAX_DECODE_DIMENSION: u32 = 20_000;
+const MAX_DECODE_ALLOC: u64 = 1024 * 1024 * 1024;
+
+fn decode_limits() -> image::Limits {
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_DECODE_DIMENSION);
+    limits.max_image_height = Some(MAX_DECODE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    limits
+}
+
+fn decode_error(e: image::ImageError) -> String {
+    match e {
+        image::ImageError::Limits(_) => format!(
+            "Image is too large to decode (limit {}x{} pixels, {})",
+            MAX_DECODE_DIMENSION,
+            MAX_DECODE_DIMENSION,
+            format_bytes(MAX_DECODE_ALLOC)
+        ),
+        e => e.to_string(),
+    }
+}
+
+// Decode by extension, falling back to the format the content shows when
+// the file has no usable extension or its decoder rejects it
+fn decode_image_raw(path: &Path) -> Result<image::DynamicImage, String> {
+    let named = extension_lower(path).unwrap_or_default();
+    if !is_supported_extension(&named) {
+        let kind =
+            sniff::image_type(path).ok_or_else(|| "Not a supported image format".to_string())?;
+        return decode_as(path, kind.extension());
+    }
+
+    decode_as(path, &named).or_else(|e| match sniff::misnamed(path) {
+        Some(actual) => {
+            tracing::debug!("Decoding {} as {}", path.display(), actual);
+            decode_as(path, actual).map_err(|_| e)
+        }
+        None => Err(e),
+    })
+}
+
+// Decode `path` with the decoder for `ext`, which must be lowercase
+fn decode_as(path: &Path, ext: &str) -> Result<image::DynamicImage, String> {
+    #[cfg(feature = "raw")]
+    if raw::is_raw_extension(ext) {
+        return raw::decode_preview(path);
+    }
+
+    #[cfg(feature = "jxl")]
+    if ext == "jxl" {
+        return decode_jxl(path);
+    }
+
+    #[cfg(feature = "video")]
+    if video::is_video_extension(ext) {
+        return video::decode_middle_frame(path);
+    }
+
+    #[cfg(feature = "psd")]
+    if psd::is_psd_extension(ext) {
+        return psd::decode_preview(path);
+    }
+
+    if animation::is_animatable_extension(ext) {
+        if let Ok(img) = animation::decode_first_frame(path, ext) {
+            return Ok(img);
+        }
+        // Misnamed files fall through to the content-guessed reader below
+    }
+
+    // AVIF is handled here by image's dav1d decoder when `avif` is enabled
+    let mut reader = ImageReader::open(paths::extended(path))
+        .and_then(|r| r.with_guessed_format())
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+    reader.limits(decode_limits());
+    reader.decode().map_err(decode_error)
+}
+
+#[cfg(feature = "jxl")]
+fn decode_jxl(path: &Path) -> Result<image::DynamicImage, String> {
+    let file = fs::File::open(paths::extended(path))
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+    let decoder = jxl_oxide::integration::JxlDecoder::new(file)
+        .map_err(|e| format!("Failed to read JPEG XL header: {}", e))?;
+    image::DynamicImage::from_decoder(decoder)
+        .map_err(|e| format!("Failed to decode JPEG XL image: {}", e))
+}
+
 #[derive(Debug, serde::Serialize, Clone)]
 struct FolderContents {
     folders: Vec<FolderInfo>,
@@ -17,6 +259,8 @@ struct FolderContents {
 struct ImageInfo {
     path: String,
     filename: String,
+    is_animated: bool,
+    frame_count: Option<u32>,
 }
 
 #[derive(Debug, serde::Serialize, Clone)]
@@ -32,13 +276,44 @@ struct FolderInfo {
     image_count: usize,
 }
 
+// Recursive count for one subfolder of a browsed folder, computed after
+// `browse_folder` has returned
 #[derive(Debug, serde::Serialize, Clone)]
+struct FolderCountUpdated {
+    parent: String,
+    path: String,
+    image_count: usize,
+    // False if the depth cap cut the count short
+    complete: bool,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 struct ThumbnailInfo {
     id: String,
-    original_path: String,
-    thumbnail_path: String,
+    original_path: StoredPath,
+    thumbnail_path: StoredPath,
     filename: String,
     relative_path: String,
+    // Hex-encoded perceptual hash, absent when the image couldn't be decoded
+    #[serde(default)]
+    dhash: Option<String>,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+    #[serde(default)]
+    orientation: Option<u16>,
+    // EXIF DateTimeOriginal, `YYYY-MM-DDTHH:MM:SS`
+    #[serde(default)]
+    captured_at: Option<String>,
+    #[serde(default)]
+    is_animated: bool,
+    // GIF and WebP only; 1 for a still image in those formats
+    #[serde(default)]
+    frame_count: Option<u32>,
+    // Clip length of a video reference
+    #[serde(default)]
+    duration_ms: Option<u64>,
 }
 
 #[derive(Debug, serde::Serialize, Clone)]
@@ -47,6 +322,8 @@ struct BatchProgress {
     total_batches: usize,
     thumbnails: Vec<ThumbnailInfo>,
     progress: f32,
+    // Unchanged files the import left out, for the whole import
+    skipped: usize,
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -55,51 +332,17 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-fn scan_for_images(folder_path: &Path) -> Result<Vec<std::path::PathBuf>, String> {
-    let mut images = Vec::new();
-
-    fn scan_recursive(path: &Path, images: &mut Vec<std::path::PathBuf>) -> Result<(), String> {
-        if !path.exists() {
-            return Err(format!("Path does not exist: {}", path.display()));
-        }
-
-        let entries = fs::read_dir(path)
-            .map_err(|e| format!("Failed to read directory {}: {}", path.display(), e))?;
-
-        for entry in entries.flatten() {
-            let entry_path = entry.path();
-
-            if entry_path.is_dir() {
-                // Recursively scan subdirectories
-                scan_recursive(&entry_path, images)?;
-            } else if entry_path.is_file() {
-                // Fast extension check
-                if let Some(ext) = entry_path.extension() {
-                    if let Some(ext_str) = ext.to_str() {
-                        let ext_lower = ext_str.to_lowercase();
-                        if VALID_EXTENSIONS.contains(&ext_lower.as_str()) {
-                            images.push(entry_path);
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    scan_recursive(folder_path, &mut images)?;
-    Ok(images)
-}
-
 #[tauri::command]
-async fn quick_scan(folder_path: String) -> Result<QuickScanResult, String> {
-    println!("Quick scanning folder: {}", folder_path);
+async fn quick_scan(
+    app: AppHandle,
+    folder_path: String,
+) -> Result<QuickScanResult, DrawStackError> {
+    tracing::info!("Quick scanning folder: {}", folder_path);
 
     let source_path = Path::new(&folder_path);
-    let images = scan_for_images(source_path)?;
+    let images = scan::scan_for_images(source_path, &config::load(&app).scan)?;
 
-    println!("Found {} images", images.len());
+    tracing::info!("Found {} images", images.len());
 
     // Build folder structure
     let mut folder_map: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
@@ -134,12 +377,8 @@ async fn quick_scan(folder_path: String) -> Result<QuickScanResult, String> {
     })
 }
 
-fn generate_fast_thumbnail(
-    source_path: &Path,
-    app_handle: &AppHandle,
-    image_id: &str,
-) -> Result<String, String> {
-    let app_data = app_handle
+fn thumbnails_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data = app
         .path()
         .app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
@@ -147,34 +386,118 @@ fn generate_fast_thumbnail(
     let thumbnails_dir = app_data.join("thumbnails");
     fs::create_dir_all(&thumbnails_dir)
         .map_err(|e| format!("Failed to create thumbnails dir: {}", e))?;
+    Ok(thumbnails_dir)
+}
+
+struct GeneratedThumbnail {
+    path: StoredPath,
+    dhash: u64,
+    timings: metrics::FileTimings,
+}
+
+fn generate_fast_thumbnail(
+    source_path: &Path,
+    app_handle: &AppHandle,
+    image_id: &str,
+    settings: &thumbnails::ThumbnailSettings,
+) -> Result<GeneratedThumbnail, String> {
+    let thumbnails_dir = thumbnails::shard_dir(app_handle, image_id)?;
+    let mut timings = metrics::FileTimings::default();
+
+    // Camera JPEGs carry a small preview in their EXIF data, which is far
+    // cheaper to decode than the photo itself
+    let stage = Instant::now();
+    let img = match exif::embedded_thumbnail(source_path, settings.min_embedded_size()) {
+        Some(preview) => preview,
+        None => decode_image(source_path).map_err(|e| e.to_string())?,
+    };
+    timings.decode = stage.elapsed();
+
+    let stage = Instant::now();
+    let thumbnail = settings.resize(&img);
+    timings.resize = stage.elapsed();
+
+    let stage = Instant::now();
+    let thumb_path = settings.save(&thumbnail, &thumbnails_dir, image_id)?;
+    timings.save = stage.elapsed();
 
-    // Open image
-    let img = ImageReader::open(source_path)
-        .map_err(|_| "Skip".to_string())?
-        .decode()
-        .map_err(|_| "Skip".to_string())?;
+    Ok(GeneratedThumbnail {
+        path: StoredPath::from(thumb_path.as_path()),
+        // The thumbnail has plenty of detail for a 9x8 difference hash
+        dhash: dedupe::dhash(&thumbnail),
+        timings,
+    })
+}
+
+// Default and hard cap on how deep recursive folder counts descend
+const FOLDER_COUNT_DEPTH: usize = 8;
+const MAX_FOLDER_COUNT_DEPTH: usize = 32;
+
+// Images directly inside `dir`, as an import's scan would list them
+fn count_images_shallow(dir: &Path, by_content: bool) -> usize {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+                .filter(|e| scan::is_listed_image(&e.path(), by_content))
+                .count()
+        })
+        .unwrap_or(0)
+}
 
-    // Use Nearest for MAXIMUM speed - 100x100 tiny thumbnails
-    let thumbnail = img.resize(100, 100, FilterType::Nearest);
+// Images under `dir` down to `max_depth` levels. The flag is false if there
+// were folders deeper than that.
+fn count_images_recursive(dir: &Path, max_depth: usize, by_content: bool) -> (usize, bool) {
+    let mut count = 0;
+    let mut complete = true;
+    let mut pending = vec![(dir.to_path_buf(), 0)];
 
-    // Save as JPEG quality 75 (fast)
-    let thumb_path = thumbnails_dir.join(format!("{}.jpg", image_id));
-    thumbnail
-        .save_with_format(&thumb_path, image::ImageFormat::Jpeg)
-        .map_err(|_| "Skip".to_string())?;
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if depth < max_depth {
+                    pending.push((entry.path(), depth + 1));
+                } else {
+                    complete = false;
+                }
+            } else if file_type.is_file() && scan::is_listed_image(&entry.path(), by_content) {
+                count += 1;
+            }
+        }
+    }
 
-    Ok(thumb_path.to_string_lossy().to_string())
+    (count, complete)
 }
 
+// Lists a folder with shallow image counts for each subfolder. With
+// `recursive_counts`, full counts follow as `folder-count-updated` events
+// from a background thread, so deep trees don't hold up the listing.
 #[tauri::command]
-async fn browse_folder(folder_path: String) -> Result<FolderContents, String> {
+async fn browse_folder(
+    app: AppHandle,
+    folder_path: String,
+    recursive_counts: Option<bool>,
+    max_depth: Option<usize>,
+    sort: Option<browse::BrowseSort>,
+) -> Result<FolderContents, DrawStackError> {
     let path = Path::new(&folder_path);
 
     if !path.exists() {
-        return Err("Folder does not exist".to_string());
+        return Err(DrawStackError::not_found(path));
     }
 
-    let entries = fs::read_dir(path).map_err(|e| format!("Failed to read directory: {}", e))?;
+    // Counts for the folder being left are no longer wanted
+    let generation = app.state::<folder_counts::FolderCounts>().navigate();
+
+    let entries = fs::read_dir(path).map_err(|e| DrawStackError::io("read directory", path, e))?;
+    let by_content = config::load(&app).scan.detect_by_content;
 
     let mut folders = Vec::new();
     let mut images = Vec::new();
@@ -192,33 +515,43 @@ async fn browse_folder(folder_path: String) -> Result<FolderContents, String> {
             folders.push(FolderInfo {
                 path: entry_path.to_string_lossy().to_string(),
                 name,
-                image_count: 0,
+                image_count: count_images_shallow(&entry_path, by_content),
             });
-        } else if entry_path.is_file() {
-            // Fast extension check with static array
-            if let Some(ext) = entry_path.extension() {
-                if let Some(ext_str) = ext.to_str() {
-                    let ext_lower = ext_str.to_lowercase();
-                    if VALID_EXTENSIONS.contains(&ext_lower.as_str()) {
-                        let filename = entry_path
-                            .file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or("Unknown")
-                            .to_string();
-
-                        images.push(ImageInfo {
-                            path: entry_path.to_string_lossy().to_string(),
-                            filename,
-                        });
-                    }
-                }
+        } else if entry_path.is_file() && scan::is_listed_image(&entry_path, by_content) {
+            if let Ok(meta) = entry.metadata() {
+                images.push(browse::ImageEntry::new(&entry_path, &meta));
             }
         }
     }
 
-    // Natural sort (case-insensitive)
-    folders.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-    images.sort_by(|a, b| a.filename.to_lowercase().cmp(&b.filename.to_lowercase()));
+    let images = browse::sort_listing(&mut folders, &mut images, sort.unwrap_or_default());
+
+    if recursive_counts.unwrap_or(false) && !folders.is_empty() {
+        let max_depth = max_depth
+            .unwrap_or(FOLDER_COUNT_DEPTH)
+            .min(MAX_FOLDER_COUNT_DEPTH);
+        let parent = folder_path.clone();
+        let paths: Vec<String> = folders.iter().map(|f| f.path.clone()).collect();
+        std::thread::spawn(move || {
+            let counts = app.state::<folder_counts::FolderCounts>();
+            for path in paths {
+                if !counts.is_current(generation) {
+                    break;
+                }
+                let (image_count, complete) =
+                    count_images_recursive(Path::new(&path), max_depth, by_content);
+                let _ = app.emit(
+                    "folder-count-updated",
+                    FolderCountUpdated {
+                        parent: parent.clone(),
+                        path,
+                        image_count,
+                        complete,
+                    },
+                );
+            }
+        });
+    }
 
     Ok(FolderContents {
         folders,
@@ -227,90 +560,623 @@ async fn browse_folder(folder_path: String) -> Result<FolderContents, String> {
     })
 }
 
+#[derive(Debug, serde::Serialize, Clone)]
+struct ImageDetails {
+    path: String,
+    // Display dimensions, swapped for EXIF-rotated photos
+    width: Option<u32>,
+    height: Option<u32>,
+    format: Option<String>,
+    size_bytes: u64,
+    size_formatted: String,
+    // Unix seconds
+    modified: Option<i64>,
+}
+
 #[tauri::command]
-async fn count_folder_images(folder_path: String) -> Result<usize, String> {
-    let path = Path::new(&folder_path);
-    let count = scan_for_images(path).unwrap_or_default().len();
-    Ok(count)
+async fn get_image_info(path: String) -> Result<ImageDetails, DrawStackError> {
+    let image_path = Path::new(&path);
+    let meta = fs::metadata(image_path).map_err(|e| DrawStackError::io("read", image_path, e))?;
+
+    // Only the header is read - the pixels are never decoded
+    let reader = ImageReader::open(image_path)
+        .and_then(|r| r.with_guessed_format())
+        .map_err(|e| DrawStackError::io("open", image_path, e))?;
+    let format = reader
+        .format()
+        .map(|f| format!("{:?}", f).to_uppercase())
+        .or_else(|| extension_lower(image_path).map(|e| e.to_uppercase()));
+    let dimensions = reader.into_dimensions().ok().map(|(w, h)| {
+        if matches!(exif::read_orientation(image_path), Some(5..=8)) {
+            (h, w)
+        } else {
+            (w, h)
+        }
+    });
+
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
+
+    Ok(ImageDetails {
+        path,
+        width: dimensions.map(|(w, _)| w),
+        height: dimensions.map(|(_, h)| h),
+        format,
+        size_bytes: meta.len(),
+        size_formatted: format_bytes(meta.len()),
+        modified,
+    })
+}
+
+// Decode an image and shrink it to fit `max_dimension` for the viewer,
+// returned as raw JPEG bytes (PNG if it has transparency). Scaling here means
+// a 60MP photo never reaches the webview at full size, and formats it can't
+// display (RAW, TIFF, JXL) still open.
+#[tauri::command]
+async fn load_image_scaled(
+    path: String,
+    max_dimension: u32,
+) -> Result<tauri::ipc::Response, DrawStackError> {
+    let image_path = Path::new(&path);
+    if max_dimension == 0 {
+        return Err(DrawStackError::invalid("max_dimension must be above 0"));
+    }
+    if !is_image_file(image_path) {
+        return Err(DrawStackError::Unsupported {
+            path: image_path.to_path_buf(),
+        });
+    }
+
+    let img = variants::load_scaled(image_path, max_dimension)?;
+
+    let mut bytes = Vec::new();
+    if img.color().has_alpha() {
+        img.write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| format!("Failed to encode preview: {}", e))?;
+    } else {
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, 90)
+            .encode_image(&img.to_rgb8())
+            .map_err(|e| format!("Failed to encode preview: {}", e))?;
+    }
+
+    Ok(tauri::ipc::Response::new(bytes))
+}
+
+// Identify an image under `source_root` and generate its thumbnail
+fn thumbnail_info(
+    app: &AppHandle,
+    source_root: &Path,
+    img_path: &Path,
+    settings: &thumbnails::ThumbnailSettings,
+    cache: Option<&thumbnail_cache::ThumbnailCache>,
+) -> ThumbnailInfo {
+    thumbnail_info_with_failure(app, source_root, img_path, settings, cache).info
+}
+
+struct ThumbnailOutcome {
+    info: ThumbnailInfo,
+    // Why the thumbnail couldn't be made when the info had to fall back to
+    // the original
+    failure: Option<String>,
+    timings: metrics::FileTimings,
+}
+
+// `thumbnail_info`, plus the failure reason and stage timings
+fn thumbnail_info_with_failure(
+    app: &AppHandle,
+    source_root: &Path,
+    img_path: &Path,
+    settings: &thumbnails::ThumbnailSettings,
+    cache: Option<&thumbnail_cache::ThumbnailCache>,
+) -> ThumbnailOutcome {
+    let filename = img_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let relative_path = img_path
+        .strip_prefix(source_root)
+        .ok()
+        .and_then(|p| p.parent())
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let original_path = StoredPath::from(img_path);
+    let mut timings = metrics::FileTimings::default();
+    let stage = Instant::now();
+    let metadata = exif::read_metadata(img_path);
+    timings.bytes = fs::metadata(paths::extended(img_path))
+        .map(|m| m.len())
+        .unwrap_or(0);
+    timings.io = stage.elapsed();
+
+    if let Some(cached) = cache.and_then(|c| c.get(img_path)) {
+        let info = ThumbnailInfo {
+            id: cached.image_id.clone(),
+            original_path,
+            thumbnail_path: cached.thumbnail_path.clone(),
+            filename,
+            relative_path,
+            dhash: cached.dhash.clone(),
+            width: metadata.width,
+            height: metadata.height,
+            orientation: metadata.orientation,
+            captured_at: metadata.captured_at,
+            is_animated: animation::is_animated(metadata.frame_count),
+            frame_count: metadata.frame_count,
+            duration_ms: metadata.duration_ms,
+        };
+        return ThumbnailOutcome {
+            info,
+            failure: None,
+            timings,
+        };
+    }
+
+    // Content-derived IDs keep re-imports idempotent; unreadable files still
+    // get a unique ID so they show up in the batch
+    let stage = Instant::now();
+    let image_id =
+        content_hash::content_id(img_path).unwrap_or_else(|_| Uuid::new_v4().to_string());
+    timings.io += stage.elapsed();
+
+    // Try to generate thumbnail, use original if it fails
+    let (thumbnail_path, dhash, failure) =
+        match generate_fast_thumbnail(img_path, app, &image_id, settings) {
+            Ok(thumbnail) => {
+                timings.add(&thumbnail.timings);
+                (thumbnail.path, Some(dedupe::to_hex(thumbnail.dhash)), None)
+            }
+            Err(e) => (original_path.clone(), None, Some(e)),
+        };
+
+    let info = ThumbnailInfo {
+        id: image_id,
+        original_path,
+        thumbnail_path,
+        filename,
+        relative_path,
+        dhash,
+        width: metadata.width,
+        height: metadata.height,
+        orientation: metadata.orientation,
+        captured_at: metadata.captured_at,
+        is_animated: animation::is_animated(metadata.frame_count),
+        frame_count: metadata.frame_count,
+        duration_ms: metadata.duration_ms,
+    };
+    ThumbnailOutcome {
+        info,
+        failure,
+        timings,
+    }
+}
+
+// Read a source file through with retries, then thumbnail it. Files that
+// stay unreachable are left out rather than cataloged with a dead path.
+fn import_one(
+    app: &AppHandle,
+    source_root: &Path,
+    img_path: &Path,
+    settings: &thumbnails::ThumbnailSettings,
+    cache: &thumbnail_cache::ThumbnailCache,
+) -> Result<(ThumbnailOutcome, Option<source_io::ReadStats>), imports::ImportFailure> {
+    // Unchanged files reuse their thumbnail, so there's nothing to read
+    let read = match cache.get(img_path) {
+        Some(_) => None,
+        None => Some(
+            source_io::prefetch(img_path, source_root).map_err(|reason| {
+                imports::ImportFailure {
+                    path: img_path.to_string_lossy().to_string(),
+                    reason,
+                    kind: imports::FailureKind::Unreachable,
+                }
+            })?,
+        ),
+    };
+
+    let mut outcome =
+        thumbnail_info_with_failure(app, source_root, img_path, settings, Some(cache));
+    if let Some(read) = read {
+        outcome.timings.io += read.elapsed;
+    }
+    Ok((outcome, read))
+}
+
+// Worker pool for thumbnail generation. `None` or 0 lets rayon use one
+// thread per logical core.
+fn build_thumbnail_pool(thread_count: Option<usize>) -> Result<rayon::ThreadPool, String> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(thread_count.unwrap_or(0))
+        .thread_name(|i| format!("thumbnail-worker-{}", i))
+        .build()
+        .map_err(|e| format!("Failed to create thumbnail thread pool: {}", e))
 }
 
 #[tauri::command]
 async fn import_pack_progressive(
     app: AppHandle,
     folder_path: String,
-    _pack_id: String,
-) -> Result<(), String> {
-    println!("Starting progressive import from: {}", folder_path);
+    pack_id: String,
+    thread_count: Option<usize>,
+    filters: Option<imports::ImportFilters>,
+) -> Result<imports::ImportReport, DrawStackError> {
+    start_import(&app, folder_path, pack_id, thread_count, filters, None)
+}
+
+fn start_import(
+    app: &AppHandle,
+    folder_path: String,
+    pack_id: String,
+    thread_count: Option<usize>,
+    filters: Option<imports::ImportFilters>,
+    job: Option<&jobs::JobHandle>,
+) -> Result<imports::ImportReport, DrawStackError> {
+    tracing::info!("Starting progressive import from: {}", folder_path);
+    let filters = filters.unwrap_or_default();
+    filters.validate().map_err(DrawStackError::invalid)?;
+    let running =
+        imports::RunningImport::start(app.state::<imports::ImportControl>().inner(), &pack_id)?;
 
     let source_path = Path::new(&folder_path);
-    let images = scan_for_images(source_path)?;
+    let images = scan::scan_for_images(source_path, &config::load(app).scan)?;
+    let (images, skipped) = imports::drop_unchanged(&catalog::open(app)?, source_path, images)?;
+    if skipped > 0 {
+        tracing::info!("Skipping {} files already in the catalog", skipped);
+    }
+    let (images, filtered) = imports::apply_filters(images, &filters);
+    if filtered > 0 {
+        tracing::info!("Filtered out {} files", filtered);
+    }
+
+    // Persist the file list so an interrupted import can be resumed later
+    let journal = imports::ImportJournal {
+        pack_id,
+        folder_path,
+        total: images.len(),
+        processed: 0,
+        skipped,
+        filtered,
+        failed: Vec::new(),
+    };
+    imports::create_journal(app, &journal, &images)?;
+
+    process_import(app, running, journal, images, thread_count, job)
+}
+
+// Deepest folder containing every path, so images keep their relative
+// folders within the drop
+fn common_parent(paths: &[PathBuf]) -> PathBuf {
+    let mut common = paths
+        .first()
+        .and_then(|p| p.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    for path in paths.iter().skip(1) {
+        while !path.starts_with(&common) {
+            if !common.pop() {
+                return PathBuf::new();
+            }
+        }
+    }
+    common
+}
+
+// Import a hand-picked list of files, e.g. dropped onto the window or pasted
+// from the clipboard, through the same batch pipeline and events as a folder
+// import. Folders in the list are scanned; unsupported files are skipped.
+#[tauri::command]
+async fn import_files(
+    app: AppHandle,
+    paths: Vec<String>,
+    pack_id: String,
+    thread_count: Option<usize>,
+    filters: Option<imports::ImportFilters>,
+) -> Result<imports::ImportReport, DrawStackError> {
+    let filters = filters.unwrap_or_default();
+    filters.validate().map_err(DrawStackError::invalid)?;
+    let running =
+        imports::RunningImport::start(app.state::<imports::ImportControl>().inner(), &pack_id)?;
+
+    let scan_settings = config::load(&app).scan;
+    let mut images: Vec<PathBuf> = Vec::new();
+    for path in paths.iter().map(PathBuf::from) {
+        if path.is_dir() {
+            images.extend(scan::scan_for_images(&path, &scan_settings)?);
+        } else if path.is_file() && is_supported_image(&path) {
+            images.push(path);
+        }
+    }
+    let mut seen = HashSet::new();
+    images.retain(|path| seen.insert(path.clone()));
+    if images.is_empty() {
+        return Err(DrawStackError::invalid("None of the files can be imported"));
+    }
+
+    let (images, filtered) = imports::apply_filters(images, &filters);
+    tracing::info!(
+        "Importing {} dropped files into pack {}",
+        images.len(),
+        pack_id
+    );
+
+    let journal = imports::ImportJournal {
+        pack_id,
+        folder_path: common_parent(&images).to_string_lossy().to_string(),
+        total: images.len(),
+        processed: 0,
+        skipped: 0,
+        filtered,
+        failed: Vec::new(),
+    };
+    imports::create_journal(&app, &journal, &images)?;
+
+    process_import(&app, running, journal, images, thread_count, None)
+}
+
+// Thumbnails sent per `import-batch` event. Each carries full image records,
+// so they go out in large, infrequent batches; `import-progress` covers the
+// counts in between.
+const EMIT_BATCH_SIZE: usize = 500;
+
+fn emit_import_batch(
+    app: &AppHandle,
+    journal: &imports::ImportJournal,
+    pending: &mut Vec<ThumbnailInfo>,
+    total_batches: usize,
+) -> Result<(), String> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let batch_progress = BatchProgress {
+        batch: (journal.processed - 1) / EMIT_BATCH_SIZE,
+        total_batches,
+        thumbnails: std::mem::take(pending),
+        progress: (journal.processed as f32 / journal.total as f32) * 100.0,
+        skipped: journal.skipped,
+    };
+    app.emit("import-batch", batch_progress)
+        .map_err(|e| format!("Failed to emit event: {}", e))
+}
+
+fn emit_import_progress(
+    app: &AppHandle,
+    journal: &imports::ImportJournal,
+    processed: usize,
+    failed: usize,
+) {
+    let _ = app.emit(
+        "import-progress",
+        imports::ImportProgress {
+            pack_id: journal.pack_id.clone(),
+            processed,
+            total: journal.total,
+            percent: (processed as f32 / journal.total as f32) * 100.0,
+            skipped: journal.skipped,
+            failed,
+        },
+    );
+}
+
+// Tell the UI an import stopped at a batch boundary and report what it got
+// through
+fn pause_import_run(
+    app: &AppHandle,
+    journal: &imports::ImportJournal,
+    reason: Option<String>,
+) -> Result<imports::ImportReport, DrawStackError> {
+    tracing::info!(
+        "Import paused for pack {} after {} of {} images",
+        journal.pack_id,
+        journal.processed,
+        journal.total
+    );
+    app.emit(
+        "import-paused",
+        imports::ImportPaused {
+            pack_id: journal.pack_id.clone(),
+            processed: journal.processed,
+            total: journal.total,
+            reason,
+        },
+    )
+    .map_err(|e| format!("Failed to emit event: {}", e))?;
+    Ok(journal.report())
+}
 
-    let total = images.len();
-    println!("Processing {} images", total);
+// Runs the batch/thumbnail loop over `images`, which are the files still
+// left to process for `journal`. Stops early if the import is paused; a
+// cancelled job stops the same way, leaving the journal to resume from.
+// Callers take `_running` before writing the journal, so a second import of
+// the pack can't replace the file list of one in progress.
+fn process_import(
+    app: &AppHandle,
+    _running: imports::RunningImport<'_>,
+    mut journal: imports::ImportJournal,
+    images: Vec<PathBuf>,
+    thread_count: Option<usize>,
+    job: Option<&jobs::JobHandle>,
+) -> Result<imports::ImportReport, DrawStackError> {
+    let control = app.state::<imports::ImportControl>();
+
+    let source_path = PathBuf::from(&journal.folder_path);
+    // Used only when the pack has no row yet, i.e. wasn't made by create_pack
+    let pack_name = source_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+    let total = journal.total;
+    let remaining = images.len();
+    tracing::info!("Processing {} of {} images", remaining, total);
+
+    let mut pool = build_thumbnail_pool(thread_count)?;
+    tracing::debug!(
+        "Using {} thumbnail worker threads",
+        pool.current_num_threads()
+    );
+
+    let settings = thumbnails::load_settings(app);
+    let mut conn = catalog::open(app)?;
 
     let start_time = std::time::Instant::now();
 
     // Smaller batches with thumbnail generation
     let batch_size = 100;
-    let total_batches = (total + batch_size - 1) / batch_size;
+    let first_batch = journal.processed / batch_size;
+    let total_batches = total.div_ceil(EMIT_BATCH_SIZE);
+    let mut pending: Vec<ThumbnailInfo> = Vec::new();
+    let throttle = imports::ProgressThrottle::default();
+    let done = AtomicUsize::new(journal.processed);
+    let mut sample = metrics::ImportSample::new(
+        &journal.pack_id,
+        &journal.folder_path,
+        pool.current_num_threads(),
+    );
+
+    for (offset, chunk) in images.chunks(batch_size).enumerate() {
+        if control.is_paused(&journal.pack_id) {
+            emit_import_batch(app, &journal, &mut pending, total_batches)?;
+            metrics::record(app, sample, false);
+            return pause_import_run(app, &journal, None);
+        }
+
+        if let Some(job) = job.filter(|j| j.is_cancelled()) {
+            emit_import_batch(app, &journal, &mut pending, total_batches)?;
+            metrics::record(app, sample, false);
+            return Err(job.cancelled_error());
+        }
 
-    for (batch_num, chunk) in images.chunks(batch_size).enumerate() {
+        let batch_num = first_batch + offset;
         let batch_start = std::time::Instant::now();
-        println!("Processing batch {} of {}", batch_num + 1, total_batches);
-
-        // Generate thumbnails - skip failures
-        let thumbnails: Vec<ThumbnailInfo> = chunk
-            .iter()
-            .filter_map(|img_path| {
-                let image_id = Uuid::new_v4().to_string();
-
-                let filename = img_path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-
-                let relative_path = img_path
-                    .strip_prefix(source_path)
-                    .ok()
-                    .and_then(|p| p.parent())
-                    .and_then(|p| p.to_str())
-                    .unwrap_or("")
-                    .to_string();
-
-                let original_path_str = img_path.to_string_lossy().to_string();
-
-                // Try to generate thumbnail, use original if it fails
-                let thumbnail_path = generate_fast_thumbnail(img_path, &app, &image_id)
-                    .unwrap_or_else(|_| original_path_str.clone());
-
-                Some(ThumbnailInfo {
-                    id: image_id,
-                    original_path: original_path_str,
-                    thumbnail_path,
-                    filename,
-                    relative_path,
+        tracing::debug!(
+            "Processing batch {} of {}",
+            batch_num + 1,
+            total.div_ceil(batch_size)
+        );
+
+        // Unchanged files reuse the thumbnail from a previous import
+        let cache = thumbnail_cache::ThumbnailCache::load(&conn, chunk, &settings)?;
+
+        // Generate thumbnails in parallel - failures fall back to the original
+        let failed_before = journal.failed.len();
+        let results: Vec<_> = pool.install(|| {
+            chunk
+                .par_iter()
+                .map(|img_path| {
+                    let result = import_one(app, &source_path, img_path, &settings, &cache);
+                    let processed = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    if throttle.ready() {
+                        emit_import_progress(app, &journal, processed, failed_before);
+                    }
+                    result
                 })
-            })
-            .collect();
+                .collect()
+        });
+
+        // Nothing in the batch could be read: the share is most likely gone.
+        // Pause before the batch is recorded so a resume retries it.
+        if !results.is_empty() && results.iter().all(Result::is_err) {
+            tracing::warn!(
+                "Source {} is unreachable, pausing import",
+                journal.folder_path
+            );
+            control.set_paused(&journal.pack_id, true);
+            emit_import_batch(app, &journal, &mut pending, total_batches)?;
+            metrics::record(app, sample, false);
+            return pause_import_run(
+                app,
+                &journal,
+                Some(format!("{} is unreachable", journal.folder_path)),
+            );
+        }
+
+        let mut thumbnails = Vec::with_capacity(results.len());
+        let mut reads = Vec::with_capacity(results.len());
+        for result in results {
+            let (outcome, read) = match result {
+                Ok(imported) => imported,
+                Err(failure) => {
+                    tracing::warn!("Skipped {}: {}", failure.path, failure.reason);
+                    sample.add(&metrics::FileTimings::default(), true);
+                    journal.failed.push(failure);
+                    continue;
+                }
+            };
+            reads.extend(read);
+            sample.add(&outcome.timings, outcome.failure.is_some());
+            if let Some(reason) = outcome.failure {
+                tracing::warn!(
+                    "No thumbnail for {}: {}",
+                    outcome.info.original_path,
+                    reason
+                );
+                journal.failed.push(imports::ImportFailure {
+                    path: outcome.info.original_path.to_string(),
+                    reason,
+                    kind: imports::FailureKind::Thumbnail,
+                });
+            }
+            thumbnails.push(outcome.info);
+        }
 
-        let progress = ((batch_num + 1) as f32 / total_batches as f32) * 100.0;
+        let unreachable = chunk.len() - thumbnails.len();
+        if pool.current_num_threads() > source_io::SLOW_SOURCE_THREADS
+            && (unreachable > 0 || source_io::is_slow(&reads))
+        {
+            tracing::warn!(
+                "Slow source {}, dropping to {} worker threads",
+                journal.folder_path,
+                source_io::SLOW_SOURCE_THREADS
+            );
+            pool = build_thumbnail_pool(Some(source_io::SLOW_SOURCE_THREADS))?;
+            app.emit(
+                "import-slow-source",
+                imports::SlowSource {
+                    pack_id: journal.pack_id.clone(),
+                    threads: source_io::SLOW_SOURCE_THREADS,
+                },
+            )
+            .map_err(|e| format!("Failed to emit event: {}", e))?;
+        }
+        cache.store(&mut conn, &thumbnails)?;
+        let duplicates = catalog::record_import(
+            app,
+            &mut conn,
+            &journal.pack_id,
+            pack_name.as_deref(),
+            Some(&journal.folder_path),
+            &thumbnails,
+        )?;
+        if !duplicates.is_empty() {
+            let paths: HashSet<&str> = duplicates.iter().map(|d| d.path.as_str()).collect();
+            thumbnails.retain(|info| !paths.contains(info.original_path.to_string().as_str()));
+            // A duplicate is reported once, not also as a thumbnail failure
+            journal.failed.retain(|f| !paths.contains(f.path.as_str()));
+            journal.failed.extend(duplicates);
+        }
 
+        journal.processed += chunk.len();
+        let progress = (journal.processed as f32 / total as f32) * 100.0;
         let batch_count = thumbnails.len();
 
-        // Emit batch to frontend
-        let batch_progress = BatchProgress {
-            batch: batch_num,
-            total_batches,
-            thumbnails,
-            progress,
-        };
+        // The heavy payload goes out every EMIT_BATCH_SIZE images
+        pending.extend(thumbnails);
+        if pending.len() >= EMIT_BATCH_SIZE {
+            emit_import_batch(app, &journal, &mut pending, total_batches)?;
+        }
 
-        app.emit("import-batch", batch_progress)
-            .map_err(|e| format!("Failed to emit event: {}", e))?;
+        imports::update_journal(app, &journal)?;
+        if let Some(job) = job {
+            job.progress(journal.processed, total, None);
+        }
 
         let batch_duration = batch_start.elapsed();
-        println!(
+        tracing::debug!(
             "Batch {} complete: {:.1}% total progress, took {:.2}s, {:.1} images/sec",
             batch_num + 1,
             progress,
@@ -319,18 +1185,39 @@ async fn import_pack_progressive(
         );
     }
 
+    emit_import_batch(app, &journal, &mut pending, total_batches)?;
+    emit_import_progress(app, &journal, journal.processed, journal.failed.len());
+
+    imports::remove_journal(app, &journal.pack_id);
+    manifest::refresh(app, [journal.pack_id.as_str()]);
+    metrics::record(app, sample, true);
+    app.emit(
+        "import-complete",
+        imports::ImportSummary {
+            pack_id: journal.pack_id.clone(),
+            total,
+            imported: journal.processed - journal.failed.len(),
+            skipped: journal.skipped,
+            filtered: journal.filtered,
+            failed: journal.failed.clone(),
+            unreachable: journal.unreachable(),
+            duplicates: journal.duplicates(),
+        },
+    )
+    .map_err(|e| format!("Failed to emit event: {}", e))?;
+
     let total_duration = start_time.elapsed();
-    println!(
+    tracing::info!(
         "Import complete! Processed {} images in {:.2}s ({:.1} images/sec)",
-        total,
+        remaining,
         total_duration.as_secs_f32(),
-        total as f32 / total_duration.as_secs_f32()
+        remaining as f32 / total_duration.as_secs_f32()
     );
-    Ok(())
+    Ok(journal.report())
 }
 
 #[tauri::command]
-async fn get_app_data_dir(app: AppHandle) -> Result<String, String> {
+async fn get_app_data_dir(app: AppHandle) -> Result<String, DrawStackError> {
     let app_data = app
         .path()
         .app_data_dir()
@@ -346,20 +1233,20 @@ async fn copy_to_library(
     app: AppHandle,
     source_path: String,
     image_id: String,
-) -> Result<String, String> {
-    // Get the configured library path (or default)
-    let library_path = get_library_path(app)?;
-    let library_dir = Path::new(&library_path);
+    root_id: Option<String>,
+) -> Result<String, DrawStackError> {
+    // The chosen library root, or the primary one
+    let library_dir = roots::root_dir(&app, root_id.as_deref())?;
 
     fs::create_dir_all(&library_dir)
         .map_err(|e| format!("Failed to create library directory: {}", e))?;
 
     let source = Path::new(&source_path);
-    let extension = source.extension().and_then(|e| e.to_str()).unwrap_or("jpg");
-
-    let dest_path = library_dir.join(format!("{}.{}", image_id, extension));
+    let dest_path = library::library_target(&library_dir, source, &image_id);
 
-    fs::copy(source, &dest_path).map_err(|e| format!("Failed to copy to library: {}", e))?;
+    fs::copy(paths::extended(source), paths::extended(&dest_path))
+        .map_err(|e| DrawStackError::io("copy", source, e))?;
+    storage::invalidate(&app);
 
     Ok(dest_path.to_string_lossy().to_string())
 }
@@ -369,177 +1256,233 @@ fn generate_uuid() -> String {
     Uuid::new_v4().to_string()
 }
 
+// The primary library root, where copies go unless a root is named
 #[tauri::command]
-fn get_library_path(app: AppHandle) -> Result<String, String> {
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+fn get_library_path(app: AppHandle) -> Result<String, DrawStackError> {
+    Ok(roots::primary_root(&app)?.path)
+}
 
-    // Check if custom path is stored in config
-    let config_path = app_data_dir.join("config.json");
-    if config_path.exists() {
-        if let Ok(config_str) = fs::read_to_string(&config_path) {
-            if let Ok(config) = serde_json::from_str::<serde_json::Value>(&config_str) {
-                if let Some(custom_path) = config.get("library_path").and_then(|p| p.as_str()) {
-                    return Ok(custom_path.to_string());
-                }
-            }
-        }
-    }
+#[tauri::command]
+fn set_library_path(app: AppHandle, path: String) -> Result<(), DrawStackError> {
+    roots::check_root_path(&app, &path)?;
+    let primary = roots::primary_root(&app)?;
+    config::update(&app, |config| {
+        roots::set_root_path(&app, config, &primary.id, path)
+    })?;
+    watcher::watch_library(&app);
+    Ok(())
+}
 
-    // Return default path in Documents folder
-    let document_dir = app
-        .path()
-        .document_dir()
-        .map_err(|e| format!("Failed to get documents dir: {}", e))?;
-    let library_dir = document_dir.join("DrawStack").join("Library");
+#[tauri::command]
+fn get_default_library_path(app: AppHandle) -> Result<String, DrawStackError> {
+    let library_dir = roots::default_library_dir(&app)?;
     Ok(library_dir.to_string_lossy().to_string())
 }
 
-#[tauri::command]
-fn set_library_path(app: AppHandle, path: String) -> Result<(), String> {
-    let app_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+const DEFAULT_KEPT_BACKUPS: usize = 3;
+const MAX_KEPT_BACKUPS: usize = 20;
 
-    fs::create_dir_all(&app_dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
 
-    let config_path = app_dir.join("config.json");
-    let config = serde_json::json!({ "library_path": path });
+// A `.tmp` sibling of `path` no other writer is using, so concurrent writes
+// of the same file each rename a complete copy into place
+fn temp_sibling(path: &Path) -> PathBuf {
+    static NEXT_TEMP: AtomicUsize = AtomicUsize::new(0);
+    let n = NEXT_TEMP.fetch_add(1, Ordering::Relaxed);
+    sibling_path(path, &format!(".{}-{}.tmp", std::process::id(), n))
+}
 
-    fs::write(&config_path, serde_json::to_string_pretty(&config).unwrap())
-        .map_err(|e| format!("Failed to write config: {}", e))?;
+// Write to a `.tmp` sibling, fsync it and rename it over `path`, so a crash
+// mid-write leaves either the old or the new contents, never a truncated file.
+fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let temp_path = temp_sibling(path);
+    let written = fs::File::create(&temp_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&temp_path, path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
 
+    // Persist the rename itself
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        fs::File::open(parent)?.sync_all()?;
+    }
     Ok(())
 }
 
 #[tauri::command]
-fn get_default_library_path(app: AppHandle) -> Result<String, String> {
-    let document_dir = app
-        .path()
-        .document_dir()
-        .map_err(|e| format!("Failed to get documents dir: {}", e))?;
-
-    let library_dir = document_dir.join("DrawStack").join("Library");
-    Ok(library_dir.to_string_lossy().to_string())
+fn write_file(app: AppHandle, path: String, contents: String) -> Result<(), DrawStackError> {
+    let path = scope::resolve(&app, &path)?;
+    write_atomic(&path, contents.as_bytes()).map_err(|e| DrawStackError::io("write", &path, e))
 }
 
+// Like `write_file`, but first shifts the current file into `<name>.bak.1`,
+// `.bak.1` into `.bak.2` and so on, dropping anything past `keep`.
 #[tauri::command]
-fn write_file(path: String, contents: String) -> Result<(), String> {
-    fs::write(&path, contents).map_err(|e| format!("Failed to write file: {}", e))?;
-    Ok(())
+fn write_file_with_backup(
+    app: AppHandle,
+    path: String,
+    contents: String,
+    keep: Option<usize>,
+) -> Result<(), DrawStackError> {
+    let path = scope::resolve(&app, &path)?;
+    let keep = keep.unwrap_or(DEFAULT_KEPT_BACKUPS).min(MAX_KEPT_BACKUPS);
+    let backup = |n: usize| sibling_path(&path, &format!(".bak.{}", n));
+
+    if keep > 0 && path.is_file() {
+        let oldest = backup(keep);
+        if oldest.exists() {
+            fs::remove_file(&oldest).map_err(|e| DrawStackError::io("remove", &oldest, e))?;
+        }
+        for n in (1..keep).rev() {
+            let from = backup(n);
+            if from.exists() {
+                fs::rename(&from, backup(n + 1))
+                    .map_err(|e| DrawStackError::io("rotate", &from, e))?;
+            }
+        }
+        // Copied rather than moved, so `path` is never missing
+        fs::copy(&path, backup(1)).map_err(|e| DrawStackError::io("back up", &path, e))?;
+    }
+
+    write_atomic(&path, contents.as_bytes()).map_err(|e| DrawStackError::io("write", &path, e))
 }
 
 #[tauri::command]
-fn read_file_contents(path: String) -> Result<String, String> {
-    fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))
+fn read_file_contents(app: AppHandle, path: String) -> Result<String, DrawStackError> {
+    let path = scope::resolve(&app, &path)?;
+    fs::read_to_string(&path).map_err(|e| DrawStackError::io("read", &path, e))
 }
 
-#[derive(Debug, serde::Serialize)]
-struct StorageInfo {
-    used_bytes: u64,
-    used_formatted: String,
-    total_bytes: Option<u64>,
-    total_formatted: Option<String>,
-    usage_percentage: Option<f32>,
+// Resolve a path from the webview that may be an image original. Those
+// usually live outside the allowed roots, so any path the catalog knows
+// about is accepted too.
+fn resolve_known_path(app: &AppHandle, path: &str) -> Result<PathBuf, DrawStackError> {
+    let requested = match scope::resolve(app, path) {
+        Err(DrawStackError::OutOfScope { path: requested }) => requested,
+        resolved => return resolved,
+    };
+    let conn = catalog::open(app)?;
+    let known = conn
+        .query_row(
+            "SELECT EXISTS(
+                 SELECT 1 FROM images WHERE original_path = ?1 OR library_path = ?1
+                 UNION ALL
+                 SELECT 1 FROM packs WHERE source_path = ?1
+             )",
+            [path],
+            |row| row.get::<_, bool>(0),
+        )
+        .map_err(|e| format!("Failed to look up {}: {}", path, e))?;
+    if known {
+        Ok(requested)
+    } else {
+        Err(DrawStackError::OutOfScope { path: requested })
+    }
 }
 
+// Show `path` selected in Explorer, Finder or the Linux file manager
 #[tauri::command]
-fn get_storage_usage(app: AppHandle) -> Result<StorageInfo, String> {
-    use std::fs::metadata;
-
-    // Get library path
-    let library_path = get_library_path(app)?;
-    let library_dir = Path::new(&library_path);
-
-    // Calculate directory size recursively
-    fn dir_size(path: &Path) -> std::io::Result<u64> {
-        let mut size = 0u64;
+async fn reveal_in_file_manager(app: AppHandle, path: String) -> Result<(), DrawStackError> {
+    let target = resolve_known_path(&app, &path)?;
+    if !target.exists() {
+        return Err(DrawStackError::not_found(&target));
+    }
 
-        if path.is_dir() {
-            for entry in fs::read_dir(path)? {
-                let entry = entry?;
-                let path = entry.path();
+    tauri_plugin_opener::reveal_item_in_dir(&target)
+        .map_err(|e| format!("Failed to reveal {}: {}", target.display(), e))?;
+    Ok(())
+}
 
-                if path.is_dir() {
-                    size += dir_size(&path)?;
-                } else {
-                    size += metadata(&path)?.len();
-                }
-            }
-        }
+// Largest single read; bigger files are fetched in several calls
+const MAX_READ_CHUNK: u64 = 64 * 1024 * 1024;
 
-        Ok(size)
+// Read `len` bytes of `path` starting at `offset` (the rest of the file by
+// default). The bytes are returned as a raw IPC payload, which arrives in
+// the webview as an ArrayBuffer.
+#[tauri::command]
+fn read_file_bytes(
+    app: AppHandle,
+    path: String,
+    offset: Option<u64>,
+    len: Option<u64>,
+) -> Result<tauri::ipc::Response, DrawStackError> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let path = scope::resolve(&app, &path)?;
+    let mut file = fs::File::open(&path).map_err(|e| DrawStackError::io("open", &path, e))?;
+    let size = file
+        .metadata()
+        .map_err(|e| DrawStackError::io("read", &path, e))?
+        .len();
+    let offset = offset.unwrap_or(0).min(size);
+    let len = len.unwrap_or(size - offset).min(size - offset);
+    if len > MAX_READ_CHUNK {
+        return Err(DrawStackError::invalid(format!(
+            "Reads are limited to {}; request the file in chunks",
+            format_bytes(MAX_READ_CHUNK)
+        )));
     }
 
-    let used_bytes = if library_dir.exists() {
-        dir_size(library_dir).unwrap_or(0)
-    } else {
-        0
-    };
+    let mut buffer = vec![0; len as usize];
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(&mut buffer))
+        .map_err(|e| DrawStackError::io("read", &path, e))?;
+    Ok(tauri::ipc::Response::new(buffer))
+}
 
-    // Get total disk space (Windows only, best effort)
-    let (total_bytes, total_formatted, usage_percentage) = if cfg!(target_os = "windows") {
-        // Try to get drive letter from library path
-        if let Some(drive) = library_path.chars().nth(0) {
-            let drive_path = format!("{}:\\", drive);
-
-            // Use GetDiskFreeSpaceEx on Windows
-            #[cfg(target_os = "windows")]
-            {
-                use std::ffi::OsStr;
-                use std::os::windows::ffi::OsStrExt;
-                use std::ptr::null_mut;
-
-                let wide_path: Vec<u16> = OsStr::new(&drive_path)
-                    .encode_wide()
-                    .chain(Some(0))
-                    .collect();
-
-                let mut total: u64 = 0;
-                let mut _free: u64 = 0;
-
-                unsafe {
-                    if winapi::um::fileapi::GetDiskFreeSpaceExW(
-                        wide_path.as_ptr(),
-                        null_mut(),
-                        &mut total as *mut _ as *mut _,
-                        &mut _free as *mut _ as *mut _,
-                    ) != 0
-                    {
-                        let percentage = if total > 0 {
-                            Some((used_bytes as f32 / total as f32) * 100.0)
-                        } else {
-                            None
-                        };
-
-                        (Some(total), Some(format_bytes(total)), percentage)
-                    } else {
-                        (None, None, None)
-                    }
-                }
-            }
+fn header<'a>(request: &'a tauri::ipc::Request<'_>, name: &str) -> Option<&'a str> {
+    request.headers().get(name).and_then(|v| v.to_str().ok())
+}
 
-            #[cfg(not(target_os = "windows"))]
-            {
-                (None, None, None)
-            }
-        } else {
-            (None, None, None)
-        }
-    } else {
-        (None, None, None)
-    };
+// Write a raw IPC payload. The target goes in a `path` header, URI-encoded
+// since headers are ASCII only. Without an `offset` header the file is
+// replaced atomically; with one, the bytes are written at that position,
+// and offset 0 starts the file over, so large files can be sent in
+// consecutive chunks.
+#[tauri::command]
+fn write_file_bytes(
+    app: AppHandle,
+    request: tauri::ipc::Request<'_>,
+) -> Result<(), DrawStackError> {
+    use std::io::{Seek, SeekFrom, Write};
 
-    Ok(StorageInfo {
-        used_bytes,
-        used_formatted: format_bytes(used_bytes),
-        total_bytes,
-        total_formatted,
-        usage_percentage,
-    })
+    let tauri::ipc::InvokeBody::Raw(bytes) = request.body() else {
+        return Err(DrawStackError::invalid("Expected a binary payload"));
+    };
+    let path =
+        header(&request, "path").ok_or_else(|| DrawStackError::invalid("Missing path header"))?;
+    let path = percent_encoding::percent_decode_str(path)
+        .decode_utf8()
+        .map_err(|_| DrawStackError::invalid("Path header is not valid UTF-8"))?;
+    let path = scope::resolve(&app, &path)?;
+
+    let Some(offset) = header(&request, "offset") else {
+        return write_atomic(&path, bytes).map_err(|e| DrawStackError::io("write", &path, e));
+    };
+    let offset: u64 = offset
+        .parse()
+        .map_err(|_| DrawStackError::invalid(format!("Invalid offset: {}", offset)))?;
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(offset == 0)
+        .open(&path)
+        .map_err(|e| DrawStackError::io("open", &path, e))?;
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.write_all(bytes))
+        .and_then(|_| file.sync_data())
+        .map_err(|e| DrawStackError::io("write", &path, e))
 }
 
 fn format_bytes(bytes: u64) -> String {
@@ -568,21 +1511,189 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_process::init())
+        .manage(config::ConfigState::default())
+        .manage(catalog::CatalogState::default())
+        .manage(imports::ImportControl::default())
+        .manage(watcher::FolderWatchers::default())
+        .manage(watcher::LibraryWatcher::default())
+        .manage(thumbnails::ThumbnailUpgrader::default())
+        .manage(storage::StorageCache::default())
+        .manage(session::SessionManager::default())
+        .manage(jobs::JobQueue::default())
+        .manage(clipboard::ClipboardState::default())
+        .manage(remote::RemoteAccess::default())
+        .manage(maintenance::MaintenanceScheduler::default())
+        .manage(metrics::PerformanceMetrics::default())
+        .manage(folder_counts::FolderCounts::default())
+        .manage(quick_find::QuickFind::default())
+        .manage(audio_cues::AudioCuePlayer::default())
+        .register_asynchronous_uri_scheme_protocol(protocol::SCHEME, protocol::handle)
+        .setup(|app| {
+            if let Err(e) = logging::init(app.handle()) {
+                eprintln!("{}", e);
+            }
+            app.state::<thumbnails::ThumbnailUpgrader>()
+                .start(app.handle());
+            app.state::<jobs::JobQueue>().start(app.handle());
+            app.state::<maintenance::MaintenanceScheduler>()
+                .start(app.handle());
+            app.state::<quick_find::QuickFind>().start(app.handle());
+            #[cfg(feature = "audio")]
+            app.state::<audio_cues::AudioCuePlayer>()
+                .start(app.handle());
+
+            // Restoring scans each watched tree, so keep it off the startup path
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                if let Err(e) = thumbnails::migrate_to_shards(&handle) {
+                    tracing::warn!("Failed to migrate thumbnails into shards: {}", e);
+                }
+                watcher::restore(&handle);
+                watcher::watch_library(&handle);
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             browse_folder,
-            count_folder_images,
+            browse::browse_folder_page,
+            folder_counts::count_folder_images,
+            folder_counts::count_folder_images_bounded,
+            folder_counts::cancel_folder_counts,
+            get_image_info,
+            load_image_scaled,
             quick_scan,
             import_pack_progressive,
+            import_files,
+            url_import::import_from_urls,
+            eagle::import_from_eagle,
             get_app_data_dir,
             copy_to_library,
+            library::copy_many_to_library,
+            library::move_to_library,
+            library::get_interrupted_library_move,
+            library::rollback_library_move,
+            library::discard_library_move_journal,
+            library::delete_images,
+            library::delete_library_files,
+            library::migrate_library,
             generate_uuid,
             get_library_path,
             set_library_path,
             get_default_library_path,
             write_file,
+            write_file_with_backup,
             read_file_contents,
-            get_storage_usage,
+            read_file_bytes,
+            write_file_bytes,
+            reveal_in_file_manager,
+            clipboard::copy_image_to_clipboard,
+            roots::get_library_roots,
+            roots::add_library_root,
+            roots::update_library_root,
+            roots::remove_library_root,
+            scope::get_allowed_roots,
+            scope::add_allowed_root,
+            scope::remove_allowed_root,
+            quota::get_storage_quota,
+            quota::set_storage_quota,
+            quota::enforce_storage_quota,
+            pack_stats::get_pack_stats,
+            atlas::build_thumbnail_atlas,
+            #[cfg(feature = "drag")]
+            native_drag::start_native_drag,
+            storage::get_storage_usage,
+            bundle::export_pack,
+            export::export_images,
+            bundle::import_drawstack_bundle,
+            catalog::catalog_insert_images,
+            catalog_export::export_catalog,
+            catalog_export::import_catalog_metadata,
+            xmp::write_xmp_sidecars,
+            manifest::write_pack_manifests,
+            manifest::ingest_pack_manifests,
+            config::get_config,
+            config::update_config,
+            catalog::catalog_get_pack,
+            catalog::catalog_delete_pack,
+            packs::create_pack,
+            packs::rename_pack,
+            packs::list_packs,
+            packs::delete_pack,
+            packs::delete_pack_data,
+            palette::extract_palette,
+            picker::pick_session_images,
+            practice::get_practice_stats,
+            ratings::set_rating,
+            ratings::toggle_favorite,
+            ratings::get_images_filtered,
+            search::search_library,
+            image_query::query_images,
+            quick_find::quick_find,
+            orientation::get_images_by_orientation,
+            session_presets::list_session_presets,
+            session_presets::save_session_preset,
+            session_presets::delete_session_preset,
+            session::start_session,
+            session::pause_session,
+            session::resume_session,
+            session::skip_image,
+            session::end_session,
+            session::get_session_state,
+            audio_cues::get_audio_cues,
+            audio_cues::set_audio_cues,
+            smart_collections::create_smart_collection,
+            smart_collections::list_smart_collections,
+            smart_collections::evaluate_smart_collection,
+            smart_collections::delete_smart_collection,
+            content_flag::set_content_flag,
+            tags::add_tags,
+            tags::remove_tags,
+            tags::list_tags,
+            tags::get_images_by_tag,
+            tags::rename_tag,
+            tags::merge_tags,
+            import_preview::preview_import,
+            imports::pause_import,
+            jobs::enqueue_job,
+            jobs::cancel_job,
+            jobs::list_jobs,
+            imports::resume_import,
+            imports::list_pending_imports,
+            archive::import_archive,
+            transforms::transform_image,
+            variants::generate_variant,
+            variants::generate_blur_levels,
+            watcher::watch_folder,
+            watcher::unwatch_folder,
+            watcher::list_watched_folders,
+            content_hash::find_duplicates,
+            dedupe::find_similar_images,
+            thumbnails::get_thumbnail_settings,
+            thumbnails::set_thumbnail_settings,
+            thumbnails::get_thumbnail_cache_size,
+            thumbnails::clean_thumbnail_cache,
+            thumbnails::regenerate_thumbnails,
+            scan::get_scan_settings,
+            scan::set_scan_settings,
+            #[cfg(feature = "pdf")]
+            pdf::import_pdf,
+            #[cfg(feature = "pdf")]
+            contact_sheet::export_contact_sheet,
+            backup::create_backup,
+            backup::restore_backup,
+            integrity::verify_library,
+            relink::relink_originals,
+            relink::fuzzy_relink,
+            remote::start_remote_access,
+            remote::stop_remote_access,
+            remote::get_remote_access_status,
+            logging::get_recent_logs,
+            logging::set_log_level,
+            maintenance::run_maintenance,
+            maintenance::get_maintenance_settings,
+            maintenance::set_maintenance_settings,
+            metrics::get_performance_metrics,
         ]);
 
     // Disable updater in dev to avoid noisy JSON fetch errors.
@@ -0,0 +1,874 @@
+use rayon::prelude::*;
+use rusqlite::params;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DrawStackError;
+use crate::paths::StoredPath;
+use crate::{catalog, config, content_hash, manifest, paths, roots, storage, thumbnails, watcher};
+
+// Copies are disk-bound, so a handful of workers is plenty
+const DEFAULT_COPY_THREADS: usize = 4;
+const PROGRESS_EVERY: usize = 10;
+
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct LibraryCopyItem {
+    pub source_path: String,
+    pub image_id: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct LibraryCopyResult {
+    pub image_id: String,
+    pub source_path: String,
+    pub library_path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct LibraryCopyProgress {
+    completed: usize,
+    failed: usize,
+    total: usize,
+}
+
+// Destination for an image inside the library: `<library>/<image_id>.<ext>`,
+// matching what `copy_to_library` has always produced.
+pub fn library_target(library_dir: &Path, source: &Path, image_id: &str) -> PathBuf {
+    let extension = source.extension().and_then(|e| e.to_str()).unwrap_or("jpg");
+    library_dir.join(format!("{}.{}", image_id, extension))
+}
+
+fn copy_one(library_dir: &Path, item: &LibraryCopyItem) -> Result<PathBuf, String> {
+    let source = Path::new(&item.source_path);
+    if !paths::extended(source).is_file() {
+        return Err(format!("Source file not found: {}", item.source_path));
+    }
+
+    let dest = library_target(library_dir, source, &item.image_id);
+    fs::copy(paths::extended(source), paths::extended(&dest))
+        .map_err(|e| format!("Failed to copy to library: {}", e))?;
+    Ok(dest)
+}
+
+// Copy many images into the library in parallel. Failures are reported per
+// item instead of aborting the whole batch.
+#[tauri::command]
+pub async fn copy_many_to_library(
+    app: AppHandle,
+    items: Vec<LibraryCopyItem>,
+    thread_count: Option<usize>,
+    root_id: Option<String>,
+) -> Result<Vec<LibraryCopyResult>, DrawStackError> {
+    let library_dir = roots::root_dir(&app, root_id.as_deref())?;
+    fs::create_dir_all(&library_dir)
+        .map_err(|e| format!("Failed to create library directory: {}", e))?;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(thread_count.unwrap_or(DEFAULT_COPY_THREADS))
+        .thread_name(|i| format!("library-copy-{}", i))
+        .build()
+        .map_err(|e| format!("Failed to create copy thread pool: {}", e))?;
+
+    let total = items.len();
+    let completed = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+
+    let emit_progress = |done: usize| {
+        let _ = app.emit(
+            "library-copy-progress",
+            LibraryCopyProgress {
+                completed: done,
+                failed: failed.load(Ordering::Relaxed),
+                total,
+            },
+        );
+    };
+
+    let results: Vec<LibraryCopyResult> = pool.install(|| {
+        items
+            .par_iter()
+            .map(|item| {
+                let outcome = copy_one(&library_dir, item);
+                if outcome.is_err() {
+                    failed.fetch_add(1, Ordering::Relaxed);
+                }
+
+                let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                if done.is_multiple_of(PROGRESS_EVERY) {
+                    emit_progress(done);
+                }
+
+                match outcome {
+                    Ok(dest) => LibraryCopyResult {
+                        image_id: item.image_id.clone(),
+                        source_path: item.source_path.clone(),
+                        library_path: Some(dest.to_string_lossy().to_string()),
+                        error: None,
+                    },
+                    Err(e) => LibraryCopyResult {
+                        image_id: item.image_id.clone(),
+                        source_path: item.source_path.clone(),
+                        library_path: None,
+                        error: Some(e),
+                    },
+                }
+            })
+            .collect()
+    });
+    emit_progress(total);
+
+    // Record where each copy went so the catalog can prefer library files
+    let mut conn = catalog::open(&app)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    for result in &results {
+        if let Some(library_path) = &result.library_path {
+            tx.execute(
+                "UPDATE images SET library_path = ?1 WHERE id = ?2",
+                params![library_path, result.image_id],
+            )
+            .map_err(|e| format!("Failed to record library path: {}", e))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    tracing::info!(
+        "Copied {} of {} images to library",
+        total - failed.load(Ordering::Relaxed),
+        total
+    );
+    let copied: Vec<String> = results
+        .iter()
+        .filter(|r| r.library_path.is_some())
+        .map(|r| r.image_id.clone())
+        .collect();
+    manifest::refresh_for_images(&app, &copied);
+    storage::invalidate(&app);
+    Ok(results)
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MoveState {
+    Pending,
+    Moved,
+    Failed,
+}
+
+// One line of the move journal. Every planned move is appended as Pending
+// before any file is touched, then again with its outcome; the last line for
+// an image wins.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct MoveRecord {
+    pub image_id: String,
+    pub source_path: String,
+    pub dest_path: String,
+    pub state: MoveState,
+}
+
+fn move_journal_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(app_data.join("library_move.jsonl"))
+}
+
+fn append_record(journal: &mut fs::File, record: &MoveRecord) -> Result<(), String> {
+    let mut line = serde_json::to_string(record)
+        .map_err(|e| format!("Failed to serialize move record: {}", e))?;
+    line.push('\n');
+    journal
+        .write_all(line.as_bytes())
+        .and_then(|_| journal.sync_data())
+        .map_err(|e| format!("Failed to write move journal: {}", e))
+}
+
+fn read_move_journal(path: &Path) -> Result<Vec<MoveRecord>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read move journal: {}", e))?;
+
+    let mut order = Vec::new();
+    let mut latest: HashMap<String, MoveRecord> = HashMap::new();
+    // A crash can leave a torn last line; anything unparseable is ignored
+    for record in contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<MoveRecord>(line).ok())
+    {
+        if !latest.contains_key(&record.image_id) {
+            order.push(record.image_id.clone());
+        }
+        latest.insert(record.image_id.clone(), record);
+    }
+
+    Ok(order
+        .into_iter()
+        .filter_map(|id| latest.remove(&id))
+        .collect())
+}
+
+fn same_contents(a: &Path, b: &Path) -> bool {
+    let sizes_match = match (
+        fs::metadata(paths::extended(a)),
+        fs::metadata(paths::extended(b)),
+    ) {
+        (Ok(a), Ok(b)) => a.len() == b.len(),
+        _ => false,
+    };
+    sizes_match
+        && matches!(
+            (content_hash::content_id(a), content_hash::content_id(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+// Rename when possible (same volume); otherwise copy, verify the copy
+// byte-for-byte by hash and only then delete the source.
+pub fn move_file(source: &Path, dest: &Path) -> Result<(), String> {
+    let (source, dest) = (&*paths::extended(source), &*paths::extended(dest));
+    if fs::rename(source, dest).is_ok() {
+        return Ok(());
+    }
+
+    fs::copy(source, dest).map_err(|e| format!("Failed to copy to library: {}", e))?;
+    if !same_contents(source, dest) {
+        let _ = fs::remove_file(dest);
+        return Err("Copied file did not match the original".to_string());
+    }
+    if let Err(e) = fs::remove_file(source) {
+        let _ = fs::remove_file(dest);
+        return Err(format!("Failed to remove original after copying: {}", e));
+    }
+    Ok(())
+}
+
+// Move images into the library instead of copying them. The move is
+// journaled so an interrupted run can be rolled back with
+// `rollback_library_move`.
+#[tauri::command]
+pub async fn move_to_library(
+    app: AppHandle,
+    items: Vec<LibraryCopyItem>,
+    root_id: Option<String>,
+) -> Result<Vec<LibraryCopyResult>, DrawStackError> {
+    let journal_path = move_journal_path(&app)?;
+    if journal_path.exists() {
+        return Err(DrawStackError::Busy(
+            "A previous move was interrupted - roll it back or discard it first".to_string(),
+        ));
+    }
+
+    let library_dir = roots::root_dir(&app, root_id.as_deref())?;
+    fs::create_dir_all(&library_dir)
+        .map_err(|e| format!("Failed to create library directory: {}", e))?;
+
+    let mut journal = fs::File::create(&journal_path)
+        .map_err(|e| format!("Failed to create move journal: {}", e))?;
+
+    let plan: Vec<MoveRecord> = items
+        .iter()
+        .map(|item| MoveRecord {
+            image_id: item.image_id.clone(),
+            source_path: item.source_path.clone(),
+            dest_path: library_target(&library_dir, Path::new(&item.source_path), &item.image_id)
+                .to_string_lossy()
+                .to_string(),
+            state: MoveState::Pending,
+        })
+        .collect();
+    for record in &plan {
+        append_record(&mut journal, record)?;
+    }
+
+    let conn = catalog::open(&app)?;
+    let total = plan.len();
+    let mut failed = 0;
+    let mut results = Vec::with_capacity(total);
+
+    for (index, mut record) in plan.into_iter().enumerate() {
+        let source = Path::new(&record.source_path);
+        let dest = Path::new(&record.dest_path);
+
+        // Never overwrite an existing library file - rollback would have no
+        // way to tell it apart from one we created
+        let outcome = if !source.is_file() {
+            Err(format!("Source file not found: {}", record.source_path))
+        } else if dest.exists() {
+            Err(format!("Already in library: {}", record.dest_path))
+        } else {
+            move_file(source, dest)
+        };
+
+        record.state = if outcome.is_ok() {
+            MoveState::Moved
+        } else {
+            MoveState::Failed
+        };
+        append_record(&mut journal, &record)?;
+
+        if outcome.is_ok() {
+            conn.execute(
+                "UPDATE images SET library_path = ?1 WHERE id = ?2",
+                params![record.dest_path, record.image_id],
+            )
+            .map_err(|e| format!("Failed to record library path: {}", e))?;
+        } else {
+            failed += 1;
+        }
+
+        let done = index + 1;
+        if done.is_multiple_of(PROGRESS_EVERY) || done == total {
+            let _ = app.emit(
+                "library-move-progress",
+                LibraryCopyProgress {
+                    completed: done,
+                    failed,
+                    total,
+                },
+            );
+        }
+
+        results.push(LibraryCopyResult {
+            image_id: record.image_id,
+            source_path: record.source_path,
+            library_path: outcome.is_ok().then_some(record.dest_path),
+            error: outcome.err(),
+        });
+    }
+
+    // The whole batch finished, so there is nothing left to roll back
+    drop(journal);
+    fs::remove_file(&journal_path).map_err(|e| format!("Failed to remove move journal: {}", e))?;
+
+    tracing::info!("Moved {} of {} images to library", total - failed, total);
+    let moved: Vec<String> = results
+        .iter()
+        .filter(|r| r.library_path.is_some())
+        .map(|r| r.image_id.clone())
+        .collect();
+    manifest::refresh_for_images(&app, &moved);
+    storage::invalidate(&app);
+    Ok(results)
+}
+
+// The journal of a move that never finished, if any.
+#[tauri::command]
+pub fn get_interrupted_library_move(
+    app: AppHandle,
+) -> Result<Option<Vec<MoveRecord>>, DrawStackError> {
+    let journal_path = move_journal_path(&app)?;
+    if !journal_path.exists() {
+        return Ok(None);
+    }
+    Ok(read_move_journal(&journal_path).map(Some)?)
+}
+
+// Put every file from an interrupted move back where it came from. The
+// filesystem is checked rather than the journal state, since a crash can
+// land between a rename and its journal line. Returns the number restored.
+#[tauri::command]
+pub async fn rollback_library_move(app: AppHandle) -> Result<usize, DrawStackError> {
+    let journal_path = move_journal_path(&app)?;
+    if !journal_path.exists() {
+        return Ok(0);
+    }
+
+    let records = read_move_journal(&journal_path)?;
+    let conn = catalog::open(&app)?;
+    let mut restored = 0;
+    let mut errors = Vec::new();
+
+    for record in &records {
+        let source = Path::new(&record.source_path);
+        let dest = Path::new(&record.dest_path);
+        if !dest.exists() {
+            continue;
+        }
+
+        let result = if source.exists() {
+            // Source still there: the library file is a copy made before the
+            // crash, possibly partial
+            fs::remove_file(dest).map_err(|e| format!("Failed to remove {}: {}", dest.display(), e))
+        } else {
+            move_file(dest, source).map(|_| restored += 1)
+        };
+
+        match result {
+            Ok(()) => {
+                let _ = conn.execute(
+                    "UPDATE images SET library_path = NULL WHERE id = ?1 AND library_path = ?2",
+                    params![record.image_id, record.dest_path],
+                );
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if !errors.is_empty() {
+        // Keep the journal so the rollback can be retried
+        return Err(DrawStackError::Other(format!(
+            "Rolled back {} files, {} failed: {}",
+            restored,
+            errors.len(),
+            errors.join("; ")
+        )));
+    }
+
+    fs::remove_file(&journal_path).map_err(|e| format!("Failed to remove move journal: {}", e))?;
+    tracing::info!("Rolled back {} moved images", restored);
+    let image_ids: Vec<String> = records.iter().map(|r| r.image_id.clone()).collect();
+    manifest::refresh_for_images(&app, &image_ids);
+    storage::invalidate(&app);
+    Ok(restored)
+}
+
+// Keep the files from an interrupted move where they are and record the
+// completed moves in the catalog.
+#[tauri::command]
+pub async fn discard_library_move_journal(app: AppHandle) -> Result<usize, DrawStackError> {
+    let journal_path = move_journal_path(&app)?;
+    if !journal_path.exists() {
+        return Ok(0);
+    }
+
+    let records = read_move_journal(&journal_path)?;
+    let conn = catalog::open(&app)?;
+    let mut kept = 0;
+
+    for record in &records {
+        if Path::new(&record.dest_path).exists() && !Path::new(&record.source_path).exists() {
+            conn.execute(
+                "UPDATE images SET library_path = ?1 WHERE id = ?2",
+                params![record.dest_path, record.image_id],
+            )
+            .map_err(|e| format!("Failed to record library path: {}", e))?;
+            kept += 1;
+        }
+    }
+
+    fs::remove_file(&journal_path).map_err(|e| format!("Failed to remove move journal: {}", e))?;
+    let image_ids: Vec<String> = records.iter().map(|r| r.image_id.clone()).collect();
+    manifest::refresh_for_images(&app, &image_ids);
+    storage::invalidate(&app);
+    Ok(kept)
+}
+
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct DeleteReport {
+    pub deleted_images: usize,
+    pub trashed_files: usize,
+    pub removed_thumbnails: usize,
+    pub errors: Vec<String>,
+}
+
+// Send a file to the OS recycle bin / trash. Missing files count as done.
+pub fn trash_file(path: &Path) -> Result<bool, String> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    trash::delete(path)
+        .map(|_| true)
+        .map_err(|e| format!("Failed to move {} to trash: {}", path.display(), e))
+}
+
+// Remove images from DrawStack: their library copies go to the trash, their
+// thumbnails and catalog rows are deleted. Original files are only trashed
+// when `include_originals` is set. An image whose files can't be trashed
+// stays in the catalog.
+#[tauri::command]
+pub async fn delete_images(
+    app: AppHandle,
+    image_ids: Vec<String>,
+    include_originals: Option<bool>,
+) -> Result<DeleteReport, DrawStackError> {
+    let include_originals = include_originals.unwrap_or(false);
+    let mut conn = catalog::open(&app)?;
+    let mut report = DeleteReport::default();
+    let mut deletable = HashSet::new();
+
+    for image_id in &image_ids {
+        let paths: Option<(StoredPath, Option<StoredPath>)> = conn
+            .query_row(
+                "SELECT original_path, library_path FROM images WHERE id = ?1",
+                params![image_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .ok();
+        let Some((original_path, library_path)) = paths else {
+            report.errors.push(format!("Image not found: {}", image_id));
+            continue;
+        };
+
+        let mut files: Vec<PathBuf> = library_path.into_iter().map(PathBuf::from).collect();
+        if include_originals {
+            files.push(original_path.into());
+        }
+
+        let mut trashed_all = true;
+        for file in &files {
+            match trash_file(file) {
+                Ok(trashed) => report.trashed_files += usize::from(trashed),
+                Err(e) => {
+                    report.errors.push(e);
+                    trashed_all = false;
+                }
+            }
+        }
+        if trashed_all {
+            deletable.insert(image_id.clone());
+        }
+    }
+
+    (report.removed_thumbnails, _) = thumbnails::remove_thumbnails_for(&app, &deletable);
+    let affected_packs = manifest::packs_of(&conn, &image_ids);
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    for image_id in &deletable {
+        // Thumbnails and tag links cascade from the image row
+        report.deleted_images += tx
+            .execute("DELETE FROM images WHERE id = ?1", params![image_id])
+            .map_err(|e| format!("Failed to delete image {}: {}", image_id, e))?;
+        tx.execute(
+            "DELETE FROM thumbnail_cache WHERE image_id = ?1",
+            params![image_id],
+        )
+        .map_err(|e| format!("Failed to clear thumbnail cache for {}: {}", image_id, e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    tracing::info!(
+        "Deleted {} images ({} files sent to trash)",
+        report.deleted_images,
+        report.trashed_files
+    );
+    manifest::refresh(&app, affected_packs.iter().map(String::as_str));
+    storage::invalidate(&app);
+    Ok(report)
+}
+
+// Send library files to the trash and forget them in the catalog. Paths
+// outside the library roots are refused.
+#[tauri::command]
+pub async fn delete_library_files(
+    app: AppHandle,
+    paths: Vec<String>,
+) -> Result<DeleteReport, DrawStackError> {
+    let library_dirs: Vec<PathBuf> = roots::root_dirs(&app)?
+        .into_iter()
+        .map(|dir| fs::canonicalize(&dir).unwrap_or(dir))
+        .collect();
+    let conn = catalog::open(&app)?;
+    let mut report = DeleteReport::default();
+    let mut affected_packs = HashSet::new();
+
+    for path in &paths {
+        let file = Path::new(path);
+        let inside_library = fs::canonicalize(file)
+            .map(|resolved| library_dirs.iter().any(|dir| resolved.starts_with(dir)))
+            .unwrap_or(false);
+        if !inside_library {
+            report
+                .errors
+                .push(format!("Not a file in the library: {}", path));
+            continue;
+        }
+
+        match trash_file(file) {
+            Ok(trashed) => {
+                report.trashed_files += usize::from(trashed);
+                if let Ok(pack_id) = conn.query_row(
+                    "SELECT pack_id FROM images WHERE library_path = ?1",
+                    params![path],
+                    |row| row.get::<_, String>(0),
+                ) {
+                    affected_packs.insert(pack_id);
+                }
+                conn.execute(
+                    "UPDATE images SET library_path = NULL WHERE library_path = ?1",
+                    params![path],
+                )
+                .map_err(|e| format!("Failed to update catalog: {}", e))?;
+            }
+            Err(e) => report.errors.push(e),
+        }
+    }
+
+    manifest::refresh(&app, affected_packs.iter().map(String::as_str));
+    storage::invalidate(&app);
+    Ok(report)
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct LibraryMigrateProgress {
+    completed: usize,
+    total: usize,
+    bytes_copied: u64,
+    total_bytes: u64,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct LibraryMigration {
+    pub new_path: String,
+    pub files: usize,
+    pub bytes: u64,
+    pub catalog_updated: usize,
+    pub removed_old: bool,
+}
+
+// Every file under `dir`, recursively, with its size.
+pub fn collect_files(dir: &Path) -> Result<Vec<(PathBuf, u64)>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        let entries = fs::read_dir(&current)
+            .map_err(|e| format!("Failed to read {}: {}", current.display(), e))?;
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                pending.push(entry.path());
+            } else if meta.is_file() {
+                files.push((entry.path(), meta.len()));
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+// Point the catalog's library paths under `old_dir` at the same files under
+// `new_dir`. Returns the number of images updated.
+fn relocate_library_paths(
+    app: &AppHandle,
+    old_dir: &Path,
+    new_dir: &Path,
+) -> Result<usize, String> {
+    let mut conn = catalog::open(app)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    let mut updated = 0;
+    {
+        let mut select = tx
+            .prepare("SELECT id, library_path FROM images WHERE library_path IS NOT NULL")
+            .map_err(|e| format!("Failed to prepare library query: {}", e))?;
+        let rows = select
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, StoredPath>(1)?))
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to read library paths: {}", e))?;
+
+        for (image_id, library_path) in rows {
+            let library_path = library_path.to_path_buf();
+            let Ok(relative) = library_path.strip_prefix(old_dir) else {
+                continue;
+            };
+            let relocated = StoredPath::from(new_dir.join(relative).as_path());
+            updated += tx
+                .execute(
+                    "UPDATE images SET library_path = ?1 WHERE id = ?2",
+                    params![relocated, image_id],
+                )
+                .map_err(|e| format!("Failed to update library path: {}", e))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+    Ok(updated)
+}
+
+// Relocate a library root (the primary one by default): copy every file to
+// `new_path`, verify each copy by size and hash, repoint the catalog and only
+// then switch the configured path. With `move_files` the old copies are
+// removed afterwards. Thumbnails live in app data rather than the library,
+// so they stay put.
+#[tauri::command]
+pub async fn migrate_library(
+    app: AppHandle,
+    new_path: String,
+    move_files: Option<bool>,
+    root_id: Option<String>,
+) -> Result<LibraryMigration, DrawStackError> {
+    let root = roots::find_root(&app, root_id.as_deref())?;
+    let old_dir = PathBuf::from(&root.path);
+    roots::check_root_path(&app, &new_path)?;
+    roots::check_overlap(
+        &roots::library_roots(&app)?,
+        &roots::LibraryRoot {
+            path: new_path.clone(),
+            ..root.clone()
+        },
+    )?;
+    let new_dir = PathBuf::from(&new_path);
+
+    fs::create_dir_all(&new_dir)
+        .map_err(|e| format!("Failed to create new library directory: {}", e))?;
+    let old_resolved = fs::canonicalize(&old_dir).unwrap_or(old_dir.clone());
+    let new_resolved = fs::canonicalize(&new_dir).unwrap_or(new_dir.clone());
+    if old_resolved == new_resolved {
+        return Err(DrawStackError::invalid(
+            "The new library location is the current one",
+        ));
+    }
+    if new_resolved.starts_with(&old_resolved) {
+        return Err(DrawStackError::invalid(
+            "The new library location can't be inside the current one",
+        ));
+    }
+
+    let files = if old_dir.exists() {
+        collect_files(&old_dir)?
+    } else {
+        Vec::new()
+    };
+    let total = files.len();
+    let total_bytes: u64 = files.iter().map(|(_, size)| size).sum();
+    tracing::info!(
+        "Migrating library ({} files, {}) to {}",
+        total,
+        crate::format_bytes(total_bytes),
+        new_path
+    );
+
+    let mut copied: Vec<PathBuf> = Vec::new();
+    let mut bytes_copied = 0;
+    let copy_result = (|| {
+        for (index, (source, size)) in files.iter().enumerate() {
+            let relative = source
+                .strip_prefix(&old_dir)
+                .map_err(|_| format!("Unexpected library file: {}", source.display()))?;
+            let dest = new_dir.join(relative);
+
+            if dest.exists() {
+                if same_contents(source, &dest) {
+                    continue;
+                }
+                return Err(format!(
+                    "A different file already exists at {}",
+                    dest.display()
+                ));
+            }
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+
+            fs::copy(source, &dest)
+                .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+            copied.push(dest.clone());
+            if !same_contents(source, &dest) {
+                return Err(format!("Copy of {} did not verify", source.display()));
+            }
+
+            bytes_copied += size;
+            let done = index + 1;
+            if done.is_multiple_of(PROGRESS_EVERY) || done == total {
+                let _ = app.emit(
+                    "library-migrate-progress",
+                    LibraryMigrateProgress {
+                        completed: done,
+                        total,
+                        bytes_copied,
+                        total_bytes,
+                    },
+                );
+            }
+        }
+        Ok(())
+    })();
+
+    let catalog_updated =
+        match copy_result.and_then(|()| relocate_library_paths(&app, &old_dir, &new_dir)) {
+            Ok(updated) => updated,
+            Err(e) => {
+                // Leave the old library untouched and undo the partial copy
+                for path in &copied {
+                    let _ = fs::remove_file(path);
+                }
+                return Err(e.into());
+            }
+        };
+
+    config::update(&app, |config| {
+        roots::set_root_path(&app, config, &root.id, new_path.clone())
+    })?;
+    watcher::watch_library(&app);
+
+    let mut removed_old = false;
+    if move_files.unwrap_or(false) {
+        removed_old = files.iter().all(|(path, _)| fs::remove_file(path).is_ok());
+        if removed_old {
+            let _ = fs::remove_dir(&old_dir);
+        }
+    }
+
+    tracing::info!("Library migrated to {}", new_path);
+    storage::invalidate(&app);
+    Ok(LibraryMigration {
+        new_path,
+        files: total,
+        bytes: total_bytes,
+        catalog_updated,
+        removed_old,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::testing;
+
+    #[test]
+    fn copies_unicode_sources_into_a_long_library_path() {
+        let root = testing::scratch_dir("library-copy");
+        let library_dir = testing::deep_dir(&root.join("library"), 300);
+
+        for (index, name) in testing::UNICODE_NAMES.iter().enumerate() {
+            let source = root.join(format!("{}.png", name));
+            testing::write_png(&source);
+            let item = LibraryCopyItem {
+                source_path: source.to_string_lossy().into_owned(),
+                image_id: format!("image-{}", index),
+            };
+
+            let dest = copy_one(&library_dir, &item).unwrap();
+            assert_eq!(dest, library_target(&library_dir, &source, &item.image_id));
+            assert!(same_contents(&source, &dest));
+        }
+
+        fs::remove_dir_all(paths::extended(&root)).unwrap();
+    }
+
+    #[test]
+    fn moves_unicode_files_out_of_deep_folders() {
+        let root = testing::scratch_dir("library-move");
+        let deep = testing::deep_dir(&root.join("source"), 300);
+        let library_dir = root.join("library");
+        fs::create_dir_all(&library_dir).unwrap();
+
+        for name in testing::UNICODE_NAMES {
+            let source = deep.join(format!("{}.png", name));
+            testing::write_png(&source);
+            let dest = library_dir.join(format!("{}.png", name));
+
+            move_file(&source, &dest).unwrap();
+            assert!(!paths::extended(&source).exists());
+            assert!(dest.is_file());
+        }
+
+        fs::remove_dir_all(paths::extended(&root)).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+// Log output for packaged builds, where there is no console to print to.
+// Everything goes to a daily log file in app_data/logs as well as stdout,
+// and error-level events are forwarded to the webview as `backend-error`.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{Builder, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Layer, Registry};
+
+use crate::config;
+use crate::error::DrawStackError;
+
+pub const LOG_DIR: &str = "logs";
+const LOG_PREFIX: &str = "drawstack";
+const LOG_SUFFIX: &str = "log";
+const KEEP_LOG_FILES: usize = 7;
+
+const DEFAULT_RECENT_LINES: usize = 200;
+const MAX_RECENT_LINES: usize = 5000;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+pub struct LogState {
+    dir: PathBuf,
+    level: reload::Handle<LevelFilter, Registry>,
+    // Dropping this stops the background writer, so it lives as long as the app
+    _writer: Mutex<WorkerGuard>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct BackendError {
+    message: String,
+    target: String,
+}
+
+// Forwards error-level events to the frontend so failures in background
+// work reach the user instead of only the log file.
+struct ErrorEvents {
+    app: AppHandle,
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for ErrorEvents {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() != Level::ERROR {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let _ = self.app.emit(
+            "backend-error",
+            BackendError {
+                message: visitor.message,
+                target: event.metadata().target().to_string(),
+            },
+        );
+    }
+}
+
+fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(app_dir.join(LOG_DIR))
+}
+
+// Install the global subscriber at the level saved in the config. Called
+// once from setup; log records from dependencies using the `log` crate are
+// captured too.
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let dir = log_dir(app)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
+    let appender = Builder::new()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_PREFIX)
+        .filename_suffix(LOG_SUFFIX)
+        .max_log_files(KEEP_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| format!("Failed to open log file: {}", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let (level, handle) = reload::Layer::new(LevelFilter::from(config::load(app).log_level));
+    tracing_subscriber::registry()
+        .with(level)
+        .with(fmt::layer().with_writer(writer).with_ansi(false))
+        .with(fmt::layer())
+        .with(ErrorEvents { app: app.clone() })
+        .try_init()
+        .map_err(|e| format!("Failed to start logging: {}", e))?;
+
+    app.manage(LogState {
+        dir,
+        level: handle,
+        _writer: Mutex::new(guard),
+    });
+    Ok(())
+}
+
+// Log files sorted newest first; the date in their names sorts lexically
+fn log_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read log directory: {}", e))?;
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .map(|n| n.to_string_lossy())
+                .is_some_and(|n| n.starts_with(LOG_PREFIX) && n.ends_with(LOG_SUFFIX))
+        })
+        .collect();
+    files.sort_by(|a, b| b.cmp(a));
+    Ok(files)
+}
+
+// The last `lines` lines logged, oldest first, reaching back into earlier
+// days' files when today's is short.
+#[tauri::command]
+pub fn get_recent_logs(
+    app: AppHandle,
+    lines: Option<usize>,
+) -> Result<Vec<String>, DrawStackError> {
+    let wanted = lines.unwrap_or(DEFAULT_RECENT_LINES).min(MAX_RECENT_LINES);
+    let dir = match app.try_state::<LogState>() {
+        Some(state) => state.dir.clone(),
+        None => log_dir(&app)?,
+    };
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut recent: Vec<String> = Vec::new();
+    for file in log_files(&dir)? {
+        if recent.len() >= wanted {
+            break;
+        }
+        let contents = fs::read(&file).map_err(|e| DrawStackError::io("read", &file, e))?;
+        let contents = String::from_utf8_lossy(&contents);
+        // Collected newest first and flipped at the end
+        recent.extend(
+            contents
+                .lines()
+                .rev()
+                .take(wanted - recent.len())
+                .map(str::to_string),
+        );
+    }
+    recent.reverse();
+    Ok(recent)
+}
+
+// Change the log level immediately and keep it for the next launch
+#[tauri::command]
+pub fn set_log_level(app: AppHandle, level: LogLevel) -> Result<(), DrawStackError> {
+    if let Some(state) = app.try_state::<LogState>() {
+        state
+            .level
+            .reload(LevelFilter::from(level))
+            .map_err(|e| format!("Failed to change log level: {}", e))?;
+    }
+    config::update(&app, |config| {
+        config.log_level = level;
+        Ok(())
+    })?;
+    tracing::info!("Log level set to {:?}", level);
+    Ok(())
+}
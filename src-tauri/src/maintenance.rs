@@ -0,0 +1,287 @@
+// Background upkeep. A scheduler thread started in `run()` wakes every few
+// minutes and, once `interval_hours` have passed since the last pass, sweeps
+// orphaned thumbnails, compacts the catalog, remeasures storage and looks for
+// missing files, then emits a `maintenance-report` event.
+use rusqlite::Connection;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DrawStackError;
+use crate::integrity::{self, MissingFile};
+use crate::storage::{self, StorageInfo};
+use crate::thumbnails::{self, ThumbnailCleanup};
+use crate::{catalog, config, imports};
+
+const REPORT_FILE: &str = "maintenance.json";
+// Give startup (shard migration, folder watchers) a head start
+const STARTUP_DELAY: Duration = Duration::from_secs(5 * 60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+// Thumbnails newer than this may belong to an import still writing its rows
+const THUMBNAIL_GRACE: Duration = Duration::from_secs(60 * 60);
+const MAX_INTERVAL_HOURS: u32 = 24 * 30;
+
+// Stored in the app config as `maintenance`.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(default)]
+pub struct MaintenanceSettings {
+    pub enabled: bool,
+    // Time between passes
+    pub interval_hours: u32,
+    // Delete thumbnails no catalog image refers to
+    pub thumbnail_gc: bool,
+    // VACUUM the catalog to give back space freed by deletions
+    pub vacuum: bool,
+    // Refresh the cached library size
+    pub storage_recount: bool,
+    // Report images whose original and library copy are gone
+    pub missing_file_check: bool,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            thumbnail_gc: true,
+            vacuum: true,
+            storage_recount: true,
+            missing_file_check: true,
+        }
+    }
+}
+
+impl MaintenanceSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_INTERVAL_HOURS).contains(&self.interval_hours) {
+            return Err(format!(
+                "interval_hours must be between 1 and {}",
+                MAX_INTERVAL_HOURS
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct VacuumResult {
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    pub freed_formatted: String,
+}
+
+// Payload of `maintenance-report`, also kept in REPORT_FILE. Tasks that were
+// off or skipped are None.
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct MaintenanceReport {
+    // Unix seconds
+    pub started_at: i64,
+    pub duration_ms: u64,
+    // True when the scheduler ran the pass rather than `run_maintenance`
+    pub scheduled: bool,
+    pub thumbnails: Option<ThumbnailCleanup>,
+    pub vacuum: Option<VacuumResult>,
+    pub storage: Option<StorageInfo>,
+    pub missing_originals: Option<Vec<MissingFile>>,
+    pub missing_library_copies: Option<Vec<MissingFile>>,
+    pub errors: Vec<String>,
+}
+
+// Only one pass at a time, whether scheduled or asked for.
+#[derive(Default)]
+pub struct MaintenanceScheduler {
+    running: Mutex<()>,
+}
+
+fn report_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(app_dir.join(REPORT_FILE))
+}
+
+// The part of a saved report the schedule needs
+#[derive(serde::Deserialize)]
+struct LastRun {
+    started_at: i64,
+}
+
+fn last_run(app: &AppHandle) -> Option<i64> {
+    let contents = fs::read_to_string(report_path(app).ok()?).ok()?;
+    serde_json::from_str::<LastRun>(&contents)
+        .ok()
+        .map(|last| last.started_at)
+}
+
+fn save_report(app: &AppHandle, report: &MaintenanceReport) -> Result<(), String> {
+    let path = report_path(app)?;
+    let contents = serde_json::to_string_pretty(report)
+        .map_err(|e| format!("Failed to serialize maintenance report: {}", e))?;
+    crate::write_atomic(&path, contents.as_bytes())
+        .map_err(|e| format!("Failed to save maintenance report: {}", e))
+}
+
+fn catalog_size(conn: &Connection) -> Result<u64, String> {
+    conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to measure catalog: {}", e))
+}
+
+fn vacuum(app: &AppHandle) -> Result<VacuumResult, DrawStackError> {
+    let conn = catalog::open(app)?;
+    let before_bytes = catalog_size(&conn)?;
+    conn.execute_batch("VACUUM; PRAGMA optimize;")
+        .map_err(|e| format!("Failed to vacuum catalog: {}", e))?;
+    let after_bytes = catalog_size(&conn)?;
+
+    Ok(VacuumResult {
+        before_bytes,
+        after_bytes,
+        freed_formatted: crate::format_bytes(before_bytes.saturating_sub(after_bytes)),
+    })
+}
+
+fn collect_thumbnail_garbage(app: &AppHandle) -> Result<ThumbnailCleanup, DrawStackError> {
+    let conn = catalog::open(app)?;
+    let mut stmt = conn
+        .prepare("SELECT id FROM images")
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+    let valid: HashSet<String> = stmt
+        .query_map([], |row| row.get(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<_>>())
+        .map_err(|e| format!("Failed to read image IDs: {}", e))?;
+    thumbnails::remove_orphans(app, &valid, THUMBNAIL_GRACE)
+}
+
+fn failed(report: &mut MaintenanceReport, task: &str, e: DrawStackError) {
+    tracing::warn!("Maintenance {} failed: {}", task, e);
+    report.errors.push(format!("{}: {}", task, e));
+}
+
+// One maintenance pass with the tasks `settings` enables. A failing task is
+// recorded in `errors` and doesn't stop the others.
+fn run_pass(
+    app: &AppHandle,
+    settings: &MaintenanceSettings,
+    scheduled: bool,
+) -> Result<MaintenanceReport, DrawStackError> {
+    let scheduler = app.state::<MaintenanceScheduler>();
+    let Ok(_running) = scheduler.running.try_lock() else {
+        return Err(DrawStackError::Busy(
+            "Maintenance is already running".to_string(),
+        ));
+    };
+
+    let start = Instant::now();
+    let mut report = MaintenanceReport {
+        started_at: catalog::now_unix(),
+        scheduled,
+        ..Default::default()
+    };
+
+    if settings.thumbnail_gc {
+        // An import's thumbnails can reach disk before its catalog rows
+        if app.state::<imports::ImportControl>().any_running() {
+            tracing::info!("Skipping thumbnail cleanup while an import runs");
+        } else {
+            match collect_thumbnail_garbage(app) {
+                Ok(cleanup) => report.thumbnails = Some(cleanup),
+                Err(e) => failed(&mut report, "thumbnail cleanup", e),
+            }
+        }
+    }
+    if settings.vacuum {
+        match vacuum(app) {
+            Ok(result) => report.vacuum = Some(result),
+            Err(e) => failed(&mut report, "catalog vacuum", e),
+        }
+    }
+    if settings.missing_file_check {
+        match tauri::async_runtime::block_on(integrity::verify_library(app.clone(), Some(false))) {
+            Ok(check) => {
+                report.missing_originals = Some(check.missing_originals);
+                report.missing_library_copies = Some(check.missing_library_copies);
+            }
+            Err(e) => failed(&mut report, "missing file check", e),
+        }
+    }
+    if settings.storage_recount {
+        storage::invalidate(app);
+        match storage::scan(app, None) {
+            Ok(info) => report.storage = Some(info),
+            Err(e) => failed(&mut report, "storage recount", e),
+        }
+    }
+
+    report.duration_ms = start.elapsed().as_millis() as u64;
+    tracing::info!(
+        "Maintenance finished in {}ms with {} errors",
+        report.duration_ms,
+        report.errors.len()
+    );
+    save_report(app, &report)?;
+    app.emit("maintenance-report", &report)
+        .map_err(|e| format!("Failed to emit event: {}", e))?;
+    Ok(report)
+}
+
+fn due(app: &AppHandle, settings: &MaintenanceSettings) -> bool {
+    let interval = i64::from(settings.interval_hours) * 60 * 60;
+    last_run(app).is_none_or(|started_at| catalog::now_unix() - started_at >= interval)
+}
+
+impl MaintenanceScheduler {
+    // Settings are re-read on every check, so changes apply without a restart
+    pub fn start(&self, app: &AppHandle) {
+        let app = app.clone();
+        std::thread::Builder::new()
+            .name("maintenance".into())
+            .spawn(move || {
+                std::thread::sleep(STARTUP_DELAY);
+                loop {
+                    let settings = config::load(&app).maintenance;
+                    if settings.enabled && due(&app, &settings) {
+                        if let Err(e) = run_pass(&app, &settings, true) {
+                            tracing::warn!("Scheduled maintenance failed: {}", e);
+                        }
+                    }
+                    std::thread::sleep(CHECK_INTERVAL);
+                }
+            })
+            .expect("failed to spawn maintenance thread");
+    }
+}
+
+// Run every enabled task now, regardless of the schedule
+#[tauri::command]
+pub async fn run_maintenance(app: AppHandle) -> Result<MaintenanceReport, DrawStackError> {
+    let settings = config::load(&app).maintenance;
+    tauri::async_runtime::spawn_blocking(move || run_pass(&app, &settings, false))
+        .await
+        .map_err(|e| format!("Maintenance task failed: {}", e))?
+}
+
+#[tauri::command]
+pub fn get_maintenance_settings(app: AppHandle) -> MaintenanceSettings {
+    config::load(&app).maintenance
+}
+
+#[tauri::command]
+pub fn set_maintenance_settings(
+    app: AppHandle,
+    settings: MaintenanceSettings,
+) -> Result<(), DrawStackError> {
+    settings.validate().map_err(DrawStackError::invalid)?;
+    config::update(&app, |config| {
+        config.maintenance = settings;
+        Ok(())
+    })?;
+    Ok(())
+}
@@ -0,0 +1,345 @@
+// Self-describing library folders. The library keeps its files flat
+// (`<root>/<image_id>.<ext>`), so each pack's manifest lives in a metadata
+// folder of every root holding its copies: `<root>/.drawstack/packs/<id>.json`.
+// A fresh install can rebuild the catalog from these without the database.
+use rusqlite::params;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::catalog::{self, PackRecord};
+use crate::error::DrawStackError;
+use crate::paths::StoredPath;
+use crate::{roots, storage, thumbnails, ThumbnailInfo};
+
+const MANIFEST_FORMAT: &str = "drawstack-pack";
+const MANIFEST_VERSION: u32 = 1;
+// Relative to a library root; skipped by orphan checks
+pub const MANIFEST_DIR: &str = ".drawstack";
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+struct PackManifest {
+    format: String,
+    version: u32,
+    id: String,
+    name: String,
+    created_at: i64,
+    source_path: Option<String>,
+    updated_at: i64,
+    images: Vec<ManifestImage>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+struct ManifestImage {
+    id: String,
+    // blake3 of the file, for images whose ID is derived from their bytes
+    hash: Option<String>,
+    filename: String,
+    relative_path: String,
+    original_path: StoredPath,
+    // Library copy, relative to the root holding this manifest
+    file: Option<String>,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ManifestIngest {
+    pub packs: usize,
+    pub images: usize,
+    // Listed images with neither a library copy nor an original left
+    pub missing: usize,
+}
+
+pub fn manifests_dir(root: &Path) -> PathBuf {
+    root.join(MANIFEST_DIR).join("packs")
+}
+
+fn manifest_path(root: &Path, pack_id: &str) -> PathBuf {
+    manifests_dir(root).join(format!("{}.json", pack_id))
+}
+
+// Content IDs are the first 32 hex digits of the blake3 digest; derived
+// images get UUIDs instead
+fn content_hash_of(image_id: &str) -> Option<String> {
+    (image_id.len() == 32 && image_id.bytes().all(|b| b.is_ascii_hexdigit()))
+        .then(|| image_id.to_string())
+}
+
+fn manifest_image(image: &catalog::CatalogImage, file: Option<String>) -> ManifestImage {
+    ManifestImage {
+        id: image.id.clone(),
+        hash: content_hash_of(&image.id),
+        filename: image.filename.clone(),
+        relative_path: image.relative_path.clone(),
+        original_path: image.original_path.clone(),
+        size: fs::metadata(image.source_path()).ok().map(|m| m.len()),
+        file,
+        width: image.width,
+        height: image.height,
+    }
+}
+
+// Split a pack's images by the root holding their library copy. Images
+// without a copy are listed in the primary root's manifest.
+fn manifests_for(
+    pack: &PackRecord,
+    root_dirs: &[PathBuf],
+    primary: &Path,
+) -> Vec<(PathBuf, PackManifest)> {
+    let now = catalog::now_unix();
+    let mut manifests: Vec<(PathBuf, PackManifest)> = Vec::new();
+    for image in &pack.images {
+        let copy = image.library_path.as_ref().and_then(|library_path| {
+            let library_path = library_path.to_path_buf();
+            root_dirs.iter().find_map(|root| {
+                library_path
+                    .strip_prefix(root)
+                    .ok()
+                    .map(|relative| (root.clone(), relative.to_string_lossy().replace('\\', "/")))
+            })
+        });
+        let (root, file) = match copy {
+            Some((root, relative)) => (root, Some(relative)),
+            None => (primary.to_path_buf(), None),
+        };
+        let entry = manifest_image(image, file);
+        match manifests.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, manifest)) => manifest.images.push(entry),
+            None => manifests.push((
+                root,
+                PackManifest {
+                    format: MANIFEST_FORMAT.to_string(),
+                    version: MANIFEST_VERSION,
+                    id: pack.id.clone(),
+                    name: pack.name.clone(),
+                    created_at: pack.created_at,
+                    source_path: pack.source_path.clone(),
+                    updated_at: now,
+                    images: vec![entry],
+                },
+            )),
+        }
+    }
+    manifests
+}
+
+fn write_manifests(
+    conn: &rusqlite::Connection,
+    pack_id: &str,
+    root_dirs: &[PathBuf],
+    primary: &Path,
+) -> Result<(), String> {
+    let manifests = match catalog::get_pack(conn, pack_id, None)? {
+        Some(pack) => manifests_for(&pack, root_dirs, primary),
+        None => Vec::new(),
+    };
+
+    for (root, manifest) in &manifests {
+        let path = manifest_path(root, pack_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let json = serde_json::to_vec_pretty(manifest)
+            .map_err(|e| format!("Failed to serialize pack manifest: {}", e))?;
+        crate::write_atomic(&path, &json)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+
+    // Deleted packs, and roots that no longer hold any of the pack's copies
+    for root in root_dirs {
+        if !manifests.iter().any(|(r, _)| r == root) {
+            let path = manifest_path(root, pack_id);
+            if path.exists() {
+                fs::remove_file(&path)
+                    .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Bring the manifests of `pack_ids` up to date after a catalog change. A
+// manifest that can't be written only costs a warning; the catalog remains
+// the source of truth.
+pub fn refresh<'a>(app: &AppHandle, pack_ids: impl IntoIterator<Item = &'a str>) {
+    let context = roots::root_dirs(app).and_then(|dirs| {
+        let primary = PathBuf::from(roots::primary_root(app)?.path);
+        let conn = catalog::open(app)?;
+        Ok((dirs, primary, conn))
+    });
+    let (root_dirs, primary, conn) = match context {
+        Ok(context) => context,
+        Err(e) => {
+            tracing::warn!("Skipping pack manifests: {}", e);
+            return;
+        }
+    };
+
+    let unique: HashSet<&str> = pack_ids.into_iter().collect();
+    for pack_id in unique {
+        if let Err(e) = write_manifests(&conn, pack_id, &root_dirs, &primary) {
+            tracing::warn!("Failed to update manifest for pack {}: {}", pack_id, e);
+        }
+    }
+}
+
+// Packs holding `image_ids`; unknown IDs are skipped
+pub fn packs_of(conn: &rusqlite::Connection, image_ids: &[String]) -> HashSet<String> {
+    image_ids
+        .iter()
+        .filter_map(|image_id| {
+            conn.query_row(
+                "SELECT pack_id FROM images WHERE id = ?1",
+                params![image_id],
+                |row| row.get::<_, String>(0),
+            )
+            .ok()
+        })
+        .collect()
+}
+
+// `refresh` for the packs holding `image_ids`
+pub fn refresh_for_images(app: &AppHandle, image_ids: &[String]) {
+    let Ok(conn) = catalog::open(app) else {
+        return;
+    };
+    let pack_ids = packs_of(&conn, image_ids);
+    refresh(app, pack_ids.iter().map(String::as_str));
+}
+
+// Rewrite the manifest of every pack, e.g. for a library that predates
+// manifests. Returns the number of packs written.
+#[tauri::command]
+pub async fn write_pack_manifests(app: AppHandle) -> Result<usize, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let mut stmt = conn
+        .prepare("SELECT id FROM packs")
+        .map_err(|e| format!("Failed to prepare pack query: {}", e))?;
+    let pack_ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read packs: {}", e))?;
+
+    refresh(&app, pack_ids.iter().map(String::as_str));
+    Ok(pack_ids.len())
+}
+
+fn read_manifest(path: &Path) -> Result<PackManifest, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let manifest: PackManifest = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid pack manifest {}: {}", path.display(), e))?;
+    if manifest.format != MANIFEST_FORMAT || manifest.version > MANIFEST_VERSION {
+        return Err(format!(
+            "Unsupported pack manifest {} (version {})",
+            path.display(),
+            manifest.version
+        ));
+    }
+    Ok(manifest)
+}
+
+fn ingest_manifest(
+    app: &AppHandle,
+    conn: &mut rusqlite::Connection,
+    root: &Path,
+    manifest: &PackManifest,
+    settings: &thumbnails::ThumbnailSettings,
+    report: &mut ManifestIngest,
+) -> Result<(), String> {
+    let mut infos: Vec<ThumbnailInfo> = Vec::new();
+    let mut copies = Vec::new();
+    for image in &manifest.images {
+        let copy = image.file.as_deref().map(|file| root.join(file));
+        let Some(path) = copy
+            .clone()
+            .filter(|p| p.is_file())
+            .or_else(|| Some(image.original_path.to_path_buf()).filter(|p| p.is_file()))
+        else {
+            report.missing += 1;
+            continue;
+        };
+
+        let mut info = crate::thumbnail_info(app, root, &path, settings, None);
+        info.id = image.id.clone();
+        info.original_path = image.original_path.clone();
+        info.filename = image.filename.clone();
+        info.relative_path = image.relative_path.clone();
+        if let Some(copy) = copy.filter(|p| p.is_file()) {
+            copies.push((image.id.clone(), copy.to_string_lossy().to_string()));
+        }
+        infos.push(info);
+    }
+
+    catalog::record_import(
+        app,
+        conn,
+        &manifest.id,
+        Some(&manifest.name),
+        manifest.source_path.as_deref(),
+        &infos,
+    )?;
+    conn.execute(
+        "UPDATE packs SET created_at = ?1 WHERE id = ?2",
+        params![manifest.created_at, manifest.id],
+    )
+    .map_err(|e| format!("Failed to restore pack date: {}", e))?;
+    for (image_id, library_path) in &copies {
+        conn.execute(
+            "UPDATE images SET library_path = ?1 WHERE id = ?2",
+            params![library_path, image_id],
+        )
+        .map_err(|e| format!("Failed to record library path: {}", e))?;
+    }
+
+    report.packs += 1;
+    report.images += infos.len();
+    Ok(())
+}
+
+// Rebuild catalog entries from the manifests in every library root, e.g.
+// after a fresh install pointed at an existing library. Packs and images
+// already in the catalog are updated in place.
+#[tauri::command]
+pub async fn ingest_pack_manifests(app: AppHandle) -> Result<ManifestIngest, DrawStackError> {
+    let mut conn = catalog::open(&app)?;
+    let settings = thumbnails::load_settings(&app);
+    let mut report = ManifestIngest {
+        packs: 0,
+        images: 0,
+        missing: 0,
+    };
+
+    for root in roots::root_dirs(&app)? {
+        let Ok(entries) = fs::read_dir(manifests_dir(&root)) else {
+            continue;
+        };
+        for path in entries.flatten().map(|e| e.path()) {
+            if crate::extension_lower(&path).as_deref() != Some("json") {
+                continue;
+            }
+            let result = read_manifest(&path).and_then(|manifest| {
+                ingest_manifest(&app, &mut conn, &root, &manifest, &settings, &mut report)
+            });
+            if let Err(e) = result {
+                tracing::warn!("Skipping pack manifest: {}", e);
+            }
+        }
+    }
+    storage::invalidate(&app);
+
+    tracing::info!(
+        "Ingested {} packs ({} images, {} missing) from pack manifests",
+        report.packs,
+        report.images,
+        report.missing
+    );
+    Ok(report)
+}
@@ -0,0 +1,182 @@
+// Import performance, for telling a slow source (a NAS, a USB disk) from a
+// slow machine. The import pipeline times each file's stages and, when the
+// `performance_metrics` setting is on, the last few imports are kept in
+// memory for `get_performance_metrics`. Nothing leaves the machine.
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::config;
+
+// Imports kept, oldest dropped first
+const MAX_IMPORTS: usize = 20;
+
+// Time one file spent in each stage of thumbnailing. `io` covers reading
+// metadata and hashing the file; `decode` includes the decoder's own reads.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileTimings {
+    pub io: Duration,
+    pub decode: Duration,
+    pub resize: Duration,
+    pub save: Duration,
+    pub bytes: u64,
+}
+
+impl FileTimings {
+    pub fn add(&mut self, other: &FileTimings) {
+        self.io += other.io;
+        self.decode += other.decode;
+        self.resize += other.resize;
+        self.save += other.save;
+        self.bytes += other.bytes;
+    }
+}
+
+// Milliseconds per stage
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct StageBreakdown {
+    pub io_ms: f64,
+    pub decode_ms: f64,
+    pub resize_ms: f64,
+    pub save_ms: f64,
+}
+
+impl StageBreakdown {
+    fn from_timings(timings: &FileTimings, divisor: usize) -> Self {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0 / divisor.max(1) as f64;
+        Self {
+            io_ms: ms(timings.io),
+            decode_ms: ms(timings.decode),
+            resize_ms: ms(timings.resize),
+            save_ms: ms(timings.save),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ImportMetrics {
+    pub pack_id: String,
+    pub source_path: String,
+    // Unix seconds
+    pub started_at: i64,
+    pub wall_ms: u64,
+    pub worker_threads: usize,
+    pub images: usize,
+    pub failed: usize,
+    pub bytes_read: u64,
+    pub images_per_sec: f64,
+    pub megabytes_per_sec: f64,
+    // Summed over all workers, so it can exceed `wall_ms`
+    pub stage_totals: StageBreakdown,
+    pub per_image: StageBreakdown,
+    // Share of stage time spent on file reads before decoding; high values
+    // point at the source rather than the CPU
+    pub io_wait_percent: f64,
+    // False while paused, or when cancelled
+    pub completed: bool,
+}
+
+// Running totals for one import run; a resumed import starts a new sample.
+pub struct ImportSample {
+    pack_id: String,
+    source_path: String,
+    started_at: i64,
+    start: Instant,
+    worker_threads: usize,
+    images: usize,
+    failed: usize,
+    timings: FileTimings,
+}
+
+impl ImportSample {
+    pub fn new(pack_id: &str, source_path: &str, worker_threads: usize) -> Self {
+        Self {
+            pack_id: pack_id.to_string(),
+            source_path: source_path.to_string(),
+            started_at: crate::catalog::now_unix(),
+            start: Instant::now(),
+            worker_threads,
+            images: 0,
+            failed: 0,
+            timings: FileTimings::default(),
+        }
+    }
+
+    pub fn add(&mut self, timings: &FileTimings, failed: bool) {
+        self.images += 1;
+        self.failed += usize::from(failed);
+        self.timings.add(timings);
+    }
+
+    fn finish(self, completed: bool) -> ImportMetrics {
+        let wall = self.start.elapsed();
+        let seconds = wall.as_secs_f64().max(f64::EPSILON);
+        let t = &self.timings;
+        let stage_total = (t.io + t.decode + t.resize + t.save).as_secs_f64();
+
+        ImportMetrics {
+            pack_id: self.pack_id,
+            source_path: self.source_path,
+            started_at: self.started_at,
+            wall_ms: wall.as_millis() as u64,
+            worker_threads: self.worker_threads,
+            images: self.images,
+            failed: self.failed,
+            bytes_read: t.bytes,
+            images_per_sec: self.images as f64 / seconds,
+            megabytes_per_sec: t.bytes as f64 / (1024.0 * 1024.0) / seconds,
+            stage_totals: StageBreakdown::from_timings(t, 1),
+            per_image: StageBreakdown::from_timings(t, self.images),
+            io_wait_percent: if stage_total > 0.0 {
+                t.io.as_secs_f64() / stage_total * 100.0
+            } else {
+                0.0
+            },
+            completed,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PerformanceMetrics {
+    imports: Mutex<VecDeque<ImportMetrics>>,
+}
+
+// Keep a finished (or paused, or cancelled) import's numbers if metrics are on
+pub fn record(app: &AppHandle, sample: ImportSample, completed: bool) {
+    if !config::load(app).performance_metrics || sample.images == 0 {
+        return;
+    }
+    let metrics = sample.finish(completed);
+    tracing::debug!(
+        "Import {}: {:.1} images/sec, {:.0}% io wait",
+        metrics.pack_id,
+        metrics.images_per_sec,
+        metrics.io_wait_percent
+    );
+
+    let state = app.state::<PerformanceMetrics>();
+    let mut imports = state.imports.lock().unwrap();
+    if imports.len() == MAX_IMPORTS {
+        imports.pop_front();
+    }
+    imports.push_back(metrics);
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct PerformanceReport {
+    pub enabled: bool,
+    // Newest first
+    pub imports: Vec<ImportMetrics>,
+}
+
+#[tauri::command]
+pub fn get_performance_metrics(app: AppHandle) -> PerformanceReport {
+    let state = app.state::<PerformanceMetrics>();
+    let imports = state.imports.lock().unwrap();
+    PerformanceReport {
+        enabled: config::load(&app).performance_metrics,
+        imports: imports.iter().rev().cloned().collect(),
+    }
+}
@@ -0,0 +1,80 @@
+// OS-level file drags out of the grid, so a reference can be dropped into
+// Krita, Photoshop or a file manager as the original file rather than the
+// webview's copy of the thumbnail.
+use ::drag::{CursorPosition, DragItem, DragResult, Image, Options};
+use std::sync::mpsc;
+use tauri::{AppHandle, Emitter, Window};
+
+use crate::error::DrawStackError;
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct NativeDragFinished {
+    dropped: bool,
+    x: i32,
+    y: i32,
+}
+
+// Start dragging `paths` from `window`. `icon` is the image shown under the
+// cursor, normally the thumbnail the drag started on; without one the first
+// file is used. Emits `native-drag-finished` once the user drops or cancels.
+#[tauri::command]
+pub async fn start_native_drag(
+    app: AppHandle,
+    window: Window,
+    paths: Vec<String>,
+    icon: Option<String>,
+) -> Result<(), DrawStackError> {
+    let mut files = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let resolved = crate::resolve_known_path(&app, path)?;
+        if !resolved.is_file() {
+            return Err(DrawStackError::not_found(&resolved));
+        }
+        files.push(resolved);
+    }
+    let Some(first) = files.first().cloned() else {
+        return Err(DrawStackError::invalid("Nothing to drag"));
+    };
+    let icon = match icon {
+        Some(icon) => crate::scope::resolve(&app, &icon)?,
+        None => first,
+    };
+
+    // Every platform wants the drag started from the UI thread
+    let (tx, rx) = mpsc::channel();
+    let handle = app.clone();
+    app.run_on_main_thread(move || {
+        let on_drop = move |result: DragResult, position: CursorPosition| {
+            let _ = handle.emit(
+                "native-drag-finished",
+                NativeDragFinished {
+                    dropped: matches!(result, DragResult::Dropped),
+                    x: position.x,
+                    y: position.y,
+                },
+            );
+        };
+
+        #[cfg(target_os = "linux")]
+        let target = window.gtk_window().map_err(|e| e.to_string());
+        #[cfg(not(target_os = "linux"))]
+        let target: Result<Window, String> = Ok(window);
+
+        let started = target.and_then(|target| {
+            ::drag::start_drag(
+                &target,
+                DragItem::Files(files),
+                Image::File(icon),
+                on_drop,
+                Options::default(),
+            )
+            .map_err(|e| e.to_string())
+        });
+        let _ = tx.send(started);
+    })
+    .map_err(|e| format!("Failed to start drag: {}", e))?;
+
+    rx.recv()
+        .map_err(|e| format!("Failed to start drag: {}", e))?
+        .map_err(|e| DrawStackError::Other(format!("Failed to start drag: {}", e)))
+}
@@ -0,0 +1,96 @@
+// Shape categories for building sessions, e.g. only portrait full-body
+// references or only landscape scenes. The catalog derives both columns from
+// the stored display dimensions; the thresholds here must match migration 15.
+use rusqlite::params_from_iter;
+use tauri::AppHandle;
+
+use crate::catalog::{self, CatalogImage, ImageSort};
+use crate::error::DrawStackError;
+use crate::search::{self, SearchFilters};
+
+// Labels for `megapixel_bucket` 0..=3
+pub const MEGAPIXEL_BUCKETS: &[&str] = &["< 2 MP", "2-8 MP", "8-20 MP", "20+ MP"];
+
+// Portrait, landscape or square by display dimensions - not the EXIF
+// orientation tag. Sides within 5% of each other count as square.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Aspect {
+    Portrait,
+    Landscape,
+    Square,
+}
+
+impl Aspect {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Portrait => "portrait",
+            Self::Landscape => "landscape",
+            Self::Square => "square",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "portrait" => Some(Self::Portrait),
+            "landscape" => Some(Self::Landscape),
+            "square" => Some(Self::Square),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct OrientationFilter {
+    pub aspect: Aspect,
+    // Only images in these megapixel buckets; empty means any size
+    #[serde(default)]
+    pub megapixel_buckets: Vec<u8>,
+    pub pack_id: Option<String>,
+    #[serde(default)]
+    pub exclude_flagged: bool,
+    pub sort: Option<ImageSort>,
+}
+
+// Images of one shape, optionally limited to a pack and to some megapixel
+// buckets. Images whose dimensions are unknown never match.
+#[tauri::command]
+pub async fn get_images_by_orientation(
+    app: AppHandle,
+    filter: OrientationFilter,
+) -> Result<Vec<CatalogImage>, DrawStackError> {
+    if let Some(bucket) = filter
+        .megapixel_buckets
+        .iter()
+        .find(|&&b| b as usize >= MEGAPIXEL_BUCKETS.len())
+    {
+        return Err(DrawStackError::invalid(format!(
+            "Unknown megapixel bucket: {}",
+            bucket
+        )));
+    }
+
+    let filters = SearchFilters {
+        pack_id: filter.pack_id,
+        aspect: Some(filter.aspect),
+        megapixel_buckets: filter.megapixel_buckets,
+        exclude_flagged: filter.exclude_flagged,
+        ..SearchFilters::default()
+    };
+    let (conditions, values) = search::build_conditions("", &filters);
+
+    let conn = catalog::open(&app)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id
+             WHERE {} ORDER BY {}",
+            catalog::IMAGE_COLUMNS,
+            conditions.join(" AND "),
+            catalog::order_by(filter.sort, "i.pack_id, i.relative_path, i.filename")
+        ))
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+
+    stmt.query_map(params_from_iter(values), catalog::map_image)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(DrawStackError::from)
+}
@@ -0,0 +1,205 @@
+use rusqlite::params;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::catalog;
+use crate::error::DrawStackError;
+use crate::paths::StoredPath;
+
+// Upper bounds (exclusive) of the dimension histogram buckets, by long edge
+const SIZE_BUCKETS: &[(u32, &str)] = &[
+    (1000, "< 1000 px"),
+    (2000, "1000-1999 px"),
+    (4000, "2000-3999 px"),
+    (8000, "4000-7999 px"),
+    (u32::MAX, "8000+ px"),
+];
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct DimensionBucket {
+    pub label: &'static str,
+    pub count: usize,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct FormatCount {
+    pub format: String,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct DateRange {
+    pub earliest: Option<i64>,
+    pub latest: Option<i64>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct PackStats {
+    pub pack_id: String,
+    pub image_count: usize,
+    pub total_bytes: u64,
+    pub total_formatted: String,
+    // Images whose file couldn't be found to measure
+    pub missing_files: usize,
+    pub dimensions: Vec<DimensionBucket>,
+    pub unknown_dimensions: usize,
+    pub portrait: usize,
+    pub landscape: usize,
+    pub square: usize,
+    // Largest share first
+    pub formats: Vec<FormatCount>,
+    // Unix seconds
+    pub imported: DateRange,
+    pub modified: DateRange,
+    // EXIF capture dates as stored, `YYYY-MM-DDTHH:MM:SS`
+    pub captured_earliest: Option<String>,
+    pub captured_latest: Option<String>,
+}
+
+struct StatsRow {
+    filename: String,
+    path: StoredPath,
+    width: Option<u32>,
+    height: Option<u32>,
+    file_size: Option<u64>,
+    modified_at: Option<i64>,
+    captured_at: Option<String>,
+    imported_at: i64,
+}
+
+fn widen(range: &mut DateRange, value: Option<i64>) {
+    let Some(value) = value else {
+        return;
+    };
+    range.earliest = Some(range.earliest.map_or(value, |e| e.min(value)));
+    range.latest = Some(range.latest.map_or(value, |l| l.max(value)));
+}
+
+// Size and format numbers for a pack's detail view. Sizes come from the
+// catalog; images imported before sizes were recorded are measured on disk.
+#[tauri::command]
+pub async fn get_pack_stats(app: AppHandle, pack_id: String) -> Result<PackStats, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT filename, COALESCE(library_path, original_path), width, height, file_size,
+                    modified_at, captured_at, imported_at
+             FROM images WHERE pack_id = ?1",
+        )
+        .map_err(|e| format!("Failed to prepare pack query: {}", e))?;
+    let rows = stmt
+        .query_map(params![pack_id], |row| {
+            Ok(StatsRow {
+                filename: row.get(0)?,
+                path: row.get(1)?,
+                width: row.get(2)?,
+                height: row.get(3)?,
+                file_size: row.get::<_, Option<i64>>(4)?.map(|s| s as u64),
+                modified_at: row.get(5)?,
+                captured_at: row.get(6)?,
+                imported_at: row.get(7)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read pack images: {}", e))?;
+
+    if rows.is_empty() && catalog::get_pack(&conn, &pack_id, None)?.is_none() {
+        return Err(DrawStackError::invalid(format!(
+            "Pack not found: {}",
+            pack_id
+        )));
+    }
+
+    let mut buckets = vec![0usize; SIZE_BUCKETS.len()];
+    let mut formats: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+    let mut stats = PackStats {
+        pack_id,
+        image_count: rows.len(),
+        total_bytes: 0,
+        total_formatted: String::new(),
+        missing_files: 0,
+        dimensions: Vec::new(),
+        unknown_dimensions: 0,
+        portrait: 0,
+        landscape: 0,
+        square: 0,
+        formats: Vec::new(),
+        imported: DateRange::default(),
+        modified: DateRange::default(),
+        captured_earliest: None,
+        captured_latest: None,
+    };
+
+    for row in &rows {
+        let size = match row.file_size {
+            Some(size) => Some(size),
+            None => fs::metadata(row.path.to_path_buf()).ok().map(|m| m.len()),
+        };
+        if size.is_none() {
+            stats.missing_files += 1;
+        }
+        let size = size.unwrap_or(0);
+        stats.total_bytes += size;
+
+        let format = crate::extension_lower(Path::new(&row.filename))
+            .unwrap_or_else(|| "unknown".to_string());
+        let entry = formats.entry(format).or_default();
+        entry.0 += 1;
+        entry.1 += size;
+
+        match (row.width, row.height) {
+            (Some(width), Some(height)) => {
+                let long_edge = width.max(height);
+                let bucket = SIZE_BUCKETS
+                    .iter()
+                    .position(|(below, _)| long_edge < *below)
+                    .unwrap_or(SIZE_BUCKETS.len() - 1);
+                buckets[bucket] += 1;
+                match width.cmp(&height) {
+                    std::cmp::Ordering::Less => stats.portrait += 1,
+                    std::cmp::Ordering::Greater => stats.landscape += 1,
+                    std::cmp::Ordering::Equal => stats.square += 1,
+                }
+            }
+            _ => stats.unknown_dimensions += 1,
+        }
+
+        widen(&mut stats.imported, Some(row.imported_at));
+        widen(&mut stats.modified, row.modified_at);
+        // Capture dates are fixed-width ISO strings, so they compare as text
+        if let Some(captured) = &row.captured_at {
+            if stats
+                .captured_earliest
+                .as_ref()
+                .is_none_or(|e| captured < e)
+            {
+                stats.captured_earliest = Some(captured.clone());
+            }
+            if stats.captured_latest.as_ref().is_none_or(|l| captured > l) {
+                stats.captured_latest = Some(captured.clone());
+            }
+        }
+    }
+
+    stats.total_formatted = crate::format_bytes(stats.total_bytes);
+    stats.dimensions = SIZE_BUCKETS
+        .iter()
+        .zip(buckets)
+        .map(|((_, label), count)| DimensionBucket { label, count })
+        .collect();
+    stats.formats = formats
+        .into_iter()
+        .map(|(format, (count, bytes))| FormatCount {
+            format,
+            count,
+            bytes,
+        })
+        .collect();
+    stats
+        .formats
+        .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.format.cmp(&b.format)));
+    Ok(stats)
+}
@@ -0,0 +1,240 @@
+// Packs as catalog rows the UI can create, rename and delete directly,
+// rather than only as a side effect of importing a folder.
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashSet;
+use std::fs;
+use tauri::AppHandle;
+
+use crate::catalog;
+use crate::error::DrawStackError;
+use crate::library;
+use crate::paths::StoredPath;
+use crate::{manifest, storage, thumbnails};
+
+const MAX_NAME_LENGTH: usize = 200;
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct PackSummary {
+    pub id: String,
+    pub name: String,
+    pub source_path: Option<String>,
+    pub created_at: i64,
+    pub content_flag: Option<String>,
+    pub image_count: usize,
+}
+
+const SUMMARY_QUERY: &str = "SELECT p.id, p.name, p.source_path, p.created_at, p.content_flag,
+        COUNT(i.id)
+     FROM packs p
+     LEFT JOIN images i ON i.pack_id = p.id";
+
+fn summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<PackSummary> {
+    Ok(PackSummary {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        source_path: row.get(2)?,
+        created_at: row.get(3)?,
+        content_flag: row.get(4)?,
+        image_count: row.get(5)?,
+    })
+}
+
+fn pack_summary(conn: &Connection, pack_id: &str) -> Result<Option<PackSummary>, String> {
+    conn.query_row(
+        &format!("{} WHERE p.id = ?1 GROUP BY p.id", SUMMARY_QUERY),
+        params![pack_id],
+        summary_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load pack {}: {}", pack_id, e))
+}
+
+fn validate_name(name: &str) -> Result<String, DrawStackError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(DrawStackError::invalid("Pack name cannot be empty"));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(DrawStackError::invalid(format!(
+            "Pack name is longer than {} characters",
+            MAX_NAME_LENGTH
+        )));
+    }
+    Ok(name.to_string())
+}
+
+#[tauri::command]
+pub async fn create_pack(
+    app: AppHandle,
+    name: String,
+    source: Option<String>,
+) -> Result<PackSummary, DrawStackError> {
+    let name = validate_name(&name)?;
+    let conn = catalog::open(&app)?;
+    let pack_id = crate::generate_uuid();
+
+    conn.execute(
+        "INSERT INTO packs (id, name, source_path, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![pack_id, name, source, catalog::now_unix()],
+    )
+    .map_err(|e| format!("Failed to create pack: {}", e))?;
+    tracing::info!("Created pack {} ({})", pack_id, name);
+
+    pack_summary(&conn, &pack_id)?
+        .ok_or_else(|| DrawStackError::Other(format!("Pack {} vanished after insert", pack_id)))
+}
+
+#[tauri::command]
+pub async fn rename_pack(
+    app: AppHandle,
+    pack_id: String,
+    name: String,
+) -> Result<PackSummary, DrawStackError> {
+    let name = validate_name(&name)?;
+    let conn = catalog::open(&app)?;
+
+    let updated = conn
+        .execute(
+            "UPDATE packs SET name = ?1 WHERE id = ?2",
+            params![name, pack_id],
+        )
+        .map_err(|e| format!("Failed to rename pack: {}", e))?;
+    if updated == 0 {
+        return Err(DrawStackError::invalid(format!("No pack {}", pack_id)));
+    }
+    manifest::refresh(&app, [pack_id.as_str()]);
+
+    pack_summary(&conn, &pack_id)?
+        .ok_or_else(|| DrawStackError::invalid(format!("No pack {}", pack_id)))
+}
+
+#[tauri::command]
+pub async fn list_packs(app: AppHandle) -> Result<Vec<PackSummary>, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} GROUP BY p.id ORDER BY p.name COLLATE NATURAL",
+            SUMMARY_QUERY
+        ))
+        .map_err(|e| format!("Failed to prepare pack query: {}", e))?;
+    let packs = stmt
+        .query_map([], summary_from_row)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to list packs: {}", e))?;
+    Ok(packs)
+}
+
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct PackDeleteReport {
+    pub pack_id: String,
+    // False when library copies couldn't all be trashed and the pack was kept
+    pub deleted: bool,
+    pub deleted_images: usize,
+    pub trashed_files: usize,
+    pub removed_thumbnails: usize,
+    // Thumbnails plus trashed library copies. Trashed files still take up
+    // space until the OS trash is emptied.
+    pub reclaimed_bytes: u64,
+    pub reclaimed_formatted: String,
+    pub errors: Vec<String>,
+}
+
+// Remove a pack, its images and their thumbnails from the catalog. With
+// `delete_library_copies` the images' library copies go to the trash first;
+// if any can't be trashed the pack is kept so nothing is left untracked.
+// Originals are never touched.
+#[tauri::command]
+pub async fn delete_pack_data(
+    app: AppHandle,
+    pack_id: String,
+    delete_library_copies: bool,
+) -> Result<PackDeleteReport, DrawStackError> {
+    let mut conn = catalog::open(&app)?;
+    if pack_summary(&conn, &pack_id)?.is_none() {
+        return Err(DrawStackError::invalid(format!("No pack {}", pack_id)));
+    }
+
+    let mut report = PackDeleteReport {
+        pack_id: pack_id.clone(),
+        ..Default::default()
+    };
+    let images: Vec<(String, Option<StoredPath>)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, library_path FROM images WHERE pack_id = ?1")
+            .map_err(|e| format!("Failed to prepare pack image query: {}", e))?;
+        stmt.query_map(params![pack_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to load pack images: {}", e))?
+    };
+
+    if delete_library_copies {
+        for library_path in images.iter().filter_map(|(_, path)| path.as_ref()) {
+            let path = library_path.to_path_buf();
+            // Measured first: once trashed the file is gone from here
+            let size = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+            match library::trash_file(&path) {
+                Ok(true) => {
+                    report.trashed_files += 1;
+                    report.reclaimed_bytes += size;
+                }
+                Ok(false) => {}
+                Err(e) => report.errors.push(e),
+            }
+        }
+        if !report.errors.is_empty() {
+            tracing::warn!(
+                "Kept pack {}: {} library files couldn't be trashed",
+                pack_id,
+                report.errors.len()
+            );
+            report.reclaimed_formatted = crate::format_bytes(report.reclaimed_bytes);
+            storage::invalidate(&app);
+            return Ok(report);
+        }
+    }
+
+    let image_ids: HashSet<String> = images.into_iter().map(|(id, _)| id).collect();
+    let (removed_thumbnails, thumbnail_bytes) = thumbnails::remove_thumbnails_for(&app, &image_ids);
+    report.removed_thumbnails = removed_thumbnails;
+    report.reclaimed_bytes += thumbnail_bytes;
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    for image_id in &image_ids {
+        tx.execute(
+            "DELETE FROM thumbnail_cache WHERE image_id = ?1",
+            params![image_id],
+        )
+        .map_err(|e| format!("Failed to clear thumbnail cache for {}: {}", image_id, e))?;
+    }
+    // Images, thumbnails and tag links cascade from the pack row
+    tx.execute("DELETE FROM packs WHERE id = ?1", params![pack_id])
+        .map_err(|e| format!("Failed to delete pack: {}", e))?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+    report.deleted = true;
+    report.deleted_images = image_ids.len();
+    report.reclaimed_formatted = crate::format_bytes(report.reclaimed_bytes);
+
+    tracing::info!(
+        "Deleted pack {} with {} images ({} files sent to trash, {} reclaimed)",
+        pack_id,
+        report.deleted_images,
+        report.trashed_files,
+        report.reclaimed_formatted
+    );
+    manifest::refresh(&app, [pack_id.as_str()]);
+    storage::invalidate(&app);
+    Ok(report)
+}
+
+// `delete_pack_data` under the pack CRUD naming
+#[tauri::command]
+pub async fn delete_pack(
+    app: AppHandle,
+    pack_id: String,
+    remove_files: bool,
+) -> Result<PackDeleteReport, DrawStackError> {
+    delete_pack_data(app, pack_id, remove_files).await
+}
@@ -0,0 +1,229 @@
+use image::imageops::FilterType;
+use image::DynamicImage;
+use rusqlite::{params, Connection};
+use tauri::AppHandle;
+
+use crate::catalog;
+use crate::error::DrawStackError;
+use crate::paths::StoredPath;
+use crate::ThumbnailInfo;
+
+const DEFAULT_COLORS: usize = 5;
+const MAX_COLORS: usize = 16;
+// Clustering a 64x64 copy is plenty for swatches and takes a few ms
+const SAMPLE_SIZE: u32 = 64;
+const MAX_ITERATIONS: usize = 20;
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct PaletteColor {
+    pub hex: String,
+    pub proportion: f32,
+    #[serde(skip)]
+    rgb: [u8; 3],
+}
+
+impl PaletteColor {
+    fn new(rgb: [u8; 3], proportion: f32) -> Self {
+        Self {
+            hex: to_hex(rgb),
+            proportion,
+            rgb,
+        }
+    }
+}
+
+pub fn to_hex(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+// "#a1b2c3" or "a1b2c3"
+pub fn from_hex(hex: &str) -> Option<[u8; 3]> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+fn nearest(pixel: [f32; 3], centers: &[[f32; 3]]) -> usize {
+    centers
+        .iter()
+        .enumerate()
+        .min_by(|a, b| distance(pixel, *a.1).total_cmp(&distance(pixel, *b.1)))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+// Dominant colors by k-means over a downscaled copy, largest share first.
+// Seeding is k-means++ from a fixed seed so the same image always gives the
+// same swatches.
+pub fn extract(img: &DynamicImage, k: usize) -> Vec<PaletteColor> {
+    let small = img
+        .resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle)
+        .to_rgba8();
+    // Mostly transparent pixels don't contribute a visible color
+    let pixels: Vec<[f32; 3]> = small
+        .pixels()
+        .filter(|p| p.0[3] >= 128)
+        .map(|p| [p.0[0] as f32, p.0[1] as f32, p.0[2] as f32])
+        .collect();
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+
+    let mut rng = fastrand::Rng::with_seed(pixels.len() as u64);
+    let mut centers = vec![pixels[rng.usize(..pixels.len())]];
+    while centers.len() < k.min(pixels.len()) {
+        let weights: Vec<f32> = pixels
+            .iter()
+            .map(|&p| distance(p, centers[nearest(p, &centers)]))
+            .collect();
+        let total: f32 = weights.iter().sum();
+        if total <= 0.0 {
+            // Fewer distinct colors than k
+            break;
+        }
+        let mut target = rng.f32() * total;
+        let index = weights
+            .iter()
+            .position(|&w| {
+                target -= w;
+                target <= 0.0
+            })
+            .unwrap_or(pixels.len() - 1);
+        centers.push(pixels[index]);
+    }
+
+    let mut assignment = vec![0; pixels.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (slot, &pixel) in assignment.iter_mut().zip(&pixels) {
+            let cluster = nearest(pixel, &centers);
+            changed |= *slot != cluster;
+            *slot = cluster;
+        }
+
+        let mut sums = vec![[0f32; 3]; centers.len()];
+        let mut counts = vec![0usize; centers.len()];
+        for (&cluster, pixel) in assignment.iter().zip(&pixels) {
+            for c in 0..3 {
+                sums[cluster][c] += pixel[c];
+            }
+            counts[cluster] += 1;
+        }
+        for (center, (sum, &count)) in centers.iter_mut().zip(sums.iter().zip(&counts)) {
+            if count > 0 {
+                *center = sum.map(|s| s / count as f32);
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let mut counts = vec![0usize; centers.len()];
+    for &cluster in &assignment {
+        counts[cluster] += 1;
+    }
+
+    let mut colors: Vec<PaletteColor> = centers
+        .iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .map(|(center, count)| {
+            PaletteColor::new(
+                center.map(|c| c.round() as u8),
+                count as f32 / pixels.len() as f32,
+            )
+        })
+        .collect();
+    colors.sort_by(|a, b| b.proportion.total_cmp(&a.proportion));
+    colors
+}
+
+pub fn store(conn: &Connection, image_id: &str, colors: &[PaletteColor]) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM image_palettes WHERE image_id = ?1",
+        params![image_id],
+    )
+    .map_err(|e| format!("Failed to clear palette: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(
+            "INSERT INTO image_palettes (image_id, position, r, g, b, proportion)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .map_err(|e| format!("Failed to prepare palette insert: {}", e))?;
+    for (position, color) in colors.iter().enumerate() {
+        stmt.execute(params![
+            image_id,
+            position as i64,
+            color.rgb[0],
+            color.rgb[1],
+            color.rgb[2],
+            color.proportion
+        ])
+        .map_err(|e| format!("Failed to store palette: {}", e))?;
+    }
+    Ok(())
+}
+
+// Palettes for freshly imported images, taken from their thumbnails. Used when
+// `extract_palettes` is on; images whose thumbnail can't be read are skipped.
+pub fn store_for_thumbnails(conn: &mut Connection, images: &[ThumbnailInfo]) -> Result<(), String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    for image in images {
+        let Ok(img) = image::open(image.thumbnail_path.to_path_buf()) else {
+            continue;
+        };
+        store(&tx, &image.id, &extract(&img, DEFAULT_COLORS))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))
+}
+
+// Dominant colors of a catalog image with their share of the picture. The
+// result is saved to the catalog so it can be used in searches.
+#[tauri::command]
+pub async fn extract_palette(
+    app: AppHandle,
+    image_id: String,
+    k: Option<usize>,
+) -> Result<Vec<PaletteColor>, DrawStackError> {
+    let k = k.unwrap_or(DEFAULT_COLORS);
+    if !(1..=MAX_COLORS).contains(&k) {
+        return Err(DrawStackError::invalid(format!(
+            "Palette size must be between 1 and {}, got {}",
+            MAX_COLORS, k
+        )));
+    }
+
+    let conn = catalog::open(&app)?;
+    let image = catalog::get_image(&conn, &image_id)?;
+
+    // The thumbnail is already small; fall back to the full image
+    let img = match image
+        .thumbnail_path
+        .as_ref()
+        .filter(|p| **p != image.original_path)
+        .map(StoredPath::to_path_buf)
+        .filter(|p| p.exists())
+    {
+        Some(thumbnail) => {
+            image::open(&thumbnail).map_err(|e| DrawStackError::decode(&thumbnail, e))?
+        }
+        None => crate::decode_image(&image.source_path())?,
+    };
+
+    let colors = extract(&img, k);
+    store(&conn, &image_id, &colors)?;
+    Ok(colors)
+}
@@ -0,0 +1,303 @@
+// Path handling that survives deep folders and odd filenames.
+//
+// Win32 file APIs reject paths longer than MAX_PATH (260 UTF-16 units)
+// unless they carry the `\\?\` extended-length prefix, and nested reference
+// packs get there quickly. Paths keep their ordinary form wherever they are
+// stored or shown; `extended` is applied only where one is handed to the OS.
+//
+// Paths also stay `PathBuf`s until they reach the UI. `to_string_lossy`
+// replaces anything that isn't valid Unicode, and a path rebuilt from that
+// string no longer names the file, so journals and the catalog use
+// `StoredPath` instead.
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// Below MAX_PATH with room for the 8.3 names and suffixes some APIs append
+#[cfg(windows)]
+const LONG_PATH_THRESHOLD: usize = 240;
+
+// `path` in a form the OS accepts whatever its length. On Windows, long
+// absolute paths get the `\\?\` prefix, which also turns off the OS's own
+// normalization, so `.` and `..` are resolved and `/` turned into `\` here.
+// Relative, already-verbatim and device paths are passed through.
+#[cfg(windows)]
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    use std::ffi::{OsStr, OsString};
+    use std::path::{Component, Prefix};
+
+    if path.as_os_str().len() < LONG_PATH_THRESHOLD || !path.has_root() {
+        return Cow::Borrowed(path);
+    }
+
+    let mut components = path.components();
+    let mut extended = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(letter) => OsString::from(format!(r"\\?\{}:", letter as char)),
+            Prefix::UNC(server, share) => {
+                let mut unc = OsString::from(r"\\?\UNC\");
+                unc.push(server);
+                unc.push(r"\");
+                unc.push(share);
+                unc
+            }
+            _ => return Cow::Borrowed(path),
+        },
+        _ => return Cow::Borrowed(path),
+    };
+
+    let mut parts: Vec<&OsStr> = Vec::new();
+    for component in components {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    for part in parts {
+        extended.push(r"\");
+        extended.push(part);
+    }
+    Cow::Owned(PathBuf::from(extended))
+}
+
+#[cfg(not(windows))]
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+// A path as written to JSON: a plain string when it's valid Unicode,
+// otherwise its raw code units (UTF-16 on Windows, bytes elsewhere)
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum StoredPath {
+    Text(String),
+    #[cfg(windows)]
+    Raw(Vec<u16>),
+    #[cfg(not(windows))]
+    Raw(Vec<u8>),
+}
+
+impl From<&Path> for StoredPath {
+    fn from(path: &Path) -> Self {
+        if let Some(text) = path.to_str() {
+            return Self::Text(text.to_string());
+        }
+        #[cfg(windows)]
+        {
+            use std::os::windows::ffi::OsStrExt;
+            Self::Raw(path.as_os_str().encode_wide().collect())
+        }
+        #[cfg(not(windows))]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            Self::Raw(path.as_os_str().as_bytes().to_vec())
+        }
+    }
+}
+
+impl StoredPath {
+    pub fn to_path_buf(&self) -> PathBuf {
+        PathBuf::from(self.clone())
+    }
+}
+
+// Lossy, for messages and logs
+impl fmt::Display for StoredPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => f.write_str(text),
+            Self::Raw(_) => self.to_path_buf().display().fmt(f),
+        }
+    }
+}
+
+// In the catalog a Unicode path is TEXT, so queries can match on it; any
+// other path is a BLOB of its raw code units (UTF-16LE on Windows)
+impl ToSql for StoredPath {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        match self {
+            Self::Text(text) => text.to_sql(),
+            #[cfg(windows)]
+            Self::Raw(units) => Ok(ToSqlOutput::from(
+                units
+                    .iter()
+                    .flat_map(|unit| unit.to_le_bytes())
+                    .collect::<Vec<u8>>(),
+            )),
+            #[cfg(not(windows))]
+            Self::Raw(bytes) => bytes.to_sql(),
+        }
+    }
+}
+
+impl FromSql for StoredPath {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Text(_) => String::column_result(value).map(Self::Text),
+            #[cfg(windows)]
+            ValueRef::Blob(bytes) => Ok(Self::Raw(
+                bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect(),
+            )),
+            #[cfg(not(windows))]
+            ValueRef::Blob(bytes) => Ok(Self::Raw(bytes.to_vec())),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl From<StoredPath> for PathBuf {
+    fn from(stored: StoredPath) -> Self {
+        match stored {
+            StoredPath::Text(text) => PathBuf::from(text),
+            #[cfg(windows)]
+            StoredPath::Raw(units) => {
+                use std::os::windows::ffi::OsStringExt;
+                PathBuf::from(std::ffi::OsString::from_wide(&units))
+            }
+            #[cfg(not(windows))]
+            StoredPath::Raw(bytes) => {
+                use std::os::unix::ffi::OsStringExt;
+                PathBuf::from(std::ffi::OsString::from_vec(bytes))
+            }
+        }
+    }
+}
+
+// Scratch folders and sample files for tests that touch the filesystem
+#[cfg(test)]
+pub mod testing {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    // Names that have broken path handling before: CJK, emoji (outside the
+    // BMP, so surrogate pairs in UTF-16), combining marks and RTL text
+    pub const UNICODE_NAMES: &[&str] = &[
+        "参考資料",
+        "스케치 모음",
+        "🎨 poses 🧍",
+        "café\u{301} déjà",
+        "رسم",
+    ];
+
+    // An empty folder unique to this test run
+    pub fn scratch_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "drawstack-test-{}-{}-{}",
+            label,
+            std::process::id(),
+            fastrand::u64(..)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // A folder chain under `root` whose full path is over `min_length`
+    // characters, past MAX_PATH when `min_length` is above 260
+    pub fn deep_dir(root: &Path, min_length: usize) -> PathBuf {
+        let mut dir = root.to_path_buf();
+        let mut level = 0;
+        while dir.as_os_str().len() < min_length {
+            dir.push(format!("nested reference folder {:02}", level));
+            level += 1;
+        }
+        fs::create_dir_all(super::extended(&dir)).unwrap();
+        dir
+    }
+
+    // A small noisy PNG, big enough to pass the default minimum file size
+    pub fn write_png(path: &Path) {
+        let img = image::RgbImage::from_fn(64, 48, |x, y| {
+            image::Rgb([(x * 4) as u8, (y * 5) as u8, fastrand::u8(..)])
+        });
+        img.save_with_format(super::extended(path), image::ImageFormat::Png)
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicode_paths_are_stored_as_text() {
+        for name in testing::UNICODE_NAMES {
+            let path = Path::new("refs").join(name).join(format!("{}.png", name));
+            let stored = StoredPath::from(path.as_path());
+            assert!(matches!(stored, StoredPath::Text(_)));
+
+            let json = serde_json::to_string(&stored).unwrap();
+            let restored: StoredPath = serde_json::from_str(&json).unwrap();
+            assert_eq!(PathBuf::from(restored), path);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_paths_round_trip() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        // Latin-1 "é", invalid as UTF-8
+        let path = Path::new(OsStr::from_bytes(b"refs/caf\xe9.png"));
+        let stored = StoredPath::from(path);
+        assert!(matches!(stored, StoredPath::Raw(_)));
+
+        let json = serde_json::to_string(&stored).unwrap();
+        let restored: StoredPath = serde_json::from_str(&json).unwrap();
+        assert_eq!(PathBuf::from(restored), path);
+
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let restored: StoredPath = conn
+            .query_row("SELECT ?1", [&stored], |row| row.get(0))
+            .unwrap();
+        assert_eq!(restored.to_path_buf(), path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn unpaired_surrogates_round_trip() {
+        use std::ffi::OsString;
+        use std::os::windows::ffi::OsStringExt;
+
+        let units: Vec<u16> = "C:\\refs\\x".encode_utf16().chain([0xD800]).collect();
+        let path = PathBuf::from(OsString::from_wide(&units));
+        let stored = StoredPath::from(path.as_path());
+        assert!(matches!(stored, StoredPath::Raw(_)));
+
+        let json = serde_json::to_string(&stored).unwrap();
+        let restored: StoredPath = serde_json::from_str(&json).unwrap();
+        assert_eq!(PathBuf::from(restored), path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_paths_get_the_extended_prefix() {
+        let folder = "nested reference folder ".repeat(12);
+        let disk = PathBuf::from(format!(r"C:\refs\{}\..\{}\pose.png", folder, folder));
+        let disk_ext = extended(&disk);
+        assert!(disk_ext.to_str().unwrap().starts_with(r"\\?\C:\refs\"));
+        assert!(!disk_ext.to_str().unwrap().contains(".."));
+
+        let unc = PathBuf::from(format!(r"\\nas\share\{}/pose.png", folder));
+        let unc_ext = extended(&unc);
+        assert!(unc_ext.to_str().unwrap().starts_with(r"\\?\UNC\nas\share\"));
+        assert!(!unc_ext.to_str().unwrap().contains('/'));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn short_and_relative_paths_are_left_alone() {
+        let short = Path::new(r"C:\refs\pose.png");
+        assert_eq!(extended(short), short);
+
+        let relative = PathBuf::from("nested reference folder\\".repeat(20));
+        assert_eq!(extended(&relative), relative.as_path());
+    }
+}
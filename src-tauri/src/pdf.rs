@@ -0,0 +1,134 @@
+// PDF references such as anatomy books and pose packs. Each page is rendered
+// through pdfium into a library image, so the rest of the app treats it like
+// any other picture in the pack. The pdfium library is loaded at runtime,
+// from the app's resource dir or the system.
+use pdfium_render::prelude::*;
+use rusqlite::params;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DrawStackError;
+use crate::{catalog, manifest, storage, thumbnails, ThumbnailInfo};
+
+const DEFAULT_DPI: u32 = 150;
+const MIN_DPI: u32 = 36;
+const MAX_DPI: u32 = 600;
+
+// PDF coordinates are in points
+const POINTS_PER_INCH: f32 = 72.0;
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct PdfImportProgress {
+    pack_id: String,
+    page: usize,
+    total: usize,
+}
+
+pub fn load_pdfium(app: &AppHandle) -> Result<Pdfium, String> {
+    let bundled = app.path().resource_dir().ok().and_then(|dir| {
+        Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&dir)).ok()
+    });
+    let bindings = match bundled {
+        Some(bindings) => bindings,
+        None => Pdfium::bind_to_system_library()
+            .map_err(|e| format!("PDF import needs the pdfium library: {}", e))?,
+    };
+    Ok(Pdfium::new(bindings))
+}
+
+// Rasterize every page of `path` at `dpi` into the library and add the pages
+// to `pack_id` in page order, grouped under the PDF's name.
+#[tauri::command]
+pub async fn import_pdf(
+    app: AppHandle,
+    path: String,
+    pack_id: String,
+    dpi: Option<u32>,
+) -> Result<Vec<ThumbnailInfo>, DrawStackError> {
+    let source = Path::new(&path);
+    if !source.is_file() {
+        return Err(DrawStackError::not_found(source));
+    }
+    let dpi = dpi.unwrap_or(DEFAULT_DPI).clamp(MIN_DPI, MAX_DPI);
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "PDF".to_string());
+
+    let pdfium = load_pdfium(&app)?;
+    let document = pdfium
+        .load_pdf_from_file(source, None)
+        .map_err(|e| DrawStackError::decode(source, e.to_string()))?;
+
+    let library_dir = PathBuf::from(crate::get_library_path(app.clone())?);
+    fs::create_dir_all(&library_dir)
+        .map_err(|e| DrawStackError::io("create library directory", &library_dir, e))?;
+
+    let settings = thumbnails::load_settings(&app);
+    let render_config = PdfRenderConfig::new().scale_page_by_factor(dpi as f32 / POINTS_PER_INCH);
+    let total = document.pages().len() as usize;
+    let digits = total.to_string().len().max(3);
+
+    let mut pages = Vec::new();
+    for (index, page) in document.pages().iter().enumerate() {
+        let rendered = match page.render_with_config(&render_config) {
+            Ok(bitmap) => bitmap.as_image(),
+            Err(e) => {
+                tracing::warn!("Skipping page {} of {}: {}", index + 1, path, e);
+                continue;
+            }
+        };
+
+        let output = library_dir.join(format!("{}.png", crate::generate_uuid()));
+        rendered
+            .to_rgb8()
+            .save(&output)
+            .map_err(|e| format!("Failed to save page {}: {}", index + 1, e))?;
+
+        let mut info = crate::thumbnail_info(&app, &library_dir, &output, &settings, None);
+        info.filename = format!("{} p{:0width$}.png", stem, index + 1, width = digits);
+        info.relative_path = stem.clone();
+        pages.push(info);
+
+        let _ = app.emit(
+            "pdf-import-progress",
+            PdfImportProgress {
+                pack_id: pack_id.clone(),
+                page: index + 1,
+                total,
+            },
+        );
+    }
+
+    let mut conn = catalog::open(&app)?;
+    let duplicates =
+        catalog::record_import(&app, &mut conn, &pack_id, Some(&stem), Some(&path), &pages)?;
+    // Pages the catalog already has, e.g. from an earlier import of this PDF
+    pages.retain(|page| {
+        let page_path = page.original_path.to_string();
+        let duplicate = duplicates.iter().any(|d| d.path == page_path);
+        if duplicate {
+            let _ = fs::remove_file(page.original_path.to_path_buf());
+        }
+        !duplicate
+    });
+    // The rendered pages are library files with no original elsewhere
+    for page in &pages {
+        conn.execute(
+            "UPDATE images SET library_path = original_path WHERE id = ?1",
+            params![page.id],
+        )
+        .map_err(|e| format!("Failed to record page {}: {}", page.id, e))?;
+    }
+    manifest::refresh(&app, [pack_id.as_str()]);
+    storage::invalidate(&app);
+
+    tracing::info!(
+        "Imported {} pages from {} at {} dpi",
+        pages.len(),
+        path,
+        dpi
+    );
+    Ok(pages)
+}
@@ -0,0 +1,101 @@
+use rusqlite::params_from_iter;
+use tauri::AppHandle;
+
+use crate::catalog::{self, CatalogImage};
+use crate::error::DrawStackError;
+use crate::search::{self, SearchFilters};
+
+// Images shown within this window are weighted down by the rating picker
+const RECENT_SECS: f64 = 24.0 * 60.0 * 60.0;
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PickStrategy {
+    #[default]
+    LeastRecentlyShown,
+    WeightedByRating,
+    Shuffle,
+}
+
+struct Candidate {
+    image: CatalogImage,
+    last_shown_at: Option<i64>,
+}
+
+// Higher rated and favorite images come up more often; anything shown in the
+// last day fades back in as it ages.
+fn weight(candidate: &Candidate, now: i64) -> f64 {
+    let mut weight = candidate.image.rating as f64 + 1.0;
+    if candidate.image.favorite {
+        weight *= 2.0;
+    }
+    if let Some(shown) = candidate.last_shown_at {
+        weight *= ((now - shown) as f64 / RECENT_SECS).clamp(0.05, 1.0);
+    }
+    weight
+}
+
+fn pick(mut candidates: Vec<Candidate>, count: usize, strategy: PickStrategy) -> Vec<CatalogImage> {
+    fastrand::shuffle(&mut candidates);
+
+    match strategy {
+        PickStrategy::Shuffle => {}
+        PickStrategy::LeastRecentlyShown => {
+            // Stable sort keeps the shuffle as the tie-break, so never-shown
+            // images (None sorts first) come out in random order
+            candidates.sort_by_key(|c| c.last_shown_at);
+        }
+        PickStrategy::WeightedByRating => {
+            // Weighted sampling without replacement (Efraimidis-Spirakis):
+            // take the smallest -ln(u) / w
+            let now = catalog::now_unix();
+            let mut keyed: Vec<(f64, Candidate)> = candidates
+                .into_iter()
+                .map(|c| (-(1.0 - fastrand::f64()).ln() / weight(&c, now), c))
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+            candidates = keyed.into_iter().map(|(_, c)| c).collect();
+        }
+    }
+
+    candidates.truncate(count);
+    candidates.into_iter().map(|c| c.image).collect()
+}
+
+#[tauri::command]
+pub async fn pick_session_images(
+    app: AppHandle,
+    filters: Option<SearchFilters>,
+    count: usize,
+    strategy: Option<PickStrategy>,
+) -> Result<Vec<CatalogImage>, DrawStackError> {
+    let filters = filters.unwrap_or_default();
+    let (conditions, values) = search::build_conditions("", &filters);
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let conn = catalog::open(&app)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {}, i.last_shown_at FROM images i
+             LEFT JOIN thumbnails t ON t.image_id = i.id {}",
+            catalog::IMAGE_COLUMNS,
+            where_clause
+        ))
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+
+    let candidates = stmt
+        .query_map(params_from_iter(values.iter()), |row| {
+            Ok(Candidate {
+                image: catalog::map_image(row)?,
+                last_shown_at: row.get("last_shown_at")?,
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read images: {}", e))?;
+
+    Ok(pick(candidates, count, strategy.unwrap_or_default()))
+}
@@ -0,0 +1,249 @@
+use rusqlite::params;
+use tauri::AppHandle;
+
+use crate::catalog;
+use crate::error::DrawStackError;
+use crate::session::SessionEnded;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsRange {
+    Today,
+    Week,
+    #[default]
+    Month,
+    Year,
+    All,
+}
+
+impl StatsRange {
+    // SQLite date modifier for the start of the range, in local time
+    fn start_modifier(self) -> Option<&'static str> {
+        match self {
+            Self::Today => Some("start of day"),
+            Self::Week => Some("-6 days"),
+            Self::Month => Some("-29 days"),
+            Self::Year => Some("-364 days"),
+            Self::All => None,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct DailyPractice {
+    pub date: String,
+    pub sessions: usize,
+    pub total_ms: u64,
+    pub break_ms: u64,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct TagPractice {
+    pub tag: String,
+    pub images_shown: usize,
+    pub total_ms: u64,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct PracticeStats {
+    pub range: StatsRange,
+    pub sessions: usize,
+    pub completed_sessions: usize,
+    // Drawing time; breaks are counted in `break_ms` instead
+    pub total_ms: u64,
+    pub break_ms: u64,
+    pub images_shown: usize,
+    pub average_session_ms: u64,
+    // Streaks always look at the full history, whatever the range
+    pub current_streak_days: usize,
+    pub longest_streak_days: usize,
+    pub days: Vec<DailyPractice>,
+    pub tags: Vec<TagPractice>,
+}
+
+// Store a finished session. Sessions ended before any image was on screen
+// aren't worth keeping.
+pub fn record_session(app: &AppHandle, session: &SessionEnded) -> Result<(), String> {
+    if session.images.iter().all(|image| image.shown_ms == 0) {
+        return Ok(());
+    }
+
+    let mut conn = catalog::open(app)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+
+    tx.execute(
+        "INSERT INTO practice_sessions (id, pack_id, started_at, duration_ms, break_ms, completed)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            session.session_id,
+            session.pack_id,
+            session.started_at,
+            session.duration_ms as i64,
+            session.break_ms as i64,
+            session.completed
+        ],
+    )
+    .map_err(|e| format!("Failed to record session: {}", e))?;
+
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT INTO practice_images (session_id, position, image_id, shown_ms, skipped)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )
+            .map_err(|e| format!("Failed to prepare session image insert: {}", e))?;
+        for (position, image) in session.images.iter().enumerate() {
+            stmt.execute(params![
+                session.session_id,
+                position as i64,
+                image.image_id,
+                image.shown_ms as i64,
+                image.skipped
+            ])
+            .map_err(|e| format!("Failed to record session image: {}", e))?;
+        }
+    }
+
+    // Feeds the least-recently-shown picker
+    tx.execute(
+        "UPDATE images SET last_shown_at = ?1, times_shown = times_shown + 1
+         WHERE id IN (SELECT image_id FROM practice_images WHERE session_id = ?2 AND shown_ms > 0)",
+        params![catalog::now_unix(), session.session_id],
+    )
+    .map_err(|e| format!("Failed to update last shown times: {}", e))?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))
+}
+
+// (current, longest) run of consecutive local days with practice
+fn streaks(conn: &rusqlite::Connection) -> Result<(usize, usize), DrawStackError> {
+    let today: i64 = conn.query_row(
+        "SELECT CAST(julianday(date('now', 'localtime')) AS INTEGER)",
+        [],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT CAST(julianday(date(started_at, 'unixepoch', 'localtime')) AS INTEGER) AS day
+         FROM practice_sessions ORDER BY day",
+    )?;
+    let days = stmt
+        .query_map([], |row| row.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<i64> = None;
+    for &day in &days {
+        run = if previous == Some(day - 1) {
+            run + 1
+        } else {
+            1
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+
+    // A streak is still alive if the last practice was today or yesterday
+    let current = match previous {
+        Some(last) if last >= today - 1 => run,
+        _ => 0,
+    };
+    Ok((current, longest))
+}
+
+#[tauri::command]
+pub async fn get_practice_stats(
+    app: AppHandle,
+    range: Option<StatsRange>,
+) -> Result<PracticeStats, DrawStackError> {
+    let range = range.unwrap_or_default();
+    let conn = catalog::open(&app)?;
+
+    // Everything below filters on started_at >= ?1
+    let since: i64 = match range.start_modifier() {
+        Some(modifier) => conn.query_row(
+            "SELECT CAST(strftime('%s', date('now', 'localtime', ?1), 'utc') AS INTEGER)",
+            params![modifier],
+            |row| row.get(0),
+        )?,
+        None => 0,
+    };
+
+    let (sessions, completed_sessions, total_ms, break_ms): (usize, usize, i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(completed), 0), COALESCE(SUM(duration_ms), 0),
+                    COALESCE(SUM(break_ms), 0)
+             FROM practice_sessions WHERE started_at >= ?1",
+            params![since],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+
+    let images_shown: usize = conn.query_row(
+        "SELECT COUNT(*) FROM practice_images pi
+         JOIN practice_sessions ps ON ps.id = pi.session_id
+         WHERE ps.started_at >= ?1 AND pi.shown_ms > 0",
+        params![since],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT date(started_at, 'unixepoch', 'localtime') AS day, COUNT(*), SUM(duration_ms),
+                SUM(break_ms)
+         FROM practice_sessions WHERE started_at >= ?1
+         GROUP BY day ORDER BY day",
+    )?;
+    let days = stmt
+        .query_map(params![since], |row| {
+            Ok(DailyPractice {
+                date: row.get(0)?,
+                sessions: row.get(1)?,
+                total_ms: row.get::<_, i64>(2)? as u64,
+                break_ms: row.get::<_, i64>(3)? as u64,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT tg.name, COUNT(*), SUM(pi.shown_ms) AS total
+         FROM practice_images pi
+         JOIN practice_sessions ps ON ps.id = pi.session_id
+         JOIN image_tags it ON it.image_id = pi.image_id
+         JOIN tags tg ON tg.id = it.tag_id
+         WHERE ps.started_at >= ?1 AND pi.shown_ms > 0
+         GROUP BY tg.id ORDER BY total DESC, tg.name",
+    )?;
+    let tags = stmt
+        .query_map(params![since], |row| {
+            Ok(TagPractice {
+                tag: row.get(0)?,
+                images_shown: row.get(1)?,
+                total_ms: row.get::<_, i64>(2)? as u64,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let (current_streak_days, longest_streak_days) = streaks(&conn)?;
+    let total_ms = total_ms as u64;
+
+    Ok(PracticeStats {
+        range,
+        sessions,
+        completed_sessions,
+        total_ms,
+        break_ms: break_ms as u64,
+        images_shown,
+        average_session_ms: if sessions > 0 {
+            total_ms / sessions as u64
+        } else {
+            0
+        },
+        current_streak_days,
+        longest_streak_days,
+        days,
+        tags,
+    })
+}
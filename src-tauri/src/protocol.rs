@@ -0,0 +1,217 @@
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::http::{header, Request, Response, StatusCode, Uri};
+use tauri::{AppHandle, UriSchemeContext, UriSchemeResponder, Wry};
+
+use crate::{catalog, thumbnails};
+
+// Serves catalog images by ID, so the webview never needs fs scope over the
+// library or thumbnails dir:
+//   drawstack://image/<id>   the library copy (or original) of an image
+//   drawstack://thumb/<id>   its best available thumbnail
+//   drawstack://atlas/<pack_id>/<page>   a page of the pack's thumbnail atlas
+// Windows and Android webviews see these as http://drawstack.localhost/image/<id>.
+pub const SCHEME: &str = "drawstack";
+
+const THUMBNAIL_EXTENSIONS: &[&str] = &["jpg", "webp", "png"];
+
+pub type Failure = (StatusCode, String);
+
+pub enum Kind {
+    Image,
+    Thumb,
+    Atlas(usize),
+}
+
+fn target(uri: &Uri) -> Option<(Kind, String)> {
+    let mut segments = uri.path().trim_start_matches('/').split('/');
+    let kind = match uri.host() {
+        Some(host @ ("image" | "thumb" | "atlas")) => host,
+        _ => segments.next()?,
+    };
+    // IDs are UUIDs or content hashes; anything else could be a path trick
+    let id = segments.next()?;
+    let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    let kind = match kind {
+        "image" => Kind::Image,
+        "thumb" => Kind::Thumb,
+        "atlas" => Kind::Atlas(segments.next()?.parse().ok()?),
+        _ => return None,
+    };
+    (valid && segments.next().is_none()).then(|| (kind, id.to_string()))
+}
+
+fn not_found(what: &str, id: &str) -> Failure {
+    (StatusCode::NOT_FOUND, format!("No {} for {}", what, id))
+}
+
+pub fn resolve(app: &AppHandle, kind: Kind, id: &str) -> Result<PathBuf, Failure> {
+    let internal = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+
+    if let Kind::Atlas(page) = kind {
+        return crate::atlas::page_path(app, id, page).map_err(internal);
+    }
+
+    if let Kind::Thumb = kind {
+        // Look on disk first: thumbnails are requested by the thousand and
+        // don't need a catalog connection each
+        let root = crate::thumbnails_dir(app).map_err(internal)?;
+        let shard = root.join(thumbnails::shard_name(id));
+        // The flat layout too, until startup has moved everything into shards
+        for dir in [&shard, &root] {
+            for name in [format!("{}@hq", id), id.to_string()] {
+                for extension in THUMBNAIL_EXTENSIONS {
+                    let path = dir.join(format!("{}.{}", name, extension));
+                    if path.is_file() {
+                        return Ok(path);
+                    }
+                }
+            }
+        }
+    }
+
+    let conn = catalog::open(app).map_err(internal)?;
+    let image = catalog::get_image(&conn, id).map_err(|_| not_found("image", id))?;
+    let path = match kind {
+        Kind::Image => image.source_path().to_path_buf(),
+        // Served from the atlas dir above
+        Kind::Atlas(_) => unreachable!(),
+        // Images whose thumbnail failed point at the original
+        Kind::Thumb => image
+            .thumbnail_path
+            .map(PathBuf::from)
+            .ok_or_else(|| not_found("thumbnail", id))?,
+    };
+    Ok(path)
+}
+
+pub fn mime_type(path: &Path) -> &'static str {
+    match crate::extension_lower(path).as_deref() {
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        Some("bmp") => "image/bmp",
+        Some("avif") => "image/avif",
+        Some("tif" | "tiff") => "image/tiff",
+        Some("jxl") => "image/jxl",
+        Some("mp4" | "m4v") => "video/mp4",
+        Some("mov") => "video/quicktime",
+        Some("webm") => "video/webm",
+        // Extensionless or unknown: go by content
+        _ => crate::sniff::image_type(path)
+            .map(|kind| kind.mime_type())
+            .unwrap_or("application/octet-stream"),
+    }
+}
+
+// Single `bytes=` range as inclusive (start, end). Multi-range requests get
+// the whole file, which is allowed.
+fn parse_range(value: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let range = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (len.saturating_sub(suffix), len.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, len.saturating_sub(1)),
+        (start, end) => {
+            let end: u64 = end.parse().ok()?;
+            (start.parse().ok()?, end.min(len.saturating_sub(1)))
+        }
+    };
+    Some(if range.0 <= range.1 && range.0 < len {
+        Ok(range)
+    } else {
+        Err(())
+    })
+}
+
+fn serve(app: &AppHandle, request: &Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Failure> {
+    let (kind, id) =
+        target(request.uri()).ok_or((StatusCode::BAD_REQUEST, "Unknown resource".to_string()))?;
+    let path = resolve(app, kind, &id)?;
+
+    let mut file = File::open(&path).map_err(|_| not_found("file", &id))?;
+    let meta = fs::metadata(&path).map_err(|_| not_found("file", &id))?;
+    let len = meta.len();
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    let etag = format!("\"{:x}-{:x}\"", len, modified);
+
+    let builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime_type(&path))
+        .header(header::ACCEPT_RANGES, "bytes")
+        // Thumbnails are rewritten in place on regeneration, so always
+        // revalidate; an unchanged file costs a 304
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::ETAG, &etag)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+
+    let header_value = |name| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|v: &header::HeaderValue| v.to_str().ok())
+    };
+
+    let io_error = |e: std::io::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let response = if header_value(header::IF_NONE_MATCH) == Some(etag.as_str()) {
+        builder.status(StatusCode::NOT_MODIFIED).body(Vec::new())
+    } else {
+        match header_value(header::RANGE).and_then(|r| parse_range(r, len)) {
+            Some(Ok((start, end))) => {
+                let mut body = vec![0; (end - start + 1) as usize];
+                file.seek(SeekFrom::Start(start)).map_err(io_error)?;
+                file.read_exact(&mut body).map_err(io_error)?;
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(
+                        header::CONTENT_RANGE,
+                        format!("bytes {}-{}/{}", start, end, len),
+                    )
+                    .body(body)
+            }
+            Some(Err(())) => builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                .body(Vec::new()),
+            None => {
+                let mut body = Vec::with_capacity(len as usize);
+                file.read_to_end(&mut body).map_err(io_error)?;
+                builder.status(StatusCode::OK).body(body)
+            }
+        }
+    };
+
+    response.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// Handler for `register_asynchronous_uri_scheme_protocol`. File reads happen
+// on a blocking thread so large images don't stall the webview's IPC.
+pub fn handle(
+    ctx: UriSchemeContext<'_, Wry>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let response = serve(&app, &request).unwrap_or_else(|(status, message)| {
+            Response::builder()
+                .status(status)
+                .header(header::CONTENT_TYPE, "text/plain")
+                .body(message.into_bytes())
+                .unwrap_or_default()
+        });
+        responder.respond(response);
+    });
+}
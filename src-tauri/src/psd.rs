@@ -0,0 +1,300 @@
+// Photoshop (.psd/.psb) and Clip Studio (.clip) files. Layers are never
+// composited here; thumbnails come from the flattened copy the application
+// saved alongside them.
+use image::{DynamicImage, GrayImage, RgbImage, RgbaImage};
+use std::fs;
+use std::path::Path;
+
+pub static PSD_EXTENSIONS: &[&str] = &["psd", "psb", "clip"];
+
+const RESOURCE_THUMBNAIL: u16 = 1036;
+
+// Guards the composite buffer against corrupt headers
+const MAX_PIXELS: u64 = 1 << 28;
+
+const MODE_GRAYSCALE: u16 = 1;
+const MODE_RGB: u16 = 3;
+const MODE_CMYK: u16 = 4;
+
+pub fn is_psd_extension(ext: &str) -> bool {
+    crate::extension_in(ext, PSD_EXTENSIONS)
+}
+
+struct Header {
+    // PSB ("large document") widens several length fields
+    large: bool,
+    channels: usize,
+    height: usize,
+    width: usize,
+    depth: u16,
+    mode: u16,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn parse_header(data: &[u8]) -> Option<Header> {
+    if !data.starts_with(b"8BPS") {
+        return None;
+    }
+    let large = match read_u16(data, 4)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+    Some(Header {
+        large,
+        channels: read_u16(data, 12)? as usize,
+        height: read_u32(data, 14)? as usize,
+        width: read_u32(data, 18)? as usize,
+        depth: read_u16(data, 22)?,
+        mode: read_u16(data, 24)?,
+    })
+}
+
+// Width and height from the header, for the library's metadata
+pub fn dimensions(path: &Path) -> Option<(u32, u32)> {
+    use std::io::Read;
+    let mut header = [0; 26];
+    fs::File::open(crate::paths::extended(path))
+        .ok()?
+        .read_exact(&mut header)
+        .ok()?;
+    let header = parse_header(&header)?;
+    Some((header.width as u32, header.height as u32))
+}
+
+pub fn decode_preview(path: &Path) -> Result<DynamicImage, String> {
+    if crate::extension_lower(path).as_deref() == Some("clip") {
+        return decode_clip_preview(path);
+    }
+
+    let data = fs::read(crate::paths::extended(path))
+        .map_err(|e| format!("Failed to read PSD file: {}", e))?;
+    let header = parse_header(&data).ok_or("Not a PSD file")?;
+
+    // Color mode data, image resources, then layer and mask info, each
+    // prefixed with its length
+    let mut offset = 26;
+    offset += 4 + read_u32(&data, offset).ok_or("Truncated PSD")? as usize;
+    let resources_len = read_u32(&data, offset).ok_or("Truncated PSD")? as usize;
+    let resources = data
+        .get(offset + 4..offset + 4 + resources_len)
+        .ok_or("Truncated PSD")?;
+    offset += 4 + resources_len;
+    offset += if header.large {
+        8 + read_u64(&data, offset).ok_or("Truncated PSD")? as usize
+    } else {
+        4 + read_u32(&data, offset).ok_or("Truncated PSD")? as usize
+    };
+
+    // Files saved without "maximize compatibility" may have no usable
+    // composite; the small embedded thumbnail is better than nothing
+    decode_composite(&data, offset, &header).or_else(|e| embedded_thumbnail(resources).ok_or(e))
+}
+
+// Unpack one PackBits-compressed row
+fn unpack_bits(mut input: &[u8], out: &mut Vec<u8>, row_len: usize) -> Option<()> {
+    let start = out.len();
+    while out.len() - start < row_len {
+        let (&n, rest) = input.split_first()?;
+        let n = n as i8;
+        if n >= 0 {
+            let count = n as usize + 1;
+            out.extend_from_slice(rest.get(..count)?);
+            input = &rest[count..];
+        } else if n != -128 {
+            let (&byte, rest) = rest.split_first()?;
+            out.extend(std::iter::repeat_n(byte, (1 - n as isize) as usize));
+            input = rest;
+        } else {
+            input = rest;
+        }
+    }
+    out.truncate(start + row_len);
+    Some(())
+}
+
+// The flattened image at the end of the file, stored one channel plane at a
+// time, raw or PackBits-compressed
+fn decode_composite(data: &[u8], offset: usize, header: &Header) -> Result<DynamicImage, String> {
+    let wanted = match header.mode {
+        MODE_GRAYSCALE => 1,
+        MODE_RGB => header.channels.min(4),
+        MODE_CMYK => 4,
+        mode => return Err(format!("Unsupported PSD color mode {}", mode)),
+    };
+    if header.channels < wanted || (header.mode == MODE_RGB && wanted < 3) {
+        return Err("PSD is missing color channels".to_string());
+    }
+    let bytes_per_sample = match header.depth {
+        8 => 1,
+        16 => 2,
+        depth => return Err(format!("Unsupported PSD bit depth {}", depth)),
+    };
+    if (header.width as u64) * (header.height as u64) > MAX_PIXELS {
+        return Err("PSD composite is too large".to_string());
+    }
+
+    let row_len = header.width * bytes_per_sample;
+    let plane_len = row_len * header.height;
+    let compression = read_u16(data, offset).ok_or("PSD has no composite image")?;
+    let mut planes = Vec::with_capacity(wanted);
+
+    match compression {
+        0 => {
+            let mut start = offset + 2;
+            for _ in 0..wanted {
+                let plane = data
+                    .get(start..start + plane_len)
+                    .ok_or("Truncated PSD composite")?;
+                planes.push(plane.to_vec());
+                start += plane_len;
+            }
+        }
+        1 => {
+            // Compressed byte count of every row of every channel, then the rows
+            let rows = header.channels * header.height;
+            let count_size = if header.large { 4 } else { 2 };
+            let mut start = offset + 2 + rows * count_size;
+            let mut row = 0;
+            for _ in 0..wanted {
+                let mut plane = Vec::with_capacity(plane_len);
+                for _ in 0..header.height {
+                    let at = offset + 2 + row * count_size;
+                    let len = if header.large {
+                        read_u32(data, at).map(|n| n as usize)
+                    } else {
+                        read_u16(data, at).map(|n| n as usize)
+                    }
+                    .ok_or("Truncated PSD composite")?;
+                    let packed = data
+                        .get(start..start + len)
+                        .ok_or("Truncated PSD composite")?;
+                    unpack_bits(packed, &mut plane, row_len).ok_or("Corrupt PSD composite")?;
+                    start += len;
+                    row += 1;
+                }
+                planes.push(plane);
+            }
+        }
+        other => return Err(format!("Unsupported PSD compression {}", other)),
+    }
+
+    // 16-bit samples are big-endian; the high byte is plenty for a preview
+    let mut planes: Vec<Vec<u8>> = planes
+        .into_iter()
+        .map(|plane| plane.into_iter().step_by(bytes_per_sample).collect())
+        .collect();
+
+    let (width, height) = (header.width as u32, header.height as u32);
+    let pixel = |x: u32, y: u32| (y * width + x) as usize;
+    let built = match (header.mode, wanted) {
+        (MODE_GRAYSCALE, _) => {
+            GrayImage::from_raw(width, height, planes.swap_remove(0)).map(DynamicImage::ImageLuma8)
+        }
+        (MODE_RGB, 3) => Some(DynamicImage::ImageRgb8(RgbImage::from_fn(
+            width,
+            height,
+            |x, y| {
+                let i = pixel(x, y);
+                image::Rgb([planes[0][i], planes[1][i], planes[2][i]])
+            },
+        ))),
+        (MODE_RGB, _) => Some(DynamicImage::ImageRgba8(RgbaImage::from_fn(
+            width,
+            height,
+            |x, y| {
+                let i = pixel(x, y);
+                image::Rgba([planes[0][i], planes[1][i], planes[2][i], planes[3][i]])
+            },
+        ))),
+        // PSD stores CMYK inverted: 255 means no ink
+        _ => Some(DynamicImage::ImageRgb8(RgbImage::from_fn(
+            width,
+            height,
+            |x, y| {
+                let i = pixel(x, y);
+                let k = planes[3][i] as u32;
+                let channel = |c: u8| (c as u32 * k / 255) as u8;
+                image::Rgb([
+                    channel(planes[0][i]),
+                    channel(planes[1][i]),
+                    channel(planes[2][i]),
+                ])
+            },
+        ))),
+    };
+    built.ok_or_else(|| "Failed to assemble PSD composite".to_string())
+}
+
+// JPEG thumbnail from the image resources section (resource 1036)
+fn embedded_thumbnail(resources: &[u8]) -> Option<DynamicImage> {
+    let mut offset = 0;
+    while resources.get(offset..offset + 4)? == b"8BIM" {
+        let id = read_u16(resources, offset + 4)?;
+        // Pascal string name, padded to an even length including its size byte
+        let name_len = *resources.get(offset + 6)? as usize;
+        let name_size = (name_len + 2) & !1;
+        let size_at = offset + 6 + name_size;
+        let size = read_u32(resources, size_at)? as usize;
+        let body = resources.get(size_at + 4..size_at + 4 + size)?;
+
+        // Format 1 is JPEG, after a 28-byte header
+        if id == RESOURCE_THUMBNAIL && read_u32(body, 0) == Some(1) {
+            return image::load_from_memory_with_format(body.get(28..)?, image::ImageFormat::Jpeg)
+                .ok();
+        }
+        offset = size_at + 4 + ((size + 1) & !1);
+    }
+    None
+}
+
+// Clip Studio files wrap a SQLite database whose CanvasPreview table holds
+// a PNG of the flattened canvas
+fn decode_clip_preview(path: &Path) -> Result<DynamicImage, String> {
+    let data = fs::read(crate::paths::extended(path))
+        .map_err(|e| format!("Failed to read Clip Studio file: {}", e))?;
+    if !data.starts_with(b"CSFCHUNK") {
+        return Err("Not a Clip Studio file".to_string());
+    }
+    let marker = data
+        .windows(8)
+        .position(|w| w == b"CHNKSQLi")
+        .ok_or("Clip Studio file has no database chunk")?;
+    let len = read_u64(&data, marker + 8).ok_or("Truncated Clip Studio file")? as usize;
+    let database = data
+        .get(marker + 16..marker + 16 + len)
+        .ok_or("Truncated Clip Studio file")?;
+
+    // rusqlite reads databases from disk, so stage it in the temp dir
+    let temp = std::env::temp_dir().join(format!("drawstack-{}.sqlite", crate::generate_uuid()));
+    fs::write(&temp, database).map_err(|e| format!("Failed to stage Clip Studio data: {}", e))?;
+    let preview =
+        rusqlite::Connection::open_with_flags(&temp, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .and_then(|conn| {
+                conn.query_row("SELECT ImageData FROM CanvasPreview LIMIT 1", [], |row| {
+                    row.get::<_, Vec<u8>>(0)
+                })
+            });
+    let _ = fs::remove_file(&temp);
+
+    let preview = preview.map_err(|e| format!("Clip Studio file has no preview: {}", e))?;
+    image::load_from_memory(&preview)
+        .map_err(|e| format!("Failed to decode Clip Studio preview: {}", e))
+}
@@ -0,0 +1,384 @@
+// Search-as-you-type over filenames. Going to SQLite on every keystroke is
+// too slow for a 100k-image library, so the names are held in memory: sorted
+// for prefix lookups and split into trigrams for substring lookups, with a
+// subsequence scan as the fuzzy fallback. The index loads in the background
+// at startup and reloads whenever the catalog's `name_index_version` moves.
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, RwLock};
+use tauri::{AppHandle, Manager};
+
+use crate::catalog;
+use crate::error::DrawStackError;
+use crate::natural;
+use crate::paths::StoredPath;
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+struct Entry {
+    id: String,
+    pack_id: String,
+    filename: String,
+    relative_path: String,
+    thumbnail_path: Option<StoredPath>,
+    // Lowercased filename, what queries match against
+    key: String,
+}
+
+#[derive(Default)]
+struct Index {
+    version: i64,
+    entries: Vec<Entry>,
+    // Entry positions in key order
+    sorted: Vec<u32>,
+    // Entry positions containing each three-character run of their key
+    trigrams: HashMap<[char; 3], Vec<u32>>,
+}
+
+fn trigrams(text: &str) -> impl Iterator<Item = [char; 3]> + '_ {
+    let chars: Vec<char> = text.chars().collect();
+    (0..chars.len().saturating_sub(2)).map(move |i| [chars[i], chars[i + 1], chars[i + 2]])
+}
+
+impl Index {
+    fn load(conn: &Connection) -> Result<Self, DrawStackError> {
+        let version = read_version(conn)?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT i.id, i.pack_id, i.filename, i.relative_path, t.path
+                 FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id",
+            )
+            .map_err(|e| format!("Failed to prepare name index query: {}", e))?;
+        let entries = stmt
+            .query_map([], |row| {
+                let filename: String = row.get(2)?;
+                Ok(Entry {
+                    id: row.get(0)?,
+                    pack_id: row.get(1)?,
+                    key: filename.to_lowercase(),
+                    filename,
+                    relative_path: row.get(3)?,
+                    thumbnail_path: row.get(4)?,
+                })
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("Failed to load name index: {}", e))?;
+
+        let mut sorted: Vec<u32> = (0..entries.len() as u32).collect();
+        sorted.sort_by(|&a, &b| entries[a as usize].key.cmp(&entries[b as usize].key));
+
+        let mut trigram_map: HashMap<[char; 3], Vec<u32>> = HashMap::new();
+        for (position, entry) in entries.iter().enumerate() {
+            let unique: HashSet<[char; 3]> = trigrams(&entry.key).collect();
+            for trigram in unique {
+                trigram_map
+                    .entry(trigram)
+                    .or_default()
+                    .push(position as u32);
+            }
+        }
+
+        Ok(Self {
+            version,
+            entries,
+            sorted,
+            trigrams: trigram_map,
+        })
+    }
+
+    // Entries whose key contains `query`. Posting lists are in entry order,
+    // so the shortest is filtered by binary searches of the others.
+    fn containing(&self, query: &str) -> Vec<u32> {
+        let mut lists: Vec<&Vec<u32>> = Vec::new();
+        for trigram in trigrams(query) {
+            match self.trigrams.get(&trigram) {
+                Some(list) => lists.push(list),
+                None => return Vec::new(),
+            }
+        }
+        if lists.is_empty() {
+            // Under three characters: nothing to narrow by
+            return (0..self.entries.len() as u32)
+                .filter(|&i| self.entries[i as usize].key.contains(query))
+                .collect();
+        }
+
+        lists.sort_by_key(|list| list.len());
+        let mut candidates = lists[0].clone();
+        for list in &lists[1..] {
+            candidates.retain(|position| list.binary_search(position).is_ok());
+        }
+        candidates.retain(|&i| self.entries[i as usize].key.contains(query));
+        candidates
+    }
+
+    fn search(&self, query: &str, limit: usize) -> Vec<QuickFindHit> {
+        let mut matches: Vec<(MatchKind, usize, u32)> = Vec::new();
+        let mut seen = HashSet::new();
+
+        // Prefixes are a contiguous run of the sorted keys
+        let start = self
+            .sorted
+            .partition_point(|&i| self.entries[i as usize].key.as_str() < query);
+        for &i in self.sorted[start..]
+            .iter()
+            .take_while(|&&i| self.entries[i as usize].key.starts_with(query))
+        {
+            seen.insert(i);
+            matches.push((MatchKind::Prefix, 0, i));
+        }
+
+        for i in self.containing(query) {
+            if seen.insert(i) {
+                let key = &self.entries[i as usize].key;
+                let kind = if starts_word(key, query) {
+                    MatchKind::Word
+                } else {
+                    MatchKind::Substring
+                };
+                matches.push((kind, 0, i));
+            }
+        }
+
+        if matches.len() < limit {
+            for (i, entry) in self.entries.iter().enumerate() {
+                let i = i as u32;
+                if seen.contains(&i) {
+                    continue;
+                }
+                if let Some(span) = subsequence_span(&entry.key, query) {
+                    matches.push((MatchKind::Fuzzy, span, i));
+                }
+            }
+        }
+
+        // Best kind first; fuzzy matches with the tightest spread; then
+        // shorter names, as they're closer to what was typed
+        let rank = |a: &(MatchKind, usize, u32), b: &(MatchKind, usize, u32)| {
+            let (left, right) = (&self.entries[a.2 as usize], &self.entries[b.2 as usize]);
+            a.0.cmp(&b.0)
+                .then(a.1.cmp(&b.1))
+                .then(left.key.len().cmp(&right.key.len()))
+                .then_with(|| natural::compare(&left.filename, &right.filename))
+        };
+        // A short query can match most of the library; only the top `limit`
+        // need a full sort
+        if matches.len() > limit {
+            matches.select_nth_unstable_by(limit, rank);
+            matches.truncate(limit);
+        }
+        matches.sort_by(rank);
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(kind, _, i)| {
+                let entry = &self.entries[i as usize];
+                QuickFindHit {
+                    id: entry.id.clone(),
+                    pack_id: entry.pack_id.clone(),
+                    filename: entry.filename.clone(),
+                    relative_path: entry.relative_path.clone(),
+                    thumbnail_path: entry.thumbnail_path.clone(),
+                    kind,
+                }
+            })
+            .collect()
+    }
+}
+
+// Whether `query` occurs in `key` at the start of a word: after a space,
+// punctuation or a letter-digit boundary
+fn starts_word(key: &str, query: &str) -> bool {
+    key.match_indices(query).any(|(at, _)| {
+        key[..at].chars().next_back().is_none_or(|before| {
+            !before.is_alphanumeric()
+                || before.is_ascii_digit() != query.starts_with(|c: char| c.is_ascii_digit())
+        })
+    })
+}
+
+// Characters between the first and last of `query`'s characters found in
+// order within `key`, or None if they aren't all there
+fn subsequence_span(key: &str, query: &str) -> Option<usize> {
+    let mut wanted = query.chars().peekable();
+    let mut first = None;
+    for (position, c) in key.chars().enumerate() {
+        if wanted.peek() == Some(&c) {
+            first.get_or_insert(position);
+            wanted.next();
+            if wanted.peek().is_none() {
+                return Some(position - first.unwrap_or(position));
+            }
+        }
+    }
+    None
+}
+
+fn read_version(conn: &Connection) -> Result<i64, DrawStackError> {
+    conn.query_row(
+        "SELECT version FROM name_index_version WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| DrawStackError::Other(format!("Failed to read name index version: {}", e)))
+}
+
+#[derive(Debug, serde::Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    // Declared best first; hits sort in this order
+    Prefix,
+    Word,
+    Substring,
+    Fuzzy,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct QuickFindHit {
+    pub id: String,
+    pub pack_id: String,
+    pub filename: String,
+    pub relative_path: String,
+    pub thumbnail_path: Option<StoredPath>,
+    pub kind: MatchKind,
+}
+
+#[derive(Default)]
+pub struct QuickFind {
+    index: RwLock<Arc<Index>>,
+    // Kept open so checking the version costs one row read
+    conn: Mutex<Option<Connection>>,
+    reloading: AtomicBool,
+}
+
+impl QuickFind {
+    pub fn start(&self, app: &AppHandle) {
+        self.reload_in_background(app);
+    }
+
+    // Rebuild the index off the calling thread; searches keep using the old
+    // one until it's ready. A rebuild already under way covers this one.
+    fn reload_in_background(&self, app: &AppHandle) {
+        if self.reloading.swap(true, AtomicOrdering::SeqCst) {
+            return;
+        }
+        let app = app.clone();
+        std::thread::Builder::new()
+            .name("quick-find".into())
+            .spawn(move || {
+                let state = app.state::<QuickFind>();
+                match catalog::open(&app)
+                    .map_err(DrawStackError::from)
+                    .and_then(|conn| Index::load(&conn).map(|index| (conn, index)))
+                {
+                    Ok((conn, index)) => {
+                        tracing::debug!(
+                            "Loaded {} names into the quick-find index",
+                            index.entries.len()
+                        );
+                        *state.index.write().unwrap() = Arc::new(index);
+                        *state.conn.lock().unwrap() = Some(conn);
+                    }
+                    Err(e) => tracing::warn!("Failed to load quick-find index: {}", e),
+                }
+                state.reloading.store(false, AtomicOrdering::SeqCst);
+            })
+            .expect("failed to spawn quick-find thread");
+    }
+
+    // Reload if the catalog has moved on since the index was built
+    fn refresh_if_stale(&self, app: &AppHandle, index: &Index) {
+        let current = match self.conn.lock().unwrap().as_ref() {
+            Some(conn) => read_version(conn).ok(),
+            // Still loading
+            None => return,
+        };
+        if current.is_some_and(|version| version != index.version) {
+            self.reload_in_background(app);
+        }
+    }
+}
+
+// Filenames matching `query` (case-insensitive), best first: prefix matches,
+// then matches at a word start, anywhere in the name, and finally names
+// holding the query's characters in order. Results come from memory; while
+// the index is rebuilding after a change they may briefly be out of date.
+#[tauri::command]
+pub fn quick_find(
+    app: AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<QuickFindHit>, DrawStackError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let state = app.state::<QuickFind>();
+    let index = Arc::clone(&state.index.read().unwrap());
+    state.refresh_if_stale(&app, &index);
+    Ok(index.search(&query, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(names: &[&str]) -> Index {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE name_index_version (id INTEGER PRIMARY KEY, version INTEGER);
+             INSERT INTO name_index_version VALUES (1, 7);
+             CREATE TABLE images (id TEXT, pack_id TEXT, filename TEXT, relative_path TEXT);
+             CREATE TABLE thumbnails (image_id TEXT, path TEXT);",
+        )
+        .unwrap();
+        for (i, name) in names.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO images VALUES (?1, 'pack', ?2, '')",
+                rusqlite::params![i.to_string(), name],
+            )
+            .unwrap();
+        }
+        Index::load(&conn).unwrap()
+    }
+
+    fn found(index: &Index, query: &str) -> Vec<(String, MatchKind)> {
+        index
+            .search(query, 10)
+            .into_iter()
+            .map(|hit| (hit.filename, hit.kind))
+            .collect()
+    }
+
+    #[test]
+    fn ranks_prefix_word_substring_then_fuzzy() {
+        let index = index(&[
+            "hand study.jpg",
+            "Hands_02.png",
+            "pose-hands.jpg",
+            "sketchhand.png",
+            "h-a-n-d.gif",
+            "feet.jpg",
+        ]);
+        assert_eq!(index.version, 7);
+        assert_eq!(
+            found(&index, "hand"),
+            vec![
+                ("Hands_02.png".to_string(), MatchKind::Prefix),
+                ("hand study.jpg".to_string(), MatchKind::Prefix),
+                ("pose-hands.jpg".to_string(), MatchKind::Word),
+                ("sketchhand.png".to_string(), MatchKind::Substring),
+                ("h-a-n-d.gif".to_string(), MatchKind::Fuzzy),
+            ]
+        );
+        assert!(found(&index, "xyz").is_empty());
+        assert_eq!(
+            found(&index, "ee"),
+            vec![("feet.jpg".to_string(), MatchKind::Substring)]
+        );
+    }
+}
@@ -0,0 +1,278 @@
+// A size cap for library copies, for libraries kept on a small SSD. Going
+// past the warning threshold emits `storage-quota-warning`; eviction frees
+// space by moving the least valuable copies out of the library.
+use rusqlite::params;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+use crate::error::DrawStackError;
+use crate::jobs::JobHandle;
+use crate::paths::StoredPath;
+use crate::{catalog, config, library, manifest, roots, storage};
+
+const DEFAULT_WARN_PERCENT: u8 = 90;
+
+// Stored in the app config as `storage_quota`.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(default)]
+pub struct StorageQuota {
+    // Largest total size of library copies, in bytes; None means no limit
+    pub max_bytes: Option<u64>,
+    // Warn once usage reaches this share of `max_bytes`
+    pub warn_percent: u8,
+    // Evict copies when over the limit instead of only warning
+    pub evict: bool,
+    // Library root evicted copies are moved to. Without one they go back to
+    // their original location, or are dropped if the original is still there.
+    pub archive_root: Option<String>,
+}
+
+impl Default for StorageQuota {
+    fn default() -> Self {
+        Self {
+            max_bytes: None,
+            warn_percent: DEFAULT_WARN_PERCENT,
+            evict: false,
+            archive_root: None,
+        }
+    }
+}
+
+impl StorageQuota {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=100).contains(&self.warn_percent) {
+            return Err("warn_percent must be between 1 and 100".to_string());
+        }
+        if self.max_bytes == Some(0) {
+            return Err("max_bytes must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct QuotaWarning {
+    used_bytes: u64,
+    max_bytes: u64,
+    percent: f32,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct EvictedImage {
+    pub image_id: String,
+    pub from: String,
+    // None when the copy was dropped because the original still exists
+    pub to: Option<String>,
+    pub bytes: u64,
+}
+
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct QuotaReport {
+    pub used_bytes: u64,
+    pub max_bytes: Option<u64>,
+    pub over_quota: bool,
+    pub warned: bool,
+    pub evicted: Vec<EvictedImage>,
+    pub freed_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+struct LibraryCopy {
+    image_id: String,
+    original_path: PathBuf,
+    library_path: PathBuf,
+    size: u64,
+}
+
+// Library copies counted against the quota, cheapest to lose first:
+// non-favorites before favorites, then by rating, then least recently shown
+// in a session (never-shown images count as oldest).
+fn library_copies(app: &AppHandle, archive_dir: Option<&Path>) -> Result<Vec<LibraryCopy>, String> {
+    let conn = catalog::open(app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, original_path, library_path FROM images
+             WHERE library_path IS NOT NULL
+             ORDER BY favorite ASC, rating ASC, COALESCE(last_shown_at, 0) ASC, imported_at ASC",
+        )
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, StoredPath>(1)?,
+                row.get::<_, StoredPath>(2)?,
+            ))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read images: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(image_id, original_path, library_path)| {
+            let library_path = PathBuf::from(library_path);
+            if archive_dir.is_some_and(|dir| library_path.starts_with(dir)) {
+                return None;
+            }
+            let size = fs::metadata(&library_path).ok()?.len();
+            Some(LibraryCopy {
+                image_id,
+                original_path: original_path.into(),
+                library_path,
+                size,
+            })
+        })
+        .collect())
+}
+
+// Move one copy out of the library and return where it went
+fn evict_one(copy: &LibraryCopy, archive_dir: Option<&Path>) -> Result<Option<PathBuf>, String> {
+    if let Some(archive_dir) = archive_dir {
+        fs::create_dir_all(archive_dir)
+            .map_err(|e| format!("Failed to create archive directory: {}", e))?;
+        let dest = library::library_target(archive_dir, &copy.library_path, &copy.image_id);
+        library::move_file(&copy.library_path, &dest)?;
+        return Ok(Some(dest));
+    }
+
+    // Images that only exist in the library (PDF pages, moved imports whose
+    // original is gone) have nowhere else to go without an archive root
+    if copy.original_path == copy.library_path {
+        return Err(format!(
+            "{} has no original to return to",
+            copy.library_path.display()
+        ));
+    }
+    if copy.original_path.exists() {
+        library::trash_file(&copy.library_path)?;
+        return Ok(None);
+    }
+    if let Some(parent) = copy.original_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to recreate {}: {}", parent.display(), e))?;
+    }
+    library::move_file(&copy.library_path, &copy.original_path)?;
+    Ok(Some(copy.original_path.clone()))
+}
+
+// Compare library usage with the configured quota, warn when close and, with
+// eviction on (from config or `evict`), move copies out until usage is back
+// under the limit.
+pub fn enforce(
+    app: &AppHandle,
+    evict: Option<bool>,
+    job: Option<&JobHandle>,
+) -> Result<QuotaReport, DrawStackError> {
+    let quota = config::load(app).storage_quota;
+    let archive_dir = match quota.archive_root.as_deref() {
+        Some(root_id) => Some(roots::root_dir(app, Some(root_id))?),
+        None => None,
+    };
+
+    let copies = library_copies(app, archive_dir.as_deref())?;
+    let used_bytes: u64 = copies.iter().map(|c| c.size).sum();
+    let mut report = QuotaReport {
+        used_bytes,
+        max_bytes: quota.max_bytes,
+        ..QuotaReport::default()
+    };
+    let Some(max_bytes) = quota.max_bytes else {
+        return Ok(report);
+    };
+
+    let percent = used_bytes as f32 / max_bytes as f32 * 100.0;
+    report.over_quota = used_bytes > max_bytes;
+    if percent >= quota.warn_percent as f32 {
+        report.warned = true;
+        let _ = app.emit(
+            "storage-quota-warning",
+            QuotaWarning {
+                used_bytes,
+                max_bytes,
+                percent,
+            },
+        );
+    }
+    if !report.over_quota || !evict.unwrap_or(quota.evict) {
+        return Ok(report);
+    }
+
+    let to_free = used_bytes - max_bytes;
+    let conn = catalog::open(app)?;
+    for copy in &copies {
+        if report.freed_bytes >= to_free {
+            break;
+        }
+        if let Some(job) = job {
+            if job.is_cancelled() {
+                return Err(job.cancelled_error());
+            }
+            job.progress(
+                report.freed_bytes.min(to_free) as usize,
+                to_free as usize,
+                Some(format!("Evicting {}", copy.image_id)),
+            );
+        }
+
+        match evict_one(copy, archive_dir.as_deref()) {
+            Ok(dest) => {
+                // Still a library copy when it landed in the archive root
+                let library_path = match (&dest, &archive_dir) {
+                    (Some(dest), Some(_)) => Some(dest.to_string_lossy().to_string()),
+                    _ => None,
+                };
+                conn.execute(
+                    "UPDATE images SET library_path = ?1 WHERE id = ?2",
+                    params![library_path, copy.image_id],
+                )
+                .map_err(|e| format!("Failed to update catalog: {}", e))?;
+                report.freed_bytes += copy.size;
+                report.evicted.push(EvictedImage {
+                    image_id: copy.image_id.clone(),
+                    from: copy.library_path.to_string_lossy().to_string(),
+                    to: dest.map(|d| d.to_string_lossy().to_string()),
+                    bytes: copy.size,
+                });
+            }
+            Err(e) => report.errors.push(e),
+        }
+    }
+
+    tracing::info!(
+        "Storage quota: evicted {} images, freed {} of {} needed",
+        report.evicted.len(),
+        crate::format_bytes(report.freed_bytes),
+        crate::format_bytes(to_free)
+    );
+    let evicted: Vec<String> = report.evicted.iter().map(|e| e.image_id.clone()).collect();
+    manifest::refresh_for_images(app, &evicted);
+    storage::invalidate(app);
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn enforce_storage_quota(
+    app: AppHandle,
+    evict: Option<bool>,
+) -> Result<QuotaReport, DrawStackError> {
+    enforce(&app, evict, None)
+}
+
+#[tauri::command]
+pub fn get_storage_quota(app: AppHandle) -> StorageQuota {
+    config::load(&app).storage_quota
+}
+
+#[tauri::command]
+pub fn set_storage_quota(app: AppHandle, quota: StorageQuota) -> Result<(), DrawStackError> {
+    quota.validate().map_err(DrawStackError::invalid)?;
+    if let Some(root_id) = quota.archive_root.as_deref() {
+        roots::find_root(&app, Some(root_id))?;
+    }
+    config::update(&app, |config| {
+        config.storage_quota = quota;
+        Ok(())
+    })?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use rusqlite::params;
+use tauri::AppHandle;
+
+use crate::catalog::{self, CatalogImage, ImageSort};
+use crate::error::DrawStackError;
+
+#[tauri::command]
+pub async fn set_rating(
+    app: AppHandle,
+    image_id: String,
+    rating: u8,
+) -> Result<(), DrawStackError> {
+    if rating > 5 {
+        return Err(DrawStackError::invalid(format!(
+            "Rating must be between 0 and 5, got {}",
+            rating
+        )));
+    }
+
+    let conn = catalog::open(&app)?;
+    let updated = conn
+        .execute(
+            "UPDATE images SET rating = ?1 WHERE id = ?2",
+            params![rating, image_id],
+        )
+        .map_err(|e| format!("Failed to set rating: {}", e))?;
+
+    if updated == 0 {
+        return Err(DrawStackError::invalid(format!(
+            "Image not found: {}",
+            image_id
+        )));
+    }
+    Ok(())
+}
+
+// Returns the new favorite state.
+#[tauri::command]
+pub async fn toggle_favorite(app: AppHandle, image_id: String) -> Result<bool, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    conn.query_row(
+        "UPDATE images SET favorite = NOT favorite WHERE id = ?1 RETURNING favorite",
+        params![image_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => {
+            DrawStackError::invalid(format!("Image not found: {}", image_id))
+        }
+        e => DrawStackError::Catalog(e),
+    })
+}
+
+#[tauri::command]
+pub async fn get_images_filtered(
+    app: AppHandle,
+    min_rating: Option<u8>,
+    favorites_only: Option<bool>,
+    sort: Option<ImageSort>,
+) -> Result<Vec<CatalogImage>, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id
+             WHERE i.rating >= ?1 AND (?2 = 0 OR i.favorite = 1)
+             ORDER BY i.rating DESC, {}",
+            catalog::IMAGE_COLUMNS,
+            catalog::order_by(sort, "i.pack_id, i.relative_path, i.filename")
+        ))
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+
+    stmt.query_map(
+        params![min_rating.unwrap_or(0), favorites_only.unwrap_or(false)],
+        catalog::map_image,
+    )
+    .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+    .map_err(DrawStackError::from)
+}
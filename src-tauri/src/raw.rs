@@ -0,0 +1,211 @@
+// Camera RAW support. Rather than demosaicing sensor data, thumbnails are
+// built from the full-size JPEG preview that cameras embed in RAW files.
+use image::DynamicImage;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+pub static RAW_EXTENSIONS: &[&str] = &[
+    "cr2", "nef", "nrw", "arw", "srf", "sr2", "dng", "orf", "rw2", "pef", "raf",
+];
+
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+const TAG_EXIF_IFD: u16 = 0x8769;
+// Panasonic RW2 stores its preview as an opaque blob under this tag
+const TAG_RW2_JPEG_FROM_RAW: u16 = 0x002E;
+
+const MAX_IFDS: usize = 64;
+
+pub fn is_raw_extension(ext: &str) -> bool {
+    crate::extension_in(ext, RAW_EXTENSIONS)
+}
+
+// Decode the largest embedded JPEG preview from a RAW file.
+pub fn decode_preview(path: &Path) -> Result<DynamicImage, String> {
+    let data = fs::read(crate::paths::extended(path))
+        .map_err(|e| format!("Failed to read RAW file: {}", e))?;
+    let (offset, len) =
+        find_preview(&data).ok_or_else(|| format!("No embedded preview in {}", path.display()))?;
+
+    image::load_from_memory_with_format(&data[offset..offset + len], image::ImageFormat::Jpeg)
+        .map_err(|e| format!("Failed to decode RAW preview: {}", e))
+}
+
+fn find_preview(data: &[u8]) -> Option<(usize, usize)> {
+    // Fujifilm RAF: fixed big-endian header pointing at the JPEG
+    if data.starts_with(b"FUJIFILMCCD-RAW") {
+        let offset = read_u32(data, 84, false)? as usize;
+        let len = read_u32(data, 88, false)? as usize;
+        return is_jpeg(data, offset, len).then_some((offset, len));
+    }
+
+    let little_endian = match data.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+
+    // 42 = TIFF, 0x4F52 = Olympus ORF, 0x55 = Panasonic RW2
+    let magic = read_u16(data, 2, little_endian)?;
+    if !matches!(magic, 42 | 0x4F52 | 0x55) {
+        return None;
+    }
+
+    let mut walker = IfdWalker {
+        data,
+        little_endian,
+        visited: HashSet::new(),
+        best: None,
+    };
+    walker.walk(read_u32(data, 4, little_endian)? as usize);
+    walker.best
+}
+
+struct IfdWalker<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+    visited: HashSet<usize>,
+    best: Option<(usize, usize)>,
+}
+
+impl IfdWalker<'_> {
+    fn walk(&mut self, mut ifd_offset: usize) {
+        while ifd_offset != 0 && self.visited.len() < MAX_IFDS && self.visited.insert(ifd_offset) {
+            let Some(count) = read_u16(self.data, ifd_offset, self.little_endian) else {
+                return;
+            };
+
+            let mut compression = None;
+            let mut strip = (None, None);
+            let mut jpeg = (None, None);
+            let mut children = Vec::new();
+
+            for i in 0..count as usize {
+                let entry = ifd_offset + 2 + i * 12;
+                let Some(tag) = read_u16(self.data, entry, self.little_endian) else {
+                    return;
+                };
+
+                match tag {
+                    TAG_COMPRESSION => compression = self.first_value(entry),
+                    TAG_STRIP_OFFSETS => strip.0 = self.single_value(entry),
+                    TAG_STRIP_BYTE_COUNTS => strip.1 = self.single_value(entry),
+                    TAG_JPEG_OFFSET => jpeg.0 = self.first_value(entry),
+                    TAG_JPEG_LENGTH => jpeg.1 = self.first_value(entry),
+                    TAG_SUB_IFDS | TAG_EXIF_IFD => children.extend(self.values(entry)),
+                    TAG_RW2_JPEG_FROM_RAW => {
+                        let len = read_u32(self.data, entry + 4, self.little_endian);
+                        let offset = read_u32(self.data, entry + 8, self.little_endian);
+                        if let (Some(offset), Some(len)) = (offset, len) {
+                            self.consider(offset as usize, len as usize);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if let (Some(offset), Some(len)) = jpeg {
+                self.consider(offset as usize, len as usize);
+            }
+            // Compression 6 and 7 are old-style and new-style JPEG strips
+            if let (Some(6 | 7), (Some(offset), Some(len))) = (compression, strip) {
+                self.consider(offset as usize, len as usize);
+            }
+
+            for child in children {
+                self.walk(child as usize);
+            }
+
+            let next = ifd_offset + 2 + count as usize * 12;
+            ifd_offset = read_u32(self.data, next, self.little_endian).unwrap_or(0) as usize;
+        }
+    }
+
+    fn consider(&mut self, offset: usize, len: usize) {
+        if !is_jpeg(self.data, offset, len) {
+            return;
+        }
+        if self.best.is_none_or(|(_, best_len)| len > best_len) {
+            self.best = Some((offset, len));
+        }
+    }
+
+    // Values of a SHORT/LONG/IFD entry, following the offset when they don't
+    // fit inline.
+    fn values(&self, entry: usize) -> Vec<u32> {
+        let le = self.little_endian;
+        let (Some(kind), Some(count)) = (
+            read_u16(self.data, entry + 2, le),
+            read_u32(self.data, entry + 4, le),
+        ) else {
+            return Vec::new();
+        };
+
+        let size = match kind {
+            3 => 2,
+            4 | 13 => 4,
+            _ => return Vec::new(),
+        };
+        let count = (count as usize).min(256);
+
+        let base = if size * count <= 4 {
+            entry + 8
+        } else {
+            match read_u32(self.data, entry + 8, le) {
+                Some(offset) => offset as usize,
+                None => return Vec::new(),
+            }
+        };
+
+        (0..count)
+            .filter_map(|i| {
+                if size == 2 {
+                    read_u16(self.data, base + i * 2, le).map(u32::from)
+                } else {
+                    read_u32(self.data, base + i * 4, le)
+                }
+            })
+            .collect()
+    }
+
+    fn first_value(&self, entry: usize) -> Option<u32> {
+        self.values(entry).first().copied()
+    }
+
+    // Multi-strip JPEGs can't be stitched back together, so only accept one
+    fn single_value(&self, entry: usize) -> Option<u32> {
+        match self.values(entry).as_slice() {
+            [value] => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+fn is_jpeg(data: &[u8], offset: usize, len: usize) -> bool {
+    len > 2
+        && offset.checked_add(len).is_some_and(|end| end <= data.len())
+        && data[offset..offset + 2] == [0xFF, 0xD8]
+}
+
+fn read_u16(data: &[u8], offset: usize, little_endian: bool) -> Option<u16> {
+    let bytes: [u8; 2] = data.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if little_endian {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize, little_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = data.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
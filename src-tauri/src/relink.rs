@@ -0,0 +1,209 @@
+use rayon::prelude::*;
+use rusqlite::params;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::error::DrawStackError;
+use crate::paths::StoredPath;
+use crate::{catalog, config, content_hash, scan, storage};
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct PrefixRelink {
+    // Stored paths rewritten, across all catalog tables
+    pub updated: usize,
+    // Originals under the new prefix that still don't exist
+    pub still_missing: usize,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct RelinkMatch {
+    pub image_id: String,
+    pub old_path: StoredPath,
+    pub new_path: StoredPath,
+    // Matched on file contents rather than name and size alone
+    pub verified: bool,
+}
+
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct FuzzyRelink {
+    pub missing: usize,
+    pub matches: Vec<RelinkMatch>,
+    // Several files fit and none could be confirmed by hash
+    pub ambiguous: Vec<String>,
+    pub unmatched: Vec<String>,
+    pub applied: bool,
+}
+
+struct MissingOriginal {
+    id: String,
+    path: StoredPath,
+    size: Option<u64>,
+}
+
+fn file_name_key(path: &Path) -> Option<String> {
+    path.file_name().map(|n| n.to_string_lossy().to_lowercase())
+}
+
+// Repoint every catalog path inside `old_prefix` to the same place under
+// `new_prefix`, for when a whole source folder or drive moved.
+#[tauri::command]
+pub async fn relink_originals(
+    app: AppHandle,
+    old_prefix: String,
+    new_prefix: String,
+) -> Result<PrefixRelink, DrawStackError> {
+    if old_prefix.trim().is_empty() || new_prefix.trim().is_empty() {
+        return Err(DrawStackError::invalid("Both folders are required"));
+    }
+
+    let mut conn = catalog::open(&app)?;
+    let updated = catalog::rewrite_path_prefix(&mut conn, &old_prefix, &new_prefix)?;
+
+    let new_prefix = new_prefix.trim_end_matches(['/', '\\']);
+    let mut stmt = conn
+        .prepare(
+            "SELECT original_path FROM images
+             WHERE original_path = ?1
+                OR substr(original_path, 1, length(?1) + 1) IN (?1 || '/', ?1 || '\\')",
+        )
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+    let paths: Vec<StoredPath> = stmt
+        .query_map(params![new_prefix], |row| row.get(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read images: {}", e))?;
+    let still_missing = paths.iter().filter(|p| !p.to_path_buf().exists()).count();
+
+    storage::invalidate(&app);
+    tracing::info!(
+        "Relinked {} paths from {} to {} ({} still missing)",
+        updated,
+        old_prefix,
+        new_prefix,
+        still_missing
+    );
+    Ok(PrefixRelink {
+        updated,
+        still_missing,
+    })
+}
+
+fn missing_originals(conn: &rusqlite::Connection) -> Result<Vec<MissingOriginal>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, original_path, file_size FROM images")
+        .map_err(|e| format!("Failed to prepare image query: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(MissingOriginal {
+                id: row.get(0)?,
+                path: row.get(1)?,
+                size: row.get::<_, Option<i64>>(2)?.map(|s| s as u64),
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read images: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .filter(|image| !image.path.to_path_buf().exists())
+        .collect())
+}
+
+// Choose among files with the right name. Image IDs are content hashes, so
+// a candidate whose hash equals the ID is certainly the same file; otherwise
+// a single candidate of the recorded size is accepted.
+fn pick_candidate(
+    image: &MissingOriginal,
+    candidates: &[(PathBuf, u64)],
+) -> Option<(PathBuf, bool)> {
+    let sized: Vec<&(PathBuf, u64)> = candidates
+        .iter()
+        .filter(|(_, size)| image.size.is_none_or(|expected| expected == *size))
+        .collect();
+
+    if let Some((path, _)) = sized
+        .par_iter()
+        .find_any(|(path, _)| content_hash::content_id(path).is_ok_and(|id| id == image.id))
+    {
+        return Some((path.clone(), true));
+    }
+    match sized.as_slice() {
+        [(path, _)] if image.size.is_some() => Some((path.clone(), false)),
+        _ => None,
+    }
+}
+
+// Look for every missing original under `new_root` by file name, size and
+// content hash. With `dry_run` the matches are only reported.
+#[tauri::command]
+pub async fn fuzzy_relink(
+    app: AppHandle,
+    new_root: String,
+    dry_run: Option<bool>,
+) -> Result<FuzzyRelink, DrawStackError> {
+    let dry_run = dry_run.unwrap_or(false);
+    let mut conn = catalog::open(&app)?;
+    let missing = missing_originals(&conn)?;
+    let mut report = FuzzyRelink {
+        missing: missing.len(),
+        ..FuzzyRelink::default()
+    };
+    if missing.is_empty() {
+        return Ok(report);
+    }
+
+    let mut by_name: HashMap<String, Vec<(PathBuf, u64)>> = HashMap::new();
+    for path in scan::scan_for_images(Path::new(&new_root), &config::load(&app).scan)? {
+        let (Some(key), Ok(meta)) = (file_name_key(&path), fs::metadata(&path)) else {
+            continue;
+        };
+        by_name.entry(key).or_default().push((path, meta.len()));
+    }
+
+    for image in &missing {
+        let candidates = file_name_key(&image.path.to_path_buf())
+            .and_then(|key| by_name.get(&key))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        match pick_candidate(image, candidates) {
+            Some((path, verified)) => report.matches.push(RelinkMatch {
+                image_id: image.id.clone(),
+                old_path: image.path.clone(),
+                new_path: StoredPath::from(path.as_path()),
+                verified,
+            }),
+            None if candidates.is_empty() => report.unmatched.push(image.id.clone()),
+            None => report.ambiguous.push(image.id.clone()),
+        }
+    }
+
+    if !dry_run && !report.matches.is_empty() {
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+        for found in &report.matches {
+            tx.execute(
+                "UPDATE images SET original_path = ?1 WHERE id = ?2",
+                params![found.new_path, found.image_id],
+            )
+            .map_err(|e| format!("Failed to relink {}: {}", found.image_id, e))?;
+            tx.execute(
+                "UPDATE OR IGNORE thumbnail_cache SET source_path = ?1 WHERE source_path = ?2",
+                params![found.new_path, found.old_path],
+            )
+            .map_err(|e| format!("Failed to relink {}: {}", found.image_id, e))?;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+        report.applied = true;
+        storage::invalidate(&app);
+    }
+
+    tracing::info!(
+        "Fuzzy relink under {}: {} of {} missing originals found",
+        new_root,
+        report.matches.len(),
+        report.missing
+    );
+    Ok(report)
+}
@@ -0,0 +1,559 @@
+// Optional read-only HTTP server for browsing the library from another device
+// on the LAN, e.g. a tablet next to the desk. Every request needs the token
+// given at start, as `Authorization: Bearer <token>` or `?token=<token>` (so
+// plain <img> tags work).
+//   GET /                  minimal gallery page
+//   GET /api/packs         packs with image counts
+//   GET /api/packs/{id}    a pack and its images
+//   GET /thumb/{id}        an image's thumbnail
+//   GET /image/{id}        the image itself
+//   GET /remote            session remote control and timer page
+//   GET /ws/session        websocket: session state out, controls in
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path as UrlPath, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::watch;
+
+use crate::catalog::{self, PackRecord};
+use crate::error::DrawStackError;
+use crate::protocol::{self, Failure, Kind};
+use crate::session;
+
+const DEFAULT_PORT: u16 = 8765;
+const MIN_TOKEN_LEN: usize = 8;
+// How often remote clients are checked for a changed session state
+const SESSION_POLL: Duration = Duration::from_millis(250);
+
+const GALLERY_HTML: &str = r##"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>DrawStack</title>
+<style>
+  body { margin: 0; font-family: system-ui, sans-serif; background: #111; color: #eee; }
+  header { padding: 12px 16px; font-size: 18px; }
+  a { color: inherit; text-decoration: none; }
+  .grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(160px, 1fr)); gap: 8px; padding: 8px; }
+  .grid img { width: 100%; aspect-ratio: 1; object-fit: cover; background: #222; border-radius: 4px; }
+  .pack { padding: 12px 16px; border-bottom: 1px solid #222; }
+</style>
+</head>
+<body>
+<header id="title">DrawStack</header>
+<main id="content"></main>
+<script>
+  const token = new URLSearchParams(location.search).get("token") || "";
+  const auth = "token=" + encodeURIComponent(token);
+  const content = document.getElementById("content");
+  const title = document.getElementById("title");
+
+  async function showPacks() {
+    const packs = await (await fetch("/api/packs?" + auth)).json();
+    title.textContent = "DrawStack";
+    content.innerHTML = "";
+    for (const pack of packs) {
+      const row = document.createElement("a");
+      row.className = "pack";
+      row.style.display = "block";
+      row.href = "#" + encodeURIComponent(pack.id);
+      row.textContent = pack.name + " (" + pack.image_count + ")";
+      content.appendChild(row);
+    }
+  }
+
+  async function showPack(id) {
+    const pack = await (await fetch("/api/packs/" + encodeURIComponent(id) + "?" + auth)).json();
+    title.textContent = pack.name;
+    content.innerHTML = "";
+    const grid = document.createElement("div");
+    grid.className = "grid";
+    for (const image of pack.images) {
+      const link = document.createElement("a");
+      link.href = "/image/" + image.id + "?" + auth;
+      const img = document.createElement("img");
+      img.loading = "lazy";
+      img.src = "/thumb/" + image.id + "?" + auth;
+      img.alt = image.filename;
+      link.appendChild(img);
+      grid.appendChild(link);
+    }
+    content.appendChild(grid);
+  }
+
+  function route() {
+    const id = decodeURIComponent(location.hash.slice(1));
+    (id ? showPack(id) : showPacks()).catch(e => content.textContent = e);
+  }
+  window.addEventListener("hashchange", route);
+  route();
+</script>
+</body>
+</html>
+"##;
+
+const REMOTE_HTML: &str = r##"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>DrawStack Remote</title>
+<style>
+  body { margin: 0; font-family: system-ui, sans-serif; background: #111; color: #eee; text-align: center; }
+  #timer { font-size: 22vw; font-variant-numeric: tabular-nums; margin: 4vh 0 1vh; }
+  #info { opacity: 0.7; min-height: 1.5em; }
+  #image { max-width: 90vw; max-height: 40vh; margin: 2vh auto; display: block; }
+  .controls { display: flex; gap: 8px; justify-content: center; padding: 16px; }
+  button { font-size: 20px; padding: 16px 20px; border: 0; border-radius: 8px; background: #333; color: inherit; }
+</style>
+</head>
+<body>
+<div id="timer">--:--</div>
+<div id="info">Connecting...</div>
+<img id="image" alt="">
+<div class="controls">
+  <button data-command="pause">Pause</button>
+  <button data-command="resume">Resume</button>
+  <button data-command="next">Next</button>
+  <button data-command="end">End</button>
+</div>
+<script>
+  const token = new URLSearchParams(location.search).get("token") || "";
+  const auth = "token=" + encodeURIComponent(token);
+  const timer = document.getElementById("timer");
+  const info = document.getElementById("info");
+  const image = document.getElementById("image");
+  let socket;
+
+  function format(ms) {
+    const secs = Math.ceil(ms / 1000);
+    return Math.floor(secs / 60) + ":" + String(secs % 60).padStart(2, "0");
+  }
+
+  function connect() {
+    const scheme = location.protocol === "https:" ? "wss://" : "ws://";
+    socket = new WebSocket(scheme + location.host + "/ws/session?" + auth);
+    socket.onmessage = event => {
+      const message = JSON.parse(event.data);
+      if (message.type === "error") {
+        info.textContent = message.message;
+        return;
+      }
+      const state = message.state;
+      if (!state) {
+        timer.textContent = "--:--";
+        info.textContent = "No session running";
+        image.removeAttribute("src");
+        return;
+      }
+      timer.textContent = format(state.remaining_ms);
+      info.textContent = (state.index + 1) + " / " + state.total + (state.paused ? " - paused" : "");
+      const src = state.image ? "/image/" + state.image.id + "?" + auth : "";
+      if (image.getAttribute("src") !== src) image.src = src;
+    };
+    socket.onclose = () => {
+      info.textContent = "Disconnected - retrying";
+      setTimeout(connect, 2000);
+    };
+  }
+
+  for (const button of document.querySelectorAll("button")) {
+    button.onclick = () => socket.send(JSON.stringify({ command: button.dataset.command }));
+  }
+  connect();
+</script>
+</body>
+</html>
+"##;
+
+#[derive(Clone)]
+struct ServerState {
+    app: AppHandle,
+    token: Arc<str>,
+    // Flips to true when the server is stopped, closing open websockets
+    stopping: watch::Receiver<bool>,
+}
+
+struct RunningServer {
+    port: u16,
+    shutdown: watch::Sender<bool>,
+}
+
+// The running server, if remote access is on
+#[derive(Default)]
+pub struct RemoteAccess {
+    server: Mutex<Option<RunningServer>>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct RemoteAccessStatus {
+    pub running: bool,
+    pub port: Option<u16>,
+    // Addresses to open on the other device
+    pub urls: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RemoteCommand {
+    Pause,
+    Resume,
+    // `skip` is accepted too, matching the app's own command
+    #[serde(alias = "skip")]
+    Next,
+    End,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RemoteMessage {
+    command: RemoteCommand,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RemoteEvent {
+    State {
+        state: Option<session::SessionState>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct PackSummary {
+    id: String,
+    name: String,
+    image_count: usize,
+}
+
+// Address other devices on the LAN reach this machine at. Connecting a UDP
+// socket only picks the outgoing interface; nothing is sent.
+fn lan_address() -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 168, 0, 1), 9)).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        std::net::IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+        _ => None,
+    }
+}
+
+fn status(port: Option<u16>) -> RemoteAccessStatus {
+    let urls = port
+        .into_iter()
+        .flat_map(|port| {
+            [Some(Ipv4Addr::LOCALHOST), lan_address()]
+                .into_iter()
+                .flatten()
+                .map(move |ip| format!("http://{}:{}/", ip, port))
+        })
+        .collect();
+    RemoteAccessStatus {
+        running: port.is_some(),
+        port,
+        urls,
+    }
+}
+
+// Compare without exiting at the first differing byte
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn authorize(State(state): State<ServerState>, request: Request, next: Next) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let query = request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+            .map(|t| {
+                percent_encoding::percent_decode_str(t)
+                    .decode_utf8_lossy()
+                    .to_string()
+            })
+    });
+
+    match bearer.or(query) {
+        Some(token) if tokens_match(&token, &state.token) => next.run(request).await,
+        _ => (StatusCode::UNAUTHORIZED, "Missing or wrong token").into_response(),
+    }
+}
+
+// Run catalog and file work off the async workers
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, Failure> + Send + 'static,
+) -> Result<T, Failure> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(internal)?
+}
+
+fn internal(e: impl ToString) -> Failure {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+async fn gallery() -> Html<&'static str> {
+    Html(GALLERY_HTML)
+}
+
+async fn list_packs(State(state): State<ServerState>) -> Result<Json<Vec<PackSummary>>, Failure> {
+    let packs = blocking(move || {
+        let conn = catalog::open(&state.app).map_err(internal)?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT p.id, p.name, COUNT(i.id) FROM packs p
+                 LEFT JOIN images i ON i.pack_id = p.id
+                 GROUP BY p.id ORDER BY p.name COLLATE NATURAL",
+            )
+            .map_err(internal)?;
+        stmt.query_map([], |row| {
+            Ok(PackSummary {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                image_count: row.get(2)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(internal)
+    })
+    .await?;
+    Ok(Json(packs))
+}
+
+async fn get_pack(
+    State(state): State<ServerState>,
+    UrlPath(pack_id): UrlPath<String>,
+) -> Result<Json<PackRecord>, Failure> {
+    let pack = blocking(move || {
+        let conn = catalog::open(&state.app).map_err(internal)?;
+        catalog::get_pack(&conn, &pack_id, None)
+            .map_err(internal)?
+            .ok_or((StatusCode::NOT_FOUND, format!("No pack {}", pack_id)))
+    })
+    .await?;
+    Ok(Json(pack))
+}
+
+async fn serve_file(state: ServerState, kind: Kind, id: String) -> Result<Response, Failure> {
+    blocking(move || {
+        let path = protocol::resolve(&state.app, kind, &id)?;
+        let bytes = std::fs::read(&path)
+            .map_err(|_| (StatusCode::NOT_FOUND, format!("No file for {}", id)))?;
+        Ok((
+            [
+                (header::CONTENT_TYPE, protocol::mime_type(&path)),
+                (header::CACHE_CONTROL, "private, max-age=300"),
+            ],
+            bytes,
+        )
+            .into_response())
+    })
+    .await
+}
+
+async fn thumb(
+    State(state): State<ServerState>,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Response, Failure> {
+    serve_file(state, Kind::Thumb, id).await
+}
+
+async fn image(
+    State(state): State<ServerState>,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Response, Failure> {
+    serve_file(state, Kind::Image, id).await
+}
+
+async fn remote_page() -> Html<&'static str> {
+    Html(REMOTE_HTML)
+}
+
+async fn session_socket(State(state): State<ServerState>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| run_session_socket(socket, state))
+}
+
+fn apply(app: &AppHandle, command: RemoteCommand) -> Result<(), DrawStackError> {
+    let app = app.clone();
+    match command {
+        RemoteCommand::Pause => session::pause_session(app),
+        RemoteCommand::Resume => session::resume_session(app),
+        RemoteCommand::Next => session::skip_image(app),
+        RemoteCommand::End => session::end_session(app),
+    }
+}
+
+async fn send(socket: &mut WebSocket, event: &RemoteEvent) -> bool {
+    let Ok(text) = serde_json::to_string(event) else {
+        return false;
+    };
+    socket.send(Message::Text(text.into())).await.is_ok()
+}
+
+// Push the session state whenever it changes and run incoming controls,
+// until the client goes away or the server stops.
+async fn run_session_socket(mut socket: WebSocket, mut state: ServerState) {
+    let mut poll = tokio::time::interval(SESSION_POLL);
+    let mut last_sent = String::new();
+    loop {
+        tokio::select! {
+            _ = poll.tick() => {
+                let current = session::get_session_state(state.app.clone());
+                let event = RemoteEvent::State { state: current };
+                let Ok(text) = serde_json::to_string(&event) else {
+                    continue;
+                };
+                if text != last_sent {
+                    if socket.send(Message::Text(text.clone().into())).await.is_err() {
+                        break;
+                    }
+                    last_sent = text;
+                }
+            }
+            message = socket.recv() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let result = serde_json::from_str::<RemoteMessage>(&text)
+                    .map_err(|e| format!("Unknown command: {}", e))
+                    .and_then(|m| apply(&state.app, m.command).map_err(|e| e.to_string()));
+                if let Err(message) = result {
+                    if !send(&mut socket, &RemoteEvent::Error { message }).await {
+                        break;
+                    }
+                }
+            }
+            _ = state.stopping.changed() => break,
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/", get(gallery))
+        .route("/api/packs", get(list_packs))
+        .route("/api/packs/{id}", get(get_pack))
+        .route("/thumb/{id}", get(thumb))
+        .route("/image/{id}", get(image))
+        .route("/remote", get(remote_page))
+        .route("/ws/session", get(session_socket))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state)
+}
+
+// Start serving the library on all interfaces at `port` (default 8765; 0
+// picks a free one). Only one server runs at a time.
+#[tauri::command]
+pub async fn start_remote_access(
+    app: AppHandle,
+    port: Option<u16>,
+    token: String,
+) -> Result<RemoteAccessStatus, DrawStackError> {
+    if token.len() < MIN_TOKEN_LEN {
+        return Err(DrawStackError::invalid(format!(
+            "The access token needs at least {} characters",
+            MIN_TOKEN_LEN
+        )));
+    }
+    if app.state::<RemoteAccess>().server.lock().unwrap().is_some() {
+        return Err(DrawStackError::Busy(
+            "Remote access is already running".to_string(),
+        ));
+    }
+
+    let listener =
+        tokio::net::TcpListener::bind((Ipv4Addr::UNSPECIFIED, port.unwrap_or(DEFAULT_PORT)))
+            .await
+            .map_err(|e| format!("Failed to open port: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to open port: {}", e))?
+        .port();
+
+    let (shutdown, stopping) = watch::channel(false);
+    let state = ServerState {
+        app: app.clone(),
+        token: Arc::from(token),
+        stopping: stopping.clone(),
+    };
+    {
+        let remote = app.state::<RemoteAccess>();
+        let mut server = remote.server.lock().unwrap();
+        if server.is_some() {
+            return Err(DrawStackError::Busy(
+                "Remote access is already running".to_string(),
+            ));
+        }
+        *server = Some(RunningServer {
+            port,
+            shutdown: shutdown.clone(),
+        });
+    }
+
+    let serve_app = app.clone();
+    let mut stopping = stopping;
+    tauri::async_runtime::spawn(async move {
+        let served = axum::serve(listener, router(state))
+            .with_graceful_shutdown(async move {
+                let _ = stopping.wait_for(|&stopped| stopped).await;
+            })
+            .await;
+        if let Err(e) = served {
+            tracing::error!("Remote access server failed: {}", e);
+        }
+        // Clear the slot if the server died on its own
+        let remote = serve_app.state::<RemoteAccess>();
+        let mut server = remote.server.lock().unwrap();
+        if server
+            .as_ref()
+            .is_some_and(|s| s.shutdown.same_channel(&shutdown))
+        {
+            *server = None;
+        }
+    });
+
+    tracing::info!("Remote access listening on port {}", port);
+    Ok(status(Some(port)))
+}
+
+#[tauri::command]
+pub fn stop_remote_access(app: AppHandle) -> Result<(), DrawStackError> {
+    let server = app.state::<RemoteAccess>().server.lock().unwrap().take();
+    let server = server.ok_or_else(|| DrawStackError::invalid("Remote access is not running"))?;
+    let _ = server.shutdown.send(true);
+    tracing::info!("Remote access stopped");
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_remote_access_status(app: AppHandle) -> RemoteAccessStatus {
+    let port = app
+        .state::<RemoteAccess>()
+        .server
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|s| s.port);
+    status(port)
+}
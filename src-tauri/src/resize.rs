@@ -0,0 +1,64 @@
+// Thumbnail resizing. With `simd-resize` the work goes through
+// fast_image_resize, whose SSE4/AVX2/NEON convolutions are several times
+// faster than image's scalar ones on full-size photos; otherwise, or for a
+// pixel layout it doesn't handle, image's own resize is used.
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+// Size of `width`x`height` scaled to fit inside `max_width`x`max_height`
+// with its aspect ratio kept, as image's `resize` computes it
+fn fit(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let ratio = f64::min(
+        max_width as f64 / width.max(1) as f64,
+        max_height as f64 / height.max(1) as f64,
+    );
+    (
+        ((width as f64 * ratio).round() as u32).max(1),
+        ((height as f64 * ratio).round() as u32).max(1),
+    )
+}
+
+#[cfg(feature = "simd-resize")]
+fn resize_simd(
+    img: &DynamicImage,
+    width: u32,
+    height: u32,
+    filter: FilterType,
+) -> Option<DynamicImage> {
+    use fast_image_resize::{FilterType as SimdFilter, ResizeAlg, ResizeOptions, Resizer};
+
+    let algorithm = match filter {
+        FilterType::Nearest => ResizeAlg::Nearest,
+        FilterType::Triangle => ResizeAlg::Convolution(SimdFilter::Bilinear),
+        FilterType::CatmullRom => ResizeAlg::Convolution(SimdFilter::CatmullRom),
+        FilterType::Gaussian => ResizeAlg::Convolution(SimdFilter::Gaussian),
+        FilterType::Lanczos3 => ResizeAlg::Convolution(SimdFilter::Lanczos3),
+    };
+    let mut resized = DynamicImage::new(width, height, img.color());
+    Resizer::new()
+        .resize(
+            img,
+            &mut resized,
+            &ResizeOptions::new().resize_alg(algorithm),
+        )
+        .ok()?;
+    Some(resized)
+}
+
+// Scale `img` to fit inside `max_width`x`max_height`, keeping its aspect
+// ratio, like `DynamicImage::resize`
+pub fn resize(
+    img: &DynamicImage,
+    max_width: u32,
+    max_height: u32,
+    filter: FilterType,
+) -> DynamicImage {
+    let (width, height) = fit(img.width(), img.height(), max_width, max_height);
+
+    #[cfg(feature = "simd-resize")]
+    if let Some(resized) = resize_simd(img, width, height, filter) {
+        return resized;
+    }
+
+    img.resize_exact(width, height, filter)
+}
@@ -0,0 +1,246 @@
+// Library locations. A library can span several roots, e.g. an internal SSD
+// and an external drive; each copy lands in one of them. With no roots
+// configured the library is the single default folder in Documents.
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::config::{self, AppConfig};
+use crate::error::DrawStackError;
+use crate::{catalog, scope, storage, watcher};
+
+const DEFAULT_ROOT_ID: &str = "primary";
+const DEFAULT_ROOT_LABEL: &str = "Library";
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct LibraryRoot {
+    pub id: String,
+    pub label: String,
+    pub path: String,
+    // Copies go to the highest-priority root that is currently reachable
+    #[serde(default)]
+    pub priority: i32,
+}
+
+pub fn default_library_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let document_dir = app
+        .path()
+        .document_dir()
+        .map_err(|e| format!("Failed to get documents dir: {}", e))?;
+    Ok(document_dir.join("DrawStack").join("Library"))
+}
+
+fn default_root(app: &AppHandle) -> Result<LibraryRoot, String> {
+    Ok(LibraryRoot {
+        id: DEFAULT_ROOT_ID.to_string(),
+        label: DEFAULT_ROOT_LABEL.to_string(),
+        path: default_library_dir(app)?.to_string_lossy().to_string(),
+        priority: 0,
+    })
+}
+
+// The configured roots, or the default one, highest priority first
+fn roots_of(app: &AppHandle, config: &AppConfig) -> Result<Vec<LibraryRoot>, String> {
+    let mut roots = config.library_roots.clone();
+    if roots.is_empty() {
+        roots.push(default_root(app)?);
+    }
+    roots.sort_by_key(|root| std::cmp::Reverse(root.priority));
+    Ok(roots)
+}
+
+pub fn library_roots(app: &AppHandle) -> Result<Vec<LibraryRoot>, String> {
+    roots_of(app, &config::load(app))
+}
+
+// A root on a drive that isn't plugged in has neither its folder nor the
+// folder's parent; a fresh root only lacks the folder itself.
+fn is_reachable(root: &LibraryRoot) -> bool {
+    let path = Path::new(&root.path);
+    path.exists() || path.parent().is_some_and(Path::exists)
+}
+
+// Where new copies go: the first reachable root by priority
+pub fn primary_root(app: &AppHandle) -> Result<LibraryRoot, String> {
+    let roots = library_roots(app)?;
+    let primary = roots
+        .iter()
+        .find(|root| is_reachable(root))
+        .or(roots.first())
+        .cloned();
+    primary.ok_or_else(|| "No library root configured".to_string())
+}
+
+// `root_id` when given, otherwise the primary root
+pub fn find_root(app: &AppHandle, root_id: Option<&str>) -> Result<LibraryRoot, DrawStackError> {
+    let Some(root_id) = root_id else {
+        return Ok(primary_root(app)?);
+    };
+    library_roots(app)?
+        .into_iter()
+        .find(|root| root.id == root_id)
+        .ok_or_else(|| DrawStackError::invalid(format!("Unknown library root: {}", root_id)))
+}
+
+pub fn root_dir(app: &AppHandle, root_id: Option<&str>) -> Result<PathBuf, DrawStackError> {
+    Ok(PathBuf::from(find_root(app, root_id)?.path))
+}
+
+pub fn root_dirs(app: &AppHandle) -> Result<Vec<PathBuf>, String> {
+    Ok(library_roots(app)?
+        .into_iter()
+        .map(|root| PathBuf::from(root.path))
+        .collect())
+}
+
+// Point root `root_id` at `path`, writing out the default root first if the
+// config has none yet.
+pub fn set_root_path(
+    app: &AppHandle,
+    config: &mut AppConfig,
+    root_id: &str,
+    path: String,
+) -> Result<(), DrawStackError> {
+    if config.library_roots.is_empty() {
+        config.library_roots.push(default_root(app)?);
+    }
+    let root = config
+        .library_roots
+        .iter_mut()
+        .find(|root| root.id == root_id)
+        .ok_or_else(|| DrawStackError::invalid(format!("Unknown library root: {}", root_id)))?;
+    root.path = path;
+    Ok(())
+}
+
+// A new location for a root: the default library folder, or a folder the
+// user picked in a dialog
+pub fn check_root_path(app: &AppHandle, path: &str) -> Result<(), DrawStackError> {
+    if Path::new(path) == default_library_dir(app)? {
+        return Ok(());
+    }
+    scope::check_new_root(app, path)
+}
+
+// Roots may not be nested, or a file would belong to two of them
+pub fn check_overlap(roots: &[LibraryRoot], candidate: &LibraryRoot) -> Result<(), DrawStackError> {
+    let path = Path::new(&candidate.path);
+    if !path.is_absolute() {
+        return Err(DrawStackError::invalid(
+            "Library roots must be absolute paths",
+        ));
+    }
+    for root in roots.iter().filter(|r| r.id != candidate.id) {
+        let other = Path::new(&root.path);
+        if path.starts_with(other) || other.starts_with(path) {
+            return Err(DrawStackError::invalid(format!(
+                "{} overlaps the library root \"{}\"",
+                candidate.path, root.label
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_library_roots(app: AppHandle) -> Result<Vec<LibraryRoot>, DrawStackError> {
+    Ok(library_roots(&app)?)
+}
+
+#[tauri::command]
+pub fn add_library_root(
+    app: AppHandle,
+    label: String,
+    path: String,
+    priority: Option<i32>,
+) -> Result<LibraryRoot, DrawStackError> {
+    if label.trim().is_empty() {
+        return Err(DrawStackError::invalid("Library roots need a label"));
+    }
+    check_root_path(&app, &path)?;
+    let root = LibraryRoot {
+        id: crate::generate_uuid(),
+        label,
+        path,
+        priority: priority.unwrap_or(0),
+    };
+    config::update(&app, |config| {
+        if config.library_roots.is_empty() {
+            config.library_roots.push(default_root(&app)?);
+        }
+        check_overlap(&config.library_roots, &root)?;
+        config.library_roots.push(root.clone());
+        Ok(())
+    })?;
+    storage::invalidate(&app);
+    watcher::watch_library(&app);
+    Ok(root)
+}
+
+// Rename or reprioritize a root. Moving its files is `migrate_library`'s job,
+// so the path can only change while the root holds no catalog images.
+#[tauri::command]
+pub fn update_library_root(app: AppHandle, root: LibraryRoot) -> Result<(), DrawStackError> {
+    let current = find_root(&app, Some(&root.id))?;
+    if current.path != root.path {
+        check_root_path(&app, &root.path)?;
+    }
+    if current.path != root.path && images_in_root(&app, &current)? > 0 {
+        return Err(DrawStackError::Busy(format!(
+            "\"{}\" still holds library images - migrate them instead",
+            current.label
+        )));
+    }
+    config::update(&app, |config| {
+        if config.library_roots.is_empty() {
+            config.library_roots.push(default_root(&app)?);
+        }
+        check_overlap(&config.library_roots, &root)?;
+        if let Some(stored) = config.library_roots.iter_mut().find(|r| r.id == root.id) {
+            *stored = root.clone();
+        }
+        Ok(())
+    })?;
+    storage::invalidate(&app);
+    watcher::watch_library(&app);
+    Ok(())
+}
+
+// Forget a root. Refused while catalog images still live in it, and for the
+// last remaining root.
+#[tauri::command]
+pub fn remove_library_root(app: AppHandle, root_id: String) -> Result<(), DrawStackError> {
+    let roots = library_roots(&app)?;
+    let root = find_root(&app, Some(&root_id))?;
+    if roots.len() == 1 {
+        return Err(DrawStackError::invalid(
+            "The library needs at least one root",
+        ));
+    }
+    let images = images_in_root(&app, &root)?;
+    if images > 0 {
+        return Err(DrawStackError::Busy(format!(
+            "\"{}\" still holds {} library images",
+            root.label, images
+        )));
+    }
+    config::update(&app, |config| {
+        config.library_roots.retain(|r| r.id != root_id);
+        Ok(())
+    })?;
+    storage::invalidate(&app);
+    watcher::watch_library(&app);
+    Ok(())
+}
+
+fn images_in_root(app: &AppHandle, root: &LibraryRoot) -> Result<usize, String> {
+    let prefix = root.path.trim_end_matches(['/', '\\']);
+    catalog::open(app)?
+        .query_row(
+            "SELECT COUNT(*) FROM images
+             WHERE substr(library_path, 1, length(?1) + 1) IN (?1 || '/', ?1 || '\\')",
+            [prefix],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|count| count as usize)
+        .map_err(|e| format!("Failed to count library images: {}", e))
+}
@@ -0,0 +1,348 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::AppHandle;
+
+use crate::error::DrawStackError;
+use crate::{config, paths};
+
+// Deep enough for any real reference collection, shallow enough that a
+// runaway tree can't exhaust the stack
+const DEFAULT_MAX_DEPTH: usize = 64;
+
+// Tool and OS clutter that never holds reference images: VCS and package
+// folders, NAS and OS thumbnail caches, trash, and macOS resource forks
+const DEFAULT_IGNORE: &[&str] = &[
+    ".git",
+    "node_modules",
+    "@eaDir",
+    "#recycle",
+    "#snapshot",
+    "__MACOSX",
+    "$RECYCLE.BIN",
+    "System Volume Information",
+    "._*",
+];
+
+// Smaller files are icons, placeholders or broken downloads
+const DEFAULT_MIN_FILE_SIZE: u64 = 1024;
+
+// How folder scans pick what to import. Stored in the app config as `scan`.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ScanOptions {
+    // Descend into symlinked folders and import symlinked files. Windows
+    // junctions count as symlinks.
+    pub follow_symlinks: bool,
+    // Folder levels below the scan root; anything deeper is skipped
+    pub max_depth: usize,
+    // Globs matched against each file or folder name and its path relative
+    // to the scan root, e.g. "node_modules" or "refs/**/wip"
+    pub ignore: Vec<String>,
+    // Skip folders whose name starts with a dot
+    pub ignore_hidden: bool,
+    // Files smaller than this many bytes are skipped
+    pub min_file_size: u64,
+    // Check the content of files without an extension, so extensionless
+    // exports are found. Costs a small read per such file.
+    pub detect_by_content: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            follow_symlinks: true,
+            max_depth: DEFAULT_MAX_DEPTH,
+            ignore: DEFAULT_IGNORE.iter().map(|p| p.to_string()).collect(),
+            ignore_hidden: true,
+            min_file_size: DEFAULT_MIN_FILE_SIZE,
+            detect_by_content: true,
+        }
+    }
+}
+
+impl ScanOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        self.ignore_set().map(|_| ())
+    }
+
+    fn ignore_set(&self) -> Result<GlobSet, String> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.ignore {
+            let glob = Glob::new(pattern)
+                .map_err(|e| format!("Invalid ignore pattern {}: {}", pattern, e))?;
+            builder.add(glob);
+        }
+        builder
+            .build()
+            .map_err(|e| format!("Failed to build ignore patterns: {}", e))
+    }
+}
+
+// Whether a file found in a folder counts as an image: by its extension or,
+// with `by_content`, by its header when it has no extension. Files named as
+// anything else (sidecars, documents, archives) are never opened; misnamed
+// images under an image extension are listed by name and decoded by content
+// (see `sniff::misnamed`).
+pub fn is_listed_image(path: &Path, by_content: bool) -> bool {
+    if crate::is_supported_image(path) {
+        return true;
+    }
+    by_content && path.extension().is_none() && crate::sniff::image_type(path).is_some()
+}
+
+struct Walk<'a> {
+    options: &'a ScanOptions,
+    root: &'a Path,
+    ignore: GlobSet,
+    // Canonical paths of folders already scanned, so link loops terminate
+    // and a folder reachable through two links is only counted once
+    visited: HashSet<PathBuf>,
+    images: Vec<PathBuf>,
+    // The walk stops once this many images are found
+    limit: usize,
+    // Checked as each folder is entered; true abandons the walk
+    cancelled: &'a dyn Fn() -> bool,
+    // Every folder entered, with its modification time
+    folders: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl Walk<'_> {
+    fn is_ignored(&self, path: &Path) -> bool {
+        let name_matches = path.file_name().is_some_and(|n| self.ignore.is_match(n));
+        name_matches
+            || path
+                .strip_prefix(self.root)
+                .is_ok_and(|relative| self.ignore.is_match(relative))
+    }
+
+    fn is_image(&self, path: &Path) -> bool {
+        is_listed_image(path, self.options.detect_by_content)
+    }
+
+    fn scan(&mut self, path: &Path, depth: usize) -> Result<(), DrawStackError> {
+        if (self.cancelled)() {
+            return Err(DrawStackError::Other("Scan was cancelled".to_string()));
+        }
+        let canonical = fs::canonicalize(paths::extended(path))
+            .map_err(|e| DrawStackError::io("resolve folder", path, e))?;
+        if !self.visited.insert(canonical) {
+            tracing::debug!("Skipping already scanned folder: {}", path.display());
+            return Ok(());
+        }
+        self.folders.push((path.to_path_buf(), modified(path)));
+
+        let entries = fs::read_dir(paths::extended(path))
+            .map_err(|e| DrawStackError::io("read directory", path, e))?;
+
+        for entry in entries.flatten() {
+            if self.images.len() >= self.limit {
+                return Ok(());
+            }
+            // Joined onto `path` rather than taken from the entry, which would
+            // carry the extended-length prefix into the catalog
+            let entry_path = path.join(entry.file_name());
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_symlink() && !self.options.follow_symlinks {
+                continue;
+            }
+            if self.is_ignored(&entry_path) {
+                continue;
+            }
+
+            // Follows the link, if it is one
+            let Ok(meta) = fs::metadata(paths::extended(&entry_path)) else {
+                // Broken link
+                continue;
+            };
+
+            if meta.is_dir() {
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                if hidden && self.options.ignore_hidden {
+                    continue;
+                }
+                if depth >= self.options.max_depth {
+                    tracing::debug!("Skipping folder past max depth: {}", entry_path.display());
+                    continue;
+                }
+                self.scan(&entry_path, depth + 1)?;
+            } else if meta.is_file()
+                && meta.len() >= self.options.min_file_size
+                && self.is_image(&entry_path)
+            {
+                self.images.push(entry_path);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// When `path` last changed. A folder's time moves when an entry is added,
+// removed or renamed directly inside it.
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(paths::extended(path))
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+fn walk<'a>(
+    folder_path: &'a Path,
+    options: &'a ScanOptions,
+    limit: usize,
+    cancelled: &'a dyn Fn() -> bool,
+) -> Result<Walk<'a>, DrawStackError> {
+    if !paths::extended(folder_path).exists() {
+        return Err(DrawStackError::not_found(folder_path));
+    }
+
+    let mut walk = Walk {
+        options,
+        root: folder_path,
+        ignore: options.ignore_set()?,
+        visited: HashSet::new(),
+        images: Vec::new(),
+        limit,
+        cancelled,
+        folders: Vec::new(),
+    };
+    walk.scan(folder_path, 0)?;
+    Ok(walk)
+}
+
+// Supported images anywhere under `folder_path`
+pub fn scan_for_images(
+    folder_path: &Path,
+    options: &ScanOptions,
+) -> Result<Vec<PathBuf>, DrawStackError> {
+    walk(folder_path, options, usize::MAX, &|| false).map(|walk| walk.images)
+}
+
+pub struct ImageCount {
+    pub count: usize,
+    // Counting stopped at the limit
+    pub capped: bool,
+    // The folders walked, with their modification times, for telling when
+    // the count is out of date
+    pub folders: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+// Count what `scan_for_images` would find, stopping at `limit`. Errs if
+// `cancelled` turns true part way.
+pub fn count_images(
+    folder_path: &Path,
+    options: &ScanOptions,
+    limit: usize,
+    cancelled: &dyn Fn() -> bool,
+) -> Result<ImageCount, DrawStackError> {
+    let walk = walk(folder_path, options, limit, cancelled)?;
+    Ok(ImageCount {
+        count: walk.images.len(),
+        capped: walk.images.len() >= limit,
+        folders: walk.folders,
+    })
+}
+
+#[tauri::command]
+pub fn get_scan_settings(app: AppHandle) -> ScanOptions {
+    config::load(&app).scan
+}
+
+#[tauri::command]
+pub fn set_scan_settings(app: AppHandle, settings: ScanOptions) -> Result<(), DrawStackError> {
+    settings.validate().map_err(DrawStackError::invalid)?;
+    config::update(&app, |config| {
+        config.scan = settings;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::testing;
+
+    fn options() -> ScanOptions {
+        ScanOptions {
+            min_file_size: 0,
+            ..ScanOptions::default()
+        }
+    }
+
+    #[test]
+    fn finds_unicode_and_emoji_filenames() {
+        let root = testing::scratch_dir("scan-unicode");
+        let mut expected = Vec::new();
+        for name in testing::UNICODE_NAMES {
+            let dir = root.join(name);
+            fs::create_dir_all(&dir).unwrap();
+            let file = dir.join(format!("{}.png", name));
+            testing::write_png(&file);
+            expected.push(file);
+        }
+
+        let mut found = scan_for_images(&root, &options()).unwrap();
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn finds_files_past_max_path() {
+        let root = testing::scratch_dir("scan-long");
+        let dir = testing::deep_dir(&root, 300);
+        let file = dir.join("🎨 pose.png");
+        testing::write_png(&file);
+
+        // Found under its ordinary path, not the extended-length one
+        let found = scan_for_images(&root, &options()).unwrap();
+        assert_eq!(found, vec![file]);
+
+        fs::remove_dir_all(paths::extended(&root)).unwrap();
+    }
+
+    #[test]
+    fn counts_stop_at_the_limit() {
+        let root = testing::scratch_dir("scan-count");
+        for i in 0..5 {
+            testing::write_png(&root.join(format!("{}.png", i)));
+        }
+
+        let capped = count_images(&root, &options(), 3, &|| false).unwrap();
+        assert_eq!((capped.count, capped.capped), (3, true));
+        let full = count_images(&root, &options(), 10, &|| false).unwrap();
+        assert_eq!((full.count, full.capped), (5, false));
+        assert_eq!(full.folders.len(), 1);
+        assert!(count_images(&root, &options(), 10, &|| true).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn finds_extensionless_images_by_content() {
+        let root = testing::scratch_dir("scan-sniff");
+        let image = root.join("IMG_0042");
+        testing::write_png(&image);
+        fs::write(root.join("README"), "reference pack").unwrap();
+        // Named as something else, so never sniffed
+        testing::write_png(&root.join("layers.dat"));
+
+        let found = scan_for_images(&root, &options()).unwrap();
+        assert_eq!(found, vec![image]);
+
+        let by_name = ScanOptions {
+            detect_by_content: false,
+            ..options()
+        };
+        assert!(scan_for_images(&root, &by_name).unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_fs::FsExt;
+
+use crate::config;
+use crate::error::DrawStackError;
+
+// Folders the raw file commands may read and write: app data, the library
+// roots and any folders whitelisted in config. Missing folders are skipped,
+// as are roots too broad to be a restriction, e.g. from an older config.
+fn allowed_roots(app: &AppHandle) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Ok(library_dirs) = crate::roots::root_dirs(app) {
+        roots.extend(library_dirs);
+    }
+    roots.extend(
+        config::load(app)
+            .allowed_roots
+            .into_iter()
+            .map(PathBuf::from),
+    );
+    roots.retain(|root| !too_broad(app, root));
+    if let Ok(app_data) = app.path().app_data_dir() {
+        roots.push(app_data);
+    }
+
+    roots
+        .into_iter()
+        .filter_map(|root| fs::canonicalize(root).ok())
+        .collect()
+}
+
+// Resolve a path sent by the webview and make sure it lands inside an
+// allowed root, or is a file the user picked in a native open/save dialog
+// (the dialog plugin grants those to the fs scope). `..` components are
+// refused outright, and the path is canonicalized so a symlink can't point
+// outside the roots. A file that doesn't exist yet is resolved through its
+// parent folder.
+pub fn resolve(app: &AppHandle, path: &str) -> Result<PathBuf, DrawStackError> {
+    let requested = Path::new(path);
+    let out_of_scope = || DrawStackError::OutOfScope {
+        path: requested.to_path_buf(),
+    };
+
+    if !requested.is_absolute()
+        || requested
+            .components()
+            .any(|c| matches!(c, Component::ParentDir))
+    {
+        return Err(out_of_scope());
+    }
+
+    let resolved = match fs::canonicalize(requested) {
+        Ok(resolved) => resolved,
+        Err(_) => {
+            let parent = requested.parent().ok_or_else(out_of_scope)?;
+            let name = requested.file_name().ok_or_else(out_of_scope)?;
+            fs::canonicalize(parent)
+                .map_err(|e| DrawStackError::io("resolve", parent, e))?
+                .join(name)
+        }
+    };
+
+    let picked_by_user = app.fs_scope().is_allowed(requested);
+    if picked_by_user
+        || allowed_roots(app)
+            .iter()
+            .any(|root| resolved.starts_with(root))
+    {
+        Ok(resolved)
+    } else {
+        Err(out_of_scope())
+    }
+}
+
+// A folder so broad that whitelisting it would lift the restriction: a
+// drive or filesystem root, the home folder, or anything above it
+fn too_broad(app: &AppHandle, path: &Path) -> bool {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if path.parent().is_none() {
+        return true;
+    }
+    app.path()
+        .home_dir()
+        .ok()
+        .map(|home| fs::canonicalize(&home).unwrap_or(home))
+        .is_some_and(|home| home.starts_with(&path))
+}
+
+// Check a folder the webview wants to make a root (a library root or an
+// allowed root). It has to come from a native folder dialog, which grants
+// the pick to the fs scope, so a compromised webview can't name its own.
+pub fn check_new_root(app: &AppHandle, path: &str) -> Result<(), DrawStackError> {
+    let requested = Path::new(path);
+    let out_of_scope = || DrawStackError::OutOfScope {
+        path: requested.to_path_buf(),
+    };
+    if !requested.is_absolute()
+        || requested
+            .components()
+            .any(|c| matches!(c, Component::ParentDir))
+        || !app.fs_scope().is_allowed(requested)
+    {
+        return Err(out_of_scope());
+    }
+    if too_broad(app, requested) {
+        return Err(DrawStackError::invalid(format!(
+            "{} is too broad to use as a root - pick a folder inside it",
+            path
+        )));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_allowed_roots(app: AppHandle) -> Vec<String> {
+    config::load(&app).allowed_roots
+}
+
+// Whitelist a folder picked in a folder dialog for the file commands.
+// `update_config` refuses to touch the list.
+#[tauri::command]
+pub fn add_allowed_root(app: AppHandle, path: String) -> Result<Vec<String>, DrawStackError> {
+    check_new_root(&app, &path)?;
+    let config = config::update(&app, |config| {
+        if !config.allowed_roots.contains(&path) {
+            config.allowed_roots.push(path);
+        }
+        Ok(())
+    })?;
+    Ok(config.allowed_roots)
+}
+
+#[tauri::command]
+pub fn remove_allowed_root(app: AppHandle, path: String) -> Result<Vec<String>, DrawStackError> {
+    let config = config::update(&app, |config| {
+        config.allowed_roots.retain(|root| *root != path);
+        Ok(())
+    })?;
+    Ok(config.allowed_roots)
+}
@@ -0,0 +1,203 @@
+use rusqlite::{params_from_iter, types::Value};
+use tauri::AppHandle;
+
+use crate::catalog::{self, CatalogImage, ImageSort};
+use crate::error::DrawStackError;
+use crate::orientation::Aspect;
+use crate::{content_flag, palette};
+
+const DEFAULT_PAGE_SIZE: usize = 200;
+const MAX_PAGE_SIZE: usize = 1000;
+const DEFAULT_COLOR_DISTANCE: u32 = 60;
+
+#[derive(Debug, Default, serde::Deserialize, Clone)]
+#[serde(default)]
+pub struct SearchFilters {
+    pub pack_id: Option<String>,
+    pub min_rating: Option<u8>,
+    pub favorites_only: bool,
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub aspect: Option<Aspect>,
+    // Any of these `orientation::MEGAPIXEL_BUCKETS`
+    pub megapixel_buckets: Vec<u8>,
+    // Inclusive bounds on the EXIF capture date, compared as
+    // "YYYY-MM-DD[THH:MM:SS]" strings
+    pub captured_after: Option<String>,
+    pub captured_before: Option<String>,
+    // Images with a palette color near this hex color, within
+    // `color_distance` (RGB euclidean, default 60)
+    pub color: Option<String>,
+    pub color_distance: Option<u32>,
+    // Leave out images with a content flag, or in a flagged pack
+    pub exclude_flagged: bool,
+    pub sort: Option<ImageSort>,
+    pub page: usize,
+    pub page_size: Option<usize>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct SearchResults {
+    pub images: Vec<CatalogImage>,
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+// Turn free text into an FTS5 query: every word must match, each as a
+// prefix, with quotes escaped so user input can't inject FTS syntax.
+pub fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+pub fn build_conditions(query: &str, filters: &SearchFilters) -> (Vec<String>, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+
+    if let Some(matcher) = fts_query(query) {
+        conditions
+            .push("i.id IN (SELECT image_id FROM images_fts WHERE images_fts MATCH ?)".to_string());
+        values.push(Value::Text(matcher));
+    }
+    if let Some(pack_id) = &filters.pack_id {
+        conditions.push("i.pack_id = ?".to_string());
+        values.push(Value::Text(pack_id.clone()));
+    }
+    if let Some(rating) = filters.min_rating {
+        conditions.push("i.rating >= ?".to_string());
+        values.push(Value::Integer(rating as i64));
+    }
+    if filters.favorites_only {
+        conditions.push("i.favorite = 1".to_string());
+    }
+    if filters.exclude_flagged {
+        conditions.push(content_flag::UNFLAGGED.to_string());
+    }
+
+    let bounds = [
+        ("i.width >= ?", filters.min_width),
+        ("i.height >= ?", filters.min_height),
+        ("i.width <= ?", filters.max_width),
+        ("i.height <= ?", filters.max_height),
+    ];
+    for (condition, bound) in bounds {
+        if let Some(bound) = bound {
+            conditions.push(condition.to_string());
+            values.push(Value::Integer(bound as i64));
+        }
+    }
+
+    if let Some(aspect) = filters.aspect {
+        conditions.push("i.aspect = ?".to_string());
+        values.push(Value::Text(aspect.as_str().to_string()));
+    }
+    if !filters.megapixel_buckets.is_empty() {
+        let placeholders = vec!["?"; filters.megapixel_buckets.len()].join(", ");
+        conditions.push(format!("i.megapixel_bucket IN ({})", placeholders));
+        values.extend(
+            filters
+                .megapixel_buckets
+                .iter()
+                .map(|&bucket| Value::Integer(bucket as i64)),
+        );
+    }
+
+    if let Some(after) = &filters.captured_after {
+        conditions.push("i.captured_at >= ?".to_string());
+        values.push(Value::Text(after.clone()));
+    }
+    if let Some(before) = &filters.captured_before {
+        // A bare date should include the whole day
+        let before = if before.len() == 10 {
+            format!("{}T23:59:59", before)
+        } else {
+            before.clone()
+        };
+        conditions.push("i.captured_at <= ?".to_string());
+        values.push(Value::Text(before));
+    }
+
+    if let Some([r, g, b]) = filters.color.as_deref().and_then(palette::from_hex) {
+        // Only colors covering a noticeable part of the image count
+        conditions.push(
+            "i.id IN (SELECT image_id FROM image_palettes WHERE proportion >= 0.05
+             AND (r - ?) * (r - ?) + (g - ?) * (g - ?) + (b - ?) * (b - ?) <= ?)"
+                .to_string(),
+        );
+        for channel in [r, r, g, g, b, b] {
+            values.push(Value::Integer(channel as i64));
+        }
+        let distance = filters.color_distance.unwrap_or(DEFAULT_COLOR_DISTANCE) as i64;
+        values.push(Value::Integer(distance * distance));
+    }
+
+    (conditions, values)
+}
+
+#[tauri::command]
+pub async fn search_library(
+    app: AppHandle,
+    query: String,
+    filters: Option<SearchFilters>,
+) -> Result<SearchResults, DrawStackError> {
+    let filters = filters.unwrap_or_default();
+    let page_size = filters
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let (conditions, values) = build_conditions(&query, &filters);
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let conn = catalog::open(&app)?;
+
+    let total: usize = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM images i {}", where_clause),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count search results: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id
+             {} ORDER BY {} LIMIT ? OFFSET ?",
+            catalog::IMAGE_COLUMNS,
+            where_clause,
+            catalog::order_by(filters.sort, "i.pack_id, i.relative_path, i.filename")
+        ))
+        .map_err(|e| format!("Failed to prepare search: {}", e))?;
+
+    let paged = values.iter().cloned().chain([
+        Value::Integer(page_size as i64),
+        Value::Integer((filters.page * page_size) as i64),
+    ]);
+
+    let images = stmt
+        .query_map(params_from_iter(paged), catalog::map_image)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to search library: {}", e))?;
+
+    Ok(SearchResults {
+        images,
+        total,
+        page: filters.page,
+        page_size,
+    })
+}
@@ -0,0 +1,557 @@
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio_cues::{self, Cue};
+use crate::error::DrawStackError;
+
+const TICK: Duration = Duration::from_secs(1);
+const MAX_IMAGE_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct SessionImage {
+    pub id: String,
+    pub path: String,
+    // Clip length of a video reference. Its slot lasts at least this long,
+    // so the clip plays through once even on a short step.
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+}
+
+// One block of a class-mode schedule, e.g. 10 images at 60 seconds each
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct ScheduleStep {
+    pub count: usize,
+    pub duration_secs: u64,
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct SessionConfig {
+    pub images: Vec<SessionImage>,
+    // Class mode: steps run in order until the images run out. Without a
+    // schedule every image gets `image_duration_secs`.
+    #[serde(default)]
+    pub schedule: Vec<ScheduleStep>,
+    #[serde(default)]
+    pub image_duration_secs: Option<u64>,
+    #[serde(default)]
+    pub pack_id: Option<String>,
+    #[serde(default)]
+    pub breaks: Option<BreakSchedule>,
+}
+
+// Pomodoro-style rest, e.g. 5 minutes every 25 minutes of drawing. A break
+// starts once the image on screen finishes, so a pose is never cut short,
+// and the next image waits until it's over.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct BreakSchedule {
+    // Drawing time between breaks; paused time doesn't count
+    pub every_secs: u64,
+    pub duration_secs: u64,
+}
+
+impl BreakSchedule {
+    fn validate(&self) -> Result<(), String> {
+        for (field, secs) in [
+            ("every_secs", self.every_secs),
+            ("duration_secs", self.duration_secs),
+        ] {
+            if secs == 0 || secs > MAX_IMAGE_SECS {
+                return Err(format!(
+                    "Break {} must be between 1 and {} seconds, got {}",
+                    field, MAX_IMAGE_SECS, secs
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// One image slot in the expanded schedule
+#[derive(Debug, Clone)]
+struct Slot {
+    image: SessionImage,
+    duration: Duration,
+    step: usize,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ShownImage {
+    pub image_id: String,
+    pub shown_ms: u64,
+    pub skipped: bool,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct SessionState {
+    pub session_id: String,
+    pub index: usize,
+    pub total: usize,
+    pub image: Option<SessionImage>,
+    pub step: usize,
+    pub duration_ms: u64,
+    pub elapsed_ms: u64,
+    pub remaining_ms: u64,
+    pub paused: bool,
+    // During a break `image` is None, `index` is the image that follows it
+    // and the timings are the break's
+    pub on_break: bool,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct SessionStarted {
+    pub session_id: String,
+    pub total_images: usize,
+    pub total_duration_ms: u64,
+}
+
+// Payload of `session-break-start`
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct BreakStarted {
+    pub session_id: String,
+    pub duration_ms: u64,
+    // Index of the image shown when the break ends
+    pub next_index: usize,
+}
+
+// Payload of `session-break-end`
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct BreakEnded {
+    pub session_id: String,
+    // Time actually spent on the break, paused time excluded
+    pub break_ms: u64,
+    // Cut short with `skip_image`, or by ending the session
+    pub skipped: bool,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct SessionEnded {
+    pub session_id: String,
+    pub completed: bool,
+    pub pack_id: Option<String>,
+    pub started_at: i64,
+    // Time from start to end, breaks and pauses excluded
+    pub duration_ms: u64,
+    pub break_ms: u64,
+    pub breaks: usize,
+    pub images: Vec<ShownImage>,
+}
+
+enum SessionCommand {
+    Pause,
+    Resume,
+    Skip,
+    End,
+}
+
+struct ActiveSession {
+    id: String,
+    sender: mpsc::Sender<SessionCommand>,
+    state: Arc<Mutex<SessionState>>,
+}
+
+// The running practice session, if any. The timer itself lives on its own
+// thread and is driven by `Instant`, so it keeps time even when the webview
+// is throttled in the background.
+#[derive(Default)]
+pub struct SessionManager {
+    active: Mutex<Option<ActiveSession>>,
+}
+
+impl SessionManager {
+    fn send(&self, command: SessionCommand) -> Result<(), DrawStackError> {
+        let active = self.active.lock().unwrap();
+        let session = active
+            .as_ref()
+            .ok_or_else(|| DrawStackError::invalid("No session is running"))?;
+        session
+            .sender
+            .send(command)
+            .map_err(|_| DrawStackError::invalid("No session is running"))
+    }
+}
+
+pub fn validate_schedule(schedule: &[ScheduleStep]) -> Result<(), String> {
+    if let Some(step) = schedule
+        .iter()
+        .find(|s| s.duration_secs == 0 || s.duration_secs > MAX_IMAGE_SECS)
+    {
+        return Err(format!(
+            "Image duration must be between 1 and {} seconds, got {}",
+            MAX_IMAGE_SECS, step.duration_secs
+        ));
+    }
+    Ok(())
+}
+
+fn build_slots(config: &SessionConfig) -> Result<Vec<Slot>, DrawStackError> {
+    if config.images.is_empty() {
+        return Err(DrawStackError::invalid(
+            "A session needs at least one image",
+        ));
+    }
+    if let Some(breaks) = &config.breaks {
+        breaks.validate().map_err(DrawStackError::invalid)?;
+    }
+
+    let schedule = if config.schedule.is_empty() {
+        let secs = config
+            .image_duration_secs
+            .ok_or_else(|| DrawStackError::invalid("Set a schedule or an image duration"))?;
+        vec![ScheduleStep {
+            count: config.images.len(),
+            duration_secs: secs,
+        }]
+    } else {
+        config.schedule.clone()
+    };
+
+    validate_schedule(&schedule).map_err(DrawStackError::invalid)?;
+
+    let durations = schedule.iter().enumerate().flat_map(|(index, step)| {
+        std::iter::repeat_n((index, Duration::from_secs(step.duration_secs)), step.count)
+    });
+
+    Ok(config
+        .images
+        .iter()
+        .zip(durations)
+        .map(|(image, (step, duration))| Slot {
+            image: image.clone(),
+            duration: image
+                .duration_ms
+                .map_or(duration, |ms| duration.max(Duration::from_millis(ms))),
+            step,
+        })
+        .collect())
+}
+
+struct SessionRunner {
+    app: AppHandle,
+    id: String,
+    slots: Vec<Slot>,
+    pack_id: Option<String>,
+    breaks: Option<BreakSchedule>,
+    state: Arc<Mutex<SessionState>>,
+    receiver: mpsc::Receiver<SessionCommand>,
+    shown: Vec<ShownImage>,
+    // Time spent paused, on images and breaks alike
+    paused: Duration,
+}
+
+enum SlotOutcome {
+    Finished,
+    Skipped,
+    Ended,
+}
+
+// What the timer is counting down
+#[derive(Clone, Copy)]
+enum Phase {
+    Image(usize),
+    // Before the image at `next`
+    Break { next: usize, duration: Duration },
+}
+
+impl SessionRunner {
+    fn publish(&self, phase: Phase, elapsed: Duration, paused: bool) -> SessionState {
+        let (index, image, duration) = match phase {
+            Phase::Image(index) => {
+                let slot = &self.slots[index];
+                (index, Some(slot.image.clone()), slot.duration)
+            }
+            Phase::Break { next, duration } => (next, None, duration),
+        };
+        let elapsed = elapsed.min(duration);
+        let snapshot = SessionState {
+            session_id: self.id.clone(),
+            index,
+            total: self.slots.len(),
+            image,
+            step: self.slots[index].step,
+            duration_ms: duration.as_millis() as u64,
+            elapsed_ms: elapsed.as_millis() as u64,
+            remaining_ms: (duration - elapsed).as_millis() as u64,
+            paused,
+            on_break: matches!(phase, Phase::Break { .. }),
+        };
+        *self.state.lock().unwrap() = snapshot.clone();
+        snapshot
+    }
+
+    // Run one image until its time is up, it's skipped or the session ends.
+    // Returns how long the image was actually on screen.
+    fn run_slot(&mut self, index: usize) -> (SlotOutcome, Duration) {
+        let snapshot = self.publish(Phase::Image(index), Duration::ZERO, false);
+        let _ = self.app.emit("session-next-image", snapshot);
+        self.run_timer(Phase::Image(index), self.slots[index].duration)
+    }
+
+    // Rest before the image at `next`. Skipping ends the break early.
+    fn run_break(&mut self, next: usize, duration: Duration) -> (SlotOutcome, Duration) {
+        let phase = Phase::Break { next, duration };
+        self.publish(phase, Duration::ZERO, false);
+        let _ = self.app.emit(
+            "session-break-start",
+            BreakStarted {
+                session_id: self.id.clone(),
+                duration_ms: duration.as_millis() as u64,
+                next_index: next,
+            },
+        );
+
+        let (outcome, rested) = self.run_timer(phase, duration);
+        let _ = self.app.emit(
+            "session-break-end",
+            BreakEnded {
+                session_id: self.id.clone(),
+                break_ms: rested.as_millis() as u64,
+                skipped: !matches!(outcome, SlotOutcome::Finished),
+            },
+        );
+        (outcome, rested)
+    }
+
+    // Count `duration` down for `phase`, emitting a tick every second and
+    // following pause, resume, skip and end. Returns how much of it ran;
+    // time spent paused is added to `self.paused`.
+    fn run_timer(&mut self, phase: Phase, duration: Duration) -> (SlotOutcome, Duration) {
+        let mut shown = Duration::ZERO;
+        let mut running_since = Some(Instant::now());
+        let mut paused_since = None;
+        let mut next_tick = Instant::now() + TICK;
+
+        loop {
+            let now = Instant::now();
+            let elapsed = shown + running_since.map_or(Duration::ZERO, |since| now - since);
+            if running_since.is_some() && elapsed >= duration {
+                return (SlotOutcome::Finished, duration);
+            }
+
+            let command = match running_since {
+                Some(_) => {
+                    let deadline = next_tick.min(now + (duration - elapsed));
+                    match self
+                        .receiver
+                        .recv_timeout(deadline.saturating_duration_since(now))
+                    {
+                        Ok(command) => Some(command),
+                        Err(mpsc::RecvTimeoutError::Timeout) => None,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => Some(SessionCommand::End),
+                    }
+                }
+                // Paused: nothing to time, just wait for the next command
+                None => Some(self.receiver.recv().unwrap_or(SessionCommand::End)),
+            };
+
+            let now = Instant::now();
+            match command {
+                None => {
+                    if now >= next_tick {
+                        let elapsed =
+                            shown + running_since.map_or(Duration::ZERO, |since| now - since);
+                        let snapshot = self.publish(phase, elapsed, false);
+                        let _ = self.app.emit("session-tick", snapshot);
+                        next_tick += TICK;
+                    }
+                }
+                Some(SessionCommand::Pause) => {
+                    if let Some(since) = running_since.take() {
+                        shown += now - since;
+                        paused_since = Some(now);
+                        let snapshot = self.publish(phase, shown, true);
+                        let _ = self.app.emit("session-tick", snapshot);
+                    }
+                }
+                Some(SessionCommand::Resume) => {
+                    if running_since.is_none() {
+                        running_since = Some(now);
+                        self.paused += paused_since
+                            .take()
+                            .map_or(Duration::ZERO, |since| now - since);
+                        next_tick = now + TICK;
+                        let snapshot = self.publish(phase, shown, false);
+                        let _ = self.app.emit("session-tick", snapshot);
+                    }
+                }
+                Some(SessionCommand::Skip) => {
+                    shown += running_since.map_or(Duration::ZERO, |since| now - since);
+                    self.paused += paused_since.map_or(Duration::ZERO, |since| now - since);
+                    return (SlotOutcome::Skipped, shown.min(duration));
+                }
+                Some(SessionCommand::End) => {
+                    shown += running_since.map_or(Duration::ZERO, |since| now - since);
+                    self.paused += paused_since.map_or(Duration::ZERO, |since| now - since);
+                    return (SlotOutcome::Ended, shown.min(duration));
+                }
+            }
+        }
+    }
+
+    fn run(mut self) {
+        let started_at = crate::catalog::now_unix();
+        let started = Instant::now();
+        let mut completed = true;
+        // Drawing time since the last break
+        let mut since_break = Duration::ZERO;
+        let mut break_time = Duration::ZERO;
+        let mut breaks = 0;
+
+        for index in 0..self.slots.len() {
+            let (outcome, shown) = self.run_slot(index);
+            self.shown.push(ShownImage {
+                image_id: self.slots[index].image.id.clone(),
+                shown_ms: shown.as_millis() as u64,
+                skipped: matches!(outcome, SlotOutcome::Skipped),
+            });
+            if matches!(outcome, SlotOutcome::Ended) {
+                completed = false;
+                break;
+            }
+
+            let next = index + 1;
+            if matches!(outcome, SlotOutcome::Finished) && next < self.slots.len() {
+                audio_cues::play(&self.app, Cue::IntervalEnd);
+            }
+
+            since_break += shown;
+            let due = self.breaks.as_ref().filter(|breaks| {
+                next < self.slots.len() && since_break >= Duration::from_secs(breaks.every_secs)
+            });
+            if let Some(duration) = due.map(|breaks| Duration::from_secs(breaks.duration_secs)) {
+                let (outcome, rested) = self.run_break(next, duration);
+                since_break = Duration::ZERO;
+                break_time += rested;
+                breaks += 1;
+                match outcome {
+                    SlotOutcome::Ended => {
+                        completed = false;
+                        break;
+                    }
+                    // Call the user back to the board
+                    SlotOutcome::Finished => audio_cues::play(&self.app, Cue::IntervalEnd),
+                    SlotOutcome::Skipped => {}
+                }
+            }
+        }
+
+        if completed {
+            audio_cues::play(&self.app, Cue::SessionEnd);
+        }
+
+        // Only clear the slot if a newer session hasn't replaced us
+        {
+            let manager = self.app.state::<SessionManager>();
+            let mut active = manager.active.lock().unwrap();
+            if active.as_ref().is_some_and(|s| s.id == self.id) {
+                *active = None;
+            }
+        }
+
+        let ended = SessionEnded {
+            session_id: self.id.clone(),
+            completed,
+            pack_id: self.pack_id.clone(),
+            started_at,
+            duration_ms: started
+                .elapsed()
+                .saturating_sub(break_time + self.paused)
+                .as_millis() as u64,
+            break_ms: break_time.as_millis() as u64,
+            breaks,
+            images: std::mem::take(&mut self.shown),
+        };
+        if let Err(e) = crate::practice::record_session(&self.app, &ended) {
+            tracing::error!("Failed to save session history: {}", e);
+        }
+        tracing::info!(
+            "Session {} ended after {} images",
+            ended.session_id,
+            ended.images.len()
+        );
+        let _ = self.app.emit("session-ended", ended);
+    }
+}
+
+#[tauri::command]
+pub fn start_session(
+    app: AppHandle,
+    config: SessionConfig,
+) -> Result<SessionStarted, DrawStackError> {
+    let slots = build_slots(&config)?;
+    let manager = app.state::<SessionManager>();
+    let mut active = manager.active.lock().unwrap();
+    if active.is_some() {
+        return Err(DrawStackError::Busy(
+            "A session is already running - end it first".to_string(),
+        ));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let (sender, receiver) = mpsc::channel();
+    let state = Arc::new(Mutex::new(SessionState {
+        session_id: id.clone(),
+        index: 0,
+        total: slots.len(),
+        image: None,
+        step: 0,
+        duration_ms: 0,
+        elapsed_ms: 0,
+        remaining_ms: 0,
+        paused: false,
+        on_break: false,
+    }));
+
+    let started = SessionStarted {
+        session_id: id.clone(),
+        total_images: slots.len(),
+        total_duration_ms: slots.iter().map(|s| s.duration.as_millis() as u64).sum(),
+    };
+
+    let runner = SessionRunner {
+        app: app.clone(),
+        id: id.clone(),
+        slots,
+        pack_id: config.pack_id,
+        breaks: config.breaks,
+        state: state.clone(),
+        receiver,
+        shown: Vec::new(),
+        paused: Duration::ZERO,
+    };
+    thread::Builder::new()
+        .name("session-timer".to_string())
+        .spawn(move || runner.run())
+        .map_err(|e| format!("Failed to start session timer: {}", e))?;
+
+    *active = Some(ActiveSession { id, sender, state });
+    Ok(started)
+}
+
+#[tauri::command]
+pub fn pause_session(app: AppHandle) -> Result<(), DrawStackError> {
+    app.state::<SessionManager>().send(SessionCommand::Pause)
+}
+
+#[tauri::command]
+pub fn resume_session(app: AppHandle) -> Result<(), DrawStackError> {
+    app.state::<SessionManager>().send(SessionCommand::Resume)
+}
+
+#[tauri::command]
+pub fn skip_image(app: AppHandle) -> Result<(), DrawStackError> {
+    app.state::<SessionManager>().send(SessionCommand::Skip)
+}
+
+#[tauri::command]
+pub fn end_session(app: AppHandle) -> Result<(), DrawStackError> {
+    app.state::<SessionManager>().send(SessionCommand::End)
+}
+
+#[tauri::command]
+pub fn get_session_state(app: AppHandle) -> Option<SessionState> {
+    let manager = app.state::<SessionManager>();
+    let active = manager.active.lock().unwrap();
+    active.as_ref().map(|s| s.state.lock().unwrap().clone())
+}
@@ -0,0 +1,157 @@
+// Named class schedules for the session timer. Saved presets live in the app
+// config, so backups carry them to other machines; the standard ones are
+// built in and never written out.
+use tauri::AppHandle;
+
+use crate::config;
+use crate::error::DrawStackError;
+use crate::natural;
+use crate::session::{self, ScheduleStep};
+
+const MAX_NAME_LEN: usize = 100;
+const MAX_PRESETS: usize = 200;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct SessionPreset {
+    pub name: String,
+    pub schedule: Vec<ScheduleStep>,
+    // Shipped with the app; can't be replaced or deleted. Never read from
+    // the config, so a saved preset can't claim to be one.
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+fn step(count: usize, duration_secs: u64) -> ScheduleStep {
+    ScheduleStep {
+        count,
+        duration_secs,
+    }
+}
+
+fn builtin(name: &str, schedule: Vec<ScheduleStep>) -> SessionPreset {
+    SessionPreset {
+        name: name.to_string(),
+        schedule,
+        builtin: true,
+    }
+}
+
+// Sessions stop when the images run out, so the counts are upper bounds
+fn builtin_presets() -> Vec<SessionPreset> {
+    vec![
+        builtin("30s gestures", vec![step(60, 30)]),
+        builtin(
+            "Class mode (1/2/5 min)",
+            vec![step(10, 60), step(5, 2 * 60), step(3, 5 * 60)],
+        ),
+        builtin("Long pose", vec![step(1, 30 * 60)]),
+    ]
+}
+
+// Names are compared ignoring case and surrounding spaces
+fn same_name(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+fn is_builtin(name: &str) -> bool {
+    builtin_presets()
+        .iter()
+        .any(|preset| same_name(&preset.name, name))
+}
+
+fn validate(preset: &SessionPreset) -> Result<(), String> {
+    let name = preset.name.trim();
+    if name.is_empty() {
+        return Err("Preset name can't be empty".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(format!(
+            "Preset name must be at most {} characters",
+            MAX_NAME_LEN
+        ));
+    }
+    if preset.schedule.is_empty() || preset.schedule.iter().any(|step| step.count == 0) {
+        return Err(format!(
+            "Preset {} needs at least one step, each with at least one image",
+            name
+        ));
+    }
+    session::validate_schedule(&preset.schedule)
+}
+
+// Checks the saved presets as a whole, for `update_config`
+pub fn validate_all(presets: &[SessionPreset]) -> Result<(), String> {
+    if presets.len() > MAX_PRESETS {
+        return Err(format!("At most {} session presets", MAX_PRESETS));
+    }
+    presets.iter().try_for_each(validate)
+}
+
+// Built-in presets first, then saved ones by name
+#[tauri::command]
+pub fn list_session_presets(app: AppHandle) -> Vec<SessionPreset> {
+    let mut saved = config::load(&app).session_presets;
+    saved.sort_by(|a, b| natural::compare(&a.name, &b.name));
+    builtin_presets().into_iter().chain(saved).collect()
+}
+
+// Save `schedule` as `name`, replacing a saved preset of the same name
+#[tauri::command]
+pub fn save_session_preset(
+    app: AppHandle,
+    name: String,
+    schedule: Vec<ScheduleStep>,
+) -> Result<SessionPreset, DrawStackError> {
+    let preset = SessionPreset {
+        name: name.trim().to_string(),
+        schedule,
+        builtin: false,
+    };
+    validate(&preset).map_err(DrawStackError::invalid)?;
+    if is_builtin(&preset.name) {
+        return Err(DrawStackError::invalid(format!(
+            "{} is a built-in preset; pick another name",
+            preset.name
+        )));
+    }
+
+    config::update(&app, |config| {
+        let presets = &mut config.session_presets;
+        let existing = presets
+            .iter()
+            .position(|saved| same_name(&saved.name, &preset.name));
+        match existing {
+            Some(index) => presets[index] = preset.clone(),
+            None if presets.len() >= MAX_PRESETS => {
+                return Err(DrawStackError::invalid(format!(
+                    "At most {} session presets",
+                    MAX_PRESETS
+                )));
+            }
+            None => presets.push(preset.clone()),
+        }
+        Ok(())
+    })?;
+    Ok(preset)
+}
+
+#[tauri::command]
+pub fn delete_session_preset(app: AppHandle, name: String) -> Result<(), DrawStackError> {
+    if is_builtin(&name) {
+        return Err(DrawStackError::invalid(format!(
+            "{} is a built-in preset",
+            name
+        )));
+    }
+    config::update(&app, |config| {
+        let before = config.session_presets.len();
+        config
+            .session_presets
+            .retain(|saved| !same_name(&saved.name, &name));
+        if config.session_presets.len() == before {
+            return Err(DrawStackError::invalid(format!("No preset named {}", name)));
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use rusqlite::{params, params_from_iter, types::Value, OptionalExtension};
+use tauri::AppHandle;
+
+use crate::catalog::{self, CatalogImage, ImageSort};
+use crate::error::DrawStackError;
+use crate::search;
+
+// A saved query. Rules nest, so "tag:hands AND rating>=4" is
+// {"type":"all","rules":[{"type":"tag","name":"hands"},{"type":"rating","min":4}]}
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Rule {
+    All {
+        rules: Vec<Rule>,
+    },
+    Any {
+        rules: Vec<Rule>,
+    },
+    Not {
+        rule: Box<Rule>,
+    },
+    Tag {
+        name: String,
+    },
+    Text {
+        query: String,
+    },
+    Pack {
+        id: String,
+    },
+    Rating {
+        min: Option<u8>,
+        max: Option<u8>,
+    },
+    Favorite,
+    Width {
+        min: Option<u32>,
+        max: Option<u32>,
+    },
+    Height {
+        min: Option<u32>,
+        max: Option<u32>,
+    },
+    Captured {
+        after: Option<String>,
+        before: Option<String>,
+    },
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct SmartCollection {
+    pub id: String,
+    pub name: String,
+    pub rule: Rule,
+    pub created_at: i64,
+}
+
+fn range(column: &str, min: Option<i64>, max: Option<i64>, values: &mut Vec<Value>) -> String {
+    let mut parts = Vec::new();
+    if let Some(min) = min {
+        parts.push(format!("{} >= ?", column));
+        values.push(Value::Integer(min));
+    }
+    if let Some(max) = max {
+        parts.push(format!("{} <= ?", column));
+        values.push(Value::Integer(max));
+    }
+    if parts.is_empty() {
+        "1".to_string()
+    } else {
+        format!("({})", parts.join(" AND "))
+    }
+}
+
+// Compile a rule into a WHERE fragment over `images i`, pushing bound
+// values in the order their placeholders appear.
+fn compile(rule: &Rule, values: &mut Vec<Value>) -> String {
+    match rule {
+        Rule::All { rules } | Rule::Any { rules } => {
+            if rules.is_empty() {
+                // An empty AND matches everything, an empty OR nothing
+                return if matches!(rule, Rule::All { .. }) {
+                    "1"
+                } else {
+                    "0"
+                }
+                .to_string();
+            }
+            let joiner = if matches!(rule, Rule::All { .. }) {
+                " AND "
+            } else {
+                " OR "
+            };
+            let parts: Vec<String> = rules.iter().map(|r| compile(r, values)).collect();
+            format!("({})", parts.join(joiner))
+        }
+        Rule::Not { rule } => format!("NOT {}", compile(rule, values)),
+        Rule::Tag { name } => {
+            values.push(Value::Text(name.trim().to_string()));
+            "i.id IN (SELECT it.image_id FROM image_tags it
+                      JOIN tags tg ON tg.id = it.tag_id WHERE tg.name = ?)"
+                .to_string()
+        }
+        Rule::Text { query } => match search::fts_query(query) {
+            Some(matcher) => {
+                values.push(Value::Text(matcher));
+                "i.id IN (SELECT image_id FROM images_fts WHERE images_fts MATCH ?)".to_string()
+            }
+            None => "1".to_string(),
+        },
+        Rule::Pack { id } => {
+            values.push(Value::Text(id.clone()));
+            "i.pack_id = ?".to_string()
+        }
+        Rule::Rating { min, max } => {
+            range("i.rating", min.map(i64::from), max.map(i64::from), values)
+        }
+        Rule::Favorite => "i.favorite = 1".to_string(),
+        Rule::Width { min, max } => {
+            range("i.width", min.map(i64::from), max.map(i64::from), values)
+        }
+        Rule::Height { min, max } => {
+            range("i.height", min.map(i64::from), max.map(i64::from), values)
+        }
+        Rule::Captured { after, before } => {
+            let mut parts = Vec::new();
+            if let Some(after) = after {
+                parts.push("i.captured_at >= ?");
+                values.push(Value::Text(after.clone()));
+            }
+            if let Some(before) = before {
+                parts.push("i.captured_at <= ?");
+                values.push(Value::Text(if before.len() == 10 {
+                    format!("{}T23:59:59", before)
+                } else {
+                    before.clone()
+                }));
+            }
+            if parts.is_empty() {
+                "1".to_string()
+            } else {
+                format!("({})", parts.join(" AND "))
+            }
+        }
+    }
+}
+
+fn parse_rule(rule_json: &str) -> Result<Rule, String> {
+    serde_json::from_str(rule_json).map_err(|e| format!("Invalid collection rule: {}", e))
+}
+
+#[tauri::command]
+pub async fn create_smart_collection(
+    app: AppHandle,
+    name: String,
+    rule_json: String,
+) -> Result<SmartCollection, DrawStackError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(DrawStackError::invalid("Collection name cannot be empty"));
+    }
+    let rule = parse_rule(&rule_json)?;
+
+    let collection = SmartCollection {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        rule,
+        created_at: catalog::now_unix(),
+    };
+    let stored_rule = serde_json::to_string(&collection.rule)
+        .map_err(|e| format!("Failed to serialize rule: {}", e))?;
+
+    let conn = catalog::open(&app)?;
+    conn.execute(
+        "INSERT INTO smart_collections (id, name, rule, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            collection.id,
+            collection.name,
+            stored_rule,
+            collection.created_at
+        ],
+    )
+    .map_err(|e| format!("Failed to save collection: {}", e))?;
+
+    Ok(collection)
+}
+
+#[tauri::command]
+pub async fn list_smart_collections(
+    app: AppHandle,
+) -> Result<Vec<SmartCollection>, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let mut stmt = conn
+        .prepare("SELECT id, name, rule, created_at FROM smart_collections ORDER BY name")
+        .map_err(|e| format!("Failed to prepare collection query: {}", e))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read collections: {}", e))?;
+
+    let mut collections = Vec::new();
+    for (id, name, rule, created_at) in rows {
+        match parse_rule(&rule) {
+            Ok(rule) => collections.push(SmartCollection {
+                id,
+                name,
+                rule,
+                created_at,
+            }),
+            Err(e) => tracing::warn!("Skipping collection {}: {}", id, e),
+        }
+    }
+
+    Ok(collections)
+}
+
+#[tauri::command]
+pub async fn evaluate_smart_collection(
+    app: AppHandle,
+    id: String,
+    sort: Option<ImageSort>,
+) -> Result<Vec<CatalogImage>, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let rule_json: String = conn
+        .query_row(
+            "SELECT rule FROM smart_collections WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read collection: {}", e))?
+        .ok_or_else(|| format!("Collection not found: {}", id))?;
+    let rule = parse_rule(&rule_json)?;
+
+    let mut values = Vec::new();
+    let condition = compile(&rule, &mut values);
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM images i LEFT JOIN thumbnails t ON t.image_id = i.id
+             WHERE {} ORDER BY {}",
+            catalog::IMAGE_COLUMNS,
+            condition,
+            catalog::order_by(sort, "i.pack_id, i.relative_path, i.filename")
+        ))
+        .map_err(|e| format!("Failed to prepare collection query: {}", e))?;
+
+    stmt.query_map(params_from_iter(values), catalog::map_image)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(DrawStackError::from)
+}
+
+#[tauri::command]
+pub async fn delete_smart_collection(app: AppHandle, id: String) -> Result<(), DrawStackError> {
+    let conn = catalog::open(&app)?;
+    conn.execute("DELETE FROM smart_collections WHERE id = ?1", params![id])
+        .map_err(|e| format!("Failed to delete collection: {}", e))?;
+    Ok(())
+}
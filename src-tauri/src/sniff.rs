@@ -0,0 +1,63 @@
+// Identifying images by their content. Extensions lie: Dropbox and chat
+// exports drop them, and plenty of "photo.jpg" files are PNGs. The magic
+// bytes at the start of the file settle it.
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// Enough for every signature `infer` checks
+const HEADER_LEN: u64 = 8192;
+
+// The format `path` holds, if its content is one DrawStack can read. The
+// type's `extension()` is canonical lowercase ("jpg", never "jpeg").
+pub fn image_type(path: &Path) -> Option<infer::Type> {
+    let mut header = Vec::with_capacity(HEADER_LEN as usize);
+    File::open(crate::paths::extended(path))
+        .ok()?
+        .take(HEADER_LEN)
+        .read_to_end(&mut header)
+        .ok()?;
+
+    let kind = infer::get(&header)?;
+    let media = matches!(
+        kind.matcher_type(),
+        infer::MatcherType::Image | infer::MatcherType::Video
+    );
+    (media && crate::is_supported_extension(kind.extension())).then_some(kind)
+}
+
+// The sniffed extension when it names a different format than the file's
+// own, e.g. "png" for a PNG saved as .jpg. `jpg` and `jpeg` count as one.
+pub fn misnamed(path: &Path) -> Option<&'static str> {
+    let actual = image_type(path)?.extension();
+    let named = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let same = named.eq_ignore_ascii_case(actual)
+        || (actual == "jpg" && named.eq_ignore_ascii_case("jpeg"));
+    (!same).then_some(actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::testing;
+
+    #[test]
+    fn detects_misnamed_and_extensionless_images() {
+        let root = testing::scratch_dir("sniff");
+        let misnamed_png = root.join("pose.JPG");
+        let bare = root.join("dropbox-export");
+        testing::write_png(&misnamed_png);
+        testing::write_png(&bare);
+        std::fs::write(root.join("notes"), b"not an image at all").unwrap();
+
+        assert_eq!(misnamed(&misnamed_png), Some("png"));
+        assert_eq!(image_type(&bare).map(|t| t.extension()), Some("png"));
+        assert!(image_type(&root.join("notes")).is_none());
+
+        let decoded = crate::decode_image_raw(&misnamed_png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (64, 48));
+        assert!(crate::decode_image_raw(&bare).is_ok());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+// Reading import sources that may sit on a network share. A hiccuping SMB
+// or NFS mount can block a read forever, so before a file is decoded it is
+// read through once on a helper thread that must keep making progress.
+// Transient network errors are retried with backoff; the decoder's own reads
+// then come from the OS cache.
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+// A read that delivers no data for this long counts as hung. The helper
+// thread is abandoned; it exits whenever the OS gives up on the read.
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const MAX_RETRIES: u32 = 3;
+// Doubled after every failed attempt
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+const READ_CHUNK: usize = 256 * 1024;
+
+// A batch whose reads average this long, or needed a retry, marks the source
+// as slow; the import then drops to SLOW_SOURCE_THREADS workers so the share
+// isn't buried under parallel requests.
+pub const SLOW_FILE_READ: Duration = Duration::from_secs(1);
+pub const SLOW_SOURCE_THREADS: usize = 2;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadStats {
+    pub elapsed: Duration,
+    pub retries: u32,
+}
+
+// Errors worth another try: the share dropped out rather than the file
+// being bad
+fn is_transient(e: &io::Error) -> bool {
+    #[cfg(windows)]
+    {
+        // ERROR_BAD_NETPATH, ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED,
+        // ERROR_BAD_NET_NAME, ERROR_NETWORK_UNREACHABLE
+        if matches!(e.raw_os_error(), Some(53 | 59 | 64 | 67 | 1231)) {
+            return true;
+        }
+    }
+    matches!(
+        e.kind(),
+        io::ErrorKind::TimedOut
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::NetworkDown
+            | io::ErrorKind::StaleNetworkFileHandle
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe
+    )
+}
+
+// Read `path` to the end on a helper thread, failing with TimedOut if it
+// stalls for STALL_TIMEOUT
+fn read_through(path: &Path) -> io::Result<()> {
+    let progress = Arc::new(AtomicU64::new(0));
+    let (tx, rx) = mpsc::channel();
+    let owned = path.to_path_buf();
+    let reader_progress = Arc::clone(&progress);
+    std::thread::Builder::new()
+        .name("source-read".into())
+        .spawn(move || {
+            let result = fs::File::open(crate::paths::extended(&owned)).and_then(|mut file| {
+                let mut buf = vec![0; READ_CHUNK];
+                loop {
+                    match file.read(&mut buf) {
+                        Ok(0) => return Ok(()),
+                        Ok(n) => reader_progress.fetch_add(n as u64, Ordering::Relaxed),
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e),
+                    };
+                }
+            });
+            let _ = tx.send(result);
+        })?;
+
+    let mut last_bytes = 0;
+    let mut last_progress = Instant::now();
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(result) => return result,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(io::Error::other("Reader thread exited"));
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+        }
+        let bytes = progress.load(Ordering::Relaxed);
+        if bytes != last_bytes {
+            last_bytes = bytes;
+            last_progress = Instant::now();
+        } else if last_progress.elapsed() >= STALL_TIMEOUT {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("No data for {}s", STALL_TIMEOUT.as_secs()),
+            ));
+        }
+    }
+}
+
+// Read a source file through, retrying transient failures. Errs only when
+// the file stayed unreachable: a missing source root (an unmounted share),
+// a stalled read or a network error on every attempt. Other errors, such as
+// a deleted or locked file, are left for the decoder to report.
+pub fn prefetch(path: &Path, source_root: &Path) -> Result<ReadStats, String> {
+    let start = Instant::now();
+    let mut backoff = RETRY_BACKOFF;
+    let mut retries = 0;
+
+    loop {
+        let error = match read_through(path) {
+            Ok(()) => {
+                return Ok(ReadStats {
+                    elapsed: start.elapsed(),
+                    retries,
+                })
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !source_root.exists() => {
+                format!("Source folder {} is unreachable", source_root.display())
+            }
+            Err(e) if is_transient(&e) => e.to_string(),
+            Err(_) => {
+                return Ok(ReadStats {
+                    elapsed: start.elapsed(),
+                    retries,
+                })
+            }
+        };
+
+        if retries == MAX_RETRIES {
+            return Err(format!(
+                "{} (gave up after {} attempts)",
+                error,
+                MAX_RETRIES + 1
+            ));
+        }
+        tracing::debug!(
+            "Retrying {} in {}ms: {}",
+            path.display(),
+            backoff.as_millis(),
+            error
+        );
+        std::thread::sleep(backoff);
+        backoff *= 2;
+        retries += 1;
+    }
+}
+
+// Whether a batch's reads point at a slow or flaky source
+pub fn is_slow(reads: &[ReadStats]) -> bool {
+    if reads.is_empty() {
+        return false;
+    }
+    let total: Duration = reads.iter().map(|read| read.elapsed).sum();
+    reads.iter().any(|read| read.retries > 0) || total / reads.len() as u32 >= SLOW_FILE_READ
+}
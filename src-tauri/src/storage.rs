@@ -0,0 +1,258 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DrawStackError;
+use crate::format_bytes;
+use crate::jobs::JobHandle;
+use crate::roots::{self, LibraryRoot};
+
+// Library scans are expensive on big libraries; reuse a result for this long
+const CACHE_TTL: Duration = Duration::from_secs(60);
+const PROGRESS_EVERY: usize = 500;
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct StorageInfo {
+    used_bytes: u64,
+    used_formatted: String,
+    total_bytes: Option<u64>,
+    total_formatted: Option<String>,
+    free_bytes: Option<u64>,
+    free_formatted: Option<String>,
+    usage_percentage: Option<f32>,
+    file_count: usize,
+    // One entry per library root; the totals above add up their files, with
+    // disk space for the primary root's volume
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    roots: Vec<RootStorage>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct RootStorage {
+    id: String,
+    label: String,
+    path: String,
+    #[serde(flatten)]
+    usage: StorageInfo,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct StorageScanProgress {
+    files_scanned: usize,
+    bytes_so_far: u64,
+}
+
+struct CachedUsage {
+    // Every root's path, so adding or moving a root misses the cache
+    library_key: String,
+    measured_at: Instant,
+    info: StorageInfo,
+}
+
+// Last storage measurement, shared across invocations.
+#[derive(Default)]
+pub struct StorageCache {
+    inner: Mutex<Option<CachedUsage>>,
+}
+
+impl StorageCache {
+    fn get(&self, library_key: &str) -> Option<StorageInfo> {
+        let cached = self.inner.lock().unwrap();
+        cached
+            .as_ref()
+            .filter(|c| c.library_key == library_key && c.measured_at.elapsed() < CACHE_TTL)
+            .map(|c| c.info.clone())
+    }
+
+    fn store(&self, library_key: String, info: StorageInfo) {
+        *self.inner.lock().unwrap() = Some(CachedUsage {
+            library_key,
+            measured_at: Instant::now(),
+            info,
+        });
+    }
+}
+
+// Drop the cached measurement after anything that changes the library.
+pub fn invalidate(app: &AppHandle) {
+    if let Some(cache) = app.try_state::<StorageCache>() {
+        *cache.inner.lock().unwrap() = None;
+    }
+}
+
+// Total size and file count under `root`, walked iteratively so deep trees
+// can't overflow the stack. `on_progress` is called every PROGRESS_EVERY
+// files and can stop the walk by returning false.
+fn dir_size(root: &Path, on_progress: &mut dyn FnMut(usize, u64) -> bool) -> Option<(u64, usize)> {
+    let mut size = 0u64;
+    let mut files = 0usize;
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                pending.push(entry.path());
+                continue;
+            }
+
+            size += meta.len();
+            files += 1;
+            if files.is_multiple_of(PROGRESS_EVERY) && !on_progress(files, size) {
+                return None;
+            }
+        }
+    }
+
+    Some((size, files))
+}
+
+fn measure(
+    library_path: &str,
+    on_progress: &mut dyn FnMut(usize, u64) -> bool,
+) -> Option<StorageInfo> {
+    let library_dir = Path::new(library_path);
+
+    let (used_bytes, file_count) = if library_dir.exists() {
+        dir_size(library_dir, on_progress)?
+    } else {
+        (0, 0)
+    };
+
+    // Disk totals for the volume holding the library (statvfs on Unix,
+    // GetDiskFreeSpaceEx on Windows). The library folder may not exist yet,
+    // so ask about its nearest existing ancestor.
+    let volume_path = library_dir.ancestors().find(|p| p.exists());
+    let total_bytes = volume_path.and_then(|p| fs2::total_space(p).ok());
+    let free_bytes = volume_path.and_then(|p| fs2::available_space(p).ok());
+    let usage_percentage = total_bytes
+        .filter(|&total| total > 0)
+        .map(|total| (used_bytes as f32 / total as f32) * 100.0);
+
+    Some(StorageInfo {
+        used_bytes,
+        used_formatted: format_bytes(used_bytes),
+        total_bytes,
+        total_formatted: total_bytes.map(format_bytes),
+        free_bytes,
+        free_formatted: free_bytes.map(format_bytes),
+        usage_percentage,
+        file_count,
+        roots: Vec::new(),
+    })
+}
+
+fn library_key(roots: &[LibraryRoot]) -> String {
+    roots
+        .iter()
+        .map(|root| root.path.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Measure every root in priority order. Progress counts run across roots.
+fn measure_roots(
+    roots: &[LibraryRoot],
+    on_progress: &mut dyn FnMut(usize, u64) -> bool,
+) -> Option<StorageInfo> {
+    let mut measured = Vec::with_capacity(roots.len());
+    let (mut files_before, mut bytes_before) = (0, 0);
+    for root in roots {
+        let usage = measure(&root.path, &mut |files, bytes| {
+            on_progress(files_before + files, bytes_before + bytes)
+        })?;
+        files_before += usage.file_count;
+        bytes_before += usage.used_bytes;
+        measured.push(RootStorage {
+            id: root.id.clone(),
+            label: root.label.clone(),
+            path: root.path.clone(),
+            usage,
+        });
+    }
+
+    let primary = &measured.first()?.usage;
+    let used_bytes = bytes_before;
+    Some(StorageInfo {
+        used_bytes,
+        used_formatted: format_bytes(used_bytes),
+        total_bytes: primary.total_bytes,
+        total_formatted: primary.total_formatted.clone(),
+        free_bytes: primary.free_bytes,
+        free_formatted: primary.free_formatted.clone(),
+        usage_percentage: primary.usage_percentage,
+        file_count: files_before,
+        roots: measured,
+    })
+}
+
+// Fresh measurement as a queued job, refreshing the cache when it completes.
+// File totals aren't known up front, so progress reports files scanned.
+pub fn scan(app: &AppHandle, job: Option<&JobHandle>) -> Result<StorageInfo, DrawStackError> {
+    let roots = roots::library_roots(app)?;
+    let info = measure_roots(&roots, &mut |files, bytes| {
+        let Some(job) = job else {
+            return true;
+        };
+        job.progress(files, 0, Some(format!("{} scanned", format_bytes(bytes))));
+        !job.is_cancelled()
+    });
+
+    // The walk only stops early when the job is cancelled
+    let info = info.ok_or_else(|| match job {
+        Some(job) => job.cancelled_error(),
+        None => DrawStackError::from("Storage scan stopped early"),
+    })?;
+    app.state::<StorageCache>()
+        .store(library_key(&roots), info.clone());
+    Ok(info)
+}
+
+// Library disk usage, in total and per root. The walk runs on a blocking
+// thread and is cached for CACHE_TTL; pass `refresh` to force a rescan and `emit_progress` to get
+// `storage-scan-progress` events while it runs.
+#[tauri::command]
+pub async fn get_storage_usage(
+    app: AppHandle,
+    refresh: Option<bool>,
+    emit_progress: Option<bool>,
+) -> Result<StorageInfo, DrawStackError> {
+    let roots = roots::library_roots(&app)?;
+    let key = library_key(&roots);
+
+    if !refresh.unwrap_or(false) {
+        if let Some(info) = app.state::<StorageCache>().get(&key) {
+            return Ok(info);
+        }
+    }
+
+    let scan_app = app.clone();
+    let info = tauri::async_runtime::spawn_blocking(move || {
+        let emit_progress = emit_progress.unwrap_or(false);
+        measure_roots(&roots, &mut |files_scanned, bytes_so_far| {
+            if emit_progress {
+                let _ = scan_app.emit(
+                    "storage-scan-progress",
+                    StorageScanProgress {
+                        files_scanned,
+                        bytes_so_far,
+                    },
+                );
+            }
+            true
+        })
+    })
+    .await
+    .map_err(|e| format!("Storage scan failed: {}", e))?
+    .ok_or("Storage scan stopped early")?;
+
+    app.state::<StorageCache>().store(key, info.clone());
+    Ok(info)
+}
@@ -0,0 +1,253 @@
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use tauri::AppHandle;
+
+use crate::catalog::{self, CatalogImage, ImageSort};
+use crate::error::DrawStackError;
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct TagSummary {
+    pub id: i64,
+    pub name: String,
+    pub image_count: usize,
+}
+
+// Tags are compared case-insensitively by the schema, but we still trim
+// whitespace and drop blanks so "  Hands " and "hands" land on one row.
+fn normalize(tags: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for tag in tags {
+        let name = tag.trim();
+        if !name.is_empty() && !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+fn find_tag(conn: &Connection, name: &str) -> Result<Option<i64>, String> {
+    conn.query_row(
+        "SELECT id FROM tags WHERE name = ?1",
+        params![name.trim()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to look up tag: {}", e))
+}
+
+fn ensure_tag(tx: &Transaction, name: &str) -> Result<i64, String> {
+    tx.execute(
+        "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
+        params![name],
+    )
+    .map_err(|e| format!("Failed to create tag {}: {}", name, e))?;
+    tx.query_row(
+        "SELECT id FROM tags WHERE name = ?1",
+        params![name],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to look up tag {}: {}", name, e))
+}
+
+// Link every tag in `tags` to every existing image in `image_ids`, creating
+// tags as needed. Returns the number of new links.
+pub fn tag_images(
+    tx: &Transaction,
+    image_ids: &[String],
+    tags: &[String],
+) -> Result<usize, String> {
+    let mut added = 0;
+    for name in normalize(tags) {
+        let tag_id = ensure_tag(tx, &name)?;
+        for image_id in image_ids {
+            // Unknown image ids are skipped rather than failing the whole batch
+            added += tx
+                .execute(
+                    "INSERT OR IGNORE INTO image_tags (image_id, tag_id)
+                     SELECT id, ?2 FROM images WHERE id = ?1",
+                    params![image_id, tag_id],
+                )
+                .map_err(|e| format!("Failed to tag image {}: {}", image_id, e))?;
+        }
+    }
+    Ok(added)
+}
+
+#[tauri::command]
+pub async fn add_tags(
+    app: AppHandle,
+    image_ids: Vec<String>,
+    tags: Vec<String>,
+) -> Result<usize, DrawStackError> {
+    let mut conn = catalog::open(&app)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+
+    let added = tag_images(&tx, &image_ids, &tags)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    Ok(added)
+}
+
+#[tauri::command]
+pub async fn remove_tags(
+    app: AppHandle,
+    image_ids: Vec<String>,
+    tags: Vec<String>,
+) -> Result<usize, DrawStackError> {
+    let names = normalize(&tags);
+    let mut conn = catalog::open(&app)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+
+    let mut removed = 0;
+    for name in &names {
+        for image_id in &image_ids {
+            removed += tx
+                .execute(
+                    "DELETE FROM image_tags
+                     WHERE image_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
+                    params![image_id, name],
+                )
+                .map_err(|e| format!("Failed to untag image {}: {}", image_id, e))?;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    Ok(removed)
+}
+
+#[tauri::command]
+pub async fn list_tags(app: AppHandle) -> Result<Vec<TagSummary>, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.id, t.name, COUNT(it.image_id)
+             FROM tags t LEFT JOIN image_tags it ON it.tag_id = t.id
+             GROUP BY t.id ORDER BY t.name COLLATE NOCASE",
+        )
+        .map_err(|e| format!("Failed to prepare tag query: {}", e))?;
+
+    stmt.query_map([], |row| {
+        Ok(TagSummary {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            image_count: row.get(2)?,
+        })
+    })
+    .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+    .map_err(DrawStackError::from)
+}
+
+#[tauri::command]
+pub async fn get_images_by_tag(
+    app: AppHandle,
+    tag: String,
+    sort: Option<ImageSort>,
+) -> Result<Vec<CatalogImage>, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM images i
+             JOIN image_tags it ON it.image_id = i.id
+             JOIN tags tg ON tg.id = it.tag_id
+             LEFT JOIN thumbnails t ON t.image_id = i.id
+             WHERE tg.name = ?1
+             ORDER BY {}",
+            catalog::IMAGE_COLUMNS,
+            catalog::order_by(sort, "i.pack_id, i.relative_path, i.filename")
+        ))
+        .map_err(|e| format!("Failed to prepare tag query: {}", e))?;
+
+    stmt.query_map(params![tag.trim()], catalog::map_image)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(DrawStackError::from)
+}
+
+#[tauri::command]
+pub async fn rename_tag(
+    app: AppHandle,
+    old_name: String,
+    new_name: String,
+) -> Result<(), DrawStackError> {
+    let new_name = new_name.trim().to_string();
+    if new_name.is_empty() {
+        return Err(DrawStackError::invalid("Tag name cannot be empty"));
+    }
+
+    let conn = catalog::open(&app)?;
+    let tag_id =
+        find_tag(&conn, &old_name)?.ok_or_else(|| format!("Tag not found: {}", old_name))?;
+
+    // Renaming onto another existing tag would violate the unique name -
+    // that case is a merge. A case-only change of the same tag is fine.
+    if let Some(existing) = find_tag(&conn, &new_name)? {
+        if existing != tag_id {
+            return Err(DrawStackError::invalid(format!(
+                "Tag '{}' already exists - merge the tags instead",
+                new_name
+            )));
+        }
+    }
+
+    conn.execute(
+        "UPDATE tags SET name = ?1 WHERE id = ?2",
+        params![new_name, tag_id],
+    )
+    .map_err(|e| format!("Failed to rename tag: {}", e))?;
+
+    Ok(())
+}
+
+// Fold every source tag into `target` (created if needed) and delete the
+// sources. Returns the number of image links moved onto the target.
+#[tauri::command]
+pub async fn merge_tags(
+    app: AppHandle,
+    sources: Vec<String>,
+    target: String,
+) -> Result<usize, DrawStackError> {
+    let target = target.trim().to_string();
+    if target.is_empty() {
+        return Err(DrawStackError::invalid("Tag name cannot be empty"));
+    }
+
+    let mut conn = catalog::open(&app)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+
+    let target_id = ensure_tag(&tx, &target)?;
+    let mut moved = 0;
+
+    for source in normalize(&sources) {
+        let Some(source_id) = find_tag(&tx, &source)? else {
+            continue;
+        };
+        if source_id == target_id {
+            continue;
+        }
+
+        moved += tx
+            .execute(
+                "INSERT OR IGNORE INTO image_tags (image_id, tag_id)
+                 SELECT image_id, ?2 FROM image_tags WHERE tag_id = ?1",
+                params![source_id, target_id],
+            )
+            .map_err(|e| format!("Failed to merge tag {}: {}", source, e))?;
+
+        // Remaining links cascade away with the tag row
+        tx.execute("DELETE FROM tags WHERE id = ?1", params![source_id])
+            .map_err(|e| format!("Failed to delete tag {}: {}", source, e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    Ok(moved)
+}
@@ -0,0 +1,141 @@
+// Remembers which thumbnail was generated for a source file, keyed by path
+// and invalidated by mtime, size or a change of thumbnail settings, so
+// re-imports can skip hashing and decoding unchanged files.
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::paths::{self, StoredPath};
+use crate::{dedupe, thumbnails::ThumbnailSettings, ThumbnailInfo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceStamp {
+    mtime_ms: i64,
+    size: i64,
+}
+
+impl SourceStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = fs::metadata(paths::extended(path)).ok()?;
+        let mtime_ms = meta
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_millis() as i64;
+        Some(Self {
+            mtime_ms,
+            size: meta.len() as i64,
+        })
+    }
+}
+
+pub struct CachedThumbnail {
+    stamp: SourceStamp,
+    pub image_id: String,
+    pub thumbnail_path: StoredPath,
+    pub dhash: Option<String>,
+}
+
+// Cache entries for one batch of source files
+pub struct ThumbnailCache {
+    settings_key: String,
+    entries: HashMap<PathBuf, CachedThumbnail>,
+}
+
+impl ThumbnailCache {
+    pub fn load(
+        conn: &Connection,
+        paths: &[PathBuf],
+        settings: &ThumbnailSettings,
+    ) -> Result<Self, String> {
+        let settings_key = settings.cache_key();
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT mtime, size, image_id, thumbnail_path, dhash FROM thumbnail_cache
+                 WHERE source_path = ?1 AND settings = ?2",
+            )
+            .map_err(|e| format!("Failed to prepare thumbnail cache query: {}", e))?;
+
+        let mut entries = HashMap::new();
+        for path in paths {
+            let entry = stmt
+                .query_row(
+                    params![StoredPath::from(path.as_path()), settings_key],
+                    |row| {
+                        Ok(CachedThumbnail {
+                            stamp: SourceStamp {
+                                mtime_ms: row.get(0)?,
+                                size: row.get(1)?,
+                            },
+                            image_id: row.get(2)?,
+                            thumbnail_path: row.get(3)?,
+                            dhash: row
+                                .get::<_, Option<i64>>(4)?
+                                .map(|hash| dedupe::to_hex(hash as u64)),
+                        })
+                    },
+                )
+                .optional()
+                .map_err(|e| format!("Failed to read thumbnail cache: {}", e))?;
+
+            if let Some(entry) = entry {
+                entries.insert(path.clone(), entry);
+            }
+        }
+
+        Ok(Self {
+            settings_key,
+            entries,
+        })
+    }
+
+    // The cached thumbnail for `path`, if the source is unchanged and the
+    // thumbnail file is still on disk
+    pub fn get(&self, path: &Path) -> Option<&CachedThumbnail> {
+        let entry = self.entries.get(path)?;
+        let fresh = SourceStamp::of(path) == Some(entry.stamp)
+            && paths::extended(&entry.thumbnail_path.to_path_buf()).exists();
+        fresh.then_some(entry)
+    }
+
+    // Record successfully generated thumbnails for next time
+    pub fn store(&self, conn: &mut Connection, thumbnails: &[ThumbnailInfo]) -> Result<(), String> {
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+        {
+            let mut stmt = tx
+                .prepare(
+                    "INSERT OR REPLACE INTO thumbnail_cache
+                        (source_path, mtime, size, image_id, thumbnail_path, dhash, settings)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )
+                .map_err(|e| format!("Failed to prepare thumbnail cache insert: {}", e))?;
+
+            for thumbnail in thumbnails {
+                let Some(dhash) = thumbnail.dhash.as_deref().and_then(dedupe::from_hex) else {
+                    continue;
+                };
+                let Some(stamp) = SourceStamp::of(&thumbnail.original_path.to_path_buf()) else {
+                    continue;
+                };
+
+                stmt.execute(params![
+                    thumbnail.original_path,
+                    stamp.mtime_ms,
+                    stamp.size,
+                    thumbnail.id,
+                    thumbnail.thumbnail_path,
+                    dhash as i64,
+                    self.settings_key
+                ])
+                .map_err(|e| format!("Failed to update thumbnail cache: {}", e))?;
+            }
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit catalog transaction: {}", e))
+    }
+}
@@ -0,0 +1,767 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use rusqlite::{params, OptionalExtension};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use rayon::prelude::*;
+
+use crate::error::DrawStackError;
+use crate::jobs::JobHandle;
+use crate::paths::{self, StoredPath};
+use crate::{catalog, config, dedupe, resize, BatchProgress, ThumbnailInfo};
+
+// Bump when thumbnail rendering changes so cached thumbnails are redone
+const RENDER_VERSION: u32 = 2;
+
+// Camera EXIF previews are usually 160x120 or 160x107
+const MIN_EMBEDDED_PREVIEW: u32 = 120;
+
+// Size of the high-quality thumbnails rendered after import
+pub const UPGRADE_SIZE: u32 = 512;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl ThumbnailFilter {
+    pub fn filter_type(self) -> FilterType {
+        match self {
+            ThumbnailFilter::Nearest => FilterType::Nearest,
+            ThumbnailFilter::Triangle => FilterType::Triangle,
+            ThumbnailFilter::CatmullRom => FilterType::CatmullRom,
+            ThumbnailFilter::Gaussian => FilterType::Gaussian,
+            ThumbnailFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    Jpeg,
+    Png,
+    // Lossless; `quality` does not apply
+    Webp,
+    // Lossy at `quality`; needs the `webp-lossy` feature
+    #[serde(rename = "webp-lossy")]
+    WebpLossy,
+    // Per image: PNG for flat graphics and line art, which JPEG smears, and
+    // lossy WebP (JPEG without `webp-lossy`) for everything else
+    Auto,
+}
+
+impl ThumbnailFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::Png => "png",
+            ThumbnailFormat::Webp | ThumbnailFormat::WebpLossy => "webp",
+            // Resolved per image before anything is written
+            ThumbnailFormat::Auto => "jpg",
+        }
+    }
+}
+
+// Thumbnails with at most this many distinct colors, or any transparency,
+// count as graphics for `ThumbnailFormat::Auto`. Anti-aliased line art stays
+// well under it; photos pass it within the first rows.
+const GRAPHICS_MAX_COLORS: usize = 1024;
+
+fn looks_like_graphics(img: &DynamicImage) -> bool {
+    if img.color().has_alpha() && img.to_rgba8().pixels().any(|p| p[3] < 255) {
+        return true;
+    }
+    let mut colors = HashSet::new();
+    for pixel in img.to_rgb8().pixels() {
+        colors.insert(pixel.0);
+        if colors.len() > GRAPHICS_MAX_COLORS {
+            return false;
+        }
+    }
+    true
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(default)]
+pub struct ThumbnailSettings {
+    // Longest edge in pixels
+    pub size: u32,
+    pub filter: ThumbnailFilter,
+    pub format: ThumbnailFormat,
+    // JPEG and lossy WebP quality, 1-100
+    pub quality: u8,
+    // Render instant Nearest previews during import, then replace them with
+    // high-quality versions in the background
+    pub progressive: bool,
+}
+
+impl Default for ThumbnailSettings {
+    fn default() -> Self {
+        Self {
+            size: 256,
+            filter: ThumbnailFilter::Triangle,
+            format: ThumbnailFormat::Jpeg,
+            quality: 80,
+            progressive: true,
+        }
+    }
+}
+
+impl ThumbnailSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(32..=1024).contains(&self.size) {
+            return Err("Thumbnail size must be between 32 and 1024 pixels".to_string());
+        }
+        if !(1..=100).contains(&self.quality) {
+            return Err("Thumbnail quality must be between 1 and 100".to_string());
+        }
+        if self.format == ThumbnailFormat::WebpLossy && !cfg!(feature = "webp-lossy") {
+            return Err("Lossy WebP thumbnails aren't available in this build".to_string());
+        }
+        Ok(())
+    }
+
+    // Identifies the output of these settings, so cached thumbnails made
+    // with different settings are not reused
+    pub fn cache_key(&self) -> String {
+        format!(
+            "v{}:{}:{:?}:{:?}:{}:{}",
+            RENDER_VERSION, self.size, self.filter, self.format, self.quality, self.progressive
+        )
+    }
+
+    // Smallest embedded EXIF preview worth using in place of decoding the
+    // image. Progressive previews are replaced by the upgrader, so any
+    // camera thumbnail will do; otherwise it has to cover the full size.
+    pub fn min_embedded_size(&self) -> u32 {
+        if self.progressive {
+            MIN_EMBEDDED_PREVIEW
+        } else {
+            self.size
+        }
+    }
+
+    pub fn resize(&self, img: &DynamicImage) -> DynamicImage {
+        let filter = if self.progressive {
+            FilterType::Nearest
+        } else {
+            self.filter.filter_type()
+        };
+        resize::resize(img, self.size, self.size, filter)
+    }
+
+    // The format `img` is written in, with `Auto` resolved for this image
+    pub fn format_for(&self, img: &DynamicImage) -> ThumbnailFormat {
+        match self.format {
+            ThumbnailFormat::Auto if looks_like_graphics(img) => ThumbnailFormat::Png,
+            ThumbnailFormat::Auto if cfg!(feature = "webp-lossy") => ThumbnailFormat::WebpLossy,
+            ThumbnailFormat::Auto => ThumbnailFormat::Jpeg,
+            format => format,
+        }
+    }
+
+    fn encode(&self, img: &DynamicImage, format: ThumbnailFormat) -> Result<Vec<u8>, String> {
+        let mut encoded = Cursor::new(Vec::new());
+        match format {
+            // JPEG has no alpha channel
+            ThumbnailFormat::Jpeg | ThumbnailFormat::Auto => {
+                JpegEncoder::new_with_quality(&mut encoded, self.quality)
+                    .encode_image(&img.to_rgb8())
+            }
+            ThumbnailFormat::Png => img.write_to(&mut encoded, ImageFormat::Png),
+            ThumbnailFormat::Webp => {
+                DynamicImage::ImageRgba8(img.to_rgba8()).write_to(&mut encoded, ImageFormat::WebP)
+            }
+            #[cfg(feature = "webp-lossy")]
+            ThumbnailFormat::WebpLossy => {
+                let rgba = DynamicImage::ImageRgba8(img.to_rgba8());
+                let encoder = webp::Encoder::from_image(&rgba)?;
+                return Ok(encoder.encode(self.quality as f32).to_vec());
+            }
+            #[cfg(not(feature = "webp-lossy"))]
+            ThumbnailFormat::WebpLossy => {
+                return Err("Lossy WebP thumbnails aren't available in this build".to_string())
+            }
+        }
+        .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
+        Ok(encoded.into_inner())
+    }
+
+    // Write `img` to `<dir>/<name>.<ext>` and return the path. Encoded in
+    // memory and renamed into place, since an import and the upgrader can
+    // write the same thumbnail while the webview reads it. A thumbnail of
+    // the same name in another format is removed, so lookups by name can't
+    // find a stale one.
+    pub fn save(&self, img: &DynamicImage, dir: &Path, name: &str) -> Result<PathBuf, String> {
+        let format = self.format_for(img);
+        let encoded = self.encode(img, format)?;
+
+        let path = dir.join(format!("{}.{}", name, format.extension()));
+        let temp_path = crate::temp_sibling(&path);
+        let (target, temp) = (paths::extended(&path), paths::extended(&temp_path));
+        let written = fs::write(&temp, &encoded).and_then(|_| fs::rename(&temp, &target));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp);
+            return Err(format!("Failed to write thumbnail: {}", e));
+        }
+
+        for extension in ["jpg", "png", "webp"] {
+            if extension != format.extension() {
+                let stale = dir.join(format!("{}.{}", name, extension));
+                let _ = fs::remove_file(paths::extended(&stale));
+            }
+        }
+        Ok(path)
+    }
+}
+
+pub fn load_settings(app: &AppHandle) -> ThumbnailSettings {
+    config::load(app).thumbnail_settings
+}
+
+#[tauri::command]
+pub fn get_thumbnail_settings(app: AppHandle) -> ThumbnailSettings {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_thumbnail_settings(
+    app: AppHandle,
+    settings: ThumbnailSettings,
+) -> Result<(), DrawStackError> {
+    settings.validate().map_err(DrawStackError::invalid)?;
+    config::update(&app, |config| {
+        config.thumbnail_settings = settings;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+pub struct UpgradeJob {
+    pub image_id: String,
+    pub source_path: PathBuf,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct ThumbnailUpgraded {
+    image_id: String,
+    thumbnail_path: StoredPath,
+}
+
+// Background queue that re-renders preview thumbnails at high quality. A
+// single worker keeps it from competing with the import pool for cores.
+#[derive(Default)]
+pub struct ThumbnailUpgrader {
+    sender: Mutex<Option<mpsc::Sender<UpgradeJob>>>,
+}
+
+impl ThumbnailUpgrader {
+    pub fn start(&self, app: &AppHandle) {
+        let (sender, receiver) = mpsc::channel::<UpgradeJob>();
+        *self.sender.lock().unwrap() = Some(sender);
+
+        let app = app.clone();
+        std::thread::Builder::new()
+            .name("thumbnail-upgrader".into())
+            .spawn(move || {
+                for job in receiver {
+                    if let Err(e) = upgrade_thumbnail(&app, &job) {
+                        tracing::warn!("Failed to upgrade thumbnail {}: {}", job.image_id, e);
+                    }
+                }
+            })
+            .expect("failed to spawn thumbnail upgrader");
+    }
+
+    pub fn enqueue(&self, job: UpgradeJob) {
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            let _ = sender.send(job);
+        }
+    }
+}
+
+fn upgrade_thumbnail(app: &AppHandle, job: &UpgradeJob) -> Result<(), String> {
+    let settings = load_settings(app);
+
+    let img = crate::decode_image(&job.source_path)?;
+    let upgraded = resize::resize(&img, UPGRADE_SIZE, UPGRADE_SIZE, FilterType::Lanczos3);
+
+    // A new file name makes the webview drop its cached preview
+    let thumb_path = settings.save(
+        &upgraded,
+        &shard_dir(app, &job.image_id)?,
+        &format!("{}@hq", job.image_id),
+    )?;
+    let thumbnail_path = StoredPath::from(thumb_path.as_path());
+
+    let conn = catalog::open(app)?;
+    let fast: Option<StoredPath> = conn
+        .query_row(
+            "SELECT path FROM thumbnails WHERE image_id = ?1",
+            params![job.image_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read catalog thumbnail: {}", e))?;
+    let updated = conn
+        .execute(
+            "UPDATE thumbnails SET path = ?1 WHERE image_id = ?2",
+            params![thumbnail_path, job.image_id],
+        )
+        .map_err(|e| format!("Failed to update catalog thumbnail: {}", e))?;
+    if updated == 0 {
+        // The image left the catalog, or never made it in
+        let _ = fs::remove_file(paths::extended(&thumb_path));
+        return Ok(());
+    }
+    conn.execute(
+        "UPDATE thumbnail_cache SET thumbnail_path = ?1 WHERE image_id = ?2",
+        params![thumbnail_path, job.image_id],
+    )
+    .map_err(|e| format!("Failed to update thumbnail cache: {}", e))?;
+
+    // Nothing points at the fast thumbnail any more
+    if let Some(fast) = fast
+        .map(|fast| fast.to_path_buf())
+        .filter(|fast| *fast != thumb_path && *fast != job.source_path)
+    {
+        let _ = fs::remove_file(paths::extended(&fast));
+    }
+
+    app.emit(
+        "thumbnail-upgraded",
+        ThumbnailUpgraded {
+            image_id: job.image_id.clone(),
+            thumbnail_path,
+        },
+    )
+    .map_err(|e| format!("Failed to emit event: {}", e))
+}
+
+pub fn queue_upgrade(app: &AppHandle, image_id: &str, source_path: &Path) {
+    app.state::<ThumbnailUpgrader>().enqueue(UpgradeJob {
+        image_id: image_id.to_string(),
+        source_path: source_path.to_path_buf(),
+    });
+}
+
+// Queue upgrades for images whose catalog rows are committed. Only fast
+// thumbnails (`<id>.ext`) qualify: not an original standing in for a failed
+// thumbnail, and not one that is already `@hq`.
+pub fn queue_upgrades(app: &AppHandle, images: &[ThumbnailInfo]) {
+    if !load_settings(app).progressive {
+        return;
+    }
+    for image in images {
+        let thumbnail = image.thumbnail_path.to_path_buf();
+        let fast = image.thumbnail_path != image.original_path
+            && thumbnail.file_stem().and_then(|stem| stem.to_str()) == Some(image.id.as_str());
+        if fast {
+            queue_upgrade(app, &image.id, &image.original_path.to_path_buf());
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ThumbnailCacheSize {
+    pub file_count: usize,
+    pub bytes: u64,
+    pub formatted: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct ThumbnailCleanup {
+    pub removed_files: usize,
+    pub freed_bytes: u64,
+    pub freed_formatted: String,
+}
+
+// Image ID a thumbnail file belongs to: `<id>.jpg` or `<id>@hq.jpg`
+pub fn thumbnail_owner(path: &Path) -> Option<&str> {
+    let stem = path.file_stem()?.to_str()?;
+    Some(stem.split('@').next().unwrap_or(stem))
+}
+
+// Thumbnails are sharded by the first two characters of their image ID,
+// `thumbnails/ab/abcdef….jpg`, so no one directory holds more than a few
+// hundred files for NTFS and virus scanners to enumerate
+pub fn shard_name(image_id: &str) -> String {
+    image_id
+        .get(..2)
+        .filter(|prefix| prefix.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or("_")
+        .to_ascii_lowercase()
+}
+
+// The shard directory for `image_id`'s thumbnails and variants, created if
+// needed
+pub fn shard_dir(app: &AppHandle, image_id: &str) -> Result<PathBuf, String> {
+    let dir = crate::thumbnails_dir(app)?.join(shard_name(image_id));
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create thumbnails dir: {}", e))?;
+    Ok(dir)
+}
+
+fn files_in(dir: &Path) -> Result<Vec<(PathBuf, u64)>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read thumbnails dir: {}", e))?;
+    Ok(entries
+        .flatten()
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            meta.is_file().then(|| (entry.path(), meta.len()))
+        })
+        .collect())
+}
+
+// Every thumbnail file with its size, in the shards and any left over from
+// the flat layout
+pub fn thumbnail_files(app: &AppHandle) -> Result<Vec<(PathBuf, u64)>, String> {
+    let dir = crate::thumbnails_dir(app)?;
+    let mut files = files_in(&dir)?;
+    let shards = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read thumbnails dir: {}", e))?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()));
+    for shard in shards {
+        files.extend(files_in(&shard.path())?);
+    }
+    Ok(files)
+}
+
+// Move thumbnails from the old flat `thumbnails/` layout into their shards
+// and repoint the catalog at them. Runs at startup; once everything is
+// moved it only lists an empty top level. Returns the number of files moved.
+pub fn migrate_to_shards(app: &AppHandle) -> Result<usize, String> {
+    let dir = crate::thumbnails_dir(app)?;
+    let mut moved = Vec::new();
+    for (path, _) in files_in(&dir)? {
+        // Leftovers of an interrupted write
+        if crate::extension_lower(&path).as_deref() == Some("tmp") {
+            let _ = fs::remove_file(&path);
+            continue;
+        }
+        let (Some(owner), Some(name)) = (thumbnail_owner(&path), path.file_name()) else {
+            continue;
+        };
+        let dest = shard_dir(app, owner)?.join(name);
+        match fs::rename(&path, &dest) {
+            Ok(()) => moved.push((path, dest)),
+            Err(e) => tracing::warn!("Failed to move thumbnail {}: {}", path.display(), e),
+        }
+    }
+    if moved.is_empty() {
+        return Ok(0);
+    }
+
+    let mut conn = catalog::open(app)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    for (old, new) in &moved {
+        let (old, new) = (
+            StoredPath::from(old.as_path()),
+            StoredPath::from(new.as_path()),
+        );
+        tx.execute(
+            "UPDATE thumbnails SET path = ?1 WHERE path = ?2",
+            params![new, old],
+        )
+        .and_then(|_| {
+            tx.execute(
+                "UPDATE thumbnail_cache SET thumbnail_path = ?1 WHERE thumbnail_path = ?2",
+                params![new, old],
+            )
+        })
+        .map_err(|e| format!("Failed to update thumbnail path: {}", e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    tracing::info!("Moved {} thumbnails into sharded folders", moved.len());
+    Ok(moved.len())
+}
+
+// Delete every thumbnail (fast and upgraded) belonging to `image_ids`.
+// Returns the number of files removed and their total size.
+pub fn remove_thumbnails_for(app: &AppHandle, image_ids: &HashSet<String>) -> (usize, u64) {
+    let Ok(files) = thumbnail_files(app) else {
+        return (0, 0);
+    };
+
+    files
+        .into_iter()
+        .filter(|(path, _)| thumbnail_owner(path).is_some_and(|id| image_ids.contains(id)))
+        .filter(|(path, _)| fs::remove_file(path).is_ok())
+        .fold((0, 0), |(count, bytes), (_, size)| {
+            (count + 1, bytes + size)
+        })
+}
+
+#[tauri::command]
+pub async fn get_thumbnail_cache_size(
+    app: AppHandle,
+) -> Result<ThumbnailCacheSize, DrawStackError> {
+    let files = thumbnail_files(&app)?;
+    let bytes = files.iter().map(|(_, size)| size).sum();
+
+    Ok(ThumbnailCacheSize {
+        file_count: files.len(),
+        bytes,
+        formatted: crate::format_bytes(bytes),
+    })
+}
+
+// Remove thumbnails whose image ID is not in `valid`. Files younger than
+// `min_age` are kept, so a sweep can't race an import that has written
+// thumbnails but not yet their catalog rows.
+pub fn remove_orphans(
+    app: &AppHandle,
+    valid: &HashSet<String>,
+    min_age: Duration,
+) -> Result<ThumbnailCleanup, DrawStackError> {
+    let old_enough = |path: &Path| {
+        min_age.is_zero()
+            || fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age >= min_age))
+    };
+
+    let mut removed_files = 0;
+    let mut freed_bytes = 0;
+    for (path, size) in thumbnail_files(app)? {
+        let orphaned = thumbnail_owner(&path).is_none_or(|id| !valid.contains(id));
+        if orphaned && old_enough(&path) && fs::remove_file(&path).is_ok() {
+            removed_files += 1;
+            freed_bytes += size;
+        }
+    }
+
+    tracing::info!(
+        "Thumbnail cache cleanup removed {} files ({})",
+        removed_files,
+        crate::format_bytes(freed_bytes)
+    );
+
+    Ok(ThumbnailCleanup {
+        removed_files,
+        freed_bytes,
+        freed_formatted: crate::format_bytes(freed_bytes),
+    })
+}
+
+// Remove thumbnails whose image ID is not in `valid_ids`
+#[tauri::command]
+pub async fn clean_thumbnail_cache(
+    app: AppHandle,
+    valid_ids: Vec<String>,
+) -> Result<ThumbnailCleanup, DrawStackError> {
+    let valid: HashSet<String> = valid_ids.into_iter().collect();
+    remove_orphans(&app, &valid, Duration::ZERO)
+}
+
+// Re-render one catalog image with the current settings, removing the
+// thumbnail it replaces. Returns None if the source can't be decoded.
+pub fn regenerate_one(
+    app: &AppHandle,
+    image: &catalog::CatalogImage,
+    settings: &ThumbnailSettings,
+) -> Option<ThumbnailInfo> {
+    let source = image.source_path();
+    let generated = crate::generate_fast_thumbnail(&source, app, &image.id, settings).ok()?;
+
+    if let Some(old) = &image.thumbnail_path {
+        if *old != generated.path && *old != image.original_path {
+            let _ = fs::remove_file(paths::extended(&old.to_path_buf()));
+        }
+    }
+
+    Some(ThumbnailInfo {
+        id: image.id.clone(),
+        original_path: image.original_path.clone(),
+        thumbnail_path: generated.path,
+        filename: image.filename.clone(),
+        relative_path: image.relative_path.clone(),
+        dhash: Some(dedupe::to_hex(generated.dhash)),
+        width: image.width,
+        height: image.height,
+        orientation: image.orientation,
+        captured_at: image.captured_at.clone(),
+        is_animated: image.is_animated,
+        frame_count: image.frame_count,
+        duration_ms: image.duration_ms,
+    })
+}
+
+pub fn record_regenerated(app: &AppHandle, thumbnails: &[ThumbnailInfo]) -> Result<(), String> {
+    let mut conn = catalog::open(app)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+
+    for thumbnail in thumbnails {
+        // An upsert, so images that lost their thumbnail row get one back
+        tx.execute(
+            "INSERT INTO thumbnails (image_id, path, created_at) VALUES (?3, ?1, ?2)
+             ON CONFLICT(image_id) DO UPDATE SET
+                path = excluded.path,
+                created_at = excluded.created_at",
+            params![thumbnail.thumbnail_path, catalog::now_unix(), thumbnail.id],
+        )
+        .map_err(|e| format!("Failed to update thumbnail {}: {}", thumbnail.id, e))?;
+
+        let dhash = thumbnail
+            .dhash
+            .as_deref()
+            .and_then(dedupe::from_hex)
+            .map(|hash| hash as i64);
+        tx.execute(
+            "UPDATE images SET dhash = ?1 WHERE id = ?2",
+            params![dhash, thumbnail.id],
+        )
+        .map_err(|e| format!("Failed to update image {}: {}", thumbnail.id, e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    // Upgrades go out once the fast paths are stored, so a quick upgrade
+    // can't be overwritten by them
+    if load_settings(app).progressive {
+        for thumbnail in thumbnails {
+            if let Ok(image) = catalog::get_image(&conn, &thumbnail.id) {
+                queue_upgrade(app, &image.id, &image.source_path());
+            }
+        }
+    }
+    Ok(())
+}
+
+// Re-render every thumbnail in a pack with the current settings, emitting
+// `regenerate-batch` events shaped like `import-batch`.
+#[tauri::command]
+pub async fn regenerate_thumbnails(
+    app: AppHandle,
+    pack_id: String,
+    thread_count: Option<usize>,
+) -> Result<usize, DrawStackError> {
+    regenerate(&app, &pack_id, thread_count, None)
+}
+
+pub fn regenerate(
+    app: &AppHandle,
+    pack_id: &str,
+    thread_count: Option<usize>,
+    job: Option<&JobHandle>,
+) -> Result<usize, DrawStackError> {
+    let pack = {
+        let conn = catalog::open(app)?;
+        catalog::get_pack(&conn, pack_id, None)?
+    }
+    .ok_or_else(|| format!("Pack not found: {}", pack_id))?;
+
+    let settings = load_settings(app);
+    let pool = crate::build_thumbnail_pool(thread_count)?;
+
+    let total = pack.images.len();
+    let batch_size = 100;
+    let total_batches = total.div_ceil(batch_size);
+    let mut regenerated = 0;
+
+    tracing::info!("Regenerating {} thumbnails for pack {}", total, pack_id);
+
+    for (batch_num, chunk) in pack.images.chunks(batch_size).enumerate() {
+        if let Some(job) = job.filter(|j| j.is_cancelled()) {
+            return Err(job.cancelled_error());
+        }
+
+        let thumbnails: Vec<ThumbnailInfo> = pool.install(|| {
+            chunk
+                .par_iter()
+                .filter_map(|image| regenerate_one(app, image, &settings))
+                .collect()
+        });
+
+        record_regenerated(app, &thumbnails)?;
+        regenerated += thumbnails.len();
+
+        app.emit(
+            "regenerate-batch",
+            BatchProgress {
+                batch: batch_num,
+                total_batches,
+                thumbnails,
+                progress: ((batch_num + 1) as f32 / total_batches as f32) * 100.0,
+                skipped: 0,
+            },
+        )
+        .map_err(|e| format!("Failed to emit event: {}", e))?;
+
+        if let Some(job) = job {
+            job.progress(
+                (batch_num * batch_size + chunk.len()).min(total),
+                total,
+                None,
+            );
+        }
+    }
+
+    tracing::info!(
+        "Regenerated {} of {} thumbnails for pack {}",
+        regenerated,
+        total,
+        pack_id
+    );
+    Ok(regenerated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::testing;
+
+    fn thumbnail_sources(root: &Path) -> Vec<PathBuf> {
+        let deep = testing::deep_dir(&root.join("deep"), 300);
+        testing::UNICODE_NAMES
+            .iter()
+            .flat_map(|name| {
+                [
+                    root.join(format!("{}.png", name)),
+                    deep.join(format!("{}.png", name)),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn thumbnails_unicode_and_long_path_sources() {
+        let root = testing::scratch_dir("thumbnail");
+        let out = testing::deep_dir(&root.join("thumbnails 缩略图"), 280);
+        let settings = ThumbnailSettings::default();
+
+        for (index, source) in thumbnail_sources(&root).iter().enumerate() {
+            testing::write_png(source);
+            let img = crate::decode_image(source).unwrap();
+            let thumbnail = settings.resize(&img);
+            assert!(thumbnail.width() <= settings.size && thumbnail.height() <= settings.size);
+
+            let saved = settings
+                .save(&thumbnail, &out, &format!("image-{}", index))
+                .unwrap();
+            assert!(paths::extended(&saved).is_file());
+            assert!(crate::exif::read_metadata(source).width.is_some());
+        }
+
+        fs::remove_dir_all(paths::extended(&root)).unwrap();
+    }
+}
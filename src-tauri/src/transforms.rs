@@ -0,0 +1,201 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat};
+use rusqlite::params;
+use std::fs;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::catalog::{self, CatalogImage};
+use crate::error::DrawStackError;
+use crate::paths::StoredPath;
+use crate::{manifest, storage, thumbnails};
+
+// Derived images are kept, so save JPEGs close to the source quality
+const JPEG_QUALITY: u8 = 95;
+
+#[derive(Debug, serde::Deserialize, Clone, Copy)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransformOp {
+    FlipHorizontal,
+    FlipVertical,
+    // Clockwise, in multiples of 90
+    Rotate {
+        degrees: u16,
+    },
+    // In pixels of the image as it stands after the previous ops
+    Crop {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl TransformOp {
+    fn apply(self, img: DynamicImage) -> Result<DynamicImage, DrawStackError> {
+        Ok(match self {
+            Self::FlipHorizontal => img.fliph(),
+            Self::FlipVertical => img.flipv(),
+            Self::Rotate { degrees: 90 } => img.rotate90(),
+            Self::Rotate { degrees: 180 } => img.rotate180(),
+            Self::Rotate { degrees: 270 } => img.rotate270(),
+            Self::Rotate { degrees } => {
+                return Err(DrawStackError::invalid(format!(
+                    "Rotation must be 90, 180 or 270 degrees, got {}",
+                    degrees
+                )))
+            }
+            Self::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let fits = width > 0
+                    && height > 0
+                    && x.checked_add(width).is_some_and(|r| r <= img.width())
+                    && y.checked_add(height).is_some_and(|b| b <= img.height());
+                if !fits {
+                    return Err(DrawStackError::invalid(format!(
+                        "Crop {}x{} at {},{} is outside the {}x{} image",
+                        width,
+                        height,
+                        x,
+                        y,
+                        img.width(),
+                        img.height()
+                    )));
+                }
+                img.crop_imm(x, y, width, height)
+            }
+        })
+    }
+}
+
+// Keep the source format where we can write it, PNG otherwise (RAW, JXL, ...)
+pub fn output_extension(source: &Path) -> &'static str {
+    match crate::extension_lower(source).as_deref() {
+        Some("jpg" | "jpeg") => "jpg",
+        Some("webp") => "webp",
+        _ => "png",
+    }
+}
+
+pub fn save(img: &DynamicImage, path: &Path) -> Result<(), String> {
+    match output_extension(path) {
+        "jpg" => {
+            let file =
+                fs::File::create(path).map_err(|e| format!("Failed to create image: {}", e))?;
+            JpegEncoder::new_with_quality(BufWriter::new(file), JPEG_QUALITY)
+                .encode_image(&img.to_rgb8())
+                .map_err(|e| format!("Failed to encode image: {}", e))
+        }
+        "webp" => img
+            .to_rgba8()
+            .save_with_format(path, ImageFormat::WebP)
+            .map_err(|e| format!("Failed to encode image: {}", e)),
+        _ => img
+            .save_with_format(path, ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode image: {}", e)),
+    }
+}
+
+// "pose.jpg" -> "pose (edited).jpg"
+fn derived_filename(source: &CatalogImage, extension: &str) -> String {
+    let stem = Path::new(&source.filename)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| source.id.clone());
+    format!("{} (edited).{}", stem, extension)
+}
+
+// Apply flips, rotations and crops to a catalog image and save the result as
+// a new library image in the same pack. The new image keeps the source's tags
+// and points back at it through `derived_from`.
+#[tauri::command]
+pub async fn transform_image(
+    app: AppHandle,
+    image_id: String,
+    ops: Vec<TransformOp>,
+) -> Result<CatalogImage, DrawStackError> {
+    if ops.is_empty() {
+        return Err(DrawStackError::invalid("No transform operations given"));
+    }
+
+    let mut conn = catalog::open(&app)?;
+    let source = catalog::get_image(&conn, &image_id)?;
+    let source_path = source.source_path();
+
+    let mut img = crate::decode_image(&source_path)?;
+    for op in ops {
+        img = op.apply(img)?;
+    }
+
+    let library_dir = PathBuf::from(crate::get_library_path(app.clone())?);
+    fs::create_dir_all(&library_dir)
+        .map_err(|e| DrawStackError::io("create library directory", &library_dir, e))?;
+
+    let new_id = crate::generate_uuid();
+    let extension = output_extension(&source_path);
+    let output = library_dir.join(format!("{}.{}", new_id, extension));
+    save(&img, &output)?;
+    let output_path = StoredPath::from(output.as_path());
+
+    let settings = thumbnails::load_settings(&app);
+    let thumbnail = crate::generate_fast_thumbnail(&output, &app, &new_id, &settings).ok();
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    tx.execute(
+        "INSERT INTO images
+            (id, pack_id, original_path, library_path, filename, relative_path, imported_at,
+             dhash, width, height, captured_at, derived_from)
+         VALUES (?1, ?2, ?3, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            new_id,
+            source.pack_id,
+            output_path,
+            derived_filename(&source, extension),
+            source.relative_path,
+            catalog::now_unix(),
+            thumbnail.as_ref().map(|t| t.dhash as i64),
+            img.width(),
+            img.height(),
+            source.captured_at,
+            source.id
+        ],
+    )
+    .map_err(|e| format!("Failed to add derived image: {}", e))?;
+    if let Some(thumbnail) = &thumbnail {
+        tx.execute(
+            "INSERT INTO thumbnails (image_id, path, created_at) VALUES (?1, ?2, ?3)",
+            params![new_id, thumbnail.path, catalog::now_unix()],
+        )
+        .map_err(|e| format!("Failed to add thumbnail: {}", e))?;
+    }
+    tx.execute(
+        "INSERT INTO image_tags (image_id, tag_id)
+         SELECT ?1, tag_id FROM image_tags WHERE image_id = ?2",
+        params![new_id, source.id],
+    )
+    .map_err(|e| format!("Failed to copy tags: {}", e))?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+
+    if settings.progressive {
+        thumbnails::queue_upgrade(&app, &new_id, &output);
+    }
+    manifest::refresh(&app, [source.pack_id.as_str()]);
+    storage::invalidate(&app);
+
+    tracing::info!(
+        "Derived {} from {} ({}x{})",
+        new_id,
+        source.id,
+        img.width(),
+        img.height()
+    );
+    catalog::get_image(&conn, &new_id)
+}
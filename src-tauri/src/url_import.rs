@@ -0,0 +1,278 @@
+// Download references straight from a link. Files go into the library (there
+// is no original elsewhere) and the catalog keeps the URL they came from.
+use rusqlite::{params, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Semaphore;
+
+use crate::error::DrawStackError;
+use crate::{catalog, content_hash, manifest, roots, storage, thumbnails, ThumbnailInfo};
+
+const MAX_CONCURRENT: usize = 4;
+const MAX_DOWNLOAD_BYTES: u64 = 100 * 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct UrlFailure {
+    pub url: String,
+    pub error: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct UrlImport {
+    pub imported: Vec<ThumbnailInfo>,
+    pub failed: Vec<UrlFailure>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct UrlImportProgress {
+    pack_id: String,
+    url: String,
+    done: usize,
+    total: usize,
+}
+
+struct Download {
+    path: PathBuf,
+    filename: String,
+}
+
+// Library extension for an image content type
+fn extension_for(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    let extension = match mime.as_str() {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/png" => "png",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "image/bmp" => "bmp",
+        "image/avif" => "avif",
+        "image/tiff" => "tiff",
+        "image/jxl" => "jxl",
+        _ => return None,
+    };
+    Some(extension)
+}
+
+// Display name from the last path segment, with the extension the content
+// type calls for
+fn filename_for(url: &reqwest::Url, extension: &str) -> String {
+    let stem = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(|segment| percent_encoding::percent_decode_str(segment).decode_utf8_lossy())
+        .map(|name| {
+            Path::new(name.as_ref())
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or_else(|| "image".to_string());
+    format!("{}.{}", stem, extension)
+}
+
+async fn download(
+    client: &reqwest::Client,
+    url: &str,
+    library_dir: &Path,
+) -> Result<Download, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Only http and https links can be imported".to_string());
+    }
+
+    let mut response = client
+        .get(parsed.clone())
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Download failed: {}", e))?;
+
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_DOWNLOAD_BYTES)
+    {
+        return Err(format!(
+            "File is larger than {}",
+            crate::format_bytes(MAX_DOWNLOAD_BYTES)
+        ));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let extension = extension_for(&content_type)
+        .ok_or_else(|| format!("Not a supported image (content type \"{}\")", content_type))?;
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Download failed: {}", e))?
+    {
+        if (bytes.len() + chunk.len()) as u64 > MAX_DOWNLOAD_BYTES {
+            return Err(format!(
+                "File is larger than {}",
+                crate::format_bytes(MAX_DOWNLOAD_BYTES)
+            ));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    // Servers mislabel files; the bytes have to look like an image too.
+    // The image crate can't sniff JPEG XL, so that one is taken on trust.
+    if extension != "jxl" && image::guess_format(&bytes).is_err() {
+        return Err("Downloaded file is not a valid image".to_string());
+    }
+
+    let partial = library_dir.join(format!("{}.{}.part", crate::generate_uuid(), extension));
+    crate::write_atomic(&partial, &bytes).map_err(|e| format!("Failed to save download: {}", e))?;
+    let image_id = content_hash::content_id(&partial)
+        .map_err(|e| format!("Failed to hash download: {}", e))?;
+    let path = library_dir.join(format!("{}.{}", image_id, extension));
+    if path.exists() {
+        // Already downloaded before; keep the existing copy
+        let _ = fs::remove_file(&partial);
+    } else {
+        fs::rename(&partial, &path).map_err(|e| format!("Failed to save download: {}", e))?;
+    }
+
+    Ok(Download {
+        path,
+        filename: filename_for(&parsed, extension),
+    })
+}
+
+// Download `urls` (a few at a time, each capped at MAX_DOWNLOAD_BYTES) into
+// the library and add them to `pack_id`. Each URL succeeds or fails on its
+// own; `url-import-progress` is emitted as downloads finish.
+#[tauri::command]
+pub async fn import_from_urls(
+    app: AppHandle,
+    urls: Vec<String>,
+    pack_id: String,
+    root_id: Option<String>,
+) -> Result<UrlImport, DrawStackError> {
+    let library_dir = roots::root_dir(&app, root_id.as_deref())?;
+    fs::create_dir_all(&library_dir)
+        .map_err(|e| DrawStackError::io("create library directory", &library_dir, e))?;
+
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("DrawStack/", env!("CARGO_PKG_VERSION")))
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let limit = Arc::new(Semaphore::new(MAX_CONCURRENT));
+
+    let tasks: Vec<_> = urls
+        .into_iter()
+        .map(|url| {
+            let client = client.clone();
+            let limit = limit.clone();
+            let library_dir = library_dir.clone();
+            tauri::async_runtime::spawn(async move {
+                let _permit = limit.acquire_owned().await;
+                let result = download(&client, url.trim(), &library_dir).await;
+                (url, result)
+            })
+        })
+        .collect();
+
+    let total = tasks.len();
+    let settings = thumbnails::load_settings(&app);
+    let mut report = UrlImport {
+        imported: Vec::new(),
+        failed: Vec::new(),
+    };
+    let mut sources = Vec::new();
+    for (done, task) in tasks.into_iter().enumerate() {
+        let (url, result) = task
+            .await
+            .map_err(|e| format!("Download task failed: {}", e))?;
+        match result {
+            Ok(download) => {
+                let mut info =
+                    crate::thumbnail_info(&app, &library_dir, &download.path, &settings, None);
+                info.filename = download.filename;
+                sources.push((info.id.clone(), url.clone()));
+                report.imported.push(info);
+            }
+            Err(error) => {
+                tracing::warn!("Failed to import {}: {}", url, error);
+                report.failed.push(UrlFailure {
+                    url: url.clone(),
+                    error,
+                });
+            }
+        }
+        let _ = app.emit(
+            "url-import-progress",
+            UrlImportProgress {
+                pack_id: pack_id.clone(),
+                url,
+                done: done + 1,
+                total,
+            },
+        );
+    }
+
+    if !report.imported.is_empty() {
+        let mut conn = catalog::open(&app)?;
+        let duplicates =
+            catalog::record_import(&app, &mut conn, &pack_id, None, None, &report.imported)?;
+        // The catalog already has these; drop the fresh copy in the library
+        for duplicate in duplicates {
+            let Some(index) = report
+                .imported
+                .iter()
+                .position(|info| info.original_path.to_string() == duplicate.path)
+            else {
+                continue;
+            };
+            let info = report.imported.remove(index);
+            // An earlier download of the same URL may be the catalogued copy
+            let in_use = conn
+                .query_row(
+                    "SELECT 1 FROM images WHERE original_path = ?1 OR library_path = ?1",
+                    params![info.original_path],
+                    |_| Ok(()),
+                )
+                .optional()
+                .map_err(|e| format!("Failed to check download {}: {}", info.id, e))?
+                .is_some();
+            if !in_use {
+                let _ = fs::remove_file(info.original_path.to_path_buf());
+            }
+            if let Some(source) = sources.iter().position(|(id, _)| *id == info.id) {
+                let (_, url) = sources.remove(source);
+                report.failed.push(UrlFailure {
+                    url,
+                    error: duplicate.reason,
+                });
+            }
+        }
+        for (image_id, url) in &sources {
+            conn.execute(
+                "UPDATE images SET library_path = original_path, source_url = ?1 WHERE id = ?2",
+                params![url, image_id],
+            )
+            .map_err(|e| format!("Failed to record download {}: {}", image_id, e))?;
+        }
+        manifest::refresh(&app, [pack_id.as_str()]);
+        storage::invalidate(&app);
+    }
+
+    tracing::info!(
+        "Imported {} of {} URLs into pack {}",
+        report.imported.len(),
+        total,
+        pack_id
+    );
+    Ok(report)
+}
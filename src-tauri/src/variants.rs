@@ -0,0 +1,186 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, ImageFormat};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::error::DrawStackError;
+use crate::{catalog, thumbnails};
+
+// Study images are for viewing, not printing; cap them so a 50MP reference
+// doesn't produce a 50MP PNG
+pub const MAX_VARIANT_SIZE: u32 = 2560;
+const MAX_BLUR_LEVELS: usize = 8;
+const MAX_SIGMA: f32 = 64.0;
+
+#[derive(Debug, serde::Deserialize, Clone, Copy)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Variant {
+    Grayscale,
+    // Value study with `levels` flat tones between black and white
+    Posterize {
+        levels: u8,
+    },
+    // Black and white only, split at `cutoff` luma
+    Threshold {
+        #[serde(default = "default_cutoff")]
+        cutoff: u8,
+    },
+}
+
+fn default_cutoff() -> u8 {
+    128
+}
+
+impl Variant {
+    // Suffix for the cached file, `<id>@<key>.png`
+    fn key(self) -> String {
+        match self {
+            Self::Grayscale => "gray".to_string(),
+            Self::Posterize { levels } => format!("posterize{}", levels),
+            Self::Threshold { cutoff } => format!("threshold{}", cutoff),
+        }
+    }
+
+    fn validate(self) -> Result<(), DrawStackError> {
+        match self {
+            Self::Posterize { levels } if !(2..=16).contains(&levels) => {
+                Err(DrawStackError::invalid(format!(
+                    "Posterize levels must be between 2 and 16, got {}",
+                    levels
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn apply(self, luma: &mut GrayImage) {
+        match self {
+            Self::Grayscale => {}
+            Self::Posterize { levels } => {
+                let steps = (levels - 1) as f32;
+                for pixel in luma.pixels_mut() {
+                    let band = (pixel.0[0] as f32 / 255.0 * steps).round();
+                    pixel.0[0] = (band / steps * 255.0).round() as u8;
+                }
+            }
+            Self::Threshold { cutoff } => {
+                for pixel in luma.pixels_mut() {
+                    pixel.0[0] = if pixel.0[0] >= cutoff { 255 } else { 0 };
+                }
+            }
+        }
+    }
+}
+
+// Decode an image for a derived rendition, scaled down to `max_size`
+pub fn load_scaled(source: &Path, max_size: u32) -> Result<DynamicImage, DrawStackError> {
+    let img = crate::decode_image(source)?;
+    Ok(if img.width() > max_size || img.height() > max_size {
+        img.resize(max_size, max_size, FilterType::Triangle)
+    } else {
+        img
+    })
+}
+
+// A cached rendition is stale once the source has been modified after it
+pub fn is_fresh(cached: &Path, source: &Path) -> bool {
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(cached), modified(source)) {
+        (Some(cached), Some(source)) => cached >= source,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct BlurLevel {
+    pub sigma: f32,
+    pub path: String,
+}
+
+fn variant_path(app: &AppHandle, image_id: &str, variant: Variant) -> Result<PathBuf, String> {
+    Ok(thumbnails::shard_dir(app, image_id)?.join(format!("{}@{}.png", image_id, variant.key())))
+}
+
+// Render a grayscale, posterized or threshold study of a catalog image and
+// return its path. Results live beside the thumbnails, so they're cleaned up
+// with them.
+#[tauri::command]
+pub async fn generate_variant(
+    app: AppHandle,
+    image_id: String,
+    variant: Variant,
+) -> Result<String, DrawStackError> {
+    variant.validate()?;
+
+    let conn = catalog::open(&app)?;
+    let image = catalog::get_image(&conn, &image_id)?;
+    let source = image.source_path();
+
+    let output = variant_path(&app, &image_id, variant)?;
+    if is_fresh(&output, &source) {
+        return Ok(output.to_string_lossy().to_string());
+    }
+
+    let mut luma = load_scaled(&source, MAX_VARIANT_SIZE)?.to_luma8();
+    variant.apply(&mut luma);
+    luma.save_with_format(&output, ImageFormat::Png)
+        .map_err(|e| format!("Failed to save variant: {}", e))?;
+
+    Ok(output.to_string_lossy().to_string())
+}
+
+// Pre-render gaussian blurred copies of an image for squint-mode block-ins.
+// `sigmas` are in pixels of the rendition, which is capped at
+// MAX_VARIANT_SIZE like the other studies. Levels already on disk are reused.
+#[tauri::command]
+pub async fn generate_blur_levels(
+    app: AppHandle,
+    image_id: String,
+    sigmas: Vec<f32>,
+) -> Result<Vec<BlurLevel>, DrawStackError> {
+    if sigmas.is_empty() || sigmas.len() > MAX_BLUR_LEVELS {
+        return Err(DrawStackError::invalid(format!(
+            "Request between 1 and {} blur levels",
+            MAX_BLUR_LEVELS
+        )));
+    }
+    if let Some(sigma) = sigmas
+        .iter()
+        .find(|s| !s.is_finite() || **s <= 0.0 || **s > MAX_SIGMA)
+    {
+        return Err(DrawStackError::invalid(format!(
+            "Blur sigma must be above 0 and at most {}, got {}",
+            MAX_SIGMA, sigma
+        )));
+    }
+
+    let conn = catalog::open(&app)?;
+    let image = catalog::get_image(&conn, &image_id)?;
+    let source = image.source_path();
+    let dir = thumbnails::shard_dir(&app, &image_id)?;
+
+    // Decode once, and only if some level actually needs rendering
+    let mut decoded: Option<DynamicImage> = None;
+    let mut levels = Vec::with_capacity(sigmas.len());
+    for sigma in sigmas {
+        let output = dir.join(format!("{}@blur{}.jpg", image_id, sigma));
+        if !is_fresh(&output, &source) {
+            let img = match decoded.as_ref() {
+                Some(img) => img,
+                None => decoded.insert(load_scaled(&source, MAX_VARIANT_SIZE)?),
+            };
+            img.fast_blur(sigma)
+                .to_rgb8()
+                .save_with_format(&output, ImageFormat::Jpeg)
+                .map_err(|e| format!("Failed to save blur level: {}", e))?;
+        }
+        levels.push(BlurLevel {
+            sigma,
+            path: output.to_string_lossy().to_string(),
+        });
+    }
+
+    Ok(levels)
+}
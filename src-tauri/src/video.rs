@@ -0,0 +1,135 @@
+// Video references, e.g. gesture clips. Decoding goes through the ffmpeg and
+// ffprobe command-line tools, which must be on PATH; without them videos are
+// still listed but get no thumbnail or duration.
+use image::DynamicImage;
+use std::path::Path;
+use std::process::{Command, Output};
+
+pub static VIDEO_EXTENSIONS: &[&str] = &["mp4", "m4v", "mov", "webm"];
+
+const FFMPEG: &str = "ffmpeg";
+const FFPROBE: &str = "ffprobe";
+
+#[derive(Debug, Clone, Copy)]
+pub struct VideoInfo {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(serde::Deserialize)]
+struct Probe {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(serde::Deserialize)]
+struct ProbeStream {
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(serde::Deserialize)]
+struct ProbeFormat {
+    // Seconds, as a decimal string
+    duration: Option<String>,
+}
+
+pub fn is_video_extension(ext: &str) -> bool {
+    crate::extension_in(ext, VIDEO_EXTENSIONS)
+}
+
+fn run(program: &str, args: &[&std::ffi::OsStr]) -> Result<Output, String> {
+    let mut command = Command::new(program);
+    command.args(args);
+
+    // Keep a console window from flashing up for every clip
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output)
+}
+
+// Dimensions of the first video stream and the container's duration
+pub fn probe(path: &Path) -> Option<VideoInfo> {
+    if !crate::extension_lower(path).is_some_and(|ext| is_video_extension(&ext)) {
+        return None;
+    }
+
+    let output = run(
+        FFPROBE,
+        &[
+            "-v".as_ref(),
+            "error".as_ref(),
+            "-select_streams".as_ref(),
+            "v:0".as_ref(),
+            "-show_entries".as_ref(),
+            "stream=width,height:format=duration".as_ref(),
+            "-of".as_ref(),
+            "json".as_ref(),
+            path.as_os_str(),
+        ],
+    )
+    .ok()?;
+    let probe: Probe = serde_json::from_slice(&output.stdout).ok()?;
+
+    let stream = probe.streams.first();
+    let duration_ms = probe
+        .format
+        .and_then(|f| f.duration)
+        .and_then(|d| d.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(|secs| (secs * 1000.0) as u64);
+
+    Some(VideoInfo {
+        width: stream.and_then(|s| s.width),
+        height: stream.and_then(|s| s.height),
+        duration_ms,
+    })
+}
+
+// A frame from the middle of the clip; the opening frame of a gesture clip
+// is often a blank or transition.
+pub fn decode_middle_frame(path: &Path) -> Result<DynamicImage, String> {
+    let middle_secs = probe(path)
+        .and_then(|info| info.duration_ms)
+        .map_or(0.0, |ms| ms as f64 / 2000.0);
+    let seek = format!("{:.3}", middle_secs);
+
+    let output = run(
+        FFMPEG,
+        &[
+            "-v".as_ref(),
+            "error".as_ref(),
+            "-ss".as_ref(),
+            seek.as_ref(),
+            "-i".as_ref(),
+            path.as_os_str(),
+            "-frames:v".as_ref(),
+            "1".as_ref(),
+            "-f".as_ref(),
+            "image2pipe".as_ref(),
+            "-c:v".as_ref(),
+            "png".as_ref(),
+            "-".as_ref(),
+        ],
+    )?;
+
+    image::load_from_memory_with_format(&output.stdout, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to decode video frame: {}", e))
+}
@@ -0,0 +1,412 @@
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::DrawStackError;
+use crate::{catalog, config, manifest, roots, scan, storage, thumbnails, ThumbnailInfo};
+
+// Long enough for most copies to finish before we try to decode the file
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct WatchedFolder {
+    pub path: String,
+    pub pack_id: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct LibraryUpdated {
+    pack_id: String,
+    folder_path: String,
+    thumbnails: Vec<ThumbnailInfo>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct LibraryFileMissing {
+    image_id: String,
+    path: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+struct LibraryFileMoved {
+    image_id: String,
+    from: String,
+    to: String,
+}
+
+struct ActiveWatch {
+    pack_id: String,
+    // Dropping the debouncer stops the watch
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+#[derive(Default)]
+pub struct FolderWatchers {
+    active: Mutex<HashMap<String, ActiveWatch>>,
+}
+
+// Watch over the library roots themselves, so copies deleted or renamed in a
+// file manager don't linger in the catalog as broken images.
+#[derive(Default)]
+pub struct LibraryWatcher {
+    debouncer: Mutex<Option<Debouncer<RecommendedWatcher>>>,
+}
+
+fn watched_folders_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let app_data = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    Ok(app_data.join("watched_folders.json"))
+}
+
+fn load_watched_folders(app: &AppHandle) -> Vec<WatchedFolder> {
+    watched_folders_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_watched_folders(app: &AppHandle, folders: &[WatchedFolder]) -> Result<(), String> {
+    let path = watched_folders_path(app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    }
+    let contents = serde_json::to_string_pretty(folders)
+        .map_err(|e| format!("Failed to serialize watched folders: {}", e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write watched folders: {}", e))
+}
+
+fn start_watch(app: &AppHandle, folder: &WatchedFolder) -> Result<(), String> {
+    let root = PathBuf::from(&folder.path);
+    if !root.is_dir() {
+        return Err(format!("Folder does not exist: {}", folder.path));
+    }
+
+    // Files already present were imported with the pack; only react to new ones
+    let mut known: HashSet<PathBuf> = scan::scan_for_images(&root, &config::load(app).scan)?
+        .into_iter()
+        .collect();
+
+    let handler_app = app.clone();
+    let handler_folder = folder.clone();
+    let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+        let events = match result {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::error!("Folder watch error for {}: {}", handler_folder.path, e);
+                return;
+            }
+        };
+
+        let new_images: Vec<PathBuf> = events
+            .into_iter()
+            .map(|event| event.path)
+            .filter(|path| path.is_file() && crate::is_supported_image(path))
+            .filter(|path| known.insert(path.clone()))
+            .collect();
+
+        if !new_images.is_empty() {
+            import_new_images(&handler_app, &handler_folder, &root, &new_images);
+        }
+    })
+    .map_err(|e| format!("Failed to create folder watcher: {}", e))?;
+
+    debouncer
+        .watcher()
+        .watch(Path::new(&folder.path), RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", folder.path, e))?;
+
+    let watchers = app.state::<FolderWatchers>();
+    watchers.active.lock().unwrap().insert(
+        folder.path.clone(),
+        ActiveWatch {
+            pack_id: folder.pack_id.clone(),
+            _debouncer: debouncer,
+        },
+    );
+
+    tracing::info!("Watching {} for pack {}", folder.path, folder.pack_id);
+    Ok(())
+}
+
+fn import_new_images(app: &AppHandle, folder: &WatchedFolder, root: &Path, images: &[PathBuf]) {
+    tracing::info!(
+        "Auto-importing {} new images from {}",
+        images.len(),
+        folder.path
+    );
+
+    let settings = thumbnails::load_settings(app);
+    let mut thumbnails: Vec<ThumbnailInfo> = images
+        .iter()
+        .map(|path| crate::thumbnail_info(app, root, path, &settings, None))
+        .collect();
+
+    match catalog::open(app).and_then(|mut conn| {
+        catalog::record_import(
+            app,
+            &mut conn,
+            &folder.pack_id,
+            None,
+            Some(&folder.path),
+            &thumbnails,
+        )
+    }) {
+        Ok(duplicates) => thumbnails.retain(|info| {
+            let path = info.original_path.to_string();
+            !duplicates.iter().any(|d| d.path == path)
+        }),
+        Err(e) => tracing::error!("Failed to record watched images in catalog: {}", e),
+    }
+    manifest::refresh(app, [folder.pack_id.as_str()]);
+
+    let _ = app.emit(
+        "library-updated",
+        LibraryUpdated {
+            pack_id: folder.pack_id.clone(),
+            folder_path: folder.path.clone(),
+            thumbnails,
+        },
+    );
+}
+
+// Re-establish watches saved by previous sessions. Folders that have since
+// disappeared are skipped but kept, in case a drive is just unplugged.
+pub fn restore(app: &AppHandle) {
+    for folder in load_watched_folders(app) {
+        if let Err(e) = start_watch(app, &folder) {
+            tracing::error!("Failed to restore watch on {}: {}", folder.path, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn watch_folder(
+    app: AppHandle,
+    path: String,
+    pack_id: String,
+) -> Result<(), DrawStackError> {
+    let folder = WatchedFolder { path, pack_id };
+    start_watch(&app, &folder)?;
+
+    let mut folders = load_watched_folders(&app);
+    folders.retain(|f| f.path != folder.path);
+    folders.push(folder);
+    Ok(save_watched_folders(&app, &folders)?)
+}
+
+#[tauri::command]
+pub fn unwatch_folder(app: AppHandle, path: String) -> Result<(), DrawStackError> {
+    app.state::<FolderWatchers>()
+        .active
+        .lock()
+        .unwrap()
+        .remove(&path);
+
+    let mut folders = load_watched_folders(&app);
+    folders.retain(|f| f.path != path);
+    Ok(save_watched_folders(&app, &folders)?)
+}
+
+#[tauri::command]
+pub fn list_watched_folders(app: AppHandle) -> Vec<WatchedFolder> {
+    let active = app.state::<FolderWatchers>();
+    let active = active.active.lock().unwrap();
+
+    // Report what's actually running, plus saved folders that failed to restore
+    let mut folders: Vec<WatchedFolder> = active
+        .iter()
+        .map(|(path, watch)| WatchedFolder {
+            path: path.clone(),
+            pack_id: watch.pack_id.clone(),
+        })
+        .collect();
+
+    for saved in load_watched_folders(&app) {
+        if !active.contains_key(&saved.path) {
+            folders.push(saved);
+        }
+    }
+
+    folders
+}
+
+// (Re)start watching every library root. Called at startup and whenever the
+// roots change; roots that don't exist yet are skipped until the next call.
+pub fn watch_library(app: &AppHandle) {
+    let state = app.state::<LibraryWatcher>();
+    let mut active = state.debouncer.lock().unwrap();
+    *active = None;
+
+    let roots = match roots::root_dirs(app) {
+        Ok(roots) => roots,
+        Err(e) => {
+            tracing::error!("Failed to watch the library: {}", e);
+            return;
+        }
+    };
+
+    let handler_app = app.clone();
+    let debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+        let paths = match result {
+            Ok(events) => events.into_iter().map(|event| event.path).collect(),
+            Err(e) => {
+                tracing::error!("Library watch error: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = reconcile_library(&handler_app, paths) {
+            tracing::error!("Failed to reconcile library changes: {}", e);
+        }
+    });
+    let mut debouncer = match debouncer {
+        Ok(debouncer) => debouncer,
+        Err(e) => {
+            tracing::error!("Failed to create library watcher: {}", e);
+            return;
+        }
+    };
+
+    for root in roots.iter().filter(|root| root.is_dir()) {
+        match debouncer.watcher().watch(root, RecursiveMode::Recursive) {
+            Ok(()) => tracing::info!("Watching library root {}", root.display()),
+            Err(e) => tracing::error!("Failed to watch {}: {}", root.display(), e),
+        }
+    }
+    *active = Some(debouncer);
+}
+
+// Catalog images whose library copy is `path` or lies under it
+fn copies_under(
+    conn: &rusqlite::Connection,
+    path: &Path,
+) -> Result<Vec<(String, String, Option<i64>)>, String> {
+    let path = path.to_string_lossy();
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT id, library_path, file_size FROM images
+             WHERE missing_since IS NULL
+               AND (library_path = ?1
+                    OR substr(library_path, 1, length(?1) + 1) IN (?1 || '/', ?1 || '\\'))",
+        )
+        .map_err(|e| format!("Failed to prepare library query: {}", e))?;
+    stmt.query_map([path.as_ref()], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })
+    .and_then(|rows| rows.collect())
+    .map_err(|e| format!("Failed to read library images: {}", e))
+}
+
+// Apply a batch of filesystem changes under the library roots. A vanished
+// copy is matched against files that appeared in the same batch with the same
+// size and extension, which is what a rename looks like; unmatched ones are
+// marked missing. Copies that come back are unmarked.
+fn reconcile_library(app: &AppHandle, paths: Vec<PathBuf>) -> Result<(), String> {
+    let conn = catalog::open(app)?;
+    let (present, gone): (Vec<PathBuf>, Vec<PathBuf>) =
+        paths.into_iter().partition(|path| path.exists());
+
+    let mut vanished = Vec::new();
+    for path in &gone {
+        vanished.extend(copies_under(&conn, path)?);
+    }
+
+    // A renamed folder arrives as a single event for the folder itself
+    let scan_settings = config::load(app).scan;
+    let mut appeared: Vec<PathBuf> = Vec::new();
+    for path in present {
+        if path.is_dir() {
+            appeared.extend(scan::scan_for_images(&path, &scan_settings).unwrap_or_default());
+        } else if crate::is_supported_image(&path) {
+            appeared.push(path);
+        }
+    }
+
+    let mut arrived: Vec<(PathBuf, u64)> = Vec::new();
+    for path in appeared {
+        let path_str = path.to_string_lossy().to_string();
+        let restored = conn
+            .execute(
+                "UPDATE images SET missing_since = NULL
+                 WHERE library_path = ?1 AND missing_since IS NOT NULL",
+                [&path_str],
+            )
+            .map_err(|e| format!("Failed to update catalog: {}", e))?;
+        let known: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM images WHERE library_path = ?1)",
+                [&path_str],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to query catalog: {}", e))?;
+        if restored == 0 && !known {
+            if let Ok(meta) = fs::metadata(&path) {
+                arrived.push((path, meta.len()));
+            }
+        }
+    }
+
+    if vanished.is_empty() {
+        return Ok(());
+    }
+
+    let now = catalog::now_unix();
+    let mut moved = Vec::new();
+    for (image_id, old_path, size) in vanished {
+        let extension = crate::extension_lower(Path::new(&old_path));
+        let matches: Vec<usize> = arrived
+            .iter()
+            .enumerate()
+            .filter(|(_, (path, len))| {
+                size == Some(*len as i64) && crate::extension_lower(path) == extension
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        if let [index] = matches[..] {
+            let (new_path, _) = arrived.remove(index);
+            let new_path = new_path.to_string_lossy().to_string();
+            conn.execute(
+                "UPDATE images SET library_path = ?1 WHERE id = ?2",
+                rusqlite::params![new_path, image_id],
+            )
+            .map_err(|e| format!("Failed to update catalog: {}", e))?;
+            tracing::info!("Library copy moved: {} -> {}", old_path, new_path);
+            moved.push(image_id.clone());
+            let _ = app.emit(
+                "library-file-moved",
+                LibraryFileMoved {
+                    image_id,
+                    from: old_path,
+                    to: new_path,
+                },
+            );
+            continue;
+        }
+
+        conn.execute(
+            "UPDATE images SET missing_since = ?1 WHERE id = ?2",
+            rusqlite::params![now, image_id],
+        )
+        .map_err(|e| format!("Failed to update catalog: {}", e))?;
+        tracing::warn!("Library copy deleted outside the app: {}", old_path);
+        let _ = app.emit(
+            "library-file-missing",
+            LibraryFileMissing {
+                image_id,
+                path: old_path,
+            },
+        );
+    }
+
+    manifest::refresh_for_images(app, &moved);
+    storage::invalidate(app);
+    Ok(())
+}
@@ -0,0 +1,330 @@
+// XMP sidecars, so ratings and keywords are shared with Lightroom, Bridge and
+// darktable. Sidecars are read as images are added to the catalog (when
+// `read_xmp_sidecars` is on) and written on request. Writing merges into an
+// existing sidecar and only replaces `xmp:Rating` and `dc:subject`, leaving
+// other tools' data alone.
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::catalog;
+use crate::error::DrawStackError;
+use crate::{tags, ThumbnailInfo};
+
+// Skeleton for images that have no sidecar yet
+const TEMPLATE: &str = "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">
+ <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">
+  <rdf:Description rdf:about=\"\"/>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end=\"w\"?>
+";
+const XMP_NS: &str = "http://ns.adobe.com/xap/1.0/";
+const DC_NS: &str = "http://purl.org/dc/elements/1.1/";
+
+// Elements are matched by their standard prefixes, which every XMP writer
+// uses; `MicrosoftPhoto:Rating` (a percentage) must not be taken for a star
+const RATING: &[u8] = b"xmp:Rating";
+const SUBJECT: &[u8] = b"dc:subject";
+const DESCRIPTION: &[u8] = b"rdf:Description";
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Sidecar {
+    pub rating: Option<u8>,
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct XmpFailure {
+    pub image_id: String,
+    pub error: String,
+}
+
+#[derive(Debug, serde::Serialize, Clone)]
+pub struct XmpExport {
+    pub written: Vec<String>,
+    pub failed: Vec<XmpFailure>,
+}
+
+// Lightroom and Bridge name sidecars `photo.xmp`; darktable uses
+// `photo.jpg.xmp`
+fn candidates(image: &Path) -> [PathBuf; 3] {
+    let mut appended = image.as_os_str().to_owned();
+    appended.push(".xmp");
+    [
+        image.with_extension("xmp"),
+        image.with_extension("XMP"),
+        PathBuf::from(appended),
+    ]
+}
+
+pub fn find_sidecar(image: &Path) -> Option<PathBuf> {
+    candidates(image).into_iter().find(|p| p.is_file())
+}
+
+// Stars 0-5; -1 means rejected in Lightroom and is read as unrated
+fn parse_rating(value: &str) -> Option<u8> {
+    let rating = value.trim().parse::<f32>().ok()?;
+    (rating >= 0.0).then(|| rating.round().min(5.0) as u8)
+}
+
+pub fn parse(text: &str) -> Result<Sidecar, String> {
+    let mut reader = Reader::from_str(text);
+    let mut sidecar = Sidecar::default();
+    let (mut in_rating, mut in_subject, mut in_item) = (false, false, false);
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid XMP: {}", e))?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                // Ratings are usually an attribute of rdf:Description
+                for attr in e.attributes().flatten() {
+                    if attr.key.as_ref() == RATING {
+                        if let Ok(value) = attr.unescape_value() {
+                            sidecar.rating = parse_rating(&value);
+                        }
+                    }
+                }
+                if matches!(event, Event::Start(_)) {
+                    match e.name().as_ref() {
+                        RATING => in_rating = true,
+                        SUBJECT => in_subject = true,
+                        b"rdf:li" if in_subject => in_item = true,
+                        _ => {}
+                    }
+                }
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(|e| format!("Invalid XMP: {}", e))?;
+                if in_item {
+                    let keyword = text.trim();
+                    if !keyword.is_empty() {
+                        sidecar.keywords.push(keyword.to_string());
+                    }
+                } else if in_rating {
+                    sidecar.rating = parse_rating(&text);
+                }
+            }
+            Event::End(e) => match e.name().as_ref() {
+                RATING => in_rating = false,
+                SUBJECT => in_subject = false,
+                b"rdf:li" => in_item = false,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(sidecar)
+}
+
+pub fn read_sidecar(image: &Path) -> Option<Sidecar> {
+    let path = find_sidecar(image)?;
+    let text = fs::read_to_string(&path).ok()?;
+    match parse(&text) {
+        Ok(sidecar) => Some(sidecar),
+        Err(e) => {
+            tracing::warn!("Ignoring sidecar {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+// Copy of an rdf:Description start tag without its rating attribute, with
+// `rating` and the namespaces it needs added when given
+fn description(start: &BytesStart, rating: Option<u8>) -> BytesStart<'static> {
+    let mut tag = BytesStart::new("rdf:Description");
+    let mut declared = (false, false);
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            RATING => continue,
+            b"xmlns:xmp" => declared.0 = true,
+            b"xmlns:dc" => declared.1 = true,
+            _ => {}
+        }
+        tag.push_attribute(attr);
+    }
+    if let Some(rating) = rating {
+        if !declared.0 {
+            tag.push_attribute(("xmlns:xmp", XMP_NS));
+        }
+        if !declared.1 {
+            tag.push_attribute(("xmlns:dc", DC_NS));
+        }
+        tag.push_attribute(("xmp:Rating", rating.to_string().as_str()));
+    }
+    tag
+}
+
+fn write_subject(writer: &mut Writer<Vec<u8>>, keywords: &[String]) -> std::io::Result<()> {
+    if keywords.is_empty() {
+        return Ok(());
+    }
+    writer.write_event(Event::Start(BytesStart::new("dc:subject")))?;
+    writer.write_event(Event::Start(BytesStart::new("rdf:Bag")))?;
+    for keyword in keywords {
+        writer.write_event(Event::Start(BytesStart::new("rdf:li")))?;
+        writer.write_event(Event::Text(BytesText::new(keyword)))?;
+        writer.write_event(Event::End(BytesEnd::new("rdf:li")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("rdf:Bag")))?;
+    writer.write_event(Event::End(BytesEnd::new("dc:subject")))
+}
+
+// Rewrite `existing` (or a fresh packet) with `rating` and `keywords`. The
+// values go into the first rdf:Description; ratings and keyword lists
+// anywhere else are dropped so readers can't see two different answers.
+pub fn render(existing: Option<&str>, rating: u8, keywords: &[String]) -> Result<String, String> {
+    let mut reader = Reader::from_str(existing.unwrap_or(TEMPLATE));
+    let mut writer = Writer::new(Vec::new());
+    let mut skip_depth = 0usize;
+    let mut written = false;
+
+    let io = |e: std::io::Error| format!("Failed to write XMP: {}", e);
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid XMP: {}", e))?;
+        if skip_depth > 0 {
+            match event {
+                Event::Start(_) => skip_depth += 1,
+                Event::End(_) => skip_depth -= 1,
+                Event::Eof => break,
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Start(e) if matches!(e.name().as_ref(), RATING | SUBJECT) => skip_depth = 1,
+            Event::Empty(e) if matches!(e.name().as_ref(), RATING | SUBJECT) => {}
+            Event::Start(e) if e.name().as_ref() == DESCRIPTION => {
+                let rating = (!written).then_some(rating);
+                writer
+                    .write_event(Event::Start(description(&e, rating)))
+                    .map_err(io)?;
+                if !written {
+                    write_subject(&mut writer, keywords).map_err(io)?;
+                    written = true;
+                }
+            }
+            Event::Empty(e) if e.name().as_ref() == DESCRIPTION => {
+                if written {
+                    writer
+                        .write_event(Event::Empty(description(&e, None)))
+                        .map_err(io)?;
+                } else {
+                    writer
+                        .write_event(Event::Start(description(&e, Some(rating))))
+                        .map_err(io)?;
+                    write_subject(&mut writer, keywords).map_err(io)?;
+                    writer
+                        .write_event(Event::End(BytesEnd::new("rdf:Description")))
+                        .map_err(io)?;
+                    written = true;
+                }
+            }
+            Event::Eof => break,
+            event => writer.write_event(event).map_err(io)?,
+        }
+    }
+
+    if !written {
+        return Err("The sidecar has no rdf:Description to update".to_string());
+    }
+    String::from_utf8(writer.into_inner()).map_err(|e| format!("Invalid XMP: {}", e))
+}
+
+// Apply the sidecars of newly catalogued images: their rating replaces ours
+// and their keywords become tags. Returns how many images had a sidecar.
+pub fn apply_sidecars(conn: &mut Connection, images: &[ThumbnailInfo]) -> Result<usize, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start catalog transaction: {}", e))?;
+    let mut applied = 0;
+    for image in images {
+        let Some(sidecar) = read_sidecar(&image.original_path.to_path_buf()) else {
+            continue;
+        };
+        if let Some(rating) = sidecar.rating {
+            tx.execute(
+                "UPDATE images SET rating = ?1 WHERE id = ?2",
+                params![rating, image.id],
+            )
+            .map_err(|e| format!("Failed to set rating: {}", e))?;
+        }
+        tags::tag_images(&tx, std::slice::from_ref(&image.id), &sidecar.keywords)?;
+        applied += 1;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit catalog transaction: {}", e))?;
+    Ok(applied)
+}
+
+fn image_tags(conn: &Connection, image_id: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT tg.name FROM image_tags it JOIN tags tg ON tg.id = it.tag_id
+             WHERE it.image_id = ?1 ORDER BY tg.name",
+        )
+        .map_err(|e| format!("Failed to prepare tag query: {}", e))?;
+    stmt.query_map(params![image_id], |row| row.get(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("Failed to read tags: {}", e))
+}
+
+fn write_one(conn: &Connection, image_id: &str) -> Result<PathBuf, DrawStackError> {
+    let image = catalog::get_image(conn, image_id)?;
+    let original = image.original_path.to_path_buf();
+    if !original.parent().is_some_and(Path::is_dir) {
+        return Err(DrawStackError::not_found(original));
+    }
+
+    let path = find_sidecar(&original).unwrap_or_else(|| original.with_extension("xmp"));
+    let existing = match fs::read_to_string(&path) {
+        Ok(text) => Some(text),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(DrawStackError::io("read sidecar", &path, e)),
+    };
+    let xmp = render(
+        existing.as_deref(),
+        image.rating,
+        &image_tags(conn, image_id)?,
+    )?;
+    crate::write_atomic(&path, xmp.as_bytes())
+        .map_err(|e| DrawStackError::io("write sidecar", &path, e))?;
+    Ok(path)
+}
+
+// Write each image's tags and rating to the XMP sidecar beside its original,
+// updating a sidecar that's already there. Returns the sidecar paths.
+#[tauri::command]
+pub async fn write_xmp_sidecars(
+    app: AppHandle,
+    image_ids: Vec<String>,
+) -> Result<XmpExport, DrawStackError> {
+    let conn = catalog::open(&app)?;
+    let mut report = XmpExport {
+        written: Vec::new(),
+        failed: Vec::new(),
+    };
+    for image_id in image_ids {
+        match write_one(&conn, &image_id) {
+            Ok(path) => report.written.push(path.to_string_lossy().to_string()),
+            Err(e) => {
+                tracing::warn!("Failed to write sidecar for {}: {}", image_id, e);
+                report.failed.push(XmpFailure {
+                    image_id,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+    Ok(report)
+}